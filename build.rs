@@ -1,15 +1,30 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
 
+#[path = "src/data_table.rs"]
+#[allow(dead_code)]
+mod data_table;
+
 fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/data_table.rs");
+    println!("cargo:rerun-if-changed=data");
+
+    generate_decompositions();
+    compile_data_tables();
+    emit_data_fingerprint();
+}
+
+fn generate_decompositions() {
     let path = Path::new("src/decompositions.rs");
     let mut file = BufWriter::new(File::create(path).unwrap());
 
     writeln!(
         &mut file,
         "pub static DECOMPOSITIONS: phf::Map<char, (&'static str, &'static str)> = phf::phf_map! {{"
-    ).unwrap();
+    )
+    .unwrap();
 
     let input = BufReader::new(File::open("data/UnicodeData.txt").unwrap());
 
@@ -45,9 +60,136 @@ fn main() {
                 codepoint as u32,
                 tag,
                 decomp_str.escape_default()
-            ).unwrap();
+            )
+            .unwrap();
         }
     }
 
     writeln!(&mut file, "}};").unwrap();
 }
+
+/// Compiles the `data/*.txt` tables into compact records in `OUT_DIR`,
+/// so that `Uroman::new()` does not have to scan `::slot` lines at runtime.
+///
+/// With the `compressed-data` feature, the tables are written deflate-compressed.
+fn compile_data_tables() {
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let compress = has_feature("compressed-data");
+    let write_table = |dst: &str, table: String| {
+        let bytes = if compress {
+            miniz_oxide::deflate::compress_to_vec(table.as_bytes(), 9)
+        } else {
+            table.into_bytes()
+        };
+        fs::write(Path::new(&out_dir).join(dst), bytes).unwrap();
+    };
+    let compile = |src: &str, dst: &str, compile_line: &dyn Fn(&str) -> Option<String>| {
+        let content = fs::read_to_string(Path::new("data").join(src)).unwrap();
+        write_table(dst, data_table::compile_table(&content, compile_line));
+    };
+
+    // Rules for scripts whose feature is disabled are dropped from the rom tables.
+    let compile_rom_line = |line: &str, file_format: &str| {
+        data_table::compile_rom_line(line, file_format)
+            .filter(|record| record.chars().next().is_some_and(is_enabled_char))
+    };
+
+    compile(
+        "romanization-auto-table.txt",
+        "romanization-auto-table.tbl",
+        &|line| compile_rom_line(line, "rom"),
+    );
+    compile(
+        "UnicodeDataOverwrite.txt",
+        "UnicodeDataOverwrite.tbl",
+        &|line| compile_rom_line(line, "u2r"),
+    );
+    compile(
+        "romanization-table.txt",
+        "romanization-table.tbl",
+        &|line| compile_rom_line(line, "rom"),
+    );
+    compile(
+        "Scripts.txt",
+        "Scripts.tbl",
+        &data_table::compile_script_line,
+    );
+    compile(
+        "ISO-639-3-list.txt",
+        "ISO-639-3-list.tbl",
+        &data_table::compile_lang_code_line,
+    );
+    compile(
+        "UnicodeDataProps.txt",
+        "UnicodeDataProps.tbl",
+        &data_table::compile_props_line,
+    );
+    if has_feature("cjk") {
+        compile(
+            "Chinese_to_Pinyin.txt",
+            "Chinese_to_Pinyin.tbl",
+            &data_table::compile_pinyin_line,
+        );
+        compile(
+            "UnicodeDataPropsCJK.txt",
+            "UnicodeDataPropsCJK.tbl",
+            &data_table::compile_props_line,
+        );
+    }
+    if has_feature("hangul") {
+        compile(
+            "UnicodeDataPropsHangul.txt",
+            "UnicodeDataPropsHangul.tbl",
+            &data_table::compile_props_line,
+        );
+    }
+    // NumProps.jsonl is already line-oriented JSON and is embedded as is.
+    write_table(
+        "NumProps.tbl",
+        fs::read_to_string("data/NumProps.jsonl").unwrap(),
+    );
+}
+
+/// Exposes a hash of the data files and the enabled script features as
+/// `UROMAN_DATA_FINGERPRINT`, used to reject state snapshots built from other data.
+fn emit_data_fingerprint() {
+    let mut paths: Vec<_> = fs::read_dir("data")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    // FNV-1a, which unlike `DefaultHasher` is stable across Rust versions.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for &b in bytes {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    for path in &paths {
+        feed(path.file_name().unwrap().as_encoded_bytes());
+        feed(&fs::read(path).unwrap());
+    }
+    for feature in ["cjk", "hangul", "braille"] {
+        feed(&[has_feature(feature) as u8]);
+    }
+    println!("cargo:rustc-env=UROMAN_DATA_FINGERPRINT={hash:016x}");
+}
+
+fn has_feature(name: &str) -> bool {
+    let var = format!("CARGO_FEATURE_{}", name.to_uppercase().replace('-', "_"));
+    std::env::var_os(var).is_some()
+}
+
+/// Returns `false` for characters of a script whose feature is disabled.
+fn is_enabled_char(c: char) -> bool {
+    let cp = c as u32;
+    let is_cjk =
+        matches!(cp, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3FFFF);
+    let is_hangul = matches!(cp, 0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7A3);
+    let is_braille = matches!(cp, 0x2800..=0x28FF);
+    (!is_cjk || has_feature("cjk"))
+        && (!is_hangul || has_feature("hangul"))
+        && (!is_braille || has_feature("braille"))
+}
@@ -0,0 +1,86 @@
+//! Compares the output of uroman-rs with that of the Python uroman 1.3 on the same input,
+//! line by line, for checking a migration from it.
+//!
+//! Produce the reference with the Python uroman first, e.g.
+//! `uroman.py -i input.txt -o python-output.txt --rom_format edges`.
+
+extern crate uroman;
+
+use std::{
+    env,
+    fs::{self, File},
+    io::BufReader,
+    process,
+};
+
+use uroman::{Compat, RomFormat, RomanizeOptions, Uroman};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 3 {
+        eprintln!(
+            "Usage: cargo run --example compare_python -- <input.txt> <python-output.txt> [str|edges|alts|lattice] [lcode]"
+        );
+        eprintln!("\nFor example, try:");
+        eprintln!(
+            "  cargo run --example compare_python -- tests/test/multi-script.txt tests/test/multi-script.uroman-ref.txt"
+        );
+        process::exit(2);
+    }
+
+    let rom_format = match args.get(3).map(String::as_str) {
+        None | Some("str") => RomFormat::Str,
+        Some("edges") => RomFormat::Edges,
+        Some("alts") => RomFormat::Alts,
+        Some("lattice") => RomFormat::Lattice,
+        Some(other) => {
+            eprintln!("Error: Unknown format '{other}'");
+            process::exit(2);
+        }
+    };
+    let mut options = RomanizeOptions::new()
+        .with_format(rom_format)
+        .with_compat(Compat::PythonV1_3);
+    if let Some(lcode) = args.get(4) {
+        options = options.with_lcode(lcode);
+    }
+
+    let (input_path, reference_path) = (&args[1], &args[2]);
+    let input = File::open(input_path).unwrap_or_else(|e| {
+        eprintln!("Error: Failed to open file '{input_path}': {e}");
+        process::exit(2);
+    });
+    let reference = fs::read_to_string(reference_path).unwrap_or_else(|e| {
+        eprintln!("Error: Failed to read file '{reference_path}': {e}");
+        process::exit(2);
+    });
+
+    let uroman = Uroman::new();
+    let mut output = Vec::new();
+    if let Err(e) = uroman.romanize_file_with_options(BufReader::new(input), &mut output, &options)
+    {
+        eprintln!("Error: {e}");
+        process::exit(2);
+    }
+    let output = String::from_utf8(output).expect("the output is UTF-8");
+
+    let actual_lines: Vec<&str> = output.lines().collect();
+    let expected_lines: Vec<&str> = reference.lines().collect();
+    let mut n_diffs = 0;
+    for i in 0..actual_lines.len().max(expected_lines.len()) {
+        let actual = actual_lines.get(i).copied().unwrap_or("<missing>");
+        let expected = expected_lines.get(i).copied().unwrap_or("<missing>");
+        if actual != expected {
+            n_diffs += 1;
+            println!("line {}:\n  python: {expected}\n  rust:   {actual}", i + 1);
+        }
+    }
+    println!(
+        "{n_diffs} of {} lines differ",
+        actual_lines.len().max(expected_lines.len())
+    );
+    if n_diffs > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,205 @@
+//! Alignments between a text and its romanization, in chars or in bytes.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Edge, RomanizationResult, Uroman, rom_format};
+
+/// The unit of the offsets of an [`Alignment`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum OffsetUnit {
+    /// Char indices, as the offsets of edges are.
+    #[default]
+    Chars,
+    /// Byte offsets into the UTF-8 of the texts, as those of `str` slices are.
+    Bytes,
+}
+
+impl OffsetUnit {
+    fn is_chars(&self) -> bool {
+        *self == OffsetUnit::Chars
+    }
+}
+
+/// Maps every char of a text to the chars of its romanization and back, for projecting
+/// annotations such as named entities or POS tags between the two, or mapping corrections made
+/// on the romanized text, such as those of human post-editing, back to the source.
+///
+/// Created by [`Uroman::align`], or by [`Alignment::from_edges`] from the edges of any
+/// romanization. Offsets are char indices, or byte offsets with [`OffsetUnit::Bytes`], and each
+/// char maps to the whole edge it belongs to, e.g. both chars of `иї` in `Київ` map to the `yi`
+/// of their edge. Serialized as JSON, the mappings are arrays of `[start, end]` pairs, and the
+/// unit is only given if it is bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alignment {
+    /// The romanized text.
+    pub romanized: String,
+    /// The unit of the offsets.
+    #[serde(default, skip_serializing_if = "OffsetUnit::is_chars")]
+    pub unit: OffsetUnit,
+    /// For each char, or byte, of the original text, the `[start, end)` range of the romanized
+    /// text it produced. The range is empty for chars that are dropped, like a Thai
+    /// cancellation mark.
+    pub orig_to_rom: Vec<[usize; 2]>,
+    /// For each char, or byte, of the romanized text, the `[start, end)` range of the original
+    /// text it was produced from.
+    pub rom_to_orig: Vec<[usize; 2]>,
+}
+
+impl Alignment {
+    /// Aligns `text` with its romanization into `edges`, those of the edges format or of any
+    /// format that has them, in `unit`. Edges that overlap an earlier one, such as the
+    /// alternatives of the alts format, are left out, and chars that no edge covers produce
+    /// nothing.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Alignment, OffsetUnit, RomFormat, RomanizationResult, RomanizeOptions, Uroman};
+    ///
+    /// let text = "Столиця: Київ";
+    /// let options = RomanizeOptions::new().with_lcode("ukr").with_format(RomFormat::Edges);
+    /// let RomanizationResult::Edges(edges) = Uroman::new().romanize_with_options(text, &options)
+    /// else {
+    ///     unreachable!();
+    /// };
+    /// let alignment = Alignment::from_edges(text, &edges, OffsetUnit::Bytes);
+    /// // A correction of "Kyiv" in the romanized text "Stolytsya: Kyiv" maps back to "Київ".
+    /// let source = alignment.to_original(11..15);
+    /// assert_eq!(&text[source], "Київ");
+    /// assert_eq!(alignment.to_romanized_offset(text.len()), alignment.romanized.len());
+    /// ```
+    pub fn from_edges(text: &str, edges: &[Edge], unit: OffsetUnit) -> Alignment {
+        // The offset of each char of `text` in `unit`, and that of its end.
+        let offsets: Vec<usize> = match unit {
+            OffsetUnit::Chars => (0..=text.chars().count()).collect(),
+            OffsetUnit::Bytes => text
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([text.len()])
+                .collect(),
+        };
+        let n_chars = offsets.len() - 1;
+        let rom_len = |txt: &str| match unit {
+            OffsetUnit::Chars => txt.chars().count(),
+            OffsetUnit::Bytes => txt.len(),
+        };
+
+        let mut romanized = String::new();
+        let mut orig_to_rom = Vec::with_capacity(offsets[n_chars]);
+        let mut rom_to_orig = Vec::new();
+        let mut covered = 0;
+        for edge in edges {
+            let (start, end) = (edge.start().min(n_chars), edge.end().min(n_chars));
+            if start < covered {
+                continue;
+            }
+            let rom_start = rom_to_orig.len();
+            // Chars not covered by any edge produce nothing.
+            orig_to_rom.resize(offsets[start], [rom_start, rom_start]);
+            romanized.push_str(edge.txt());
+            rom_to_orig.resize(
+                rom_start + rom_len(edge.txt()),
+                [offsets[start], offsets[end]],
+            );
+            let rom_end = rom_to_orig.len();
+            orig_to_rom.resize(offsets[end], [rom_start, rom_end]);
+            covered = end;
+        }
+        orig_to_rom.resize(offsets[n_chars], [rom_to_orig.len(), rom_to_orig.len()]);
+
+        Alignment {
+            romanized,
+            unit,
+            orig_to_rom,
+            rom_to_orig,
+        }
+    }
+
+    /// Returns the range of romanized chars produced by the original chars in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of the original text.
+    pub fn to_romanized(&self, range: Range<usize>) -> Range<usize> {
+        project(&self.orig_to_rom, range)
+    }
+
+    /// Returns the range of original chars that produced the romanized chars in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of the romanized text.
+    pub fn to_original(&self, range: Range<usize>) -> Range<usize> {
+        project(&self.rom_to_orig, range)
+    }
+
+    /// Returns the offset in the romanized text of `offset` in the original text, a position
+    /// between chars from 0 to the length of the text. An offset inside an edge maps to the
+    /// start of the edge.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is past the end of the original text.
+    pub fn to_romanized_offset(&self, offset: usize) -> usize {
+        self.to_romanized(offset..offset).start
+    }
+
+    /// Returns the offset in the original text of `offset` in the romanized text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is past the end of the romanized text.
+    pub fn to_original_offset(&self, offset: usize) -> usize {
+        self.to_original(offset..offset).start
+    }
+}
+
+impl Uroman {
+    /// Romanizes `s` like [`Uroman::romanize_string`] and aligns the chars of `s` with those
+    /// of the romanization.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let alignment = Uroman::new().align("Столиця: Київ", Some("ukr"));
+    /// assert_eq!(alignment.romanized, "Stolytsya: Kyiv");
+    /// // Project the entity "Київ" onto the romanized text and back.
+    /// let entity = alignment.to_romanized(9..13);
+    /// let romanized_entity: String = alignment
+    ///     .romanized
+    ///     .chars()
+    ///     .skip(entity.start)
+    ///     .take(entity.len())
+    ///     .collect();
+    /// assert_eq!(romanized_entity, "Kyiv");
+    /// assert_eq!(alignment.to_original(entity), 9..13);
+    /// ```
+    pub fn align(&self, s: &str, lcode: Option<&str>) -> Alignment {
+        let RomanizationResult::Edges(edges) =
+            self.romanize_string::<rom_format::Edges>(s, lcode).result
+        else {
+            unreachable!("the edges format always yields edges");
+        };
+        Alignment::from_edges(s, &edges, OffsetUnit::Chars)
+    }
+}
+
+/// Maps `range` through the monotonic `alignment` to the range on the other side.
+fn project(alignment: &[[usize; 2]], range: Range<usize>) -> Range<usize> {
+    let spans = &alignment[range.clone()];
+    match (spans.first(), spans.last()) {
+        (Some(first), Some(last)) => first[0]..last[1],
+        // An empty range maps to the position before the char at its start.
+        _ => {
+            let position = match alignment.get(range.start) {
+                Some(span) => span[0],
+                None => alignment.last().map_or(0, |span| span[1]),
+            };
+            position..position
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Romanizing async streams with tokio.
+
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
+
+use crate::hash::HashMap;
+use crate::utils::LossyLines;
+use crate::{FileSummary, RomanizationError, RomanizeOptions, Uroman};
+
+impl Uroman {
+    /// Romanizes an async stream of text like [`Uroman::romanize_file_with_options`], e.g. an
+    /// upload in an async server.
+    ///
+    /// Lines are read and written one at a time, so the stream is never buffered as a whole.
+    /// Each line is romanized on the calling task between the awaits on I/O. The stream is
+    /// read as UTF-8 in whole lines, whatever `options.input_encoding` and
+    /// `options.max_chunk_bytes` are.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Uroman::romanize_file_with_options`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(lcode = ?options.output.lcode, rom_format = ?options.output.rom_format)
+        )
+    )]
+    pub async fn romanize_file_async<R: AsyncBufRead + Unpin, W: AsyncWrite + Unpin>(
+        &self,
+        reader: R,
+        mut writer: W,
+        options: &RomanizeOptions,
+    ) -> Result<FileSummary, RomanizationError> {
+        let mut lines = LossyLines::new(reader)
+            .with_invalid_utf8(options.invalid_utf8)
+            .quiet();
+        if options.keep_bom && lines.starts_with_bom_async().await? {
+            writer.write_all("\u{FEFF}".as_bytes()).await?;
+        }
+        let mut changed_lines = 0;
+        let mut per_script_counts: HashMap<&str, usize> = HashMap::default();
+        let mut output = Vec::new();
+
+        while lines.next_line_async().await?.is_some() {
+            let line = lines.current_line();
+            self.count_scripts(line, &mut per_script_counts);
+            let line_end = options.line_ending.terminator(lines.current_line_ending());
+            output.clear();
+            let changed = self
+                .write_file_line(&mut output, line, line_end, options)
+                .map_err(|e| lines.error_at_current_line(e))?;
+            changed_lines += usize::from(changed);
+            writer
+                .write_all(&output)
+                .await
+                .map_err(|e| lines.error_at_current_line(e))?;
+
+            if options
+                .max_lines
+                .is_some_and(|max| lines.line_number() >= max)
+            {
+                break;
+            }
+        }
+
+        lines.finish(options.silent);
+
+        writer.flush().await?;
+        Ok(lines.summary(changed_lines, per_script_counts))
+    }
+}
@@ -0,0 +1,85 @@
+//! A common interface for romanization engines.
+
+use crate::{Edge, RomFormat, RomanizationResult, Uroman, rom_format};
+
+/// A romanization engine, so that applications can swap uroman-rs for another engine (e.g. one
+/// backed by ICU) or for a mock in tests.
+///
+/// Only [`RomanizerBackend::romanize_str`] is required. Engines without a notion of edges get a
+/// single edge over the whole text by default.
+///
+/// # Example
+/// ```
+/// use uroman::{RomanizerBackend, Uroman};
+///
+/// struct Upper;
+///
+/// impl RomanizerBackend for Upper {
+///     fn romanize_str(&self, s: &str, _lcode: Option<&str>) -> String {
+///         s.to_uppercase()
+///     }
+/// }
+///
+/// fn shout(backend: &dyn RomanizerBackend) -> String {
+///     backend.romanize_str("Ελληνικά", None)
+/// }
+///
+/// assert_eq!(shout(&Uroman::new()), "Ellenika");
+/// assert_eq!(shout(&Upper), "ΕΛΛΗΝΙΚΆ");
+/// ```
+pub trait RomanizerBackend: Send + Sync {
+    /// Romanizes `s` to a string, optionally with an ISO 639-3 language code `lcode`.
+    fn romanize_str(&self, s: &str, lcode: Option<&str>) -> String;
+
+    /// Romanizes `s` to the edges of its best path, with char offsets into `s`.
+    fn romanize_edges(&self, s: &str, lcode: Option<&str>) -> Vec<Edge<'static>> {
+        if s.is_empty() {
+            return Vec::new();
+        }
+        vec![Edge::new_regular(
+            0,
+            s.chars().count(),
+            self.romanize_str(s, lcode),
+            "backend",
+        )]
+    }
+
+    /// Romanizes `s` to `rom_format`. The alts and lattice formats fall back to the edges by
+    /// default.
+    fn romanize_format(
+        &self,
+        s: &str,
+        lcode: Option<&str>,
+        rom_format: RomFormat,
+    ) -> RomanizationResult {
+        match rom_format {
+            RomFormat::Str => RomanizationResult::Str(self.romanize_str(s, lcode)),
+            RomFormat::Edges | RomFormat::Alts | RomFormat::Lattice => {
+                RomanizationResult::Edges(self.romanize_edges(s, lcode))
+            }
+        }
+    }
+}
+
+impl RomanizerBackend for Uroman {
+    fn romanize_str(&self, s: &str, lcode: Option<&str>) -> String {
+        self.romanize_string::<rom_format::Str>(s, lcode)
+            .to_output_string()
+    }
+
+    fn romanize_edges(&self, s: &str, lcode: Option<&str>) -> Vec<Edge<'static>> {
+        match self.romanize_string::<rom_format::Edges>(s, lcode).result {
+            RomanizationResult::Edges(edges) => edges,
+            RomanizationResult::Str(_) => unreachable!("the edges format always yields edges"),
+        }
+    }
+
+    fn romanize_format(
+        &self,
+        s: &str,
+        lcode: Option<&str>,
+        rom_format: RomFormat,
+    ) -> RomanizationResult {
+        self.romanize_with_format(s, lcode, Some(rom_format))
+    }
+}
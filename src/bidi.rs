@@ -0,0 +1,196 @@
+//! The display order of bidirectional text, for UIs that highlight edges over it.
+
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+use crate::{Edge, RomanizationResult, RomanizeOptions, Uroman};
+
+/// The bidi classes that the display order depends on, a simplification of those of the
+/// Unicode Bidirectional Algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BidiClass {
+    Left,
+    Right,
+    Number,
+    Neutral,
+}
+
+impl Uroman {
+    /// Sets the display span of each of `edges` of `s`, the range of positions that its chars
+    /// are shown at when `s` is rendered, see the `display` field of
+    /// [`Edge::get_data`](crate::Edge::get_data).
+    ///
+    /// In left-to-right text, the display span of an edge is its span. In text with
+    /// right-to-left scripts such as Arabic and Hebrew, the display order follows the Unicode
+    /// Bidirectional Algorithm for a single paragraph without explicit embeddings.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomanizationResult, RomFormat, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let s = "שלום";
+    /// let result = uroman.romanize_with_format(s, None, Some(RomFormat::Edges));
+    /// let RomanizationResult::Edges(mut edges) = result else {
+    ///     unreachable!();
+    /// };
+    /// uroman.add_display_positions(s, &mut edges);
+    /// // The first char of the text is shown last.
+    /// assert_eq!(edges[0].get_data().display, Some((3, 4)));
+    /// ```
+    pub fn add_display_positions(&self, s: &str, edges: &mut [Edge]) {
+        let chars: Vec<char> = s.chars().collect();
+        let positions = self.display_positions(&chars);
+        for edge in edges {
+            let data = edge.get_data_mut();
+            let span_positions = positions.get(data.start..data.end).unwrap_or_default();
+            data.display = span_positions
+                .iter()
+                .min()
+                .zip(span_positions.iter().max())
+                .map(|(&first, &last)| (first, last + 1));
+        }
+    }
+
+    /// Adds the display positions of `s` to the edges of `result` if `options` ask for them.
+    pub(crate) fn with_display_positions(
+        &self,
+        mut result: RomanizationResult,
+        s: &str,
+        options: &RomanizeOptions,
+    ) -> RomanizationResult {
+        if options.output.display_positions
+            && let RomanizationResult::Edges(edges) = &mut result
+        {
+            self.add_display_positions(s, edges);
+        }
+        result
+    }
+
+    /// Returns the position that each of `chars` is shown at.
+    fn display_positions(&self, chars: &[char]) -> Vec<usize> {
+        let mut classes: Vec<BidiClass> = Vec::with_capacity(chars.len());
+        for &c in chars {
+            // Marks take the class of the char they are on.
+            let class = if c.general_category_group() == GeneralCategoryGroup::Mark {
+                classes.last().copied().unwrap_or(BidiClass::Neutral)
+            } else {
+                self.bidi_class(c)
+            };
+            classes.push(class);
+        }
+        let rtl_paragraph = classes
+            .iter()
+            .find(|class| matches!(class, BidiClass::Left | BidiClass::Right))
+            == Some(&BidiClass::Right);
+        let paragraph_class = if rtl_paragraph {
+            BidiClass::Right
+        } else {
+            BidiClass::Left
+        };
+
+        // Numbers stay left-to-right, but act as right-to-left text on the neutrals around
+        // them if they follow it.
+        let mut number_follows_rtl = vec![false; chars.len()];
+        let mut last_strong = paragraph_class;
+        for (i, &class) in classes.iter().enumerate() {
+            match class {
+                BidiClass::Left | BidiClass::Right => last_strong = class,
+                BidiClass::Number => number_follows_rtl[i] = last_strong == BidiClass::Right,
+                BidiClass::Neutral => {}
+            }
+        }
+
+        // Neutrals between text of the same direction take it, and others that of the paragraph.
+        let mut resolved = classes;
+        let direction_of = |i: usize, resolved: &[BidiClass]| match resolved[i] {
+            BidiClass::Number if number_follows_rtl[i] || rtl_paragraph => BidiClass::Right,
+            BidiClass::Number => BidiClass::Left,
+            class => class,
+        };
+        let mut i = 0;
+        while i < resolved.len() {
+            if resolved[i] != BidiClass::Neutral {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < resolved.len() && resolved[i] == BidiClass::Neutral {
+                i += 1;
+            }
+            let before = match start {
+                0 => paragraph_class,
+                _ => direction_of(start - 1, &resolved),
+            };
+            let after = match resolved.get(i) {
+                Some(_) => direction_of(i, &resolved),
+                None => paragraph_class,
+            };
+            let class = if before == after {
+                before
+            } else {
+                paragraph_class
+            };
+            resolved[start..i].fill(class);
+        }
+
+        let levels: Vec<u8> = resolved
+            .iter()
+            .enumerate()
+            .map(|(i, class)| match (class, rtl_paragraph) {
+                (BidiClass::Right, _) => 1,
+                (BidiClass::Number, true) => 2,
+                (BidiClass::Number, false) if number_follows_rtl[i] => 2,
+                (_, true) => 2,
+                _ => 0,
+            })
+            .collect();
+
+        // From the highest level down to the lowest odd one, reverse the runs at that level
+        // or higher.
+        let mut order: Vec<usize> = (0..chars.len()).collect();
+        let max_level = levels.iter().copied().max().unwrap_or(0);
+        for level in (1..=max_level).rev() {
+            let mut i = 0;
+            while i < order.len() {
+                if levels[order[i]] < level {
+                    i += 1;
+                    continue;
+                }
+                let start = i;
+                while i < order.len() && levels[order[i]] >= level {
+                    i += 1;
+                }
+                order[start..i].reverse();
+            }
+        }
+
+        let mut positions = vec![0; chars.len()];
+        for (position, &i) in order.iter().enumerate() {
+            positions[i] = position;
+        }
+        positions
+    }
+
+    fn bidi_class(&self, c: char) -> BidiClass {
+        match c {
+            // RIGHT-TO-LEFT MARK and ARABIC LETTER MARK
+            '\u{200F}' | '\u{061C}' => BidiClass::Right,
+            // LEFT-TO-RIGHT MARK
+            '\u{200E}' => BidiClass::Left,
+            _ if c.is_numeric() => BidiClass::Number,
+            _ if c.is_alphabetic() => {
+                let script_name = self.chr_script_name(c);
+                let is_rtl = self
+                    .scripts
+                    .get(&script_name.to_lowercase())
+                    .is_some_and(|script| script.direction.as_deref() == Some("right-to-left"));
+                if is_rtl {
+                    BidiClass::Right
+                } else {
+                    BidiClass::Left
+                }
+            }
+            _ => BidiClass::Neutral,
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Defines `RomanizationCache`, a bounded LRU cache of romanized lines, and the
+//! thread-safe memo table behind the internal lookup caches.
+
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::{PoisonError, RwLock};
+
+use lru::LruCache;
+
+use crate::hash::HashMap;
+use crate::{OutputOptions, RomanizationError, RomanizeOptions};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    line: String,
+    options: OutputOptions,
+}
+
+/// Hit and miss counters of a [`RomanizationCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Returns the fraction of lookups served from the cache, or `0.0` before the first lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// A bounded LRU cache of romanized lines, for corpora with many duplicate lines.
+///
+/// Pass it to [`Uroman::romanize_file_with_context`](crate::Uroman::romanize_file_with_context)
+/// with [`FileContext::with_cache`](crate::FileContext::with_cache).
+/// Entries are keyed by the line and the [`OutputOptions`] of its [`RomanizeOptions`], such as
+/// the language code, the output format and the subsystems, and hold the formatted output
+/// line. The settings of the [`Uroman`](crate::Uroman) itself, such as its rules and the
+/// policies of its `with_*` builders, aren't part of the key, so a cache must not be shared
+/// between differently configured `Uroman`s.
+#[derive(Debug)]
+pub struct RomanizationCache {
+    entries: LruCache<CacheKey, String>,
+    stats: CacheStats,
+    memory_budget: Option<usize>,
+    /// The estimated memory of the entries, see [`RomanizationCache::estimated_bytes`].
+    bytes: usize,
+}
+
+/// Returns the estimated memory of a cache entry: its strings and the node of the LRU list.
+fn estimated_entry_bytes(key: &CacheKey, output: &str) -> usize {
+    size_of::<(CacheKey, String)>()
+        + 4 * size_of::<usize>()
+        + key.line.len()
+        + key.options.lcode.as_ref().map_or(0, String::len)
+        + output.len()
+}
+
+impl RomanizationCache {
+    /// Creates a cache holding at most `capacity` lines.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: LruCache::new(capacity),
+            stats: CacheStats::default(),
+            memory_budget: None,
+            bytes: 0,
+        }
+    }
+
+    /// Sets a soft limit in bytes on the estimated memory of the entries. The least recently
+    /// used entries are evicted to make room for a new one, which is kept even if it alone
+    /// exceeds the limit.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::num::NonZeroUsize;
+    /// # use uroman::RomanizationCache;
+    /// let cache = RomanizationCache::new(NonZeroUsize::new(100_000).unwrap())
+    ///     .with_memory_budget(64 << 20);
+    /// ```
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Returns the estimated memory of the entries in bytes, from the lengths of their lines
+    /// and outputs.
+    pub fn estimated_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all entries and resets the statistics.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.stats = CacheStats::default();
+        self.bytes = 0;
+    }
+
+    /// Returns the cached output for the line, computing and storing it with `romanize` on a miss.
+    /// Nothing is stored if `romanize` fails.
+    pub(crate) fn try_get_or_insert_with(
+        &mut self,
+        line: &str,
+        options: &RomanizeOptions,
+        romanize: impl FnOnce() -> Result<String, RomanizationError>,
+    ) -> Result<&str, RomanizationError> {
+        let key = CacheKey {
+            line: line.to_string(),
+            options: options.output.clone(),
+        };
+        if self.entries.contains(&key) {
+            self.stats.hits += 1;
+            return Ok(self.entries.get(&key).expect("the entry is cached"));
+        }
+        self.stats.misses += 1;
+        let output = romanize()?;
+        let entry_bytes = estimated_entry_bytes(&key, &output);
+        if let Some(budget) = self.memory_budget {
+            while self.bytes + entry_bytes > budget
+                && let Some((key, output)) = self.entries.pop_lru()
+            {
+                self.bytes -= estimated_entry_bytes(&key, &output);
+            }
+        }
+        self.bytes += entry_bytes;
+        if let Some((key, output)) = self.entries.push(key, output) {
+            self.bytes -= estimated_entry_bytes(&key, &output);
+        }
+        let (_, output) = self.entries.peek_mru().expect("the entry was just added");
+        Ok(output)
+    }
+}
+
+/// A memo table shared between threads, used for the per-`Uroman` lookup caches.
+///
+/// Unlike a `RefCell`, it keeps `Uroman` `Sync`, so one instance can romanize on several
+/// threads at once. Cloning copies the current entries.
+#[derive(Debug)]
+pub(crate) struct MemoMap<K, V>(RwLock<HashMap<K, V>>);
+
+impl<K: Eq + Hash, V: Clone> MemoMap<K, V> {
+    pub(crate) fn get(&self, key: &K) -> Option<V> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .cloned()
+    }
+
+    /// Inserts an entry, after emptying the table if it already has `max_len` entries.
+    pub(crate) fn insert(&self, key: K, value: V, max_len: Option<usize>) {
+        let mut entries = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        if max_len.is_some_and(|max| entries.len() >= max) {
+            entries.clear();
+        }
+        entries.insert(key, value);
+    }
+}
+
+impl<K, V> Default for MemoMap<K, V> {
+    fn default() -> Self {
+        Self(RwLock::new(HashMap::default()))
+    }
+}
+
+impl<K: Clone, V: Clone> Clone for MemoMap<K, V> {
+    fn clone(&self) -> Self {
+        let entries = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        Self(RwLock::new(entries.clone()))
+    }
+}
@@ -0,0 +1,133 @@
+//! Stopping long romanization calls early, for servers that enforce request timeouts.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::{RomFormatType, RomanizationError, RomanizationOutput, Uroman};
+
+/// A flag to cancel romanization calls from another thread, see
+/// [`Uroman::romanize_string_with_cancellation`]. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the calls that use the token, now and from then on.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// When a romanization call stops building lattices: at a deadline, or once a token is
+/// cancelled.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Interrupt {
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) token: Option<CancellationToken>,
+}
+
+impl Interrupt {
+    pub(crate) fn is_due(&self) -> bool {
+        self.check().is_err()
+    }
+
+    /// Fails with [`RomanizationError::Cancelled`] or [`RomanizationError::DeadlineExceeded`]
+    /// if the interrupt is due.
+    pub(crate) fn check(&self) -> Result<(), RomanizationError> {
+        if self
+            .token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(RomanizationError::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(RomanizationError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+}
+
+impl Uroman {
+    /// Romanizes `s` like [`Uroman::romanize_string`], or stops once `deadline` passes.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::DeadlineExceeded`] if the deadline passes first. Nothing
+    /// of the romanization is kept then.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::{Duration, Instant};
+    /// use uroman::{RomanizationError, Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new();
+    /// let deadline = Instant::now() + Duration::from_secs(1);
+    /// let romanize = |deadline| {
+    ///     let lcode = Some("ukr");
+    ///     uroman.romanize_string_with_deadline::<rom_format::Str>("Київ", lcode, deadline)
+    /// };
+    /// assert_eq!(romanize(deadline).unwrap().to_output_string(), "Kyiv");
+    /// assert!(matches!(
+    ///     romanize(Instant::now()),
+    ///     Err(RomanizationError::DeadlineExceeded)
+    /// ));
+    /// ```
+    pub fn romanize_string_with_deadline<F: RomFormatType + 'static>(
+        &self,
+        s: &str,
+        lcode: Option<&str>,
+        deadline: Instant,
+    ) -> Result<RomanizationOutput<F>, RomanizationError> {
+        let stop = Interrupt {
+            deadline: Some(deadline),
+            token: None,
+        };
+        self.romanize_segments_until(&[(s, lcode)], self.policies(), Some(&stop))
+    }
+
+    /// Romanizes `s` like [`Uroman::romanize_string`], or stops once `token` is cancelled.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::Cancelled`] if the token is cancelled first. Nothing of
+    /// the romanization is kept then.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{CancellationToken, RomanizationError, Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new();
+    /// let token = CancellationToken::new();
+    /// let romanize = |token| {
+    ///     let lcode = Some("ukr");
+    ///     uroman.romanize_string_with_cancellation::<rom_format::Str>("Київ", lcode, token)
+    /// };
+    /// assert_eq!(romanize(&token).unwrap().to_output_string(), "Kyiv");
+    ///
+    /// // E.g. from the thread that handles the request, when the client goes away.
+    /// token.cancel();
+    /// assert!(matches!(romanize(&token), Err(RomanizationError::Cancelled)));
+    /// ```
+    pub fn romanize_string_with_cancellation<F: RomFormatType + 'static>(
+        &self,
+        s: &str,
+        lcode: Option<&str>,
+        token: &CancellationToken,
+    ) -> Result<RomanizationOutput<F>, RomanizationError> {
+        let stop = Interrupt {
+            deadline: None,
+            token: Some(token.clone()),
+        };
+        self.romanize_segments_until(&[(s, lcode)], self.policies(), Some(&stop))
+    }
+}
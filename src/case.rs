@@ -0,0 +1,151 @@
+//! Carrying the case of the chars of bicameral scripts, such as Greek, Cyrillic and Armenian,
+//! over to their romanizations, see [`Uroman::with_case_carrying`](crate::Uroman).
+
+use std::borrow::Cow;
+
+use crate::lattice::Lattice;
+use crate::rom_rule::RomRule;
+use crate::rule_trie::RuleTrie;
+
+impl<'a> Lattice<'a> {
+    /// The chars of `s_chars` with their uppercase letters lowercased, for the rules of spans
+    /// that only have rules in lowercase. `None` unless the case is carried and `s_chars` has
+    /// such letters. Letters that don't lowercase to a single char are kept as they are, so
+    /// that the spans stay the same.
+    pub(crate) fn fold_chars(&self) -> Option<Vec<char>> {
+        if !self.uroman.carry_case || !self.s_chars.iter().any(|c| c.is_uppercase()) {
+            return None;
+        }
+        let folded = self.s_chars.iter().map(|&c| {
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(lower), None) => lower,
+                _ => c,
+            }
+        });
+        Some(folded.collect())
+    }
+
+    /// The rules for the chars of the span `[start, end)`, or else for their lowercase forms.
+    pub(crate) fn rules_for_span(&self, start: usize, end: usize) -> Option<&'a Vec<RomRule>> {
+        let uroman = self.uroman;
+        uroman
+            .rom_rules_for_chars(&self.s_chars[start..end])
+            .or_else(|| uroman.rom_rules_for_chars(&self.folded_chars.as_ref()?[start..end]))
+    }
+
+    /// Adds the ends of the spans at each start that only have rules in lowercase to
+    /// `rule_source_ends`, see [`Lattice::fold_chars`].
+    pub(crate) fn add_folded_source_ends(&self, rule_source_ends: &mut [Vec<usize>]) {
+        let Some(folded) = &self.folded_chars else {
+            return;
+        };
+        let trie = &self.uroman.rule_trie;
+        for (start, span_ends) in rule_source_ends.iter_mut().enumerate() {
+            let mut node = RuleTrie::ROOT;
+            for end in (start + 1)..=self.max_vertex {
+                let Some(child) = trie.child(node, folded[end - 1]) else {
+                    break;
+                };
+                node = child;
+                if trie.value(node).is_some() && !span_ends.contains(&end) {
+                    span_ends.push(end);
+                }
+            }
+            span_ends.sort_unstable();
+        }
+    }
+
+    /// Gives `rom`, the romanization of the span `[start, end)`, the case of the chars of the
+    /// span if the case is carried, rather than the case of the rule:
+    ///
+    /// - Lowercase chars make `rom` lowercase.
+    /// - Uppercase chars make it uppercase if there are several or the letter next to them is
+    ///   uppercase too, as in `ЯБЛОКО`, and capitalize it otherwise, as in `Яблоко`.
+    /// - Chars of mixed case carry their cases over to the letters of `rom` in proportion, so
+    ///   that the first half of `rom` has the case of the first half of the chars.
+    ///
+    /// Spans without cased chars, such as those of unicameral scripts, keep `rom` as it is.
+    pub(crate) fn carry_case(&self, rom: Cow<'a, str>, start: usize, end: usize) -> Cow<'a, str> {
+        if !self.uroman.carry_case {
+            return rom;
+        }
+        let cases: Vec<bool> = self.s_chars[start..end]
+            .iter()
+            .filter(|&&c| carries_case(c))
+            .map(|c| c.is_uppercase())
+            .collect();
+        let n_cased = rom.chars().filter(|&c| is_cased(c)).count();
+        if cases.is_empty() || n_cased == 0 {
+            return rom;
+        }
+
+        let carried: String = if cases.iter().all(|&is_upper| !is_upper) {
+            rom.to_lowercase()
+        } else if cases.iter().all(|&is_upper| is_upper) {
+            if cases.len() > 1 || self.neighbor_is_upper(start, end) {
+                rom.to_uppercase()
+            } else {
+                capitalize(&rom)
+            }
+        } else {
+            let mut i = 0;
+            rom.chars()
+                .map(|c| {
+                    if !is_cased(c) {
+                        return c.to_string();
+                    }
+                    let is_upper = cases[i * cases.len() / n_cased];
+                    i += 1;
+                    with_case(c, is_upper)
+                })
+                .collect()
+        };
+        if carried == rom {
+            rom
+        } else {
+            Cow::Owned(carried)
+        }
+    }
+
+    /// Whether the cased letter right after the span `[start, end)`, or else right before it,
+    /// is uppercase.
+    fn neighbor_is_upper(&self, start: usize, end: usize) -> bool {
+        let next = self.s_chars.get(end).copied().filter(|&c| carries_case(c));
+        let prev = start
+            .checked_sub(1)
+            .map(|i| self.s_chars[i])
+            .filter(|&c| carries_case(c));
+        next.or(prev).is_some_and(char::is_uppercase)
+    }
+}
+
+fn is_cased(c: char) -> bool {
+    c.is_uppercase() || c.is_lowercase()
+}
+
+/// Whether the case of `c` is carried over to its romanization. Cherokee is written in its
+/// uppercase letters, which are romanized in lowercase.
+fn carries_case(c: char) -> bool {
+    is_cased(c) && !matches!(c, '\u{13A0}'..='\u{13FF}' | '\u{AB70}'..='\u{ABBF}')
+}
+
+fn with_case(c: char, is_upper: bool) -> String {
+    if is_upper {
+        c.to_uppercase().collect()
+    } else {
+        c.to_lowercase().collect()
+    }
+}
+
+/// `s` with its first cased letter uppercase and the others lowercase.
+fn capitalize(s: &str) -> String {
+    let mut is_first = true;
+    s.chars()
+        .map(|c| {
+            let is_upper = is_first && is_cased(c);
+            is_first &= !is_cased(c);
+            with_case(c, is_upper)
+        })
+        .collect()
+}
@@ -0,0 +1,144 @@
+//! Output of the lines of `romanize_file` input that are read in chunks, see
+//! [`RomanizeOptions::max_chunk_bytes`].
+
+use std::io::Write;
+
+use crate::compat::python_edge_json;
+use crate::directives::split_directives;
+use crate::{Compat, RomFormat, RomanizationError, RomanizationResult, RomanizeOptions, Uroman};
+
+/// What the chunks of a line read so far carry over to the next one.
+#[derive(Default)]
+pub(crate) struct LineChunks {
+    /// The options to romanize the chunks after the first one with, without utterance IDs
+    /// and with the settings of the directives of the line, such as `::lcode`.
+    options: Option<RomanizeOptions>,
+    /// The number of chars romanized so far, which the edges of the next chunk start at.
+    n_chars: usize,
+    /// Whether the edges JSON of the line has any edges yet.
+    has_edges: bool,
+    /// Whether the line so far is only an utterance ID, so that the next chunk starts with
+    /// the separator after it.
+    separator_pending: bool,
+}
+
+impl Uroman {
+    /// Romanizes a chunk of a line of `romanize_file` input and writes its output to `writer`,
+    /// so that the outputs of the chunks of a line make up one output line, ended with
+    /// `line_end` after the last chunk.
+    ///
+    /// In the edge formats, the edges of each chunk are shifted by the chars before it and
+    /// added to the JSON array of the line. The alternatives of a chunk follow its edges.
+    /// Returns whether the output of the chunk differs from it.
+    pub(crate) fn write_file_chunk<W: Write>(
+        &self,
+        writer: &mut W,
+        chunk: &str,
+        (starts_line, ends_line): (bool, bool),
+        line_end: &str,
+        state: &mut LineChunks,
+        options: &RomanizeOptions,
+    ) -> Result<bool, RomanizationError> {
+        let is_python = options.output.compat == Some(Compat::PythonV1_3);
+        let changed = if starts_line {
+            let output = self.romanize_file_line(chunk, options)?;
+            let mut text = file_line_text(chunk, options);
+            let mut chunk_options = options.clone();
+            chunk_options.output.utt_ids = false;
+            if let Some((_, directives, text_to_romanize)) = split_directives(text) {
+                chunk_options = directives.apply(&chunk_options, self);
+                text = text_to_romanize;
+            }
+            let rom_format = chunk_options.output.rom_format;
+            *state = LineChunks {
+                options: Some(chunk_options),
+                n_chars: text.chars().count(),
+                has_edges: false,
+                separator_pending: options.output.utt_ids && !chunk.contains(char::is_whitespace),
+            };
+            if rom_format == RomFormat::Str {
+                writer.write_all(output.as_bytes())?;
+                output != chunk
+            } else {
+                // The array of the line stays open for the edges of the next chunks.
+                let open = output.strip_suffix(']').unwrap_or(&output);
+                let open = open.trim_end_matches('\n');
+                state.has_edges = open != "[";
+                writer.write_all(open.as_bytes())?;
+                true
+            }
+        } else {
+            let mut chunk = chunk;
+            if state.separator_pending && !chunk.is_empty() {
+                state.separator_pending = false;
+                let separator_len = chunk
+                    .chars()
+                    .next()
+                    .filter(|c| c.is_whitespace())
+                    .map_or(0, char::len_utf8);
+                if options.output.rom_format == RomFormat::Str {
+                    writer.write_all(&chunk.as_bytes()[..separator_len])?;
+                }
+                chunk = &chunk[separator_len..];
+            }
+            let chunk_options = state.options.as_ref().unwrap_or(options);
+            match self.romanize_checked(chunk, chunk_options)? {
+                RomanizationResult::Str(output) => {
+                    writer.write_all(output.as_bytes())?;
+                    output != chunk
+                }
+                RomanizationResult::Edges(mut edges) => {
+                    for edge in &mut edges {
+                        let data = edge.get_data_mut();
+                        data.start += state.n_chars;
+                        data.end += state.n_chars;
+                        if let Some((start, end)) = &mut data.display {
+                            *start += state.n_chars;
+                            *end += state.n_chars;
+                        }
+                    }
+                    for edge in &edges {
+                        let json = if is_python {
+                            python_edge_json(edge.start(), edge.end(), edge.txt(), edge.r#type())
+                        } else {
+                            // Indented as an element of the pretty-printed array.
+                            serde_json::to_string_pretty(edge)?.replace('\n', "\n  ")
+                        };
+                        let separator = match (state.has_edges, is_python) {
+                            (false, true) => "",
+                            (true, true) => ", ",
+                            (false, false) => "\n  ",
+                            (true, false) => ",\n  ",
+                        };
+                        write!(writer, "{separator}{json}")?;
+                        state.has_edges = true;
+                    }
+                    state.n_chars += chunk.chars().count();
+                    true
+                }
+            }
+        };
+        if ends_line {
+            let rom_format = state.options.as_ref().unwrap_or(options).output.rom_format;
+            if rom_format != RomFormat::Str {
+                let close = if state.has_edges && !is_python {
+                    "\n]"
+                } else {
+                    "]"
+                };
+                writer.write_all(close.as_bytes())?;
+            }
+            writer.write_all(line_end.as_bytes())?;
+        }
+        Ok(changed)
+    }
+}
+
+/// Returns the text of a line of `romanize_file` input after any utterance ID.
+fn file_line_text<'a>(line: &'a str, options: &RomanizeOptions) -> &'a str {
+    if !options.output.utt_ids {
+        return line;
+    }
+    let rest = &line[line.find(char::is_whitespace).unwrap_or(line.len())..];
+    &rest[rest.chars().next().map_or(0, char::len_utf8)..]
+}
@@ -0,0 +1,255 @@
+//! Comparing romanized outputs line by line, e.g. to validate a new version of uroman against
+//! the output of an old one.
+
+use crate::utils::edit_distance;
+use crate::{RomanizationResult, Uroman, rom_format};
+
+/// The most cells of the alignment table of a line and the output of another romanizer for it,
+/// see [`Uroman::disagreements`]. Longer lines are not aligned.
+const MAX_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// A line on which a romanized output differs from its reference, see [`Comparison`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineDivergence {
+    /// The number of the line, from 1.
+    pub line: usize,
+    pub reference: String,
+    pub hypothesis: String,
+    /// The edit distance between the two lines, in chars.
+    pub distance: usize,
+}
+
+/// The differences between a romanized output and a reference, created by [`compare_outputs`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Comparison {
+    /// The lines of the longer of the two outputs.
+    pub lines: usize,
+    pub differing_lines: usize,
+    pub reference_chars: usize,
+    /// The sum of the edit distances of the lines, in chars.
+    pub distance: usize,
+    /// The lines with the largest edit distances, largest first and then in order.
+    pub divergences: Vec<LineDivergence>,
+}
+
+impl Comparison {
+    /// Whether the outputs are the same, apart from line endings.
+    pub fn is_identical(&self) -> bool {
+        self.differing_lines == 0
+    }
+
+    /// The character error rate: the edit distance divided by the chars of the reference.
+    /// It is `0.0` for identical outputs, and can be above `1.0`.
+    pub fn char_error_rate(&self) -> f64 {
+        if self.distance == 0 {
+            0.0
+        } else {
+            self.distance as f64 / self.reference_chars.max(1) as f64
+        }
+    }
+}
+
+/// Compares the romanized output `hypothesis` with `reference`, line by line, and keeps the
+/// `max_divergences` lines with the largest character edit distances.
+///
+/// Lines are aligned by number, and a line that is missing from the shorter output counts as
+/// empty. `\n` and `\r\n` line endings are the same.
+///
+/// # Example
+/// ```
+/// use uroman::compare_outputs;
+///
+/// let comparison = compare_outputs("Kyiv\nLviv\nOdesa\n", "Kyiv\r\nL'viv\nOdessa\n", 10);
+/// assert_eq!(comparison.differing_lines, 2);
+/// assert_eq!(comparison.distance, 2);
+/// assert_eq!(comparison.divergences[0].line, 2);
+/// assert_eq!(comparison.divergences[0].hypothesis, "L'viv");
+/// ```
+pub fn compare_outputs(reference: &str, hypothesis: &str, max_divergences: usize) -> Comparison {
+    let reference: Vec<&str> = reference.lines().collect();
+    let hypothesis: Vec<&str> = hypothesis.lines().collect();
+
+    let mut comparison = Comparison {
+        lines: reference.len().max(hypothesis.len()),
+        reference_chars: reference.iter().map(|line| line.chars().count()).sum(),
+        ..Comparison::default()
+    };
+    // The distance and index of each line that differs.
+    let mut differences: Vec<(usize, usize)> = Vec::new();
+    for i in 0..comparison.lines {
+        let (reference, hypothesis) = (line(&reference, i), line(&hypothesis, i));
+        if reference != hypothesis {
+            differences.push((edit_distance(reference, hypothesis), i));
+        }
+    }
+    comparison.differing_lines = differences.len();
+    comparison.distance = differences.iter().map(|&(distance, _)| distance).sum();
+
+    differences.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    comparison.divergences = differences
+        .into_iter()
+        .take(max_divergences)
+        .map(|(distance, i)| LineDivergence {
+            line: i + 1,
+            reference: line(&reference, i).to_string(),
+            hypothesis: line(&hypothesis, i).to_string(),
+            distance,
+        })
+        .collect();
+    comparison
+}
+
+/// Line `i` of `lines`, or an empty line if it is missing.
+fn line<'a>(lines: &[&'a str], i: usize) -> &'a str {
+    lines.get(i).copied().unwrap_or_default()
+}
+
+/// A span of a line that another romanizer romanizes differently, see
+/// [`Uroman::disagreements`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disagreement {
+    /// The chars of the line, or the whole line if it was too long to align.
+    pub orig: String,
+    /// Their romanization by this romanizer.
+    pub ours: String,
+    /// Their romanization by the other romanizer.
+    pub theirs: String,
+    /// The script of the chars, such as `Cyrillic`, or `Common` for chars without one. Empty
+    /// for a line that was too long to align.
+    pub script: String,
+    /// The provenance of the rule that romanized the chars, such as `ud` for the table
+    /// generated from the Unicode data, or else the type of their edge, such as `num`.
+    /// `unaligned` for a line that was too long to align.
+    pub source: String,
+}
+
+impl Uroman {
+    /// Romanizes `line` and finds the spans of it whose romanizations differ from `other`,
+    /// the romanization of `line` by another romanizer, e.g. the Python uroman.
+    ///
+    /// The romanizations are aligned char by char with the smallest edit distance, and the
+    /// chars of `other` are attributed to the edges of `line` they are aligned with, so a span
+    /// is an edge whose text differs from its share of `other`. Lines that are too long to
+    /// align are one span.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let disagreements = Uroman::new().disagreements("Київ", Some("ukr"), "Kiyiv");
+    /// assert_eq!(disagreements.len(), 1);
+    /// assert_eq!(disagreements[0].orig, "иї");
+    /// assert_eq!((&*disagreements[0].ours, &*disagreements[0].theirs), ("yi", "iyi"));
+    /// assert_eq!(disagreements[0].script, "Cyrillic");
+    /// ```
+    pub fn disagreements(&self, line: &str, lcode: Option<&str>, other: &str) -> Vec<Disagreement> {
+        let output = self.romanize_string::<rom_format::Edges>(line, lcode);
+        let RomanizationResult::Edges(edges) = output.result else {
+            unreachable!("the edges format returns edges");
+        };
+        let ours: String = edges.iter().map(|edge| edge.txt()).collect();
+        if ours == other {
+            return Vec::new();
+        }
+        let ours_chars: Vec<char> = ours.chars().collect();
+        let other_chars: Vec<char> = other.chars().collect();
+        let Some(positions_of_other) = align_chars(&ours_chars, &other_chars) else {
+            return vec![Disagreement {
+                orig: line.to_string(),
+                ours,
+                theirs: other.to_string(),
+                script: String::new(),
+                source: "unaligned".to_string(),
+            }];
+        };
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut disagreements = Vec::new();
+        // The chars of `other` at the positions below `claimed` belong to the edges before.
+        let (mut rom_start, mut claimed) = (0, 0);
+        for (i, edge) in edges.iter().enumerate() {
+            let rom_end = rom_start + edge.txt().chars().count();
+            // An edge without text takes the chars inserted where it is, and the last edge
+            // those after the end.
+            let end = if i + 1 == edges.len() {
+                usize::MAX
+            } else if rom_end == rom_start {
+                (2 * rom_start + 1).max(claimed)
+            } else {
+                2 * rom_end
+            };
+            let positions = claimed..end;
+            claimed = end;
+            rom_start = rom_end;
+            let theirs: String = other_chars
+                .iter()
+                .zip(&positions_of_other)
+                .filter(|&(_, position)| positions.contains(position))
+                .map(|(&c, _)| c)
+                .collect();
+            if theirs == edge.txt() {
+                continue;
+            }
+            let orig: String = chars[edge.start()..edge.end()].iter().collect();
+            let script = orig
+                .chars()
+                .map(|c| self.chr_script_name(c))
+                .find(|script| !script.is_empty())
+                .unwrap_or("Common");
+            let source = self
+                .rule_provenance(&orig, edge.txt())
+                .unwrap_or_else(|| edge.r#type());
+            disagreements.push(Disagreement {
+                script: script.to_string(),
+                source: source.to_string(),
+                orig,
+                ours: edge.txt().to_string(),
+                theirs,
+            });
+        }
+        disagreements
+    }
+}
+
+/// Aligns `a` and `b` with the smallest edit distance, and returns the position in `a` of
+/// each char of `b`: `2 * i + 1` for a char aligned with char `i` of `a`, and `2 * i` for one
+/// inserted before it, or at the end for `i == a.len()`. Returns `None` if the strings are too
+/// long.
+fn align_chars(a: &[char], b: &[char]) -> Option<Vec<usize>> {
+    let width = b.len() + 1;
+    if (a.len() + 1).checked_mul(width)? > MAX_ALIGNMENT_CELLS {
+        return None;
+    }
+    let mut distances: Vec<usize> = (0..width).collect();
+    distances.resize((a.len() + 1) * width, 0);
+    for i in 1..=a.len() {
+        distances[i * width] = i;
+        for j in 1..width {
+            let substitution =
+                distances[(i - 1) * width + j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let deletion = distances[(i - 1) * width + j] + 1;
+            let insertion = distances[i * width + j - 1] + 1;
+            distances[i * width + j] = substitution.min(deletion).min(insertion);
+        }
+    }
+
+    let mut positions = vec![0; b.len()];
+    let (mut i, mut j) = (a.len(), b.len());
+    while j > 0 {
+        let distance = distances[i * width + j];
+        if i > 0
+            && distance == distances[(i - 1) * width + j - 1] + usize::from(a[i - 1] != b[j - 1])
+        {
+            positions[j - 1] = 2 * (i - 1) + 1;
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && distance == distances[(i - 1) * width + j] + 1 {
+            i -= 1;
+        } else {
+            positions[j - 1] = 2 * i;
+            j -= 1;
+        }
+    }
+    Some(positions)
+}
@@ -0,0 +1,76 @@
+//! Formatting output as other implementations of uroman do, see [`Compat`].
+
+use crate::{Compat, Edge, RomanizationError, RomanizationResult};
+
+impl RomanizationResult {
+    /// Formats the result like [`RomanizationResult::to_output_string`], or as `compat` does
+    /// if it is given.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Compat, RomFormat, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let result = uroman.romanize_with_format("Київ", Some("ukr"), Some(RomFormat::Edges));
+    /// assert_eq!(
+    ///     result.to_compat_output_string(Some(Compat::PythonV1_3)).unwrap(),
+    ///     r#"[[0, 1, "K", "rom"], [1, 3, "yi", "rom"], [3, 4, "v", "rom"]]"#
+    /// );
+    /// ```
+    pub fn to_compat_output_string(
+        &self,
+        compat: Option<Compat>,
+    ) -> Result<String, RomanizationError> {
+        match (self, compat) {
+            (RomanizationResult::Edges(edges), Some(Compat::PythonV1_3)) => {
+                Ok(python_edges_json(edges))
+            }
+            _ => self.to_output_string(),
+        }
+    }
+}
+
+/// Formats `edges` as the Python uroman does, as a JSON array of edge arrays.
+fn python_edges_json(edges: &[Edge]) -> String {
+    let edges: Vec<String> = edges
+        .iter()
+        .map(|edge| python_edge_json(edge.start(), edge.end(), edge.txt(), edge.r#type()))
+        .collect();
+    format!("[{}]", edges.join(", "))
+}
+
+/// Formats an edge as the Python uroman does, as `json.dumps([start, end, txt, type])`.
+pub(crate) fn python_edge_json(start: usize, end: usize, txt: &str, r#type: &str) -> String {
+    format!(
+        "[{start}, {end}, {}, {}]",
+        python_json_string(txt),
+        python_json_string(r#type)
+    )
+}
+
+/// Quotes `s` as Python's `json.dumps` does, which escapes all non-ASCII chars, those beyond
+/// the BMP as surrogate pairs.
+fn python_json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '\u{08}' => json.push_str("\\b"),
+            '\u{0C}' => json.push_str("\\f"),
+            ' '..='~' => json.push(c),
+            _ => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    json.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,109 @@
+//! Custom rules loaded at runtime, in the format of the hand-written table and with the same
+//! conditions on their context as its rules.
+
+use std::io::BufRead;
+
+use crate::cache::MemoMap;
+use crate::data_table::{compile_rom_line, is_skipped_line, slot_value_in_double_colon_del_list};
+use crate::rule_matcher::RuleMatcher;
+use crate::utils::LossyLines;
+use crate::{InvalidUtf8Policy, OVERRIDE_PROV, RomanizationError, Uroman};
+
+/// The conditions on the position of the romanized chars in their word that a rule can have,
+/// as slots without a value, e.g. `::s ντ ::t d ::use-only-at-start-of-word`. Words are runs
+/// of letters and combining marks.
+pub const RULE_CONDITIONS: [&str; 5] = [
+    "use-only-at-start-of-word",
+    "dont-use-at-start-of-word",
+    "use-only-at-end-of-word",
+    "dont-use-at-end-of-word",
+    "use-only-for-whole-word",
+];
+
+impl Uroman {
+    /// Loads rules in the format of the hand-written table, one per line, on top of the rules
+    /// loaded so far. `#` starts a comment line. A rule has the chars it romanizes in `::s` and
+    /// their romanization in `::t`, and can have these conditions on their context:
+    ///
+    /// - `::lcode ell`: only for text in one of the comma-separated languages.
+    /// - The [`RULE_CONDITIONS`] on the position of the chars in their word, e.g.
+    ///   `::use-only-at-end-of-word`. A rule can have several of them.
+    /// - `::t-end-of-syllable kh`: another romanization for the chars at the end of a syllable.
+    ///
+    /// Rules can also have alternatives in `::t-alt`, and slots that are only comments, such as
+    /// `::comment` and `::example`. A rule replaces the rules for the same chars with the same
+    /// conditions, whichever table they come from, and is preferred by
+    /// [`RulePriority::Provenance`](crate::RulePriority::Provenance). Of the rules that can
+    /// romanize the same chars, those with the most conditions are preferred by default, so a
+    /// rule with a condition overrides the rules without one where its condition holds.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::InvalidRule`] for a line without `::s`, or with a
+    /// condition on the context that rules don't have, such as a misspelled one, wrapped in
+    /// [`RomanizationError::AtLine`]. No rules are loaded then.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let mut uroman = Uroman::new();
+    /// let romanize = |uroman: &Uroman| {
+    ///     let output = uroman.romanize_string::<rom_format::Str>("ντομάτα κέντρο", Some("ell"));
+    ///     output.to_output_string()
+    /// };
+    /// assert_eq!(romanize(&uroman), "ndomata kendro");
+    /// let rules = "::s ντ ::t d ::lcode ell ::use-only-at-start-of-word\n";
+    /// uroman.load_rules(rules.as_bytes()).unwrap();
+    /// assert_eq!(romanize(&uroman), "domata kendro");
+    /// ```
+    pub fn load_rules<R: BufRead>(&mut self, reader: R) -> Result<(), RomanizationError> {
+        let mut lines = LossyLines::new(reader)
+            .with_invalid_utf8(InvalidUtf8Policy::Error)
+            .quiet();
+        let mut table = String::new();
+        while let Some(line) = lines.next_line()? {
+            if is_skipped_line(line) {
+                continue;
+            }
+            let record = check_rule(line).and_then(|()| {
+                compile_rom_line(line, "rom").ok_or_else(|| invalid_rule(line, "no ::s"))
+            });
+            table.push_str(&record.map_err(|e| lines.error_at_current_line(e))?);
+            table.push('\n');
+        }
+        self.load_rom_file(&table, OVERRIDE_PROV);
+        self.rule_matcher = RuleMatcher::new(&self.rom_rules);
+        // Abugida romanizations are derived from the rules.
+        self.abugida_cache = MemoMap::default();
+        Ok(())
+    }
+}
+
+/// Checks that the conditions on the context of the rule of `line` are ones that rules have.
+fn check_rule(line: &str) -> Result<(), RomanizationError> {
+    if slot_value_in_double_colon_del_list(line, "s").is_none_or(str::is_empty) {
+        return Err(invalid_rule(line, "no ::s"));
+    }
+    let slots = line
+        .split("::")
+        .skip(1)
+        .filter_map(|slot| slot.split_whitespace().next());
+    for slot in slots {
+        let is_condition = slot.starts_with("use-") || slot.starts_with("dont-use-");
+        if is_condition && !RULE_CONDITIONS.contains(&slot) {
+            let reason = format!(
+                "unknown condition ::{slot}, expected one of ::{}",
+                RULE_CONDITIONS.join(", ::")
+            );
+            return Err(invalid_rule(line, &reason));
+        }
+    }
+    Ok(())
+}
+
+fn invalid_rule(line: &str, reason: &str) -> RomanizationError {
+    RomanizationError::InvalidRule {
+        line: line.to_string(),
+        reason: reason.to_string(),
+    }
+}
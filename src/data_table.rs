@@ -0,0 +1,318 @@
+//! Compact record format for the embedded data tables.
+//!
+//! `build.rs` compiles the line-oriented `data/*.txt` files into records of
+//! `FIELD_SEP`-separated fields, one record per line, so that loading them at
+//! runtime is a plain split instead of repeated `::slot` scanning.
+//!
+//! This module is shared with `build.rs` via `#[path]`, so it must not depend on
+//! the rest of the crate.
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+/// Separates the fields of a record (ASCII unit separator).
+pub const FIELD_SEP: char = '\u{1f}';
+
+/// Marks an optional field as present; an empty field means `None`.
+const PRESENT: char = '=';
+
+// Bits of the flags field of a rom record.
+pub const USE_ONLY_AT_START_OF_WORD: u32 = 1 << 0;
+pub const DONT_USE_AT_START_OF_WORD: u32 = 1 << 1;
+pub const USE_ONLY_AT_END_OF_WORD: u32 = 1 << 2;
+pub const DONT_USE_AT_END_OF_WORD: u32 = 1 << 3;
+pub const USE_ONLY_FOR_WHOLE_WORD: u32 = 1 << 4;
+pub const IS_MINUS_SIGN: u32 = 1 << 5;
+pub const IS_PLUS_SIGN: u32 = 1 << 6;
+pub const IS_DECIMAL_POINT: u32 = 1 << 7;
+pub const FRACTION_CONNECTOR: u32 = 1 << 8;
+pub const PERCENTAGE_MARKER: u32 = 1 << 9;
+pub const INT_FRAC_CONNECTOR: u32 = 1 << 10;
+pub const IS_LARGE_POWER: u32 = 1 << 11;
+
+const FLAG_SLOTS: [(&str, u32); 12] = [
+    ("use-only-at-start-of-word", USE_ONLY_AT_START_OF_WORD),
+    ("dont-use-at-start-of-word", DONT_USE_AT_START_OF_WORD),
+    ("use-only-at-end-of-word", USE_ONLY_AT_END_OF_WORD),
+    ("dont-use-at-end-of-word", DONT_USE_AT_END_OF_WORD),
+    ("use-only-for-whole-word", USE_ONLY_FOR_WHOLE_WORD),
+    ("is-minus-sign", IS_MINUS_SIGN),
+    ("is-plus-sign", IS_PLUS_SIGN),
+    ("is-decimal-point", IS_DECIMAL_POINT),
+    ("fraction-connector", FRACTION_CONNECTOR),
+    ("percentage-marker", PERCENTAGE_MARKER),
+    ("int-frac-connector", INT_FRAC_CONNECTOR),
+    ("is-large-power", IS_LARGE_POWER),
+];
+
+/// Captures the value associated with a `::slot` in a line.
+///
+/// This function is a Rust port of the Python version's `slot_value_in_double_colon_del_list`.
+///
+/// # Example
+/// `slot_value_in_double_colon_del_list("::s1 of course ::s2 ::cost 0.3", "cost")` returns `Some("0.3")`.
+pub fn slot_value_in_double_colon_del_list<'a>(line: &'a str, slot: &str) -> Option<&'a str> {
+    let search_str = format!("::{slot}");
+    if let Some(start_index) = line.find(&search_str) {
+        let remaining = &line[start_index + search_str.len()..];
+        if let Some(end_index) = remaining.find("::") {
+            Some(remaining[..end_index].trim())
+        } else {
+            Some(remaining.trim())
+        }
+    } else {
+        None
+    }
+}
+
+/// Checks if a slot exists in the line, even if it has no value.
+pub fn has_value_in_double_colon_del_list(line: &str, slot: &str) -> bool {
+    slot_value_in_double_colon_del_list(line, slot).is_some()
+}
+
+/// Removes matching quotes from the start and end of a string.
+///
+/// Handles single quotes, double quotes, and curly double quotes.
+pub fn dequote_string(s: &str) -> &str {
+    let trimmed = s.trim();
+    let mut chars = trimmed.chars();
+    if let (Some(open_quote), Some(close_quote)) = (chars.next(), chars.next_back())
+        && matches!(
+            (open_quote, close_quote),
+            ('\'', '\'') | ('"', '"') | ('“', '”')
+        )
+    {
+        return chars.as_str();
+    }
+    s
+}
+
+/// Removes the tone accents from a pinyin syllable, e.g. `líng` -> `ling`.
+pub fn de_accent_pinyin(pinyin_with_accent: &str) -> String {
+    // NFD decomposition separates base chars and accents.
+    pinyin_with_accent
+        .nfd()
+        .filter(|c| !matches!(c.general_category_group(), GeneralCategoryGroup::Mark))
+        .collect::<String>()
+        .replace('ü', "u")
+}
+
+/// Returns `true` for lines that carry no data.
+pub fn is_skipped_line(line: &str) -> bool {
+    line.starts_with('#') || line.trim().is_empty()
+}
+
+/// Compiles every data line of `content` with `compile_line`, one record per line.
+pub fn compile_table(content: &str, compile_line: impl Fn(&str) -> Option<String>) -> String {
+    let mut table = String::with_capacity(content.len() / 2);
+    for line in content.lines().filter(|line| !is_skipped_line(line)) {
+        if let Some(record) = compile_line(line) {
+            table.push_str(&record);
+            table.push('\n');
+        }
+    }
+    table
+}
+
+fn push_opt(record: &mut String, value: Option<&str>) {
+    record.push(FIELD_SEP);
+    if let Some(v) = value {
+        record.push(PRESENT);
+        record.push_str(v);
+    }
+}
+
+fn parse_opt(field: &str) -> Option<&str> {
+    field.strip_prefix(PRESENT)
+}
+
+/// Compiles one line of a romanization table.
+///
+/// `file_format` is `"u2r"` for `::u <hex> ::r <rom>` lines and `"rom"` for `::s <src> ::t <tgt>` lines.
+pub fn compile_rom_line(line: &str, file_format: &str) -> Option<String> {
+    let slot = |name| slot_value_in_double_colon_del_list(line, name);
+
+    let (s, t) = if file_format == "u2r" {
+        let cp = u32::from_str_radix(slot("u")?, 16).ok()?;
+        (
+            char::from_u32(cp)?.to_string(),
+            slot("r").map(dequote_string),
+        )
+    } else {
+        (
+            dequote_string(slot("s")?).to_string(),
+            slot("t").map(dequote_string),
+        )
+    };
+
+    let flags = FLAG_SLOTS
+        .iter()
+        .filter(|(name, _)| has_value_in_double_colon_del_list(line, name))
+        .fold(0, |acc, (_, bit)| acc | bit);
+
+    let mut record = s;
+    push_opt(&mut record, t);
+    push_opt(&mut record, slot("lcode"));
+    record.push(FIELD_SEP);
+    record.push_str(&flags.to_string());
+    push_opt(&mut record, slot("t-alt"));
+    push_opt(&mut record, slot("num"));
+    push_opt(&mut record, slot("t-end-of-syllable").map(dequote_string));
+    if file_format == "u2r" {
+        push_opt(&mut record, slot("tone-mark"));
+        push_opt(&mut record, slot("syllable-info"));
+        push_opt(&mut record, slot("pic"));
+        push_opt(&mut record, slot("name"));
+    }
+    Some(record)
+}
+
+/// A parsed record of a romanization table, see `compile_rom_line`.
+#[derive(Debug)]
+pub struct RomRecord<'a> {
+    pub s: &'a str,
+    pub t: Option<&'a str>,
+    pub lcode: Option<&'a str>,
+    pub flags: u32,
+    pub t_alt: Option<&'a str>,
+    pub num: Option<&'a str>,
+    pub t_at_end_of_syllable: Option<&'a str>,
+    pub tone_mark: Option<&'a str>,
+    pub syllable_info: Option<&'a str>,
+    pub pic: Option<&'a str>,
+    pub name: Option<&'a str>,
+}
+
+impl<'a> RomRecord<'a> {
+    pub fn parse(record: &'a str) -> Option<Self> {
+        let mut fields = record.split(FIELD_SEP);
+        Some(Self {
+            s: fields.next()?,
+            t: fields.next().and_then(parse_opt),
+            lcode: fields.next().and_then(parse_opt),
+            flags: fields.next()?.parse().ok()?,
+            t_alt: fields.next().and_then(parse_opt),
+            num: fields.next().and_then(parse_opt),
+            t_at_end_of_syllable: fields.next().and_then(parse_opt),
+            tone_mark: fields.next().and_then(parse_opt),
+            syllable_info: fields.next().and_then(parse_opt),
+            pic: fields.next().and_then(parse_opt),
+            name: fields.next().and_then(parse_opt),
+        })
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+}
+
+/// Compiles one line of `Chinese_to_Pinyin.txt` into `chinese FIELD_SEP rom`.
+pub fn compile_pinyin_line(line: &str) -> Option<String> {
+    let (chinese, pinyin_with_accent) = line.split_once(char::is_whitespace)?;
+    Some(format!(
+        "{chinese}{FIELD_SEP}{}",
+        de_accent_pinyin(pinyin_with_accent)
+    ))
+}
+
+/// Compiles one line of `UnicodeDataProps*.txt`.
+///
+/// Fields: script name, chars, vowel signs, medial consonant signs, viramas.
+pub fn compile_props_line(line: &str) -> Option<String> {
+    let slot = |name| slot_value_in_double_colon_del_list(line, name);
+    let mut record = slot("script-name")?.to_string();
+    push_opt(&mut record, slot("char"));
+    push_opt(&mut record, slot("vowel-sign"));
+    push_opt(&mut record, slot("medial-consonant-sign"));
+    push_opt(&mut record, slot("sign-virama"));
+    Some(record)
+}
+
+/// A parsed record of a props table, see `compile_props_line`.
+#[derive(Debug)]
+pub struct PropsRecord<'a> {
+    pub script_name: &'a str,
+    pub chars: Option<&'a str>,
+    pub vowel_signs: Option<&'a str>,
+    pub medial_consonant_signs: Option<&'a str>,
+    pub viramas: Option<&'a str>,
+}
+
+impl<'a> PropsRecord<'a> {
+    pub fn parse(record: &'a str) -> Option<Self> {
+        let mut fields = record.split(FIELD_SEP);
+        Some(Self {
+            script_name: fields.next()?,
+            chars: fields.next().and_then(parse_opt),
+            vowel_signs: fields.next().and_then(parse_opt),
+            medial_consonant_signs: fields.next().and_then(parse_opt),
+            viramas: fields.next().and_then(parse_opt),
+        })
+    }
+}
+
+/// Compiles one line of `Scripts.txt`.
+///
+/// Fields: script name, direction, abugida default vowels, alt script names, languages.
+pub fn compile_script_line(line: &str) -> Option<String> {
+    let slot = |name| slot_value_in_double_colon_del_list(line, name);
+    let mut record = slot("script-name")?.to_string();
+    push_opt(&mut record, slot("direction"));
+    push_opt(&mut record, slot("abugida-default-vowel"));
+    push_opt(&mut record, slot("alt-script-name"));
+    push_opt(&mut record, slot("language"));
+    Some(record)
+}
+
+/// A parsed record of the scripts table, see `compile_script_line`.
+#[derive(Debug)]
+pub struct ScriptRecord<'a> {
+    pub script_name: &'a str,
+    pub direction: Option<&'a str>,
+    pub abugida_default_vowels: Option<&'a str>,
+    pub alt_script_names: Option<&'a str>,
+    pub languages: Option<&'a str>,
+}
+
+impl<'a> ScriptRecord<'a> {
+    pub fn parse(record: &'a str) -> Option<Self> {
+        let mut fields = record.split(FIELD_SEP);
+        Some(Self {
+            script_name: fields.next()?,
+            direction: fields.next().and_then(parse_opt),
+            abugida_default_vowels: fields.next().and_then(parse_opt),
+            alt_script_names: fields.next().and_then(parse_opt),
+            languages: fields.next().and_then(parse_opt),
+        })
+    }
+}
+
+/// Compiles one line of `ISO-639-3-list.txt`.
+///
+/// Fields: language code, deprecated language codes, language names.
+pub fn compile_lang_code_line(line: &str) -> Option<String> {
+    let slot = |name| slot_value_in_double_colon_del_list(line, name);
+    let mut record = slot("lang-code")?.to_string();
+    push_opt(&mut record, slot("deprecated-lang-codes"));
+    push_opt(&mut record, slot("lang-names"));
+    Some(record)
+}
+
+/// A parsed record of the language code table, see `compile_lang_code_line`.
+#[derive(Debug)]
+pub struct LangCodeRecord<'a> {
+    pub lang_code: &'a str,
+    pub deprecated_lang_codes: Option<&'a str>,
+    pub lang_names: Option<&'a str>,
+}
+
+impl<'a> LangCodeRecord<'a> {
+    pub fn parse(record: &'a str) -> Option<Self> {
+        let mut fields = record.split(FIELD_SEP);
+        Some(Self {
+            lang_code: fields.next()?,
+            deprecated_lang_codes: fields.next().and_then(parse_opt),
+            lang_names: fields.next().and_then(parse_opt),
+        })
+    }
+}
@@ -0,0 +1,71 @@
+//! Detecting the language code of a text, for romanizing without a given `lcode`.
+
+use std::cmp::Reverse;
+
+use whatlang::Lang;
+
+use crate::hash::HashMap;
+use crate::{RomFormat, RomanizationResult, Uroman};
+
+impl Uroman {
+    /// Detects the [ISO 639-3 language code](https://www.loc.gov/standards/iso639-2/php/code_list.php)
+    /// of `s`, which changes how some rules romanize Cyrillic, Arabic and Indic text.
+    ///
+    /// The language is detected with [whatlang](https://crates.io/crates/whatlang) if it is
+    /// confident. Otherwise, the language of the most frequent script in `s` is used if that
+    /// script is written in only one language (e.g. Korean for Hangul).
+    ///
+    /// Returns `None` if neither is conclusive.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// assert_eq!(uroman.detect_lcode("Привіт, як у тебе справи сьогодні?"), Some("ukr"));
+    /// assert_eq!(uroman.detect_lcode("한국어"), Some("kor"));
+    /// ```
+    pub fn detect_lcode(&self, s: &str) -> Option<&'static str> {
+        if let Some(info) = whatlang::detect(s)
+            && info.is_reliable()
+        {
+            return Some(info.lang().code());
+        }
+
+        let script = self
+            .scripts
+            .get(&self.dominant_script_name(s)?.to_lowercase())?;
+        let [language] = script.languages.as_slice() else {
+            return None;
+        };
+        Lang::all()
+            .iter()
+            .find(|lang| lang.eng_name() == language)
+            .map(|lang| lang.code())
+    }
+
+    /// Romanizes `s` like [`Uroman::romanize_with_format`], with the language code detected by
+    /// [`Uroman::detect_lcode`].
+    pub fn romanize_with_detected_lcode(
+        &self,
+        s: &str,
+        rom_format: Option<RomFormat>,
+    ) -> RomanizationResult {
+        self.romanize_with_format(s, self.detect_lcode(s), rom_format)
+    }
+
+    /// Returns the name of the script of most of the non-ASCII letters in `s`.
+    fn dominant_script_name(&self, s: &str) -> Option<&str> {
+        let mut counts: HashMap<&str, usize> = HashMap::default();
+        for c in s.chars().filter(|c| !c.is_ascii() && c.is_alphabetic()) {
+            let script_name = self.chr_script_name(c);
+            if !script_name.is_empty() {
+                *counts.entry(script_name).or_default() += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .max_by_key(|&(script_name, count)| (count, Reverse(script_name)))
+            .map(|(script_name, _)| script_name)
+    }
+}
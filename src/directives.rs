@@ -0,0 +1,114 @@
+//! Directives at the start of lines of `romanize_file` input, such as `::lcode ukr`, which set
+//! how the rest of the line is romanized.
+
+use crate::{RomFormat, RomanizeOptions, Uroman};
+
+/// The directives at the start of a line, see [`split_directives`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct LineDirectives<'a> {
+    /// `::lcode <code>`: the language code of the line.
+    pub lcode: Option<&'a str>,
+    /// `::format <str|edges|alts|lattice>`: the output format of the line.
+    pub rom_format: Option<RomFormat>,
+    /// `::no-numbers`: leave the numbers of the line as they are.
+    pub no_numbers: bool,
+}
+
+impl LineDirectives<'_> {
+    /// Returns `options` with the settings of the directives.
+    pub fn apply(&self, options: &RomanizeOptions, uroman: &Uroman) -> RomanizeOptions {
+        let mut options = options.clone();
+        if let Some(lcode) = self.lcode {
+            options.output.lcode = Some(lcode.to_string());
+        }
+        if let Some(rom_format) = self.rom_format {
+            options.output.rom_format = rom_format;
+        }
+        if self.no_numbers {
+            let subsystems = options.output.subsystems.unwrap_or(uroman.subsystems);
+            options.output.subsystems = Some(subsystems.with_numbers(false));
+        }
+        options
+    }
+
+    /// The texts of the meta edges that echo the directives in the edge formats, e.g.
+    /// `lcode: ukr`.
+    pub fn meta_edges(&self) -> Vec<String> {
+        let mut metas = Vec::new();
+        if let Some(lcode) = self.lcode {
+            metas.push(format!("lcode: {lcode}"));
+        }
+        if let Some(rom_format) = self.rom_format {
+            metas.push(format!("format: {}", rom_format_name(rom_format)));
+        }
+        if self.no_numbers {
+            metas.push("no-numbers".to_string());
+        }
+        metas
+    }
+}
+
+/// Splits `line` into its directives, including the whitespace after them, the directives and
+/// the text after them. Returns `None` if `line` doesn't start with a directive.
+///
+/// Directives can come in any order, and their names are case-insensitive, with `_` for `-`.
+/// As in the Python uroman, `::lcode` and any whitespace after it is only a directive if a code
+/// follows, and other words starting with `::`, such as unknown directives, start the text. So
+/// do a repeated directive and a `::format` without a known format, so that corpus lines that
+/// happen to start like directives are romanized as text.
+pub(crate) fn split_directives(line: &str) -> Option<(&str, LineDirectives<'_>, &str)> {
+    let mut directives = LineDirectives::default();
+    let mut text = line;
+    while let Some(rest) = text.strip_prefix("::") {
+        let name_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase().replace('_', "-");
+        let after_name = &rest[name_len..];
+        let value = after_name.trim_start();
+        let (value, after_value) =
+            value.split_at(value.find(char::is_whitespace).unwrap_or(value.len()));
+        let after = match name.as_str() {
+            "lcode" if value.is_empty() || directives.lcode.is_some() => break,
+            "lcode" => {
+                directives.lcode = Some(value);
+                after_value
+            }
+            "format" => match parse_rom_format(value) {
+                Some(rom_format) if directives.rom_format.is_none() => {
+                    directives.rom_format = Some(rom_format);
+                    after_value
+                }
+                _ => break,
+            },
+            "no-numbers" if !directives.no_numbers => {
+                directives.no_numbers = true;
+                after_name
+            }
+            _ => break,
+        };
+        text = after.trim_start();
+    }
+    if text.len() == line.len() {
+        return None;
+    }
+    Some((&line[..line.len() - text.len()], directives, text))
+}
+
+fn parse_rom_format(s: &str) -> Option<RomFormat> {
+    [
+        RomFormat::Str,
+        RomFormat::Edges,
+        RomFormat::Alts,
+        RomFormat::Lattice,
+    ]
+    .into_iter()
+    .find(|&rom_format| s.eq_ignore_ascii_case(rom_format_name(rom_format)))
+}
+
+fn rom_format_name(rom_format: RomFormat) -> &'static str {
+    match rom_format {
+        RomFormat::Str => "str",
+        RomFormat::Edges => "edges",
+        RomFormat::Alts => "alts",
+        RomFormat::Lattice => "lattice",
+    }
+}
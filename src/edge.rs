@@ -1,14 +1,32 @@
-use crate::{Uroman, Value};
+use crate::intern::intern;
+use crate::Uroman;
 use num_rational::Ratio;
 use serde::Serialize;
+use std::borrow::Cow;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
+/// The span and romanization of an edge.
+///
+/// `txt` borrows from the rule tables or the input where it can, so building a lattice
+/// doesn't copy every candidate romanization.
 #[derive(Debug, Clone, Serialize, PartialEq, PartialOrd)]
-pub struct EdgeData {
+#[non_exhaustive]
+pub struct EdgeData<'a> {
     pub start: usize,
     pub end: usize,
-    pub txt: String,
-    pub r#type: String,
+    pub txt: Cow<'a, str>,
+    pub r#type: Arc<str>,
+    /// The span of positions that the chars of the edge are shown at in right-to-left or
+    /// mixed-direction text, see [`Uroman::add_display_positions`]. `None` unless requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<(usize, usize)>,
+    /// The conditions on the context of the rule that romanized the edge, such as
+    /// `lcode: ukr` or `use-only-at-start-of-word`, empty for a rule without any, see
+    /// [`Uroman::with_rule_conditions`]. `None` unless requested, and for edges that are not
+    /// romanized by a single rule.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, PartialOrd, Serialize)]
@@ -18,13 +36,22 @@ pub struct NumData {
     pub fraction: Option<Ratio<i64>>,
     pub num_base: Option<i64>,
     pub base_multiplier: Option<f64>,
-    pub script: Option<String>,
+    pub script: Option<Arc<str>>,
     pub is_large_power: bool,
     pub active: bool,
     pub value_s: Option<String>,
     pub n_decimals: Option<usize>,
 }
 
+/// Returns the fraction `numer/denom`, or `None` if it has no value or can't be reduced
+/// without overflowing.
+pub(crate) fn checked_ratio(numer: i64, denom: i64) -> Option<Ratio<i64>> {
+    if denom == 0 || numer == i64::MIN || denom == i64::MIN {
+        return None;
+    }
+    Some(Ratio::new(numer, denom))
+}
+
 /// A dedicated struct for flexibly updating fields of a `NumData`.
 /// This mimics Python's keyword arguments, allowing partial updates.
 #[derive(Default, Debug)]
@@ -33,8 +60,8 @@ pub struct NumDataUpdates {
     pub fraction: Option<Ratio<i64>>,
     pub num_base: Option<i64>,
     pub base_multiplier: Option<f64>,
-    pub r#type: Option<String>,
-    pub script: Option<String>,
+    pub r#type: Option<Arc<str>>,
+    pub script: Option<Arc<str>>,
     pub is_large_power: Option<bool>,
     pub active: Option<bool>,
     pub n_decimals: Option<usize>,
@@ -43,13 +70,16 @@ pub struct NumDataUpdates {
 }
 
 /// A unified Edge type.
+///
+/// Edges in romanization output own their text and are `Edge<'static>`, see
+/// [`Edge::into_owned`].
 #[derive(Debug, Clone, Serialize, PartialOrd)]
-pub enum Edge {
-    Regular(EdgeData),
-    Numeric { data: EdgeData, num_data: NumData },
+pub enum Edge<'a> {
+    Regular(EdgeData<'a>),
+    Numeric { data: EdgeData<'a>, num_data: NumData },
 }
 
-impl Hash for Edge {
+impl Hash for Edge<'_> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         let d = self.get_data();
         d.start.hash(state);
@@ -59,7 +89,7 @@ impl Hash for Edge {
     }
 }
 
-impl PartialEq for Edge {
+impl PartialEq for Edge<'_> {
     fn eq(&self, other: &Self) -> bool {
         let d1 = self.get_data();
         let d2 = other.get_data();
@@ -67,98 +97,49 @@ impl PartialEq for Edge {
     }
 }
 
-impl Eq for Edge {}
+impl Eq for Edge<'_> {}
 
-impl Edge {
+impl<'a> Edge<'a> {
     /// Creates a regular edge.
-    pub fn new_regular(start: usize, end: usize, txt: String, r#type: String) -> Self {
+    pub fn new_regular(
+        start: usize,
+        end: usize,
+        txt: impl Into<Cow<'a, str>>,
+        r#type: impl AsRef<str>,
+    ) -> Self {
         Edge::Regular(EdgeData {
             start,
             end,
-            txt,
-            r#type,
+            txt: txt.into(),
+            r#type: intern(r#type.as_ref()),
+            display: None,
+            conditions: None,
         })
     }
 
     /// Creates an initial numeric edge from `uroman.num_props`.
-    pub fn new_numeric(start: usize, end: usize, char: char, uroman: &Uroman) -> Option<Self> {
-        let props_map = uroman.num_props.get(&char.to_string())?;
-
-        let rom_text = props_map
-            .get("rom")
-            .and_then(|v| match v {
-                Value::String(s) => Some(s.clone()),
-                _ => None,
-            })
-            .unwrap_or_else(|| char.to_string());
-
-        let value = props_map.get("value").and_then(|v| match v {
-            Value::Int(i) => Some(*i as f64),
-            Value::Float(f) => Some(*f),
-            _ => None,
-        });
-
-        let fraction = props_map.get("fraction").and_then(|v| match v {
-            Value::String(s) => s
-                .split_once('/')
-                .and_then(|(num, den)| Some(Ratio::new(num.parse().ok()?, den.parse().ok()?))),
-            Value::Array(arr) if arr.len() == 2 => {
-                if let (Some(Value::Int(num)), Some(Value::Int(den))) = (arr.first(), arr.get(1)) {
-                    if *den != 0 {
-                        Some(Ratio::new(*num, *den))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            },
-            _ => None,
-        });
-
-        let r#type = props_map
-            .get("type")
-            .and_then(|v| match v {
-                Value::String(s) => Some(s.clone()),
-                _ => None,
-            })
-            .unwrap_or_default();
-
-        let is_large_power = props_map
-            .get("is-large-power")
-            .is_some_and(|v| matches!(v, Value::Int(1)));
-
-        let num_base = props_map.get("base").and_then(|v| match v {
-            Value::Int(i) => Some(*i),
-            _ => None,
-        });
-
-        let base_multiplier = props_map.get("mult").and_then(|v| match v {
-            Value::Int(i) => Some(*i as f64),
-            Value::Float(f) => Some(*f),
-            _ => None,
-        });
-
-        let script = props_map.get("script").and_then(|v| match v {
-            Value::String(s) => Some(s.clone()),
-            _ => None,
-        });
-
+    pub fn new_numeric(start: usize, end: usize, char: char, uroman: &'a Uroman) -> Option<Self> {
+        let prop = uroman.num_props.get(&char)?;
         let edge = Edge::Numeric {
             data: EdgeData {
                 start,
                 end,
-                txt: rom_text,
-                r#type,
+                txt: match &prop.rom {
+                    Some(rom) => Cow::Borrowed(rom),
+                    None => Cow::Owned(char.to_string()),
+                },
+                r#type: prop.r#type.clone(),
+                display: None,
+                conditions: None,
             },
             num_data: NumData {
                 orig_txt: char.to_string(),
-                value,
-                fraction,
-                num_base,
-                base_multiplier,
-                script,
-                is_large_power,
+                value: prop.value,
+                fraction: prop.fraction,
+                num_base: prop.num_base,
+                base_multiplier: prop.base_multiplier,
+                script: prop.script.clone(),
+                is_large_power: prop.is_large_power,
                 active: true,
                 ..Default::default()
             },
@@ -181,8 +162,8 @@ impl Edge {
         start: usize,
         end: usize,
         value: f64,
-        e_type: String,
-        script: Option<String>,
+        e_type: impl AsRef<str>,
+        script: Option<Arc<str>>,
         num_base: Option<i64>,
         n_decimals: Option<usize>,
         orig_txt: String, // Accepts the combined original text.
@@ -202,8 +183,10 @@ impl Edge {
             data: EdgeData {
                 start,
                 end,
-                txt: "".to_string(),
-                r#type: e_type,
+                txt: Cow::Borrowed(""),
+                r#type: intern(e_type.as_ref()),
+                display: None,
+                conditions: None,
             },
             num_data,
         };
@@ -230,7 +213,7 @@ impl Edge {
         self.get_num_data().and_then(|d| d.num_base)
     }
 
-    pub fn get_script(&self) -> Option<String> {
+    pub fn get_script(&self) -> Option<Arc<str>> {
         self.get_num_data().and_then(|d| d.script.clone())
     }
 
@@ -291,7 +274,7 @@ impl Edge {
             } else if let Some(v) = num_data.value {
                 if let Some(nd) = num_data.n_decimals {
                     format!("{v:.nd$}")
-                } else if v.fract() == 0.0 {
+                } else if v.fract() == 0.0 && v.abs() < i64::MAX as f64 {
                     (v as i64).to_string()
                 } else {
                     v.to_string()
@@ -315,22 +298,22 @@ impl Edge {
             let final_txt = format!("{value_s}{delimiter}{fraction_s}");
 
             // Fallback to original text if the calculated text is empty.
-            data.txt = if final_txt.is_empty() {
+            data.txt = Cow::Owned(if final_txt.is_empty() {
                 num_data.orig_txt.clone()
             } else {
                 final_txt
-            };
+            });
         }
     }
 
     // --- Accessors for common data ---
-    pub fn get_data(&self) -> &EdgeData {
+    pub fn get_data(&self) -> &EdgeData<'a> {
         match self {
             Edge::Regular(data) | Edge::Numeric { data, .. } => data,
         }
     }
 
-    pub fn get_data_mut(&mut self) -> &mut EdgeData {
+    pub fn get_data_mut(&mut self) -> &mut EdgeData<'a> {
         match self {
             Edge::Regular(data) | Edge::Numeric { data, .. } => data,
         }
@@ -377,4 +360,28 @@ impl Edge {
     pub fn value(&self) -> Option<f64> {
         self.get_num_data().and_then(|d| d.value)
     }
+
+    /// Copies any borrowed text, detaching the edge from the lattice it was built in.
+    pub fn into_owned(self) -> Edge<'static> {
+        match self {
+            Edge::Regular(data) => Edge::Regular(data.into_owned()),
+            Edge::Numeric { data, num_data } => Edge::Numeric {
+                data: data.into_owned(),
+                num_data,
+            },
+        }
+    }
+}
+
+impl EdgeData<'_> {
+    fn into_owned(self) -> EdgeData<'static> {
+        EdgeData {
+            start: self.start,
+            end: self.end,
+            txt: Cow::Owned(self.txt.into_owned()),
+            r#type: self.r#type,
+            display: self.display,
+            conditions: self.conditions,
+        }
+    }
 }
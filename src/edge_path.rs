@@ -0,0 +1,61 @@
+//! Checking that edge paths cover their text, for consumers that slice it by edge offsets.
+
+use crate::{Edge, RomanizationError};
+
+impl Edge<'_> {
+    /// Checks that `edges` are a path over a text of `n_chars` chars: each edge spans at least
+    /// one char and starts where the one before it ends, from 0 to `n_chars`. This holds for
+    /// the edges of the `edges` format. The alternatives that the `alts` format adds after
+    /// them, of types starting with `rom-alt`, are skipped.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::InvalidEdgePath`] with the index of the first edge that
+    /// breaks the path, or `edges.len()` if the path ends early.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Edge, RomFormat, RomanizationResult, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let s = "Київ";
+    /// let result = uroman.romanize_with_format(s, None, Some(RomFormat::Alts));
+    /// let RomanizationResult::Edges(edges) = result else {
+    ///     unreachable!();
+    /// };
+    /// assert!(Edge::validate_path(&edges, s.chars().count()).is_ok());
+    /// assert!(Edge::validate_path(&edges[1..], s.chars().count()).is_err());
+    /// ```
+    pub fn validate_path(edges: &[Edge], n_chars: usize) -> Result<(), RomanizationError> {
+        let invalid = |index, reason| Err(RomanizationError::InvalidEdgePath { index, reason });
+        let mut position = 0;
+        for (index, edge) in edges.iter().enumerate() {
+            if edge.r#type().starts_with("rom-alt") {
+                continue;
+            }
+            let (start, end) = (edge.start(), edge.end());
+            if start != position {
+                return invalid(
+                    index,
+                    format!("it starts at {start}, not at the end of the path so far, {position}"),
+                );
+            }
+            if end <= start {
+                return invalid(index, format!("it spans no chars, from {start} to {end}"));
+            }
+            if end > n_chars {
+                return invalid(
+                    index,
+                    format!("it ends at {end}, after the text of {n_chars} chars"),
+                );
+            }
+            position = end;
+        }
+        if position != n_chars {
+            return invalid(
+                edges.len(),
+                format!("the path ends at {position}, before the text of {n_chars} chars"),
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Transcoding UTF-16 input to UTF-8 while it is read.
+
+use std::io::{self, BufRead, Read};
+
+use serde::{Deserialize, Serialize};
+
+/// The encoding of the text in a file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum InputEncoding {
+    /// UTF-16 if the file starts with a UTF-16 byte order mark, and UTF-8 otherwise.
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    /// UTF-8, with invalid bytes handled by [`InvalidUtf8Policy`](crate::InvalidUtf8Policy).
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// Little-endian UTF-16, as written by Windows.
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    /// Big-endian UTF-16.
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+}
+
+/// Reads a stream in an [`InputEncoding`] as UTF-8.
+///
+/// UTF-16 is transcoded chunk by chunk, and unpaired surrogates become U+FFFD. A UTF-16 byte
+/// order mark is transcoded to the UTF-8 one, which `LossyLines` drops.
+pub(crate) struct DecodedReader<R> {
+    inner: R,
+    encoding: InputEncoding,
+    /// The transcoded UTF-8 and the position of the next byte to read from it.
+    output: Vec<u8>,
+    pos: usize,
+    /// The first byte of a code unit split between two chunks.
+    odd_byte: Option<u8>,
+    /// A high surrogate at the end of the last chunk, waiting for its low surrogate.
+    high_surrogate: Option<u16>,
+    units: Vec<u16>,
+}
+
+impl<R: BufRead> DecodedReader<R> {
+    pub(crate) fn new(inner: R, encoding: InputEncoding) -> Self {
+        Self {
+            inner,
+            encoding,
+            output: Vec::new(),
+            pos: 0,
+            odd_byte: None,
+            high_surrogate: None,
+            units: Vec::new(),
+        }
+    }
+
+    /// Transcodes the next chunk of UTF-16 into `output`, which stays empty at the end of the
+    /// stream.
+    fn transcode_chunk(&mut self, big_endian: bool) -> io::Result<()> {
+        self.output.clear();
+        self.pos = 0;
+        while self.output.is_empty() {
+            let bytes = self.inner.fill_buf()?;
+            if bytes.is_empty() {
+                if self.odd_byte.take().is_some() | self.high_surrogate.take().is_some() {
+                    push_char(&mut self.output, char::REPLACEMENT_CHARACTER);
+                }
+                return Ok(());
+            }
+
+            self.units.clear();
+            self.units.extend(self.high_surrogate.take());
+            let mut bytes_iter = self
+                .odd_byte
+                .take()
+                .into_iter()
+                .chain(bytes.iter().copied());
+            while let Some(first) = bytes_iter.next() {
+                let Some(second) = bytes_iter.next() else {
+                    self.odd_byte = Some(first);
+                    break;
+                };
+                self.units.push(if big_endian {
+                    u16::from_be_bytes([first, second])
+                } else {
+                    u16::from_le_bytes([first, second])
+                });
+            }
+            let n_bytes = bytes.len();
+            self.inner.consume(n_bytes);
+
+            if let Some(&last) = self.units.last()
+                && (0xD800..0xDC00).contains(&last)
+            {
+                self.high_surrogate = self.units.pop();
+            }
+            for c in char::decode_utf16(self.units.iter().copied()) {
+                push_char(&mut self.output, c.unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for DecodedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for DecodedReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.encoding == InputEncoding::Auto {
+            let start = self.inner.fill_buf()?;
+            self.encoding = if start.starts_with(&[0xFF, 0xFE]) {
+                InputEncoding::Utf16Le
+            } else if start.starts_with(&[0xFE, 0xFF]) {
+                InputEncoding::Utf16Be
+            } else {
+                InputEncoding::Utf8
+            };
+        }
+        let big_endian = match self.encoding {
+            InputEncoding::Utf16Le => false,
+            InputEncoding::Utf16Be => true,
+            _ => return self.inner.fill_buf(),
+        };
+        if self.pos == self.output.len() {
+            self.transcode_chunk(big_endian)?;
+        }
+        Ok(&self.output[self.pos..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        match self.encoding {
+            InputEncoding::Utf16Le | InputEncoding::Utf16Be => self.pos += amount,
+            _ => self.inner.consume(amount),
+        }
+    }
+}
+
+fn push_char(output: &mut Vec<u8>, c: char) {
+    output.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
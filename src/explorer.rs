@@ -0,0 +1,108 @@
+//! Defines `LatticeExplorer`, a read-only view over a populated romanization lattice.
+
+use crate::Edge;
+use crate::lattice::{BackwardsPathResult, Lattice};
+
+/// All edges covering one `[start, end)` character span of the input.
+#[derive(Debug, Clone)]
+pub struct LatticeSpan<'a> {
+    pub start: usize,
+    pub end: usize,
+    /// The original substring covered by this span.
+    pub orig: String,
+    /// The competing edges, with the one preferred by the lattice first.
+    pub edges: Vec<Edge<'a>>,
+}
+
+/// Lets callers inspect competing edges and see how forcing one of them changes the best path.
+///
+/// Created by [`Uroman::explore_lattice`](crate::Uroman::explore_lattice).
+pub struct LatticeExplorer<'a> {
+    lattice: Lattice<'a>,
+    spans: Vec<LatticeSpan<'a>>,
+}
+
+impl<'a> LatticeExplorer<'a> {
+    pub(crate) fn new(lattice: Lattice<'a>) -> Self {
+        let mut keys: Vec<(usize, usize)> = lattice.edge_lattice.keys().copied().collect();
+        keys.sort_unstable();
+
+        let spans = keys
+            .into_iter()
+            .filter_map(|(start, end)| {
+                let preferred = lattice.best_edge_in_span(start, end, false);
+                let mut edges: Vec<Edge<'a>> = lattice
+                    .edge_lattice
+                    .get(&(start, end))?
+                    .iter()
+                    .cloned()
+                    .collect();
+                if edges.is_empty() {
+                    return None;
+                }
+                edges.sort_by(|a, b| {
+                    let a_pref = preferred.as_ref() == Some(a);
+                    let b_pref = preferred.as_ref() == Some(b);
+                    b_pref
+                        .cmp(&a_pref)
+                        .then_with(|| a.txt().cmp(b.txt()))
+                        .then_with(|| a.r#type().cmp(b.r#type()))
+                });
+                Some(LatticeSpan {
+                    start,
+                    end,
+                    orig: lattice.substr(start, end).to_string(),
+                    edges,
+                })
+            })
+            .collect();
+
+        Self { lattice, spans }
+    }
+
+    /// Returns the spans of the lattice, sorted by `(start, end)`.
+    pub fn spans(&self) -> &[LatticeSpan<'a>] {
+        &self.spans
+    }
+
+    /// Returns the number of characters in the input.
+    pub fn len(&self) -> usize {
+        self.lattice.max_vertex
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lattice.max_vertex == 0
+    }
+
+    /// Returns the best edge path, as used for `Str` and `Edges` output.
+    pub fn best_path(&mut self) -> Vec<Edge<'a>> {
+        self.lattice
+            .best_rom_edge_path(0, self.lattice.max_vertex, false)
+    }
+
+    /// Returns the best path as a romanized string.
+    pub fn best_path_string(&mut self) -> String {
+        self.best_path().iter().map(|e| e.txt()).collect()
+    }
+
+    /// Returns the best path that is forced to go through `edge`.
+    ///
+    /// The part before the edge is found by walking backwards from `edge.start()`,
+    /// and the part after it by the regular best-path search from `edge.end()`.
+    pub fn best_path_through(&mut self, edge: &Edge<'a>) -> Vec<Edge<'a>> {
+        let mut path =
+            match self
+                .lattice
+                .find_rom_edge_path_backwards(0, edge.start(), None, false, false)
+            {
+                BackwardsPathResult::Edges(edges) => edges,
+                BackwardsPathResult::Str(_) => Vec::new(),
+            };
+        path.push(edge.clone());
+        path.extend(
+            self.lattice
+                .best_rom_edge_path(edge.end(), self.lattice.max_vertex, false),
+        );
+        path
+    }
+}
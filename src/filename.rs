@@ -0,0 +1,60 @@
+//! Romanizing file names to names that are safe on any file system.
+
+use crate::{Uroman, rom_format};
+
+/// ASCII chars that Windows doesn't allow in file names, and the path separators.
+const UNSAFE_FILENAME_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// The device names that Windows reserves, which a file name can't be, with or without an
+/// extension. `COM1` to `COM9` and `LPT1` to `LPT9` are also reserved.
+const RESERVED_DEVICE_NAMES: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+
+impl Uroman {
+    /// Romanizes the file name `name` with `lcode` to a name of printable ASCII that is valid
+    /// on Linux, macOS and Windows.
+    ///
+    /// Chars that can't be romanized, control chars and the chars that Windows doesn't allow,
+    /// such as `:` and `?`, become `_`. Leading spaces and trailing spaces and dots are
+    /// dropped, and a name that is left empty, `.` or `..` becomes `_`. The device names that
+    /// Windows reserves, such as `CON` or `com1.txt`, get a `_` before their extension.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// assert_eq!(uroman.romanize_filename("Звіт: 2024 ☃.pdf", None), "Zvit_ 2024 _.pdf");
+    /// assert_eq!(uroman.romanize_filename("con.txt", None), "con_.txt");
+    /// ```
+    pub fn romanize_filename(&self, name: &str, lcode: Option<&str>) -> String {
+        let romanized = self
+            .romanize_string::<rom_format::Str>(name, lcode)
+            .to_output_string();
+        let safe: String = romanized
+            .chars()
+            .map(|c| {
+                let is_safe =
+                    c == ' ' || (c.is_ascii_graphic() && !UNSAFE_FILENAME_CHARS.contains(&c));
+                if is_safe { c } else { '_' }
+            })
+            .collect();
+        let mut safe = match safe.trim_start_matches(' ').trim_end_matches([' ', '.']) {
+            "" | "." | ".." => "_".to_string(),
+            safe => safe.to_string(),
+        };
+        let stem_len = safe.find('.').unwrap_or(safe.len());
+        if is_reserved_device_name(safe[..stem_len].trim_end_matches(' ')) {
+            safe.insert(stem_len, '_');
+        }
+        safe
+    }
+}
+
+/// Whether Windows reserves `stem` for a device, such as `CON` or `lpt1`.
+fn is_reserved_device_name(stem: &str) -> bool {
+    let upper = stem.to_ascii_uppercase();
+    match upper.as_bytes() {
+        [b'C', b'O', b'M', b'1'..=b'9'] | [b'L', b'P', b'T', b'1'..=b'9'] => true,
+        _ => RESERVED_DEVICE_NAMES.contains(&upper.as_str()),
+    }
+}
@@ -0,0 +1,537 @@
+//! Regenerating the data tables that are derived from the Unicode Character Database, see
+//! [`Uroman::generate_data_tables`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::{fs, io};
+
+use regex::Regex;
+
+use crate::{RomanizationError, Uroman};
+
+/// The word of a char name that the name of the letter follows, e.g. `LETTER` in
+/// `GREEK SMALL LETTER ALPHA`. The first one in the name is used.
+static NAME_KEYWORD_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(LETTER|SYLLABLE|SIGN|LIGATURE|CHARACTER|SMALL|CAPITAL|VOWEL) (.*)$").unwrap()
+});
+
+/// A consonant-vowel syllable name, e.g. `KI` in `CHEROKEE LETTER KI`.
+static SYLLABLE_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:LETTER|SYLLABLE|SIGN) ([^AEIOU ]+)([AEIOU])$").unwrap());
+
+/// The words of a numeral name, e.g. `DIGIT` in `BENGALI DIGIT ONE`.
+static NUMERAL_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(DIGIT|NUMBER|NUMERAL)\b").unwrap());
+
+/// The words of a letter name that describe its form rather than its sound, e.g. `FINAL` in
+/// `HEBREW LETTER FINAL NUN`.
+const FORM_WORDS: &[&str] = &[
+    "BARRED",
+    "CAPITAL",
+    "CLOSED",
+    "CONSONANT",
+    "CURLED",
+    "DOTLESS",
+    "FINAL",
+    "FORM",
+    "HOOKED",
+    "INITIAL",
+    "INVERTED",
+    "ISOLATED",
+    "LETTER",
+    "LONG",
+    "MEDIAL",
+    "OPEN",
+    "REVERSED",
+    "SCRIPT",
+    "SHORT",
+    "SIDEWAYS",
+    "SIGN",
+    "SMALL",
+    "STRETCHED",
+    "SUBJOINED",
+    "TURNED",
+    "VOWEL",
+];
+
+/// The words that compose a sign of others, e.g. `TIMES` in `CUNEIFORM SIGN KA TIMES BAD`.
+/// The names of such signs don't spell a sound.
+const COMPOSITION_WORDS: &[&str] = &["CROSSING", "GUNU", "OPPOSING", "OVER", "PLUS", "TIMES"];
+
+/// The scripts of the Unicode `Scripts.txt` that are not the script of a writing system.
+const SHARED_SCRIPTS: &[&str] = &["Common", "Inherited", "Unknown"];
+
+/// The data tables generated by [`Uroman::generate_data_tables`], in the formats of the files
+/// in `data/`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DataTables {
+    /// `romanization-auto-table.txt`: the romanization of letters and marks derived from
+    /// their names, such as `ka` for `DEVANAGARI LETTER KA`.
+    pub romanization_auto_table: String,
+    /// `UnicodeDataProps.txt`: the chars, numerals, vowel signs, medial consonant signs and
+    /// viramas of each script.
+    pub unicode_data_props: String,
+    /// `UnicodeDataPropsCJK.txt`: the chars of the CJK script.
+    pub unicode_data_props_cjk: String,
+    /// `UnicodeDataPropsHangul.txt`: the chars of the Hangul script.
+    pub unicode_data_props_hangul: String,
+}
+
+impl DataTables {
+    /// Writes the tables to their files in `dir`, e.g. `data/` of the crate, replacing them.
+    pub fn write_to_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (file_name, table) in [
+            ("romanization-auto-table.txt", &self.romanization_auto_table),
+            ("UnicodeDataProps.txt", &self.unicode_data_props),
+            ("UnicodeDataPropsCJK.txt", &self.unicode_data_props_cjk),
+            (
+                "UnicodeDataPropsHangul.txt",
+                &self.unicode_data_props_hangul,
+            ),
+        ] {
+            fs::write(dir.join(file_name), table)?;
+        }
+        Ok(())
+    }
+}
+
+/// A char of `UnicodeData.txt`.
+struct UcdChar {
+    c: char,
+    name: String,
+    category: String,
+    combining_class: String,
+}
+
+/// What the chars of a script have in common, for deriving their romanization.
+#[derive(Default)]
+struct ScriptTraits {
+    /// The inherent vowel of the consonants of an abugida, e.g. `A` for Devanagari.
+    default_vowel: Option<String>,
+    /// Whether the letters of the script are syllables such as `KA`, `KI` and `KU`.
+    syllabic: bool,
+    /// The names of the letters that are named by one word, e.g. `HEH` of `ARABIC LETTER HEH`.
+    letter_names: BTreeSet<String>,
+}
+
+/// The first and last codepoint of a range of the Unicode `Scripts.txt`, and its script.
+type ScriptRange = ((u32, u32), String);
+
+/// The props of a script in `UnicodeDataProps*.txt`, by slot.
+type ScriptProps = BTreeMap<&'static str, String>;
+
+impl Uroman {
+    /// Generates the data tables that are derived from the Unicode Character Database, from
+    /// the `UnicodeData.txt` and `Scripts.txt` of a Unicode release, so that new scripts and
+    /// chars can be romanized before the Python uroman adds them.
+    ///
+    /// The scripts are those of the loaded `Scripts.txt` of uroman and those of the Unicode
+    /// `Scripts.txt`. A char belongs to the script its name starts with, e.g. Devanagari for
+    /// `DEVANAGARI LETTER KA`, unless the Unicode `Scripts.txt` gives it a script shared by
+    /// several writing systems, such as the marks of Inherited.
+    ///
+    /// The romanizations of `romanization-auto-table.txt` are derived from the names of the
+    /// chars: the letter of an alphabet, e.g. `d` for `HEBREW LETTER DALET`, the consonants of
+    /// a letter of an abugida, e.g. `k` for `DEVANAGARI LETTER KA`, and the whole syllable of
+    /// a syllabary, e.g. `ka` for `HIRAGANA LETTER KA`. They are a starting point, and are
+    /// meant to be reviewed as a diff against the current tables.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::InvalidUnicodeData`] with a line of `unicode_data` or
+    /// `scripts` that can't be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let unicode_data = "\
+    /// 0915;DEVANAGARI LETTER KA;Lo;0;L;;;;;N;;;;;
+    /// 094D;DEVANAGARI SIGN VIRAMA;Mn;9;NSM;;;;;N;;;;;
+    /// 0967;DEVANAGARI DIGIT ONE;Nd;0;L;;1;1;1;N;;;;;
+    /// ";
+    /// let scripts = "0900..097F ; Devanagari # Mn [128] DEVANAGARI SIGN INVERTED CANDRABINDU..";
+    /// let tables = Uroman::new().generate_data_tables(unicode_data, scripts).unwrap();
+    /// assert_eq!(
+    ///     tables.romanization_auto_table,
+    ///     "::cp 0915 ::s क ::cat Lo ::name DEVANAGARI LETTER KA ::t k\n"
+    /// );
+    /// assert_eq!(
+    ///     tables.unicode_data_props,
+    ///     "::script-name Devanagari ::n-char 2 ::char क् ::numeral १ ::sign-virama ्\n"
+    /// );
+    /// ```
+    pub fn generate_data_tables(
+        &self,
+        unicode_data: &str,
+        scripts: &str,
+    ) -> Result<DataTables, RomanizationError> {
+        let chars = parse_unicode_data(unicode_data)?;
+        let unicode_scripts = parse_scripts(scripts)?;
+
+        let mut script_names: BTreeMap<String, String> = BTreeMap::new();
+        for script in self.scripts.values() {
+            script_names.insert(
+                script.script_name.to_uppercase(),
+                script.script_name.clone(),
+            );
+        }
+        for name in unicode_scripts.iter().map(|(_, name)| name) {
+            if !SHARED_SCRIPTS.contains(&name.as_str()) {
+                let name = name.replace('_', " ");
+                script_names.entry(name.to_uppercase()).or_insert(name);
+            }
+        }
+        // The longest names first, so that e.g. `ARABIC-INDIC DIGIT ONE` is Arabic-Indic.
+        let mut prefixes: Vec<(&str, String)> = script_names
+            .keys()
+            .map(|prefix| (prefix.as_str(), format!(" {prefix} ")))
+            .collect();
+        prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        let script_of = |name: &str| {
+            prefixes
+                .iter()
+                .find(|(prefix, infix)| {
+                    name.strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with(' '))
+                        || name.contains(infix.as_str())
+                })
+                .map(|(prefix, _)| *prefix)
+        };
+
+        let traits = self.script_traits(&chars, &script_names, &script_of);
+        let mut auto_table = String::new();
+        let mut props: BTreeMap<&str, ScriptProps> = BTreeMap::new();
+        for ucd_char in &chars {
+            let UcdChar {
+                c,
+                name,
+                category,
+                combining_class,
+            } = ucd_char;
+            let script = script_of(name);
+            let letter_traits = script.and_then(|script| traits.get(script));
+            if category.starts_with(['L', 'M'])
+                && let Some(rom) = romanize_name(name, category, letter_traits)
+            {
+                writeln!(
+                    auto_table,
+                    "::cp {:04X} ::s {c} ::cat {category} ::name {name} ::t {rom}",
+                    *c as u32
+                )
+                .unwrap();
+            }
+
+            let Some(script) = script.filter(|script| name.starts_with(&format!("{script} ")))
+            else {
+                continue;
+            };
+            if lookup_script(&unicode_scripts, *c)
+                .is_some_and(|unicode_script| SHARED_SCRIPTS.contains(&unicode_script))
+            {
+                continue;
+            }
+            let script_props = props.entry(&script_names[script]).or_default();
+            let mut add = |slot, c| script_props.entry(slot).or_default().push(c);
+            let is_numeral = category.starts_with('N') || NUMERAL_NAME_RE.is_match(name);
+            if is_numeral {
+                add("numeral", *c);
+            }
+            if category.starts_with(['L', 'M'])
+                || category == "Sc"
+                || (is_numeral && !category.starts_with('N'))
+            {
+                add("char", *c);
+            }
+            if name.contains("VOWEL SIGN") {
+                add("vowel-sign", *c);
+            }
+            if name.contains("CONSONANT SIGN MEDIAL") {
+                add("medial-consonant-sign", *c);
+            }
+            if category.starts_with('M') && combining_class == "9" {
+                add("sign-virama", *c);
+            }
+        }
+
+        let mut tables = DataTables {
+            romanization_auto_table: auto_table,
+            ..DataTables::default()
+        };
+        for (script_name, script_props) in props {
+            let table = match script_name {
+                "CJK" => &mut tables.unicode_data_props_cjk,
+                "Hangul" => &mut tables.unicode_data_props_hangul,
+                _ => &mut tables.unicode_data_props,
+            };
+            write!(table, "::script-name {script_name}").unwrap();
+            if let Some(chars) = script_props.get("char") {
+                write!(table, " ::n-char {}", chars.chars().count()).unwrap();
+            }
+            for (slot, chars) in &script_props {
+                write!(table, " ::{slot} {chars}").unwrap();
+            }
+            table.push('\n');
+        }
+        Ok(tables)
+    }
+
+    /// Returns the traits of the scripts that the names of `chars` start with.
+    fn script_traits<'a>(
+        &self,
+        chars: &[UcdChar],
+        script_names: &'a BTreeMap<String, String>,
+        script_of: &impl Fn(&str) -> Option<&'a str>,
+    ) -> BTreeMap<&'a str, ScriptTraits> {
+        let mut syllables: BTreeMap<&str, BTreeMap<&str, BTreeSet<&str>>> = BTreeMap::new();
+        let mut traits: BTreeMap<&str, ScriptTraits> = BTreeMap::new();
+        for (prefix, script_name) in script_names {
+            let default_vowel = self
+                .scripts
+                .get(&script_name.to_lowercase())
+                .and_then(|script| script.abugida_default_vowels.first())
+                .map(|vowel| vowel.to_uppercase());
+            traits.insert(
+                prefix,
+                ScriptTraits {
+                    default_vowel,
+                    ..ScriptTraits::default()
+                },
+            );
+        }
+        for ucd_char in chars {
+            let Some(script) = script_of(&ucd_char.name) else {
+                continue;
+            };
+            if let Some(letter_name) = NAME_KEYWORD_RE
+                .captures(&ucd_char.name)
+                .and_then(|caps| caps.get(2))
+                .filter(|letter_name| !letter_name.as_str().contains(' '))
+                && let Some(script_traits) = traits.get_mut(script)
+            {
+                script_traits
+                    .letter_names
+                    .insert(letter_name.as_str().to_string());
+            }
+            if let Some(caps) = SYLLABLE_NAME_RE.captures(&ucd_char.name)
+                && let (Some(consonants), Some(vowel)) = (caps.get(1), caps.get(2))
+            {
+                syllables
+                    .entry(script)
+                    .or_default()
+                    .entry(consonants.as_str())
+                    .or_default()
+                    .insert(vowel.as_str());
+            }
+        }
+        for (script, consonants) in syllables {
+            let n_syllabic = consonants
+                .values()
+                .filter(|vowels| vowels.len() >= 3)
+                .count();
+            if let Some(script_traits) = traits.get_mut(script) {
+                script_traits.syllabic = script_traits.default_vowel.is_none() && n_syllabic >= 3;
+            }
+        }
+        traits
+    }
+}
+
+/// Derives the romanization of a letter or mark from its name, e.g. `k` from
+/// `DEVANAGARI LETTER KA`, or returns `None` if the name doesn't spell a sound.
+fn romanize_name(
+    name: &str,
+    category: &str,
+    script_traits: Option<&ScriptTraits>,
+) -> Option<String> {
+    let words: Vec<&str> = name.split(' ').collect();
+    if words.iter().any(|word| COMPOSITION_WORDS.contains(word)) {
+        return None;
+    }
+    let (keyword, rest) = match NAME_KEYWORD_RE.captures(name) {
+        Some(caps) => (caps.get(1)?.as_str(), caps.get(2)?.as_str()),
+        // Letters of a script such as `CANADIAN SYLLABICS E` and `HANGUL JUNGSEONG A`.
+        None if script_traits.is_some() && category.starts_with('L') && words.len() > 2 => {
+            ("SYLLABLE", name.split_once(' ')?.1)
+        }
+        None => return None,
+    };
+    let rest = [" WITH ", " BELOW", " ABOVE"]
+        .iter()
+        .fold(rest, |rest, modifier| {
+            rest.split(modifier).next().unwrap_or(rest)
+        });
+    let letter_words: Vec<&str> = rest
+        .split(' ')
+        .filter(|word| !FORM_WORDS.contains(word))
+        .collect();
+    // Of names such as `ARABIC LETTER HEH DOACHASHMEE` and `TAI THAM LETTER HIGH KA`, the
+    // word that names a letter of its own, or else the first word of a Thai or Lao name
+    // such as `THAI CHARACTER PO PLA`, and the last word of others.
+    let letter = letter_words
+        .iter()
+        .rev()
+        .find(|word| {
+            script_traits.is_some_and(|script_traits| script_traits.letter_names.contains(**word))
+        })
+        .or(match keyword {
+            "CHARACTER" => letter_words.first(),
+            _ => letter_words.last(),
+        })?;
+    if name.contains("ANUSVARA") {
+        return Some("+m".to_string());
+    }
+    // Marks such as `DEVANAGARI SIGN VIRAMA` change the sound of a letter rather than
+    // having one.
+    if keyword == "SIGN" && category.starts_with('M') {
+        return None;
+    }
+
+    let is_word = |word: &str| !word.is_empty() && word.bytes().all(|b| b.is_ascii_uppercase());
+    let letter = if is_word(letter) {
+        letter
+    } else {
+        // Syllables such as `A-O` of `HANGUL JUNGSEONG A-O`, and numbered letters such as
+        // `KA-1` of `HENTAIGANA LETTER KA-1`.
+        let (first, numbers) = letter.split_once('-')?;
+        if keyword == "SYLLABLE" {
+            return letter
+                .split('-')
+                .all(is_word)
+                .then(|| letter.to_lowercase());
+        }
+        if !is_word(first)
+            || !numbers
+                .split('-')
+                .all(|n| n.bytes().all(|b| b.is_ascii_digit()))
+        {
+            return None;
+        }
+        first
+    };
+
+    let is_vowel = |c: char| "AEIOU".contains(c);
+    let is_whole = keyword == "SYLLABLE"
+        || name.contains("VOWEL")
+        || name.contains("CONSONANT SIGN")
+        || letter.len() == 1
+        || script_traits.is_none_or(|script_traits| script_traits.syllabic);
+    let rom = match script_traits.and_then(|script_traits| script_traits.default_vowel.as_deref()) {
+        _ if is_whole => letter,
+        Some(default_vowel) if keyword == "LETTER" => letter
+            .strip_suffix(default_vowel)
+            .filter(|consonants| consonants.ends_with(|c| !is_vowel(c)))
+            .unwrap_or(letter),
+        _ => {
+            // The sound of an alphabet letter is the start of its name, e.g. `GH` of `GHAD`,
+            // or the consonants after a vowel, e.g. `L` of `EL`.
+            let consonants_end = letter.find(is_vowel).unwrap_or(letter.len());
+            let vowels_end = letter[consonants_end..]
+                .find(|c| !is_vowel(c))
+                .map_or(letter.len(), |i| consonants_end + i);
+            let (consonants, vowels, tail) = (
+                &letter[..consonants_end],
+                &letter[consonants_end..vowels_end],
+                &letter[vowels_end..],
+            );
+            if consonants == "Y" && !vowels.is_empty() {
+                &letter[..2]
+            } else if !consonants.is_empty() {
+                consonants
+            } else if tail.contains(is_vowel) {
+                &vowels[..1]
+            } else if tail.is_empty() {
+                vowels
+            } else {
+                tail
+            }
+        }
+    };
+    let is_capital = words.contains(&"CAPITAL") && !words.contains(&"SMALL");
+    Some(if is_capital {
+        rom.to_string()
+    } else {
+        rom.to_lowercase()
+    })
+}
+
+/// Parses the chars of `UnicodeData.txt`, expanding ranges such as those of the CJK
+/// ideographs, which have one line for their first and last char each.
+fn parse_unicode_data(unicode_data: &str) -> Result<Vec<UcdChar>, RomanizationError> {
+    let mut chars = Vec::new();
+    let mut range_start = None;
+    for line in unicode_data.lines().filter(|line| !line.trim().is_empty()) {
+        let invalid = || RomanizationError::InvalidUnicodeData(line.to_string());
+        let fields: Vec<&str> = line.split(';').collect();
+        let [codepoint, name, category, combining_class, ..] = fields[..] else {
+            return Err(invalid());
+        };
+        let codepoint = u32::from_str_radix(codepoint, 16).map_err(|_| invalid())?;
+        if let Some(range_name) = name.strip_suffix(", First>") {
+            range_start = Some((codepoint, range_name));
+            continue;
+        }
+        let (first, range_name) = match range_start.take() {
+            Some((first, range_name)) if name.ends_with(", Last>") => (first, Some(range_name)),
+            _ => (codepoint, None),
+        };
+        for codepoint in first..=codepoint {
+            // Surrogates are not chars.
+            let Some(c) = char::from_u32(codepoint) else {
+                continue;
+            };
+            let name = match range_name {
+                Some(range_name) if range_name.starts_with("<CJK Ideograph") => {
+                    format!("CJK UNIFIED IDEOGRAPH-{codepoint:04X}")
+                }
+                Some(range_name) if range_name.starts_with("<Hangul Syllable") => {
+                    "HANGUL SYLLABLE".to_string()
+                }
+                Some(range_name) if !category.starts_with('C') => {
+                    format!("{}-{codepoint:04X}", range_name[1..].to_uppercase())
+                }
+                Some(_) => continue,
+                None if name.starts_with('<') => continue,
+                None => name.to_string(),
+            };
+            chars.push(UcdChar {
+                c,
+                name,
+                category: category.to_string(),
+                combining_class: combining_class.to_string(),
+            });
+        }
+    }
+    Ok(chars)
+}
+
+/// Parses the ranges of the Unicode `Scripts.txt`, e.g. `0900..097F ; Devanagari`, sorted by
+/// their first codepoint.
+fn parse_scripts(scripts: &str) -> Result<Vec<ScriptRange>, RomanizationError> {
+    let mut ranges = Vec::new();
+    for line in scripts.lines() {
+        let data = line.split('#').next().unwrap_or("").trim();
+        if data.is_empty() {
+            continue;
+        }
+        let invalid = || RomanizationError::InvalidUnicodeData(line.to_string());
+        let (range, script) = data.split_once(';').ok_or_else(invalid)?;
+        let range = range.trim();
+        let (first, last) = range.split_once("..").unwrap_or((range, range));
+        let parse = |codepoint: &str| u32::from_str_radix(codepoint, 16).map_err(|_| invalid());
+        ranges.push(((parse(first)?, parse(last)?), script.trim().to_string()));
+    }
+    ranges.sort_unstable();
+    Ok(ranges)
+}
+
+/// Returns the script that the Unicode `Scripts.txt` gives `c`.
+fn lookup_script(ranges: &[ScriptRange], c: char) -> Option<&str> {
+    let codepoint = c as u32;
+    let index = ranges.partition_point(|((first, _), _)| *first <= codepoint);
+    let ((_, last), script) = ranges.get(index.checked_sub(1)?)?;
+    (codepoint <= *last).then_some(script.as_str())
+}
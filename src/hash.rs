@@ -0,0 +1,14 @@
+//! The hash maps of the lookup tables and the lattice.
+//!
+//! They are keyed by chars, char spans and short strings, and hashing such small keys with
+//! SipHash costs about as much as the rest of the lookup. The `fast-hash` feature switches
+//! them to ahash.
+
+#[cfg(feature = "fast-hash")]
+pub(crate) type RandomState = ahash::RandomState;
+#[cfg(not(feature = "fast-hash"))]
+pub(crate) type RandomState = std::hash::RandomState;
+
+pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, RandomState>;
+pub(crate) type HashSet<T> = std::collections::HashSet<T, RandomState>;
+pub(crate) type IndexMap<K, V> = indexmap::IndexMap<K, V, RandomState>;
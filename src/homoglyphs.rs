@@ -0,0 +1,112 @@
+//! Normalizing homoglyphs of Latin letters, which spoofed text uses to look like Latin, see
+//! [`Uroman::with_normalized_homoglyphs`](crate::Uroman::with_normalized_homoglyphs).
+
+use std::borrow::Cow;
+
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+/// Maps the fullwidth forms of ASCII chars to ASCII, and the Cyrillic and Greek letters that
+/// look like Latin ones to those in words that have ASCII letters, e.g. `pаypal` with a
+/// Cyrillic `а`. Words without ASCII letters are left as they are, so `рау` stays Cyrillic.
+///
+/// Chars are mapped one to one, so char offsets don't change.
+pub(crate) fn normalize_homoglyphs(s: &str) -> Cow<'_, str> {
+    if s.is_ascii() {
+        return Cow::Borrowed(s);
+    }
+    let chars: Vec<char> = s
+        .chars()
+        .map(|c| fullwidth_to_ascii(c).unwrap_or(c))
+        .collect();
+    let mut normalized = String::with_capacity(s.len());
+    let mut word_start = 0;
+    for (i, &c) in chars.iter().chain([&' ']).enumerate() {
+        if is_word_char(c) {
+            continue;
+        }
+        let word = &chars[word_start..i];
+        if word.iter().any(char::is_ascii_alphabetic) {
+            normalized.extend(word.iter().map(|&c| latin_homoglyph(c).unwrap_or(c)));
+        } else {
+            normalized.extend(word);
+        }
+        if i < chars.len() {
+            normalized.push(c);
+        }
+        word_start = i + 1;
+    }
+    if normalized == s {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c.general_category_group() == GeneralCategoryGroup::Mark
+}
+
+/// Maps the fullwidth forms of printable ASCII chars, U+FF01 to U+FF5E, to ASCII.
+fn fullwidth_to_ascii(c: char) -> Option<char> {
+    matches!(c, '\u{ff01}'..='\u{ff5e}')
+        .then(|| char::from_u32(c as u32 - 0xfee0))
+        .flatten()
+}
+
+/// The Latin letter that a Cyrillic or Greek letter is easily mistaken for.
+fn latin_homoglyph(c: char) -> Option<char> {
+    let latin = match c {
+        // Cyrillic
+        'А' => 'A',
+        'В' => 'B',
+        'С' => 'C',
+        'Е' => 'E',
+        'Н' => 'H',
+        'І' => 'I',
+        'Ј' => 'J',
+        'К' => 'K',
+        'М' => 'M',
+        'О' => 'O',
+        'Р' => 'P',
+        'Ѕ' => 'S',
+        'Т' => 'T',
+        'Х' => 'X',
+        'Ү' => 'Y',
+        'а' => 'a',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' => 'i',
+        'ј' => 'j',
+        'ӏ' => 'l',
+        'о' => 'o',
+        'р' => 'p',
+        'ԛ' => 'q',
+        'ѕ' => 's',
+        'ԝ' => 'w',
+        'х' => 'x',
+        'у' => 'y',
+        // Greek
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        'ι' => 'i',
+        'ν' => 'v',
+        'ο' => 'o',
+        'ρ' => 'p',
+        _ => return None,
+    };
+    Some(latin)
+}
@@ -0,0 +1,115 @@
+//! Exporting the romanization rules as ICU transform rules.
+
+use std::fmt::Write;
+
+use crate::rom_rule::RomRule;
+use crate::{Uroman, lcode_chain};
+
+impl Uroman {
+    /// Returns the loaded romanization rules in the rule syntax of ICU's `Transliterator`, for
+    /// keeping other ICU-based code consistent with uroman-rs.
+    ///
+    /// Rules restricted to language codes are included only if `lcode`, or a code of its
+    /// fallback chain, is one of them. Those of earlier codes in the chain come first. The word
+    /// position restrictions of rules become contexts on `$L`, the letters and marks.
+    ///
+    /// ICU applies the first rule that matches at each position, so the rules are ordered from
+    /// the longest source to the shortest. This approximates the best path through uroman's
+    /// lattice. Everything that is not table-driven, such as numbers, abugida vowels and Hangul
+    /// syllables, is left out, so the output of the transform is close to but not the same as
+    /// that of uroman-rs.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let rules = Uroman::new().to_icu_rules(Some("ukr"));
+    /// assert!(rules.contains("и > y ;"));
+    /// ```
+    pub fn to_icu_rules(&self, lcode: Option<&str>) -> String {
+        let mut rules = String::new();
+        rules.push_str("# Romanization rules exported from uroman-rs ");
+        rules.push_str(env!("CARGO_PKG_VERSION"));
+        match lcode {
+            Some(lcode) => writeln!(rules, " for {lcode}.").unwrap(),
+            None => rules.push_str(".\n"),
+        }
+        rules.push_str("$L = [[:L:][:M:]] ;\n");
+        rules.push_str("$nonL = [^[:L:][:M:]] ;\n");
+
+        let mut sources: Vec<_> = self.rom_rules.iter().collect();
+        sources.sort_by_key(|(s, _)| std::cmp::Reverse(s.chars().count()));
+        for (s, rules_for_s) in sources {
+            let s = escape(s);
+            // Of the rules that apply at a position, uroman uses the first one by its rule
+            // priority, which comes first here.
+            let mut candidates: Vec<_> = rules_for_s
+                .iter()
+                .filter(|rule| rule.t.is_some())
+                .filter_map(|rule| {
+                    if rule.lcodes.is_empty() {
+                        return Some((rule, 0));
+                    }
+                    let rank =
+                        lcode_chain(lcode).position(|lc| rule.lcodes.iter().any(|l| l == lc))?;
+                    Some((rule, rank))
+                })
+                .collect();
+            candidates.sort_by(|&(rule1, rank1), &(rule2, rank2)| {
+                rule1
+                    .cmp_priority(rule2, &self.rule_priority)
+                    .then(rank1.cmp(&rank2))
+            });
+            for (rule, _) in candidates {
+                let t = escape(rule.t.as_deref().unwrap_or_default());
+                for before in before_contexts(rule) {
+                    for after in after_contexts(rule) {
+                        writeln!(rules, "{before}{s}{after} > {t} ;").unwrap();
+                    }
+                }
+                // The rules after one without a word position restriction can never apply.
+                if before_contexts(rule) == [""] && after_contexts(rule) == [""] {
+                    break;
+                }
+            }
+        }
+        rules
+    }
+}
+
+/// Returns the contexts before the source that express the start-of-word restrictions of `rule`.
+fn before_contexts(rule: &RomRule) -> &'static [&'static str] {
+    if rule.use_only_at_start_of_word || rule.use_only_for_whole_word {
+        &["^ ", "$nonL { "]
+    } else if rule.dont_use_at_start_of_word {
+        &["$L { "]
+    } else {
+        &[""]
+    }
+}
+
+/// Returns the contexts after the source that express the end-of-word restrictions of `rule`.
+fn after_contexts(rule: &RomRule) -> &'static [&'static str] {
+    if rule.use_only_at_end_of_word || rule.use_only_for_whole_word {
+        &[" $", " } $nonL"]
+    } else if rule.dont_use_at_end_of_word {
+        &[" } $L"]
+    } else {
+        &[""]
+    }
+}
+
+/// Escapes everything but letters and digits, which may be syntax in ICU rules.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            escaped.push(c);
+        } else if let Ok(c) = u16::try_from(u32::from(c)) {
+            write!(escaped, "\\u{c:04X}").unwrap();
+        } else {
+            write!(escaped, "\\U{:08X}", u32::from(c)).unwrap();
+        }
+    }
+    escaped
+}
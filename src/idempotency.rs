@@ -0,0 +1,58 @@
+//! Detecting text that is already romanized, so that callers can skip it.
+
+use crate::{Uroman, rom_format};
+
+impl Uroman {
+    /// Returns whether romanizing `s` would change it. Text that uroman has romanized never
+    /// needs it again, so romanizing only text that needs it makes romanization idempotent.
+    ///
+    /// ASCII text is decided without romanizing it.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// assert!(uroman.needs_romanization("Київ"));
+    /// assert!(uroman.needs_romanization("café"));
+    /// assert!(!uroman.needs_romanization("Kyiv"));
+    /// // Chars that can't be romanized stay as they are.
+    /// assert!(!uroman.needs_romanization("Kyiv ☃"));
+    /// ```
+    pub fn needs_romanization(&self, s: &str) -> bool {
+        if s.is_ascii() {
+            return false;
+        }
+        self.romanize_string::<rom_format::Str>(s, None)
+            .to_output_string()
+            != s
+    }
+
+    /// Returns the share of the letters of `s` that are not in the Latin script, from `0.0`
+    /// for text that is already romanized to `1.0` for text in other scripts only. Text
+    /// without letters has a share of `0.0`.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// assert_eq!(uroman.non_latin_share("Kyiv"), 0.0);
+    /// assert_eq!(uroman.non_latin_share("Київ Kyiv"), 0.5);
+    /// assert_eq!(uroman.non_latin_share("1, 2, 3"), 0.0);
+    /// ```
+    pub fn non_latin_share(&self, s: &str) -> f64 {
+        let (mut n_letters, mut n_non_latin) = (0_usize, 0_usize);
+        for c in s.chars().filter(|c| c.is_alphabetic()) {
+            n_letters += 1;
+            if !c.is_ascii() && self.chr_script_name(c) != "Latin" {
+                n_non_latin += 1;
+            }
+        }
+        if n_letters == 0 {
+            0.0
+        } else {
+            n_non_latin as f64 / n_letters as f64
+        }
+    }
+}
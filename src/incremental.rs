@@ -0,0 +1,132 @@
+//! Keeps the romanization of an edited text up to date without romanizing all of it again.
+
+use std::ops::Range;
+
+use crate::{Edge, RomanizationResult, Uroman, rom_format};
+
+/// Upper bound on the chars of unchanged context re-romanized on each side of an edit, for
+/// text without whitespace to stop at.
+const MAX_CONTEXT_CHARS: usize = 32;
+
+/// The romanization of a text that is edited piece by piece, e.g. for a live preview in an
+/// editor.
+///
+/// Created by [`Uroman::incremental`](crate::Uroman::incremental). An edit re-romanizes only the
+/// words around it, and the new edges are spliced in between the unchanged ones.
+pub struct IncrementalRomanization<'a> {
+    uroman: &'a Uroman,
+    lcode: Option<String>,
+    chars: Vec<char>,
+    /// The best path over `chars`, as for [`RomFormat::Edges`](crate::RomFormat::Edges).
+    edges: Vec<Edge<'static>>,
+}
+
+impl<'a> IncrementalRomanization<'a> {
+    pub(crate) fn new(uroman: &'a Uroman, text: &str, lcode: Option<&str>) -> Self {
+        let mut romanization = Self {
+            uroman,
+            lcode: lcode.map(String::from),
+            chars: text.chars().collect(),
+            edges: Vec::new(),
+        };
+        romanization.edges = romanization.romanize_range(0..romanization.chars.len());
+        romanization
+    }
+
+    /// Returns the current text.
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Returns the edges of the current text, with char offsets into it.
+    pub fn edges(&self) -> &[Edge<'static>] {
+        &self.edges
+    }
+
+    /// Returns the romanized current text.
+    pub fn romanized(&self) -> String {
+        self.edges.iter().map(|edge| edge.txt()).collect()
+    }
+
+    /// Replaces the chars in `range` with `replacement` and updates the romanization.
+    ///
+    /// Returns the char range of the new text whose edges were replaced. The edges outside of
+    /// it are kept, shifted by the change in length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of the current text.
+    pub fn apply_edit(&mut self, range: Range<usize>, replacement: &str) -> Range<usize> {
+        assert!(
+            range.start <= range.end && range.end <= self.chars.len(),
+            "edit range {range:?} is out of bounds for a text of {} chars",
+            self.chars.len()
+        );
+        let old_len = self.chars.len();
+        self.chars.splice(range.clone(), replacement.chars());
+        let inserted_end = self.chars.len() - (old_len - range.end);
+        // Maps positions at or after the end of the edit between the old and the new text.
+        let to_new = |old: usize| match old.checked_sub(range.end) {
+            Some(after) => inserted_end + after,
+            None => old.min(range.start),
+        };
+        let to_old = |new: usize| range.end + (new - inserted_end);
+
+        // Extend the edit to whole words, and then to whole edges of the old path.
+        let mut start = range.start;
+        while start > 0
+            && range.start - start < MAX_CONTEXT_CHARS
+            && !self.chars[start - 1].is_whitespace()
+        {
+            start -= 1;
+        }
+        let mut end = inserted_end;
+        while end < self.chars.len()
+            && end - inserted_end < MAX_CONTEXT_CHARS
+            && !self.chars[end].is_whitespace()
+        {
+            end += 1;
+        }
+        let first = self.edges.partition_point(|edge| edge.end() <= start);
+        let last = self
+            .edges
+            .partition_point(|edge| edge.start() < to_old(end));
+        if let Some(edge) = self.edges.get(first) {
+            start = start.min(edge.start());
+        }
+        if last > 0 {
+            end = end.max(to_new(self.edges[last - 1].end()));
+        }
+
+        let mut edges = self.romanize_range(start..end);
+        edges.extend(self.edges.drain(last..).map(|mut edge| {
+            let data = edge.get_data_mut();
+            data.start = to_new(data.start);
+            data.end = to_new(data.end);
+            edge
+        }));
+        self.edges.truncate(first);
+        self.edges.append(&mut edges);
+        start..end
+    }
+
+    /// Romanizes the chars in `range` on their own, with edge offsets into the whole text.
+    fn romanize_range(&self, range: Range<usize>) -> Vec<Edge<'static>> {
+        if range.is_empty() {
+            return Vec::new();
+        }
+        let s: String = self.chars[range.clone()].iter().collect();
+        let output = self
+            .uroman
+            .romanize_string::<rom_format::Edges>(&s, self.lcode.as_deref());
+        let RomanizationResult::Edges(mut edges) = output.result else {
+            unreachable!("the edges format always yields edges");
+        };
+        for edge in &mut edges {
+            let data = edge.get_data_mut();
+            data.start += range.start;
+            data.end += range.start;
+        }
+        edges
+    }
+}
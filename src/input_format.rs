@@ -0,0 +1,207 @@
+//! Structured input formats, whose fields are romanized and whose markup is kept, see
+//! [`OutputOptions::input_format`](crate::OutputOptions::input_format).
+
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{RomFormat, RomanizationError, RomanizeOptions, Uroman};
+
+/// The number of non-empty lines at the start of the input that [`InputFormat::detect`] looks
+/// at.
+const SNIFFED_LINES: usize = 20;
+
+static SRT_TIMING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*\d+:\d{2}:\d{2}[,.]\d{3}\s+-->\s+\d+:\d{2}:\d{2}[,.]\d{3}").unwrap()
+});
+
+/// The format of the lines of a file, see [`OutputOptions::input_format`](crate::OutputOptions::input_format).
+///
+/// The structured formats romanize the text in their fields in the `str` format and keep the
+/// rest of the line as it is, so that the output is a file of the same format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum InputFormat {
+    /// Plain text, whose lines are romanized whole.
+    #[default]
+    Text,
+    /// JSON Lines, with a JSON value on each line. The strings of the values are romanized,
+    /// and the keys of objects, the other values and the whitespace are kept as they are.
+    Jsonl,
+    /// Tab-separated values. Each field is romanized on its own, and the tabs are kept.
+    Tsv,
+    /// SubRip subtitles. The timings and the blank lines between cues are kept as they are,
+    /// and the other lines are romanized, which keeps the cue numbers as they are too.
+    Srt,
+}
+
+impl InputFormat {
+    /// Guesses the format of a file from `sample`, the start of the file cut at the end of a
+    /// line. Files are SubRip subtitles if they start with a cue number and a timing, JSON Lines
+    /// if every line is a JSON object or array, and tab-separated values if there are several
+    /// lines with the same number of tabs, and plain text otherwise. Files with lines that start
+    /// with `::`, such as the `::lcode` directives of plain text, are plain text.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::InputFormat;
+    ///
+    /// let srt = "1\n00:00:01,000 --> 00:00:02,500\nПривіт!\n";
+    /// assert_eq!(InputFormat::detect(srt), InputFormat::Srt);
+    /// assert_eq!(InputFormat::detect("{\"text\": \"Привіт\"}\n"), InputFormat::Jsonl);
+    /// assert_eq!(InputFormat::detect("1\tПривіт\n2\tсвіт\n"), InputFormat::Tsv);
+    /// assert_eq!(InputFormat::detect("Привіт, світ!\n"), InputFormat::Text);
+    /// assert_eq!(InputFormat::detect("::lcode ukr Київ\tЛьвів\n"), InputFormat::Text);
+    /// ```
+    pub fn detect(sample: &str) -> InputFormat {
+        let lines: Vec<&str> = sample
+            .trim_start_matches('\u{FEFF}')
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(SNIFFED_LINES)
+            .collect();
+        // Directives apply to the whole line, not to a field of it.
+        if lines.iter().any(|line| line.starts_with("::")) {
+            return InputFormat::Text;
+        }
+        let [first, second, ..] = lines[..] else {
+            return match lines.first() {
+                Some(line) if is_json_line(line) => InputFormat::Jsonl,
+                _ => InputFormat::Text,
+            };
+        };
+
+        let tabs = first.matches('\t').count();
+        if is_cue_number(first) && SRT_TIMING.is_match(second) {
+            InputFormat::Srt
+        } else if lines.iter().all(|line| is_json_line(line)) {
+            InputFormat::Jsonl
+        } else if tabs > 0 && lines.iter().all(|line| line.matches('\t').count() == tabs) {
+            InputFormat::Tsv
+        } else {
+            InputFormat::Text
+        }
+    }
+
+    /// The format of a file by the extension of its `path`, such as `.jsonl` or `.srt`, if it
+    /// is one of the structured formats.
+    pub fn from_extension(path: &Path) -> Option<InputFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "jsonl" | "ndjson" => Some(InputFormat::Jsonl),
+            "tsv" | "tab" => Some(InputFormat::Tsv),
+            "srt" => Some(InputFormat::Srt),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `line` is a JSON object or array, as the lines of JSON Lines files usually are.
+fn is_json_line(line: &str) -> bool {
+    line.trim_start().starts_with(['{', '['])
+        && serde_json::from_str::<serde::de::IgnoredAny>(line).is_ok()
+}
+
+/// Whether `line` looks like the cue number of a SubRip subtitle, which it is if a timing
+/// follows it.
+fn is_cue_number(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && line.bytes().all(|b| b.is_ascii_digit())
+}
+
+impl Uroman {
+    /// Romanizes a line of a file in one of the structured formats, see [`InputFormat`].
+    pub(crate) fn romanize_structured_line(
+        &self,
+        line: &str,
+        options: &RomanizeOptions,
+    ) -> Result<String, RomanizationError> {
+        let options = if options.output.rom_format == RomFormat::Str {
+            Cow::Borrowed(options)
+        } else {
+            Cow::Owned(options.clone().with_format(RomFormat::Str))
+        };
+        map_structured_fields(line, options.output.input_format, |field| {
+            self.romanize_checked(field, &options)?.to_output_string()
+        })
+    }
+}
+
+/// Replaces the fields of `line` in the format `input_format` that are romanized with what
+/// `romanize` returns for them, and keeps the rest of the line as it is.
+pub(crate) fn map_structured_fields(
+    line: &str,
+    input_format: InputFormat,
+    mut romanize: impl FnMut(&str) -> Result<String, RomanizationError>,
+) -> Result<String, RomanizationError> {
+    match input_format {
+        InputFormat::Text => romanize(line),
+        InputFormat::Jsonl => romanize_json_strings(line, romanize),
+        InputFormat::Tsv => Ok(line
+            .split('\t')
+            .map(romanize)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\t")),
+        // A cue number is only told from subtitle text such as `2024` by the timing after it,
+        // but both are romanized as text, to themselves as they are ASCII digits.
+        InputFormat::Srt if line.trim().is_empty() || SRT_TIMING.is_match(line) => {
+            Ok(line.to_string())
+        }
+        InputFormat::Srt => romanize(line),
+    }
+}
+
+/// Romanizes the strings of the JSON values in `line` with `romanize`, leaving the keys of
+/// objects and everything else as it is. Blank lines are kept.
+fn romanize_json_strings(
+    line: &str,
+    mut romanize: impl FnMut(&str) -> Result<String, RomanizationError>,
+) -> Result<String, RomanizationError> {
+    if line.trim().is_empty() {
+        return Ok(line.to_string());
+    }
+    serde_json::from_str::<serde::de::IgnoredAny>(line)
+        .map_err(|e| RomanizationError::InvalidJsonLine(e.to_string()))?;
+
+    // Every quote outside of a string starts one in valid JSON.
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('"') {
+        let end = start + string_literal_len(&rest[start..]);
+        output.push_str(&rest[..start]);
+        let literal = &rest[start..end];
+        rest = &rest[end..];
+        if rest.trim_start().starts_with(':') {
+            output.push_str(literal);
+            continue;
+        }
+        let value: String = serde_json::from_str(literal)?;
+        let romanized = romanize(&value)?;
+        if romanized == value {
+            output.push_str(literal);
+        } else {
+            output.push_str(&serde_json::to_string(&romanized)?);
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// The length in bytes of the JSON string literal that `s` starts with, with its quotes.
+fn string_literal_len(s: &str) -> usize {
+    let mut bytes = s.bytes().enumerate().skip(1);
+    while let Some((i, b)) = bytes.next() {
+        match b {
+            b'\\' => {
+                bytes.next();
+            }
+            b'"' => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
@@ -0,0 +1,30 @@
+//! A small string interner for edge types, script names and rule provenances.
+//!
+//! These strings take only a handful of distinct values, but are attached to every edge
+//! and rule. Interning lets them share one allocation instead of being cloned each time.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Upper bound on interned strings per thread, in case annotations are built from input text.
+const MAX_INTERNED: usize = 4096;
+
+thread_local! {
+    static INTERNED: RefCell<HashSet<Arc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// Returns a shared `Arc<str>` for `s`, allocating only the first time `s` is seen on this thread.
+pub(crate) fn intern(s: &str) -> Arc<str> {
+    INTERNED.with(|interned| {
+        let mut interned = interned.borrow_mut();
+        if let Some(existing) = interned.get(s) {
+            return existing.clone();
+        }
+        let new: Arc<str> = Arc::from(s);
+        if interned.len() < MAX_INTERNED {
+            interned.insert(new.clone());
+        }
+        new
+    })
+}
@@ -0,0 +1,226 @@
+//! Normalizing BCP-47 language tags and ISO 639-1 codes to the ISO 639-3 codes of the rules.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+use crate::data_table::LangCodeRecord;
+use crate::lattice::NO_FINAL_VOWEL_LCODES;
+use crate::utils::edit_distance;
+use crate::{RomanizationError, Uroman};
+
+/// The most close matches an [`RomanizationError::UnknownLanguageCode`] suggests.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// The language codes of `ISO-639-3-list.txt`.
+struct LangCodes {
+    /// The ISO 639-3 code of each code, and of each deprecated code (ISO 639-1 codes such as
+    /// `uk`, and ISO 639-2/B codes such as `chi`) that is not itself a current code.
+    codes: HashMap<String, String>,
+    /// Each code with its names, such as `Bengali; Bangla`.
+    names: Vec<(String, String)>,
+}
+
+static LANG_CODES: LazyLock<LangCodes> = LazyLock::new(|| {
+    let table = crate::decode_embedded_table(include_bytes!(concat!(
+        env!("OUT_DIR"),
+        "/ISO-639-3-list.tbl"
+    )));
+    let records: Vec<LangCodeRecord> = table.lines().filter_map(LangCodeRecord::parse).collect();
+    let mut codes: HashMap<String, String> = records
+        .iter()
+        .map(|rec| (rec.lang_code.to_string(), rec.lang_code.to_string()))
+        .collect();
+    for rec in &records {
+        let deprecated = rec
+            .deprecated_lang_codes
+            .into_iter()
+            .flat_map(|codes| codes.split(';'));
+        for code in deprecated.map(str::trim).filter(|code| !code.is_empty()) {
+            codes
+                .entry(code.to_string())
+                .or_insert_with(|| rec.lang_code.to_string());
+        }
+    }
+    let names = records
+        .iter()
+        .filter_map(|rec| Some((rec.lang_code.to_string(), rec.lang_names?.to_string())))
+        .collect();
+    LangCodes { codes, names }
+});
+
+impl Uroman {
+    /// Returns the language codes that change the romanization: those that rules are
+    /// conditioned on, and those of the language-specific behaviors that are not rules.
+    ///
+    /// Other codes romanize text like no code.
+    pub fn effective_lcodes(&self) -> BTreeSet<&str> {
+        let rule_lcodes = self
+            .rom_rules
+            .values()
+            .flatten()
+            .flat_map(|rule| &rule.lcodes)
+            .map(String::as_str);
+        rule_lcodes
+            .chain(NO_FINAL_VOWEL_LCODES)
+            .chain(["san"])
+            .collect()
+    }
+
+    /// Normalizes `lcode` like [`normalize_lcode`], and checks that each of its codes is an
+    /// ISO 639-3 code or one of [`Uroman::effective_lcodes`].
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::UnknownLanguageCode`] with close matches for a code that is
+    /// neither, or can't be normalized.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomanizationError, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// assert_eq!(uroman.validate_lcode("uk,srp2").unwrap(), "ukr,srp2");
+    /// let Err(RomanizationError::UnknownLanguageCode { suggestions, .. }) =
+    ///     uroman.validate_lcode("ukrainian")
+    /// else {
+    ///     panic!("expected an unknown language code");
+    /// };
+    /// assert_eq!(suggestions, ["ukr"]);
+    /// ```
+    pub fn validate_lcode(&self, lcode: &str) -> Result<String, RomanizationError> {
+        let effective_lcodes = self.effective_lcodes();
+        let mut codes = Vec::new();
+        for tag in lcode
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+        {
+            match normalize_lang_tag(tag) {
+                Ok(code)
+                    if effective_lcodes.contains(code) || LANG_CODES.codes.contains_key(code) =>
+                {
+                    codes.push(code);
+                }
+                _ => {
+                    return Err(RomanizationError::UnknownLanguageCode {
+                        lcode: tag.to_string(),
+                        suggestions: suggest_lcodes(tag, &effective_lcodes),
+                        effective_lcodes: effective_lcodes
+                            .iter()
+                            .map(|lc| lc.to_string())
+                            .collect(),
+                    });
+                }
+            }
+        }
+        if codes.is_empty() {
+            return Err(RomanizationError::UnknownLanguageTag(lcode.to_string()));
+        }
+        Ok(codes.join(","))
+    }
+}
+
+/// Normalizes a language code, or a comma-separated fallback chain of them, to the ISO 639-3
+/// codes that the romanization rules are conditioned on.
+///
+/// Each code may be an ISO 639-3 code (`ukr`), an ISO 639-1 code (`uk`) or a BCP-47 tag
+/// (`zh-Hant`, `sr_Latn`), of which only the language subtag is kept. Codes that are not in
+/// the ISO 639-3 list but look like rule codes, such as `srp2`, are kept as they are.
+///
+/// # Errors
+/// Returns [`RomanizationError::UnknownLanguageTag`] for a code that is neither.
+///
+/// # Example
+/// ```
+/// assert_eq!(uroman::normalize_lcode("uk, sr-Latn").unwrap(), "ukr,srp");
+/// assert!(uroman::normalize_lcode("xx").is_err());
+/// ```
+pub fn normalize_lcode(lcode: &str) -> Result<String, RomanizationError> {
+    let codes = lcode
+        .split(',')
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(normalize_lang_tag)
+        .collect::<Result<Vec<_>, _>>()?;
+    if codes.is_empty() {
+        return Err(RomanizationError::UnknownLanguageTag(lcode.to_string()));
+    }
+    Ok(codes.join(","))
+}
+
+/// Normalizes a single language tag, see [`normalize_lcode`].
+pub(crate) fn normalize_lang_tag(tag: &str) -> Result<&str, RomanizationError> {
+    let unknown = || RomanizationError::UnknownLanguageTag(tag.to_string());
+    let mut subtags = tag.split(['-', '_']);
+    let language = subtags.next().unwrap_or_default();
+    let is_subtag = |subtag: &str| {
+        (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+    };
+    if !is_subtag(language) || !subtags.all(is_subtag) {
+        return Err(unknown());
+    }
+
+    if let Some(code) = LANG_CODES.codes.get(language) {
+        return Ok(code);
+    }
+    let lowercase = language.to_ascii_lowercase();
+    if let Some(code) = LANG_CODES.codes.get(&lowercase) {
+        return Ok(code);
+    }
+    // Codes of the rules that are not ISO 639-3 codes, such as `srp2`.
+    if language.len() >= 3 && language == tag {
+        return Ok(language);
+    }
+    Err(unknown())
+}
+
+/// Returns the ISO 639-3 code of the language with the name `name`, e.g. `jpn` for
+/// `Japanese`.
+pub(crate) fn lcode_of_language_name(name: &str) -> Option<&'static str> {
+    LANG_CODES
+        .names
+        .iter()
+        .find(|(_, names)| {
+            names
+                .split(';')
+                .any(|n| n.trim().eq_ignore_ascii_case(name))
+        })
+        .map(|(lc, _)| lc.as_str())
+}
+
+/// Returns the codes that `code` is the start of a language name of, and then those that it
+/// may be a typo of, codes with rules first.
+fn suggest_lcodes(code: &str, effective_lcodes: &BTreeSet<&str>) -> Vec<String> {
+    let code = code.to_lowercase();
+    let known_codes = effective_lcodes
+        .iter()
+        .copied()
+        .chain(LANG_CODES.names.iter().map(|(lc, _)| lc.as_str()));
+    let by_spelling = known_codes.filter(|lc| edit_distance(&code, lc) <= 1);
+    let by_name = LANG_CODES
+        .names
+        .iter()
+        .filter(|(_, names)| {
+            code.len() >= 4
+                && names
+                    .split(';')
+                    .any(|name| name.trim().to_lowercase().starts_with(&code))
+        })
+        .map(|(lc, _)| lc.as_str());
+
+    let mut suggestions: Vec<String> = Vec::new();
+    for lc in by_name.chain(by_spelling) {
+        if suggestions.len() < MAX_SUGGESTIONS && !suggestions.iter().any(|s| s == lc) {
+            suggestions.push(lc.to_string());
+        }
+    }
+    suggestions
+}
+
+/// Formats the suggestions of an [`RomanizationError::UnknownLanguageCode`].
+pub(crate) fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" (did you mean {}?)", suggestions.join(", "))
+    }
+}
@@ -1,10 +1,16 @@
+use crate::cancel::Interrupt;
 use crate::decompositions::DECOMPOSITIONS;
-use crate::edge::{Edge, EdgeData, NumData, NumDataUpdates};
+use crate::edge::{Edge, EdgeData, NumData, NumDataUpdates, checked_ratio};
+use crate::hash::{HashMap, HashSet};
+use crate::intern::intern;
 use crate::rom_rule::RomRule;
-use crate::{AbugidaCacheEntry, Uroman, rom_format};
-use num_rational::Ratio;
+use crate::rule_trie::RuleTrie;
+use crate::{
+    AbugidaCacheEntry, BoolProp, CombiningMarkPolicy, FormatCharPolicy, PINYIN_PROV, StrProp,
+    Subsystems, UnmappableCharPolicy, Uroman, lcode_chain, rom_format,
+};
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
 use std::sync::LazyLock;
 use unicode_properties::{GeneralCategory, GeneralCategoryGroup, UnicodeGeneralCategory};
 
@@ -35,6 +41,10 @@ static CONTAINS_VOWEL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[aeiou
 static STARTS_WITH_DIGIT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d").unwrap());
 static ENDS_WITH_DIGIT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d$").unwrap());
 
+/// Languages whose abugida words don't end with the default vowel. Devanagari words don't
+/// either, except in Sanskrit.
+pub(crate) const NO_FINAL_VOWEL_LCODES: [&str; 5] = ["asm", "ben", "guj", "kas", "pan"];
+
 static GOOD_PREFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"'?(?:.|bd|br|brg|brgy|bs|bsh|bst|bt|bts|by|bz|bzh|ch|db|dby|dk|dm|dp|dpy|dr|gl|gn|gr|gs|gt|gy|gzh|kh|khr|khy|kr|ky|ld|lh|lt|mkh|mny|mth|mtsh|ny|ph|phr|phy|rgy|rk|el|rn|rny|rt|rts|sk|skr|sky|sl|sm|sn|sny|sp|spy|sr|st|th|ts|tsh)$").unwrap()
 });
@@ -53,13 +63,19 @@ static THAI_CONSONANT_XZ_ONLY_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^[bcdfghjklmnpqrstvwxz]+$").unwrap());
 
 pub(super) struct Lattice<'a> {
-    pub s: String,
+    pub s: &'a str,
     pub s_chars: Vec<char>,
+    /// Byte offset of each char in `s`, followed by `s.len()`, so that a char span
+    /// maps to a byte range in O(1).
+    pub char_offsets: Vec<usize>,
+    /// The first of `lcodes`, for the language-specific behaviors that are not rules.
     pub lcode: Option<String>,
+    /// The language codes that conditional rules are tried against, in priority order.
+    pub lcodes: Vec<String>,
     pub uroman: &'a Uroman,
 
     // self.lattice[(edge.start, edge.end)]
-    pub edge_lattice: HashMap<(usize, usize), HashSet<Edge>>,
+    pub edge_lattice: HashMap<(usize, usize), HashSet<Edge<'a>>>,
     // self.lattice[(edge.start, 'right')]
     pub right_links: HashMap<usize, HashSet<usize>>,
     // self.lattice[(edge.end, 'left')]
@@ -71,47 +87,89 @@ pub(super) struct Lattice<'a> {
     pub props: HashMap<(String, usize), Option<bool>>,
 
     // self.simple_top_rom_cache: dict
-    pub simple_top_rom_cache: HashMap<(usize, usize), Option<String>>,
+    pub simple_top_rom_cache: HashMap<(usize, usize), Option<Cow<'a, str>>>,
     // self.contains_script: defaultdict(bool)
     pub contains_script: HashMap<String, bool>,
+    /// Set once a span would exceed `Uroman::max_edges_per_span`, the lattice would exceed
+    /// `max_edges`, or `interrupt` becomes due. From then on no more edges are added, and
+    /// the string is romanized with `greedy_rom_edge_path` instead.
+    pub exceeded_edge_cap: bool,
+    /// The number of edges added.
+    pub n_edges: usize,
+    /// The maximum number of edges, see `Uroman::lattice_edge_cap`.
+    pub max_edges: Option<usize>,
+    /// When to stop building the lattice, see `Uroman::with_time_limit`.
+    pub interrupt: Interrupt,
+    /// The stages of romanization to run.
+    pub subsystems: Subsystems,
+    /// The spans romanized by rules with negative weights, which the best path avoids.
+    pub avoided_spans: HashSet<(usize, usize)>,
+    /// The conditions of the rules that romanized spans, see `Uroman::with_rule_conditions`.
+    pub span_conditions: HashMap<(usize, usize), Vec<String>>,
+    /// `s_chars` with their uppercase letters lowercased, see `Lattice::fold_chars`.
+    pub folded_chars: Option<Vec<char>>,
 }
 
 #[allow(unused)]
 #[derive(Debug, Clone)]
-pub enum BackwardsPathResult {
+pub enum BackwardsPathResult<'a> {
     Str(String),
-    Edges(Vec<Edge>),
+    Edges(Vec<Edge<'a>>),
 }
 
 impl<'a> Lattice<'a> {
     pub fn new(s: &'a str, uroman: &'a Uroman, lcode: Option<&str>) -> Self {
-        let s_chars: Vec<char> = s.chars().collect();
+        let mut s_chars = Vec::with_capacity(s.len());
+        let mut char_offsets = Vec::with_capacity(s.len() + 1);
+        for (offset, c) in s.char_indices() {
+            s_chars.push(c);
+            char_offsets.push(offset);
+        }
+        char_offsets.push(s.len());
         let max_vertex = s_chars.len();
+        let lcodes: Vec<String> = lcode_chain(lcode).map(String::from).collect();
 
         let mut lattice = Self {
             s_chars,
-            s: s.to_string(),
-            lcode: lcode.map(String::from),
+            char_offsets,
+            s,
+            lcode: lcodes.first().cloned(),
+            lcodes,
             uroman,
-            edge_lattice: HashMap::new(),
-            right_links: HashMap::new(),
-            left_links: HashMap::new(),
+            edge_lattice: HashMap::default(),
+            right_links: HashMap::default(),
+            left_links: HashMap::default(),
             max_vertex,
-            props: HashMap::new(),
-            simple_top_rom_cache: HashMap::new(),
-            contains_script: HashMap::new(),
+            props: HashMap::default(),
+            simple_top_rom_cache: HashMap::default(),
+            contains_script: HashMap::default(),
+            exceeded_edge_cap: false,
+            n_edges: 0,
+            max_edges: uroman.lattice_edge_cap(),
+            interrupt: Interrupt::default(),
+            subsystems: uroman.subsystems,
+            avoided_spans: HashSet::default(),
+            span_conditions: HashMap::default(),
+            folded_chars: None,
         };
 
+        lattice.folded_chars = lattice.fold_chars();
         lattice.check_for_scripts();
 
         lattice
     }
 
+    /// Returns the substring of `s` covering the char span `[start, end)`.
+    #[inline]
+    pub fn substr(&self, start: usize, end: usize) -> &'a str {
+        &self.s[self.char_offsets[start]..self.char_offsets[end]]
+    }
+
     fn check_for_scripts(&mut self) {
         for &c in &self.s_chars {
             let script_name = self.uroman.chr_script_name(c);
             if !script_name.is_empty() {
-                self.contains_script.insert(script_name, true);
+                self.contains_script.insert(script_name.to_string(), true);
             }
         }
         if self
@@ -122,32 +180,76 @@ impl<'a> Lattice<'a> {
         }
     }
 
-    pub fn add_edge(&mut self, edge: Edge) {
+    pub fn add_edge(&mut self, edge: Edge<'a>) {
+        if self.exceeded_edge_cap {
+            return;
+        }
         let (start, end) = (edge.start(), edge.end());
 
-        self.edge_lattice
-            .entry((start, end))
-            .or_default()
-            .insert(edge);
+        // Reading the clock costs more than adding an edge, so it is only read now and then.
+        let interrupted = self.n_edges.is_multiple_of(256) && self.interrupt.is_due();
+        let edges = self.edge_lattice.entry((start, end)).or_default();
+        let exceeds_caps = !edges.contains(&edge)
+            && (self
+                .uroman
+                .max_edges_per_span
+                .is_some_and(|max| edges.len() >= max)
+                || self.max_edges.is_some_and(|max| self.n_edges >= max));
+        if exceeds_caps || interrupted {
+            self.exceeded_edge_cap = true;
+            return;
+        }
+        if edges.insert(edge) {
+            self.n_edges += 1;
+        }
 
         self.right_links.entry(start).or_default().insert(end);
 
         self.left_links.entry(end).or_default().insert(start);
     }
 
-    fn cand_is_valid(&mut self, rule: &RomRule, start: usize, end: usize) -> bool {
-        let lcode_ok = rule.lcodes.is_empty()
-            || self
-                .lcode
-                .as_ref()
-                .is_some_and(|lc| rule.lcodes.contains(lc));
+    /// Returns the index in `lcodes` of the language code to romanize the span with: the first
+    /// one that a rule of `rules` is conditioned on and could be used for the span with.
+    fn span_lcode(&mut self, rules: &[RomRule], start: usize, end: usize) -> Option<usize> {
+        if self.lcodes.len() <= 1 {
+            return (!self.lcodes.is_empty()).then_some(0);
+        }
+        for i in 0..self.lcodes.len() {
+            for rule in rules {
+                if rule.lcodes.contains(&self.lcodes[i])
+                    && self.word_conditions_ok(rule, start, end)
+                {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns `true` if `rule` can be used for the span, with the language code
+    /// `lcodes[lcode]`.
+    fn cand_is_valid(
+        &mut self,
+        rule: &RomRule,
+        start: usize,
+        end: usize,
+        lcode: Option<usize>,
+    ) -> bool {
+        let lcode_ok =
+            rule.lcodes.is_empty() || lcode.is_some_and(|i| rule.lcodes.contains(&self.lcodes[i]));
+        let subsystem_ok = self.subsystems.pinyin || &*rule.prov != PINYIN_PROV;
+        lcode_ok && subsystem_ok && self.word_conditions_ok(rule, start, end)
+    }
+
+    /// Returns `true` if the span satisfies the word boundary conditions of `rule`.
+    fn word_conditions_ok(&mut self, rule: &RomRule, start: usize, end: usize) -> bool {
         let start_ok = (!rule.use_only_at_start_of_word || self.is_at_start_of_word(start))
             && (!rule.dont_use_at_start_of_word || !self.is_at_start_of_word(start));
         let end_ok = (!rule.use_only_at_end_of_word || self.is_at_end_of_word(end))
             && (!rule.dont_use_at_end_of_word || !self.is_at_end_of_word(end));
         let whole_word_ok = !rule.use_only_for_whole_word
             || (self.is_at_start_of_word(start) && self.is_at_end_of_word(end));
-        lcode_ok && start_ok && end_ok && whole_word_ok
+        start_ok && end_ok && whole_word_ok
     }
 
     pub fn is_at_start_of_word(&mut self, position: usize) -> bool {
@@ -222,12 +324,18 @@ impl<'a> Lattice<'a> {
             start += 1;
         }
 
+        let uroman = self.uroman;
+        let mut node = RuleTrie::ROOT;
         for end in (start + 1)..=self.max_vertex {
-            let sub: String = self.s_chars[start..end].iter().collect();
-            if !self.uroman.dict_bool_get("s-prefix", &sub) {
+            let Some(child) = uroman.rule_trie.child(node, self.s_chars[end - 1]) else {
                 break;
-            }
-            if let Some(rom_rules) = self.uroman.rom_rules.get(&sub) {
+            };
+            node = child;
+            if let Some((_, rom_rules)) = uroman
+                .rule_trie
+                .value(node)
+                .and_then(|i| uroman.rom_rules.get_index(i))
+            {
                 for rom_rule in rom_rules {
                     if !rom_rule.use_only_at_start_of_word
                         && let Some(rom) = &rom_rule.t
@@ -254,7 +362,7 @@ impl<'a> Lattice<'a> {
 
         let mut adj_position = position;
         if let Some(nc) = next_char
-            && !self.uroman.dict_str_get("tone-mark", nc).is_empty()
+            && !self.uroman.dict_str_get(StrProp::ToneMark, nc).is_empty()
         {
             adj_position += 1;
             next_char = self.s_chars.get(adj_position).copied();
@@ -268,7 +376,7 @@ impl<'a> Lattice<'a> {
             if !P_L_OR_M_END.is_match(&pc.to_string()) {
                 return (false, "start-of-token".to_string());
             }
-            if self.uroman.dict_str_get("syllable-info", pc)
+            if self.uroman.dict_str_get(StrProp::SyllableInfo, pc)
                 == "written-pre-consonant-spoken-post-consonant"
             {
                 return (false, "pre-post-vowel-on-left".to_string());
@@ -276,7 +384,7 @@ impl<'a> Lattice<'a> {
         }
 
         if let Some(nc) = next_char
-            && self.uroman.dict_str_get("syllable-info", nc)
+            && self.uroman.dict_str_get(StrProp::SyllableInfo, nc)
                 == "written-pre-consonant-spoken-post-consonant"
         {
             return (true, "pre-post-vowel-on-right".to_string());
@@ -313,7 +421,7 @@ impl<'a> Lattice<'a> {
                     true,
                 )
             })
-            .unwrap_or_else(|| "?".to_string());
+            .unwrap_or(Cow::Borrowed("?"));
 
         if !VOWEL_START_RE.is_match(&next_char_rom.to_lowercase()) {
             return (true, format!("not-followed-by-vowel {next_char_rom}"));
@@ -327,7 +435,7 @@ impl<'a> Lattice<'a> {
                     (adj_position + 2).min(self.max_vertex),
                     true,
                 )
-                .unwrap_or_else(|| "?".to_string());
+                .unwrap_or(Cow::Borrowed("?"));
             if VOWEL_START_RE.is_match(&next_char2_rom.to_lowercase()) {
                 return (true, "o-ang-followed-by-vowel".to_string());
             }
@@ -342,7 +450,7 @@ impl<'a> Lattice<'a> {
 
     fn edge_is_digit(edge: &Edge) -> bool {
         if let Edge::Numeric { data, num_data } = edge
-            && data.r#type == "digit"
+            && &*data.r#type == "digit"
             && data.end - data.start == 1
             && let Some(val) = num_data.value
         {
@@ -352,12 +460,12 @@ impl<'a> Lattice<'a> {
     }
 
     fn char_is_letter_or_vowel_sign(&self, c: char) -> bool {
-        let is_vowel_sign = self.uroman.dict_bool_get("is-vowel-sign", &c.to_string());
+        let is_vowel_sign = self.uroman.dict_bool_get_char(BoolProp::VowelSign, c);
         self.uroman.chr_name(c).contains("LETTER") || is_vowel_sign
     }
 
     fn char_is_vowel_sign(&self, c: char) -> bool {
-        self.uroman.dict_bool_get("is-vowel-sign", &c.to_string())
+        self.uroman.dict_bool_get_char(BoolProp::VowelSign, c)
     }
 
     fn char_is_subjoined_letter(&self, c: char) -> bool {
@@ -369,7 +477,7 @@ impl<'a> Lattice<'a> {
         start: usize,
         end: usize,
         simple_search: bool,
-    ) -> Option<String> {
+    ) -> Option<Cow<'a, str>> {
         if end > self.max_vertex { return None; }
         let span_range = (start, end);
         if !simple_search
@@ -377,8 +485,8 @@ impl<'a> Lattice<'a> {
                 return cached_result.clone();
             }
 
-        let sub: String = self.s_chars[start..end].iter().collect();
-        let Some(rules) = self.uroman.rom_rules.get(&sub) else {
+        let uroman = self.uroman;
+        let Some(rules) = self.rules_for_span(start, end) else {
             if !simple_search { self.simple_top_rom_cache.insert(span_range, None); }
             return None;
         };
@@ -386,21 +494,24 @@ impl<'a> Lattice<'a> {
         let mut best_rule_with_t: Option<&RomRule> = None;
         let mut best_rule_without_t: Option<&RomRule> = None;
 
+        let lcode = self.span_lcode(rules, start, end);
+        let priority = &uroman.rule_priority;
         for rule in rules.iter() {
-            if self.cand_is_valid(rule, start, end) {
-                if rule.t.is_some() {
-                    if best_rule_with_t.is_none() || rule.n_restr > best_rule_with_t.unwrap().n_restr {
-                        best_rule_with_t = Some(rule);
-                    }
-                } else if best_rule_without_t.is_none() || rule.n_restr > best_rule_without_t.unwrap().n_restr {
-                    best_rule_without_t = Some(rule);
+            if self.cand_is_valid(rule, start, end, lcode) {
+                let best_rule = if rule.t.is_some() {
+                    &mut best_rule_with_t
+                } else {
+                    &mut best_rule_without_t
+                };
+                if best_rule.is_none_or(|best| rule.cmp_priority(best, priority).is_lt()) {
+                    *best_rule = Some(rule);
                 }
             }
         }
 
         let best_rule = best_rule_with_t.or(best_rule_without_t);
 
-        let best_cand = best_rule.and_then(|r| r.t.clone());
+        let best_cand = best_rule.and_then(|r| r.t.as_deref()).map(Cow::Borrowed);
 
         if simple_search {
             return best_cand;
@@ -410,13 +521,25 @@ impl<'a> Lattice<'a> {
             self.simple_top_rom_cache.insert(span_range, None);
             return None;
         };
+        if rule.weight < 0.0 {
+            self.avoided_spans.insert(span_range);
+        }
 
-        let mut final_cand = rule.t.clone();
+        let mut final_cand = rule.t.as_deref().map(Cow::Borrowed);
+        let mut is_syllable_end = false;
         if let Some(t_at_end) = &rule.t_at_end_of_syllable {
             let (is_end, _rationale) = self.is_at_end_of_syllable(end);
             if is_end {
-                final_cand = Some(t_at_end.clone());
+                final_cand = Some(Cow::Borrowed(t_at_end));
+                is_syllable_end = true;
+            }
+        }
+        if uroman.rule_conditions {
+            let mut conditions = rule.conditions();
+            if is_syllable_end {
+                conditions.push("t-end-of-syllable".to_string());
             }
+            self.span_conditions.insert(span_range, conditions);
         }
 
         self.simple_top_rom_cache
@@ -424,6 +547,7 @@ impl<'a> Lattice<'a> {
         final_cand
     }
 
+    #[cfg(feature = "braille")]
     pub fn prep_braille(&mut self) {
         if !self
             .contains_script
@@ -468,12 +592,7 @@ impl<'a> Lattice<'a> {
             let orig_char = self.s_chars[start];
             let (rom, edge_annotation) = self.get_fallback_rom_and_annot(orig_char, start, end);
 
-            self.add_edge(Edge::new_regular(
-                start,
-                end,
-                rom.clone(),
-                edge_annotation.clone(),
-            ));
+            self.add_edge(Edge::new_regular(start, end, rom, edge_annotation));
         }
     }
 
@@ -482,36 +601,157 @@ impl<'a> Lattice<'a> {
         orig_char: char,
         start: usize,
         end: usize,
-    ) -> (String, String) {
+    ) -> (Cow<'a, str>, &'static str) {
         match orig_char.general_category() {
             // Mn (Nonspacing_Mark) -> empty string
-            GeneralCategory::NonspacingMark => ("".to_string(), "Mn".to_string()),
+            GeneralCategory::NonspacingMark => (Cow::Borrowed(""), "Mn"),
             // Cf (Format) -> empty string
-            GeneralCategory::Format => ("".to_string(), "Cf".to_string()),
+            GeneralCategory::Format => (Cow::Borrowed(""), "Cf"),
             // Co (PrivateUse) -> empty string
-            GeneralCategory::PrivateUse => ("".to_string(), "Co".to_string()),
+            GeneralCategory::PrivateUse => (Cow::Borrowed(""), "Co"),
             // Zs (SpaceSeparator) -> half-width space
-            GeneralCategory::SpaceSeparator => (" ".to_string(), "Zs".to_string()),
+            GeneralCategory::SpaceSeparator => (Cow::Borrowed(" "), "Zs"),
             _ => {
                 if let Some(rom) = self.simple_top_romanization_candidate_for_span(start, end, true)
                 {
-                    let final_rom = if rom.starts_with('+')
-                        && rom.len() > 1
-                        && "mngr".contains(rom.chars().nth(1).unwrap())
-                    {
-                        rom[1..].to_string()
-                    } else {
-                        rom
+                    let final_rom = match rom {
+                        Cow::Borrowed(r)
+                            if r.starts_with('+')
+                                && r.len() > 1
+                                && "mngr".contains(r.chars().nth(1).unwrap()) =>
+                        {
+                            Cow::Borrowed(&r[1..])
+                        }
+                        rom => rom,
                     };
-                    (final_rom, "rom single".to_string())
+                    (final_rom, "rom single")
                 } else {
-                    (orig_char.to_string(), "orig".to_string())
+                    (Cow::Borrowed(self.substr(start, end)), "orig")
                 }
             }
         }
     }
 
-    pub fn all_edges(&self, start: usize, end: usize) -> Vec<Edge> {
+    /// Replaces the romanization of each format character (Cf) according to `policy`: with
+    /// the character itself, or with a space if it is between two words.
+    pub fn replace_format_chars(&mut self, policy: FormatCharPolicy) {
+        let is_format = |c: char| c.general_category() == GeneralCategory::Format;
+        for start in 0..self.max_vertex {
+            if !is_format(self.s_chars[start]) {
+                continue;
+            }
+            let rom = match policy {
+                FormatCharPolicy::Keep => self.substr(start, start + 1),
+                _ => {
+                    let is_word_char = |c: &char| !c.is_whitespace() && !is_format(*c);
+                    let prev_char = start.checked_sub(1).map(|i| self.s_chars[i]);
+                    let next_char = self.s_chars.get(start + 1);
+                    if prev_char.as_ref().is_some_and(is_word_char)
+                        && next_char.is_some_and(is_word_char)
+                    {
+                        " "
+                    } else {
+                        ""
+                    }
+                }
+            };
+            if let Some(edges) = self.edge_lattice.get_mut(&(start, start + 1)) {
+                edges.clear();
+            }
+            self.add_edge(Edge::new_regular(start, start + 1, rom, "Cf"));
+        }
+    }
+
+    /// Replaces the empty romanization of each combining mark (Mn) that no rule covers
+    /// according to `policy`: with the mark itself, or with its name.
+    pub fn replace_combining_marks(&mut self, policy: CombiningMarkPolicy) {
+        for start in 0..self.max_vertex {
+            let is_uncovered_mark = self
+                .edge_lattice
+                .get(&(start, start + 1))
+                .is_some_and(|edges| edges.iter().any(|edge| &*edge.get_data().r#type == "Mn"));
+            if !is_uncovered_mark {
+                continue;
+            }
+            let mark = self.s_chars[start];
+            let rom: Cow<'a, str> = match policy {
+                CombiningMarkPolicy::Name => match self.uroman.chr_name(mark) {
+                    name if name.is_empty() => format!("[U+{:04X}]", mark as u32).into(),
+                    name => format!("[{name}]").into(),
+                },
+                _ => self.substr(start, start + 1).into(),
+            };
+            if let Some(edges) = self.edge_lattice.get_mut(&(start, start + 1)) {
+                edges.clear();
+            }
+            self.add_edge(Edge::new_regular(start, start + 1, rom, "Mn"));
+        }
+    }
+
+    /// Replaces each non-ASCII char that passes through as it is according to `policy`: with
+    /// a marker, or with an escape of its code point.
+    pub fn replace_unmappable_chars(&mut self, policy: &'a UnmappableCharPolicy) {
+        for start in 0..self.max_vertex {
+            let c = self.s_chars[start];
+            let passes_through = self
+                .edge_lattice
+                .get(&(start, start + 1))
+                .is_some_and(|edges| edges.iter().any(|edge| &*edge.get_data().r#type == "orig"));
+            if c.is_ascii() || !passes_through {
+                continue;
+            }
+            let rom: Cow<'a, str> = match policy {
+                UnmappableCharPolicy::Replace(marker) => Cow::Borrowed(marker),
+                _ => Cow::Owned(format!("\\u{{{:04X}}}", c as u32)),
+            };
+            if let Some(edges) = self.edge_lattice.get_mut(&(start, start + 1)) {
+                edges.clear();
+            }
+            self.add_edge(Edge::new_regular(start, start + 1, rom, "orig"));
+        }
+    }
+
+    /// Keeps each whitespace char as it is instead of its romanization, and removes the edges
+    /// of rules and numbers that take it together with the chars around it.
+    pub fn preserve_whitespace(&mut self) {
+        let mut n_spaces_before = Vec::with_capacity(self.max_vertex + 1);
+        n_spaces_before.push(0);
+        for &c in &self.s_chars {
+            n_spaces_before.push(n_spaces_before.last().unwrap() + usize::from(c.is_whitespace()));
+        }
+        if n_spaces_before[self.max_vertex] == 0 {
+            return;
+        }
+        for (&(start, end), edges) in self.edge_lattice.iter_mut() {
+            if end - start > 1 && n_spaces_before[end] > n_spaces_before[start] {
+                edges.clear();
+            }
+        }
+        for start in 0..self.max_vertex {
+            let c = self.s_chars[start];
+            if !c.is_whitespace() {
+                continue;
+            }
+            let orig = self.substr(start, start + 1);
+            let is_kept = self
+                .edge_lattice
+                .get(&(start, start + 1))
+                .is_some_and(|edges| edges.iter().all(|edge| edge.get_data().txt == orig));
+            if is_kept {
+                continue;
+            }
+            let edge_annotation = match c.general_category() {
+                GeneralCategory::SpaceSeparator => "Zs",
+                _ => "orig",
+            };
+            if let Some(edges) = self.edge_lattice.get_mut(&(start, start + 1)) {
+                edges.clear();
+            }
+            self.add_edge(Edge::new_regular(start, start + 1, orig, edge_annotation));
+        }
+    }
+
+    pub fn all_edges(&self, start: usize, end: usize) -> Vec<Edge<'a>> {
         let mut result = Vec::new();
 
         // Python: for start2 in range(start, end):
@@ -537,7 +777,7 @@ impl<'a> Lattice<'a> {
         result
     }
 
-    pub fn best_edge_in_span(&self, start: usize, end: usize, skip_num_edge: bool) -> Option<Edge> {
+    pub fn best_edge_in_span(&self, start: usize, end: usize, skip_num_edge: bool) -> Option<Edge<'a>> {
         let edges = self.edge_lattice.get(&(start, end))?;
 
         let mut active_num_edge = None;
@@ -594,42 +834,54 @@ impl<'a> Lattice<'a> {
     //     None
     // }
 
-    pub fn best_right_neighbor_edge(&self, start: usize, skip_num_edge: bool) -> Option<Edge> {
+    pub fn best_right_neighbor_edge(&self, start: usize, skip_num_edge: bool) -> Option<Edge<'a>> {
         if let Some(ends) = self.right_links.get(&start) {
             let mut sorted_ends: Vec<_> = ends.iter().collect();
             sorted_ends.sort_by(|a, b| b.cmp(a));
 
+            // A span romanized by a rule with a negative weight is only used if no other span
+            // starts here.
+            let mut avoided_edge = None;
             for &end in sorted_ends {
                 if let Some(best_edge) = self.best_edge_in_span(start, end, skip_num_edge) {
-                    return Some(best_edge);
+                    if !self.avoided_spans.contains(&(start, end)) {
+                        return Some(best_edge);
+                    }
+                    avoided_edge.get_or_insert(best_edge);
                 }
             }
+            return avoided_edge;
         }
         None
     }
 
-    pub fn best_left_neighbor_edge(&self, end: usize, skip_num_edge: bool) -> Option<Edge> {
+    pub fn best_left_neighbor_edge(&self, end: usize, skip_num_edge: bool) -> Option<Edge<'a>> {
         if let Some(starts) = self.left_links.get(&end) {
             let mut sorted_starts: Vec<_> = starts.iter().collect();
             sorted_starts.sort();
 
+            let mut avoided_edge = None;
             for &start in sorted_starts {
                 if let Some(best_edge) = self.best_edge_in_span(start, end, skip_num_edge) {
-                    return Some(best_edge);
+                    if !self.avoided_spans.contains(&(start, end)) {
+                        return Some(best_edge);
+                    }
+                    avoided_edge.get_or_insert(best_edge);
                 }
             }
+            return avoided_edge;
         }
         None
     }
 
-    fn find_rom_edge_path_backwards(
+    pub fn find_rom_edge_path_backwards(
         &self,
         start: usize,
         end: usize,
         min_char_len: Option<usize>,
         return_str: bool,
         skip_num_edge: bool,
-    ) -> BackwardsPathResult {
+    ) -> BackwardsPathResult<'a> {
         let mut current_pos = end;
 
         if return_str {
@@ -762,14 +1014,14 @@ impl<'a> Lattice<'a> {
         for positions in tibetan_syllables {
             let mut vowel_pos: Option<usize> = None;
             let mut roms: Vec<String> = Vec::new();
-            let mut subjoined_letter_positions = HashSet::new();
+            let mut subjoined_letter_positions = HashSet::default();
             let first_letter_position = *positions.first().unwrap_or(&0);
 
             for &i in &positions {
                 let c = self.s_chars[i];
                 let mut rom = self
                     .simple_top_romanization_candidate_for_span(i, i + 1, true)
-                    .unwrap_or("?".to_string());
+                    .map_or_else(|| "?".to_string(), Cow::into_owned);
                 self.props.insert(("edge-vowel".to_string(), i), None);
 
                 if self.char_is_vowel_sign(c) || ROM_VOWEL_END_RE.is_match(&rom) {
@@ -850,7 +1102,10 @@ impl<'a> Lattice<'a> {
                         } else {
                             let good_prefix = GOOD_PREFIX_RE.is_match(&pre);
                             let good_suffix = GOOD_SUFFIX_RE.is_match(&post);
-                            let subjoined_suffix = positions[idx + 2..]
+                            // The last position has no suffix to check, as in uroman.py.
+                            let subjoined_suffix = positions
+                                .get(idx + 2..)
+                                .unwrap_or_default()
                                 .iter()
                                 .all(|p| subjoined_letter_positions.contains(p));
 
@@ -883,26 +1138,63 @@ impl<'a> Lattice<'a> {
         }
     }
 
+    /// Returns, for every start position, the end positions of all spans that are rule sources,
+    /// in ascending order.
+    ///
+    /// Multi-character sources are found in one pass with `rule_matcher`; if it is unavailable,
+    /// the prefixes are extended position by position along `rule_trie`.
+    fn rule_source_ends(&self) -> Vec<Vec<usize>> {
+        let uroman = self.uroman;
+        if let Some(matcher) = &uroman.rule_matcher {
+            let mut ends = matcher.candidate_ends(self.s, &self.char_offsets);
+            for (start, span_ends) in ends.iter_mut().enumerate() {
+                if uroman.rule_trie.get(&self.s_chars[start..start + 1]).is_some() {
+                    span_ends.insert(0, start + 1);
+                }
+            }
+            return ends;
+        }
+
+        (0..self.max_vertex)
+            .map(|start| {
+                let mut span_ends = Vec::new();
+                let mut node = RuleTrie::ROOT;
+                for end in (start + 1)..=self.max_vertex {
+                    let Some(child) = uroman.rule_trie.child(node, self.s_chars[end - 1]) else {
+                        break;
+                    };
+                    node = child;
+                    if uroman.rule_trie.value(node).is_some() {
+                        span_ends.push(end);
+                    }
+                }
+                span_ends
+            })
+            .collect()
+    }
+
     pub fn add_romanization(&mut self) {
+        let mut rule_source_ends = self.rule_source_ends();
+        self.add_folded_source_ends(&mut rule_source_ends);
+
         // Python: for start in range(self.max_vertex):
-        for start in 0..self.max_vertex {
+        for (start, span_ends) in rule_source_ends.iter().enumerate() {
             // Python: for end in range(start+1, self.max_vertex+1):
             //         if not self.uroman.dict_bool[('s-prefix', self.s[start:end])]: break
             //         if (rom := self.simple_top_romanization_candidate_for_span(start, end)) is not None:
             //             ...
-            for end in (start + 1)..(self.max_vertex + 1) {
-                let sub: String = self.s_chars[start..end].iter().collect();
-                if !self.uroman.dict_bool_get("s-prefix", &sub) {
-                    break;
-                }
+            // Only spans that are rule sources can yield a candidate, see `rule_source_ends`.
+            for &end in span_ends {
                 if let Some(mut rom) =
                     self.simple_top_romanization_candidate_for_span(start, end, false)
                 {
-                    if self
-                        .contains_script
-                        .get("Braille")
-                        .copied()
-                        .unwrap_or(false)
+                    rom = self.carry_case(rom, start, end);
+                    if self.subsystems.braille
+                        && self
+                            .contains_script
+                            .get("Braille")
+                            .copied()
+                            .unwrap_or(false)
                         && (end - start == 1)
                         && self
                             .props
@@ -911,23 +1203,28 @@ impl<'a> Lattice<'a> {
                             .flatten()
                             .unwrap_or(false)
                     {
-                        rom = rom.to_uppercase();
+                        rom = rom.to_uppercase().into();
                     }
 
                     // rom tail
                     let mut edge_annotation = "rom".to_string();
-                    if let Some(stripped) = rom.strip_prefix('+')
-                        && (stripped.len() == 1
-                            || (stripped.len() == 2 && stripped.starts_with('n')))
-                    {
-                        rom = stripped.to_string();
+                    if rom.strip_prefix('+').is_some_and(|stripped| {
+                        stripped.len() == 1 || (stripped.len() == 2 && stripped.starts_with('n'))
+                    }) {
+                        rom = match rom {
+                            Cow::Borrowed(r) => Cow::Borrowed(&r[1..]),
+                            Cow::Owned(r) => Cow::Owned(r[1..].to_string()),
+                        };
                         edge_annotation = "rom tail".to_string();
                     }
 
                     // Add the default vowel for Abugida
-                    let new_rom =
-                        self.add_default_abugida_vowel(rom.clone(), start, end, &edge_annotation);
-                    if new_rom.starts_with(&rom) {
+                    let new_rom = if self.subsystems.abugida_vowels {
+                        self.add_default_abugida_vowel(rom.clone(), start, end, &edge_annotation)
+                    } else {
+                        rom.clone()
+                    };
+                    if new_rom.starts_with(&*rom) {
                         let suffix = &new_rom[rom.len()..];
                         if !suffix.is_empty() && suffix.chars().all(|c| "aeiou".contains(c)) {
                             edge_annotation = format!("{edge_annotation} c:{rom} s:{suffix}");
@@ -940,26 +1237,27 @@ impl<'a> Lattice<'a> {
                     let (expanded_rom, new_start, new_end, exp_edge_annotation) =
                         self.expand_rom_with_special_chars(rom, start, end);
 
+                    if self.avoided_spans.contains(&(start, end)) {
+                        self.avoided_spans.insert((new_start, new_end));
+                    }
                     let final_annotation = exp_edge_annotation.unwrap_or(edge_annotation);
 
-                    self.add_edge(Edge::new_regular(
-                        new_start,
-                        new_end,
-                        expanded_rom,
-                        final_annotation,
-                    ));
+                    let mut edge =
+                        Edge::new_regular(new_start, new_end, expanded_rom, final_annotation);
+                    edge.get_data_mut().conditions =
+                        self.span_conditions.get(&(start, end)).cloned();
+                    self.add_edge(edge);
                 }
             }
 
             // Python: if start < len(self.s): ...
-            let char = self.s_chars[start];
-            let cp = char as u32;
-
             // Python: if 0xAC00 <= cp <= 0xD7A3: ... self.add_edge(...)
-            if (0xAC00..=0xD7A3).contains(&cp)
-                && let Some(rom) = self.uroman.unicode_hangul_romanization(char)
+            #[cfg(feature = "hangul")]
+            if self.subsystems.hangul
+                && (0xAC00..=0xD7A3).contains(&(self.s_chars[start] as u32))
+                && let Some(rom) = Uroman::unicode_hangul_romanization(self.s_chars[start])
             {
-                self.add_edge(Edge::new_regular(start, start + 1, rom, "rom".to_string()));
+                self.add_edge(Edge::new_regular(start, start + 1, rom.to_string(), "rom"));
             }
 
             // Python: if rom_decomp := self.decomp_rom(start): self.add_edge(...)
@@ -968,12 +1266,13 @@ impl<'a> Lattice<'a> {
                     start,
                     start + 1,
                     rom_decomp,
-                    "rom decomp".to_string(),
+                    "rom decomp",
                 ));
             }
         }
     }
 
+    #[cfg(feature = "braille")]
     pub fn add_braille_numbers(&mut self) {
         if !self
             .contains_script
@@ -1024,22 +1323,30 @@ impl<'a> Lattice<'a> {
             // Remove commas as they cause issues in numerical parsers
             let clean_txt = txt.replace(',', "");
             if let Ok(value) = clean_txt.parse::<f64>() {
-                let new_edge = Edge::new_combined_numeric(
+                let mut new_edge = Edge::new_combined_numeric(
                     start_pos,
                     end_pos,
                     value,
-                    "number".to_string(),        // type
-                    Some("Braille".to_string()), // script
-                    None,                        // num_base
-                    None,                        // n_decimals
+                    "number",                // type
+                    Some(intern("Braille")), // script
+                    None,                    // num_base
+                    None,                    // n_decimals
                     txt,
                 );
+                // Keep the digits of numbers beyond the precision of `f64`.
+                if value.abs() > MAX_EXACT_F64_INT {
+                    new_edge.update(NumDataUpdates {
+                        value_s: Some(clean_txt),
+                        ..Default::default()
+                    });
+                }
                 self.add_edge(new_edge);
             }
         }
     }
 
     /// Convert Braille characters to their corresponding numeric string representations ("0"-"9").
+    #[cfg(feature = "braille")]
     fn braille_digit(&self, char: char) -> Option<String> {
         const BRAILLE_DIGITS: [char; 10] = [
             '\u{281A}', // 0
@@ -1060,7 +1367,7 @@ impl<'a> Lattice<'a> {
             .map(|pos| pos.to_string())
     }
 
-    fn initialize_num_edges(&mut self) -> Vec<Edge> {
+    fn initialize_num_edges(&mut self) -> Vec<Edge<'a>> {
         let mut num_edges = Vec::new();
         for start in 0..self.s_chars.len() {
             if let Some(edge) =
@@ -1094,7 +1401,7 @@ impl<'a> Lattice<'a> {
     }
 
     #[inline]
-    fn apply_d1_digits(&mut self, prev_pass_edges: Vec<Edge>) -> Vec<Edge> {
+    fn apply_d1_digits(&mut self, prev_pass_edges: Vec<Edge<'a>>) -> Vec<Edge<'a>> {
         let mut next_pass_edges = Vec::new();
         let mut i = 0;
         while i < prev_pass_edges.len() {
@@ -1159,7 +1466,7 @@ impl<'a> Lattice<'a> {
                     start_edge.start(),
                     last_edge_end,
                     new_value,
-                    "D1".to_string(),
+                    "D1",
                     sub_edges.last().unwrap().get_script(),
                     Some(1),
                     n_decimals,
@@ -1185,7 +1492,7 @@ impl<'a> Lattice<'a> {
     }
 
     #[inline]
-    fn apply_g1_multiplication(&mut self, prev_pass_edges: Vec<Edge>) -> Vec<Edge> {
+    fn apply_g1_multiplication(&mut self, prev_pass_edges: Vec<Edge<'a>>) -> Vec<Edge<'a>> {
         let mut next_pass_edges = Vec::new();
         let mut i = 0;
         while i < prev_pass_edges.len() {
@@ -1193,33 +1500,33 @@ impl<'a> Lattice<'a> {
                 let left = &prev_pass_edges[i];
                 let right = &prev_pass_edges[i + 1];
 
-                let (left_val, is_single_digit) = left.get_num_data().map_or((0.0, false), |d| {
-                    (
-                        d.value.unwrap_or(0.0),
-                        d.num_base == Some(1) && d.value.is_some_and(|v| v >= 1.0),
-                    )
-                });
-                let (right_val, right_base, is_base_number) =
-                    right.get_num_data().map_or((0.0, None, false), |d| {
+                let is_single_digit = left
+                    .get_num_data()
+                    .is_some_and(|d| d.num_base == Some(1) && d.value.is_some_and(|v| v >= 1.0));
+                let (right_base, is_base_number) =
+                    right.get_num_data().map_or((None, false), |d| {
                         (
-                            d.value.unwrap_or(0.0),
                             d.num_base,
                             d.num_base.is_some_and(|b| b > 1) && !d.is_large_power,
                         )
                     });
 
-                if left.end() == right.start() && is_single_digit && is_base_number {
-                    let new_value = left_val * right_val;
-                    let new_edge = Edge::new_combined_numeric(
+                if left.end() == right.start()
+                    && is_single_digit
+                    && is_base_number
+                    && let Some(product) = CombinedValue::product(left, right)
+                {
+                    let mut new_edge = Edge::new_combined_numeric(
                         left.start(),
                         right.end(),
-                        new_value,
-                        "G1".to_string(),
+                        product.value,
+                        "G1",
                         right.get_script(),
                         right_base,
                         None,
                         format!("{}{}", left.orig_txt(), right.orig_txt()),
                     );
+                    product.set_digits(&mut new_edge);
                     self.add_edge(new_edge.clone());
                     next_pass_edges.push(new_edge);
                     i += 2;
@@ -1233,7 +1540,7 @@ impl<'a> Lattice<'a> {
     }
 
     #[inline]
-    fn apply_g2_addition(&mut self, prev_pass_edges: Vec<Edge>) -> Vec<Edge> {
+    fn apply_g2_addition(&mut self, prev_pass_edges: Vec<Edge<'a>>) -> Vec<Edge<'a>> {
         let mut next_pass_edges = Vec::new();
         let mut i = 0;
         while i < prev_pass_edges.len() {
@@ -1277,19 +1584,21 @@ impl<'a> Lattice<'a> {
                 }
             }
 
-            if sub_edges.len() > 1 {
-                let new_value: f64 = sub_edges.iter().map(|e| e.value().unwrap_or(0.0)).sum();
+            if sub_edges.len() > 1
+                && let Some(sum) = CombinedValue::sum(&sub_edges)
+            {
                 let last = sub_edges.last().unwrap();
-                let new_edge = Edge::new_combined_numeric(
+                let mut new_edge = Edge::new_combined_numeric(
                     start_edge.start(),
                     last.end(),
-                    new_value,
-                    "G2".to_string(),
+                    sum.value,
+                    "G2",
                     last.get_script(),
                     last.get_num_base(),
                     None,
                     sub_edges.iter().map(|e| e.orig_txt()).collect::<String>(),
                 );
+                sum.set_digits(&mut new_edge);
                 self.add_edge(new_edge.clone());
                 next_pass_edges.push(new_edge);
                 i += sub_edges.len();
@@ -1302,7 +1611,7 @@ impl<'a> Lattice<'a> {
     }
 
     #[inline]
-    fn apply_g3_large_power_multiplication(&mut self, prev_pass_edges: Vec<Edge>) -> Vec<Edge> {
+    fn apply_g3_large_power_multiplication(&mut self, prev_pass_edges: Vec<Edge<'a>>) -> Vec<Edge<'a>> {
         let mut next_pass_edges = Vec::new();
         let mut i = 0;
         while i < prev_pass_edges.len() {
@@ -1317,18 +1626,19 @@ impl<'a> Lattice<'a> {
                     && right.is_active()
                     && right.is_large_power()
                     && right.value().is_some()
+                    && let Some(product) = CombinedValue::product(left, right)
                 {
-                    let new_value = left.value().unwrap() * right.value().unwrap();
                     let mut new_edge = Edge::new_combined_numeric(
                         left.start(),
                         right.end(),
-                        new_value,
-                        "G3".to_string(),
+                        product.value,
+                        "G3",
                         right.get_script(),
                         right.get_num_base(),
                         None,
                         format!("{}{}", left.orig_txt(), right.orig_txt()),
                     );
+                    product.set_digits(&mut new_edge);
                     if let Some(nd) = new_edge.get_num_data_mut() {
                         nd.is_large_power = true;
                     }
@@ -1345,7 +1655,7 @@ impl<'a> Lattice<'a> {
     }
 
     #[inline]
-    fn apply_g4_large_block_addition(&mut self, prev_pass_edges: Vec<Edge>) -> Vec<Edge> {
+    fn apply_g4_large_block_addition(&mut self, prev_pass_edges: Vec<Edge<'a>>) -> Vec<Edge<'a>> {
         let mut next_pass_edges = Vec::new();
         let mut i = 0;
         while i < prev_pass_edges.len() {
@@ -1382,7 +1692,7 @@ impl<'a> Lattice<'a> {
                     let new_num_base = prev_base / 10;
                     num_data.value = Some(new_num_base as f64 * num_data.value.unwrap());
                     num_data.num_base = Some(new_num_base);
-                    right_edge.get_data_mut().r#type = "G4tag".to_string();
+                    right_edge.get_data_mut().r#type = intern("G4tag");
                 }
 
                 let can_combine = if let (Some(right_val), Some(right_base)) =
@@ -1402,19 +1712,21 @@ impl<'a> Lattice<'a> {
                 }
             }
 
-            if sub_edges.len() > 1 {
-                let new_value: f64 = sub_edges.iter().map(|e| e.value().unwrap_or(0.0)).sum();
+            if sub_edges.len() > 1
+                && let Some(sum) = CombinedValue::sum(&sub_edges)
+            {
                 let last = sub_edges.last().unwrap();
-                let new_edge = Edge::new_combined_numeric(
+                let mut new_edge = Edge::new_combined_numeric(
                     start_edge.start(),
                     last.end(),
-                    new_value,
-                    "G4".to_string(),
+                    sum.value,
+                    "G4",
                     last.get_script(),
                     last.get_num_base(),
                     None,
                     sub_edges.iter().map(|e| e.orig_txt()).collect::<String>(),
                 );
+                sum.set_digits(&mut new_edge);
                 self.add_edge(new_edge.clone());
                 next_pass_edges.push(new_edge);
                 i += sub_edges.len();
@@ -1428,8 +1740,8 @@ impl<'a> Lattice<'a> {
 
     #[inline]
     fn apply_fraction_and_percentage_patterns(&mut self) {
-        let mut new_edges: Vec<Edge> = Vec::new();
-        let mut edges_to_deactivate: Vec<Edge> = Vec::new();
+        let mut new_edges: Vec<Edge<'a>> = Vec::new();
+        let mut edges_to_deactivate: Vec<Edge<'a>> = Vec::new();
 
         // Combine all markers and sort them by length descending.
         // This ensures that longer markers (like "百分之") are matched before shorter ones.
@@ -1437,11 +1749,11 @@ impl<'a> Lattice<'a> {
             .map(|m| (m, "percentage"))
             .chain(self.uroman.fraction_connectors.iter().map(|c| (c, "fraction")))
             .collect();
-        markers.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+        markers.sort_by_key(|(m, _)| std::cmp::Reverse(m.len()));
 
         // Use a label to efficiently skip to the next start position once a match is found.
         'outer: for start in 0..self.s_chars.len() {
-            if let Some((marker_str, marker_type)) = markers.iter().find(|(m, _)| self.s_chars[start..].starts_with(&m.chars().collect::<Vec<_>>())) {
+            if let Some((marker_str, marker_type)) = markers.iter().find(|(m, _)| self.substr(start, self.max_vertex).starts_with(m.as_str())) {
 
                 let marker_end = start + marker_str.chars().count();
 
@@ -1454,8 +1766,8 @@ impl<'a> Lattice<'a> {
                         if left_edge.end() != start || right_edge.start() != marker_end { continue; }
 
                         if let (Some(left_val), Some(right_val)) = (
-                            left_edge.value().and_then(|v| if v.fract() == 0.0 { Some(v as i64) } else { None }),
-                            right_edge.value().and_then(|v| if v.fract() == 0.0 { Some(v as i64) } else { None })
+                            Decimal::integer_of_edge(&left_edge),
+                            Decimal::integer_of_edge(&right_edge),
                         ) {
                             let combined_start = left_edge.start();
                             let combined_end = right_edge.end();
@@ -1465,22 +1777,28 @@ impl<'a> Lattice<'a> {
                             // treat it as a percentage. This correctly handles "百分之一".
                             // This also handles explicit percentage markers like "100 % 1".
                             if left_val == 100 && (*marker_type == "fraction" || *marker_type == "percentage") {
-                                new_edges.push(Edge::new_regular(combined_start, combined_end, format!("{right_val}%"), "percentage".to_string()));
+                                new_edges.push(Edge::new_regular(combined_start, combined_end, format!("{right_val}%"), "percentage"));
                                 consumed = true;
                             } else if *marker_type == "fraction" && left_val != 0 {
-                                // Standard fraction case like "十分之一".
-                                let fraction = Ratio::new(right_val, left_val);
+                                // Standard fraction case like "十分之一". Fractions beyond
+                                // `Ratio<i64>` are only kept as text.
+                                let fraction = i64::try_from(right_val)
+                                    .ok()
+                                    .zip(i64::try_from(left_val).ok())
+                                    .and_then(|(numer, denom)| checked_ratio(numer, denom));
                                 new_edges.push(Edge::Numeric {
                                     data: EdgeData {
                                         start: combined_start,
                                         end: combined_end,
-                                        txt: format!("{right_val}/{left_val}"),
-                                        r#type: "fraction".to_string(),
+                                        txt: format!("{right_val}/{left_val}").into(),
+                                        r#type: intern("fraction"),
+                                        display: None,
+                                        conditions: None,
                                     },
                                     num_data: NumData {
                                         orig_txt: format!("{}/{}", right_val, left_val),
                                         value: None,
-                                        fraction: Some(fraction),
+                                        fraction,
                                         script: right_edge.get_script(),
                                         active: true,
                                         ..Default::default()
@@ -1511,7 +1829,7 @@ impl<'a> Lattice<'a> {
                             start,
                             right_edge.end(),
                             format!("{}%", right_edge.txt()),
-                            "percentage".to_string(),
+                            "percentage",
                         ));
                         edges_to_deactivate.push(right_edge.clone());
                     }
@@ -1532,17 +1850,13 @@ impl<'a> Lattice<'a> {
     }
 
     #[inline]
-    fn apply_g6_plus_minus_signs(&mut self, active_edges: &[Edge]) {
+    fn apply_g6_plus_minus_signs(&mut self, active_edges: &[Edge<'a>]) {
         for edge in active_edges.iter().filter(|e| e.value().is_some()) {
             let edge_start_char_idx = edge.start();
 
             for minus_sign in &self.uroman.minus_signs {
-                let sign_char_len = minus_sign.chars().count();
-                let sign_as_chars: Vec<char> = minus_sign.chars().collect();
-                if let Some(start_pos_char_idx) = edge_start_char_idx.checked_sub(sign_char_len)
-                    && self.s_chars.get(start_pos_char_idx..edge_start_char_idx)
-                        == Some(sign_as_chars.as_slice())
-                {
+                if self.substr(0, edge_start_char_idx).ends_with(minus_sign.as_str()) {
+                    let start_pos_char_idx = edge_start_char_idx - minus_sign.chars().count();
                     let new_edge = Edge::new_regular(
                         start_pos_char_idx,
                         edge.end(),
@@ -1554,12 +1868,8 @@ impl<'a> Lattice<'a> {
             }
 
             for plus_sign in &self.uroman.plus_signs {
-                let sign_char_len = plus_sign.chars().count();
-                let sign_as_chars: Vec<char> = plus_sign.chars().collect();
-                if let Some(start_pos_char_idx) = edge_start_char_idx.checked_sub(sign_char_len)
-                    && self.s_chars.get(start_pos_char_idx..edge_start_char_idx)
-                        == Some(sign_as_chars.as_slice())
-                {
+                if self.substr(0, edge_start_char_idx).ends_with(plus_sign.as_str()) {
+                    let start_pos_char_idx = edge_start_char_idx - plus_sign.chars().count();
                     let new_edge = Edge::new_regular(
                         start_pos_char_idx,
                         edge.end(),
@@ -1574,8 +1884,8 @@ impl<'a> Lattice<'a> {
 
     #[inline]
     fn apply_f1_final_adjustments(&mut self) {
-        let mut edges_to_add: Vec<Edge> = Vec::new();
-        let mut edges_to_deactivate: Vec<Edge> = Vec::new();
+        let mut edges_to_add: Vec<Edge<'a>> = Vec::new();
+        let mut edges_to_deactivate: Vec<Edge<'a>> = Vec::new();
 
         for (_, left_edges) in self.edge_lattice.iter() {
             for left_edge in left_edges.iter() {
@@ -1591,7 +1901,7 @@ impl<'a> Lattice<'a> {
                                     let mut new_edge = right_edge.clone();
                                     let has_fraction = new_edge.get_num_data().is_some_and(|d| d.fraction.is_some());
                                     let separator = if has_fraction { " " } else { "·" };
-                                    new_edge.get_data_mut().txt.insert_str(0, separator);
+                                    new_edge.get_data_mut().txt.to_mut().insert_str(0, separator);
 
                                     edges_to_add.push(new_edge);
                                     edges_to_deactivate.push(right_edge.clone());
@@ -1617,7 +1927,7 @@ impl<'a> Lattice<'a> {
     }
 
     #[inline]
-    fn deactivate_exceptional_singles(&mut self, num_edges: &mut [Edge]) {
+    fn deactivate_exceptional_singles(&mut self, num_edges: &mut [Edge<'a>]) {
         let exceptional_chars: HashSet<&str> = ["兩", "参", "參", "伍", "陆", "陸", "什", "京兆"]
             .into_iter()
             .collect();
@@ -1660,7 +1970,7 @@ impl<'a> Lattice<'a> {
                     start,
                     end,
                     digit_val.to_string(),
-                    "num".to_string(),
+                    "num",
                 ));
             }
         }
@@ -1671,11 +1981,11 @@ impl<'a> Lattice<'a> {
 
     fn add_default_abugida_vowel(
         &mut self,
-        mut rom: String,
+        mut rom: Cow<'a, str>,
         start: usize,
         end: usize,
         annotation: &str,
-    ) -> String {
+    ) -> Cow<'a, str> {
         let Some(first_s_char) = self.s_chars.get(start).copied() else {
             return rom;
         };
@@ -1691,24 +2001,24 @@ impl<'a> Lattice<'a> {
         let Some(script) = self.uroman.scripts.get(&script_name.to_lowercase()) else {
             return rom;
         };
-        let Some((re1, re2)) = &script.abugida_regexes else {
+        if script.abugida_default_vowels.is_empty() {
             return rom;
-        };
+        }
 
-        let cache_key = (script_name.clone(), rom.clone());
-        let cache_entry = if let Some(entry) = self.uroman.abugida_cache.borrow().get(&cache_key) {
-            entry.clone()
+        let cache_key = (script_name.to_string(), rom.to_string());
+        let cache_entry = if let Some(entry) = self.uroman.abugida_cache.get(&cache_key) {
+            entry
         } else {
             let mut base_rom: Option<String>;
             let mut base_rom_plus_vowel: Option<String>;
-            let mut modified_rom = rom.clone();
-
-            if let Some(caps) = re1.captures(&rom) {
-                base_rom = Some(caps[1].to_string());
-                base_rom_plus_vowel = Some(format!("{}{}", &caps[1], &caps[2]));
-            } else if let Some(caps) = re2.captures(&rom) {
-                base_rom = Some(caps[1].to_string());
-                base_rom_plus_vowel = Some(format!("{}{}", &caps[1], &caps[2]));
+            let mut modified_rom = rom.to_string();
+
+            if let Some((consonants, vowels)) = script.split_y_vowels(&rom) {
+                base_rom = Some(consonants.to_string());
+                base_rom_plus_vowel = Some(format!("{consonants}{vowels}"));
+            } else if let Some((consonants, vowel)) = script.split_consonant_vowel(&rom) {
+                base_rom = Some(consonants.to_string());
+                base_rom_plus_vowel = Some(format!("{consonants}{vowel}"));
                 if rom.ends_with('-')
                     && start + 1 == end
                     && rom.chars().next().is_some_and(|c| c.is_alphabetic())
@@ -1716,7 +2026,7 @@ impl<'a> Lattice<'a> {
                     modified_rom.pop();
                 }
             } else {
-                base_rom = Some(rom.clone());
+                base_rom = Some(rom.to_string());
                 base_rom_plus_vowel = Some(format!("{}{}", rom, &script.abugida_default_vowels[0]));
             }
 
@@ -1732,14 +2042,14 @@ impl<'a> Lattice<'a> {
                 base_rom_plus_vowel,
                 modified_rom,
             };
+            let max_len = self.uroman.abugida_cache_cap();
             self.uroman
                 .abugida_cache
-                .borrow_mut()
-                .insert(cache_key, entry.clone());
+                .insert(cache_key, entry.clone(), max_len);
             entry
         };
 
-        rom = cache_entry.modified_rom;
+        rom = Cow::Owned(cache_entry.modified_rom);
         let Some(base_rom) = cache_entry.base_rom else {
             return rom;
         };
@@ -1766,7 +2076,7 @@ impl<'a> Lattice<'a> {
                 .flatten()
                 .unwrap_or(false)
             {
-                return "".to_string();
+                return Cow::Borrowed("");
             } else if self
                 .props
                 .get(&("edge-vowel".to_string(), start))
@@ -1774,14 +2084,13 @@ impl<'a> Lattice<'a> {
                 .flatten()
                 .unwrap_or(false)
             {
-                return base_rom_plus_vowel;
+                return base_rom_plus_vowel.into();
             } else {
-                return base_rom;
+                return base_rom.into();
             }
         }
 
         if let Some(nc) = next_s_char {
-            let nc_string = &nc.to_string();
             let khmer_yo_condition = (base_rom == "b"
                 || base_rom == "c"
                 || base_rom == "d"
@@ -1789,66 +2098,66 @@ impl<'a> Lattice<'a> {
                 || base_rom == "ng")
                 && nc == 'យ';
             if khmer_yo_condition {
-                return base_rom;
+                return base_rom.into();
             }
-            if self.uroman.dict_bool_get("is-vowel-sign", nc_string) {
-                return base_rom;
+            if self.uroman.dict_bool_get_char(BoolProp::VowelSign, nc) {
+                return base_rom.into();
             }
             if self
                 .uroman
-                .dict_bool_get("is-medial-consonant-sign", nc_string)
+                .dict_bool_get_char(BoolProp::MedialConsonantSign, nc)
             {
-                return base_rom;
+                return base_rom.into();
             }
             if self.char_is_subjoined_letter(nc) {
-                return base_rom;
+                return base_rom.into();
             }
             if self.uroman.char_is_nonspacing_mark(nc)
                 && let Some(n2c) = next2_s_char
-                && self.uroman.dict_bool_get("is-vowel-sign", &n2c.to_string())
+                && self.uroman.dict_bool_get_char(BoolProp::VowelSign, n2c)
             {
-                return base_rom;
+                return base_rom.into();
             }
-            if self.uroman.dict_bool_get("is-virama", nc_string) {
-                return base_rom;
+            if self.uroman.dict_bool_get_char(BoolProp::Virama, nc) {
+                return base_rom.into();
             }
             if self.uroman.char_is_nonspacing_mark(nc)
                 && let Some(n2c) = next2_s_char
-                && self.uroman.dict_bool_get("is-virama", &n2c.to_string())
+                && self.uroman.dict_bool_get_char(BoolProp::Virama, n2c)
             {
-                return base_rom;
+                return base_rom.into();
             }
         }
 
         if let Some(pc) = prev_s_char
-            && self.uroman.dict_bool_get("is-virama", &pc.to_string())
+            && self.uroman.dict_bool_get_char(BoolProp::Virama, pc)
         {
-            return base_rom_plus_vowel;
+            return base_rom_plus_vowel.into();
         }
 
         if self.is_at_start_of_word(start) && !CONTAINS_VOWEL_RE.is_match(&rom) {
-            return base_rom_plus_vowel;
+            return base_rom_plus_vowel.into();
         }
 
         if self.is_at_end_of_word(end) {
             if script_name == "Devanagari" && self.lcode.as_deref() != Some("san") {
                 return rom;
             } else if let Some(lc) = &self.lcode
-                && ["asm", "ben", "guj", "kas", "pan"].contains(&lc.as_str())
+                && NO_FINAL_VOWEL_LCODES.contains(&lc.as_str())
             {
                 return rom;
             }
-            return base_rom_plus_vowel;
+            return base_rom_plus_vowel.into();
         }
 
         if prev_s_char.is_none_or(|pc| self.uroman.chr_script_name(pc) != script_name) {
-            return base_rom_plus_vowel;
+            return base_rom_plus_vowel.into();
         }
         if self.uroman.chr_name(last_s_char).contains("VOCALIC") {
-            return base_rom;
+            return base_rom.into();
         }
         if next_s_char.is_some_and(|nc| self.uroman.chr_script_name(nc) == script_name) {
-            return base_rom_plus_vowel;
+            return base_rom_plus_vowel.into();
         }
 
         rom
@@ -1856,10 +2165,10 @@ impl<'a> Lattice<'a> {
 
     fn expand_rom_with_special_chars(
         &mut self,
-        mut rom: String,
+        mut rom: Cow<'a, str>,
         mut start: usize,
         mut end: usize,
-    ) -> (String, usize, usize, Option<String>) {
+    ) -> (Cow<'a, str>, usize, usize, Option<String>) {
         // Python: orig_start = start
         // Python: annot = None
         let orig_start = start;
@@ -1888,7 +2197,7 @@ impl<'a> Lattice<'a> {
         if prev_char == Some('\u{2820}') && BRAILLE_LOWER_RE.is_match(&rom) {
             let mut new_rom = rom[0..1].to_uppercase();
             new_rom.push_str(&rom[1..]);
-            return (new_rom, start - 1, end, Some("rom exp".to_string()));
+            return (new_rom.into(), start - 1, end, Some("rom exp".to_string()));
         }
 
         // Python: if start+1 == end and rom.isupper() and next_char.islower():
@@ -1898,7 +2207,7 @@ impl<'a> Lattice<'a> {
             && next_char.is_some_and(|nc| nc.is_lowercase())
             && let Some(first) = rom.chars().next()
         {
-            rom = first.to_uppercase().to_string() + &rom[1..].to_lowercase();
+            rom = (first.to_uppercase().to_string() + &rom[1..].to_lowercase()).into();
         }
 
         // Python: if (prev_char and prev_char in 'っッ\u0A71') ...
@@ -1913,7 +2222,7 @@ impl<'a> Lattice<'a> {
             } else {
                 consonant_to_double.replace("ch", "c")
             };
-            rom.insert_str(0, &prefix);
+            rom.to_mut().insert_str(0, &prefix);
             start -= 1;
             annot = Some("rom exp".to_string());
             prev_char = if start > 0 {
@@ -1929,7 +2238,7 @@ impl<'a> Lattice<'a> {
             if end - start == 1 && THAI_CONSONANT_END_RE.is_match(&rom) {
                 // Python: if uroman.dict_str[('syllable-info', prev_char)] == 'written-pre-consonant-spoken-post-consonant':
                 if let Some(pc) = prev_char
-                    && self.uroman.dict_str_get("syllable-info", pc)
+                    && self.uroman.dict_str_get(StrProp::SyllableInfo, pc)
                         == "written-pre-consonant-spoken-post-consonant"
                 {
                     // Python: for vowel_prefix_len in [1]:
@@ -1957,7 +2266,7 @@ impl<'a> Lattice<'a> {
                                     {
                                         // Python: return rom + vowel_rom, start-vowel_prefix_len, end+vowel_suffix_len, 'rom exp'
                                         return (
-                                            format!("{rom}{vowel_rom}"),
+                                            format!("{rom}{vowel_rom}").into(),
                                             start - vowel_prefix_len,
                                             end + vowel_suffix_len,
                                             Some("rom exp".to_string()),
@@ -1975,13 +2284,13 @@ impl<'a> Lattice<'a> {
             // and regex.match(r'[bcdfghjklmnpqrstvwxyz]', rom) and (vowel_rom := self.romanization_by_first_rule(prev_char)):
             if let Some(pc) = prev_char
                 && self.uroman.chr_script_name(pc) == "Thai"
-                && self.uroman.dict_str_get("syllable-info", pc)
+                && self.uroman.dict_str_get(StrProp::SyllableInfo, pc)
                     == "written-pre-consonant-spoken-post-consonant"
                 && THAI_CONSONANT_START_RE.is_match(&rom)
                 && let Some(vowel_rom) = self.romanization_by_first_rule(&pc.to_string())
             {
                 // Python: return rom + vowel_rom, start-1, end, 'rom exp'
-                rom.push_str(&vowel_rom);
+                rom.to_mut().push_str(vowel_rom);
                 start -= 1;
                 annot = Some("rom exp".to_string());
                 prev_char = if start > 0 {
@@ -1994,10 +2303,10 @@ impl<'a> Lattice<'a> {
             if first_char == '\u{0E2D}' && end - start == 1 {
                 // Python: prev_script = uroman.chr_script_name(prev_char)
                 let prev_script =
-                    prev_char.map_or("".to_string(), |c| self.uroman.chr_script_name(c));
+                    prev_char.map_or("", |c| self.uroman.chr_script_name(c));
                 // Python: next_script = uroman.chr_script_name(next_char)
                 let next_script =
-                    next_char.map_or("".to_string(), |c| self.uroman.chr_script_name(c));
+                    next_char.map_or("", |c| self.uroman.chr_script_name(c));
                 // Python: prev_rom = self.find_rom_edge_path_backwards(0, start, 1, return_str=True)
                 let prev_rom = if start > 0 {
                     match self.find_rom_edge_path_backwards(0, start, Some(1), true, false) {
@@ -2015,10 +2324,10 @@ impl<'a> Lattice<'a> {
                 let is_between_consonants = prev_script == "Thai"
                     && next_script == "Thai"
                     && THAI_CONSONANT_XZ_END_RE.is_match(&prev_rom)
-                    && THAI_CONSONANT_XZ_ONLY_RE.is_match(&next_rom);
+                    && THAI_CONSONANT_XZ_ONLY_RE.is_match(next_rom);
                 if !is_between_consonants {
                     // Python: return '', start, end, 'rom del'
-                    return ("".to_string(), start, end, Some("rom del".to_string()));
+                    return (Cow::Borrowed(""), start, end, Some("rom del".to_string()));
                 }
             }
         }
@@ -2030,7 +2339,7 @@ impl<'a> Lattice<'a> {
                 .simple_top_romanization_candidate_for_span(orig_start, end + 1, true)
                 .is_none()
         {
-            rom.insert(0, 'e');
+            rom.to_mut().insert(0, 'e');
             end += 1;
             annot = Some("rom exp".to_string());
             last_char = self.s_chars[end - 1];
@@ -2050,8 +2359,9 @@ impl<'a> Lattice<'a> {
                 .simple_top_romanization_candidate_for_span(start, end + 1, true)
                 .is_none()
         {
+            let rom = rom.to_mut();
             rom.pop();
-            rom.push_str(&y_rom);
+            rom.push_str(y_rom);
             end += 1;
             annot = Some("rom exp".to_string());
             last_char = self.s_chars[end - 1];
@@ -2066,38 +2376,38 @@ impl<'a> Lattice<'a> {
             if ("Hiragana" == last_s_char_script || "Katakana" == last_s_char_script)
                 && "aeiou".contains(last_rom_char)
             {
-                rom.push(last_rom_char);
+                rom.to_mut().push(last_rom_char);
                 return (rom, start, end + 1, Some("rom exp".to_string()));
             }
         }
 
         // Python: if self.uroman.dict_bool[('is-virama', next_char)]:
         // Python:     return rom, start, end + 1, "rom exp"
-        if next_char.is_some_and(|nc| self.uroman.dict_bool_get("is-virama", &nc.to_string())) {
+        if next_char.is_some_and(|nc| self.uroman.dict_bool_get_char(BoolProp::Virama, nc)) {
             return (rom, start, end + 1, Some("rom exp".to_string()));
         }
 
         // Python: if rom.startswith(' ') and ((start == 0) or (prev_char == ' ')): rom = rom[1:]
         if rom.starts_with(' ') && (start == 0 || prev_char == Some(' ')) {
-            rom.remove(0);
+            rom.to_mut().remove(0);
         }
         // Python: if rom.endswith(' ') and ((end == len(full_string)+1) or (next_char == ' ')): rom = rom[:-1]
         if rom.ends_with(' ') && next_char == Some(' ') {
-            rom.pop();
+            rom.to_mut().pop();
         }
 
         (rom, start, end, annot)
     }
 
-    fn romanization_by_first_rule(&self, s: &str) -> Option<String> {
+    fn romanization_by_first_rule(&self, s: &str) -> Option<&'a str> {
         self.uroman
             .rom_rules
             .get(s)
             .and_then(|rules| rules.first())
-            .and_then(|rule| rule.t.clone())
+            .and_then(|rule| rule.t.as_deref())
     }
 
-    pub fn add_alternatives(&mut self, edges: &mut Vec<Edge>) {
+    pub fn add_alternatives(&mut self, edges: &mut Vec<Edge<'a>>) {
         let mut existing_edges: HashSet<(usize, usize, String)> = edges
             .iter()
             .map(|e| (e.start(), e.end(), e.txt().to_string()))
@@ -2114,7 +2424,6 @@ impl<'a> Lattice<'a> {
             let start = base_edge.start();
             let end = base_edge.end();
 
-            let orig_s: String = self.s_chars[start..end].iter().collect();
             let old_rom = base_edge.txt();
 
             let (old_rom_core, old_rom_suffix) =
@@ -2127,21 +2436,32 @@ impl<'a> Lattice<'a> {
                     (None, None)
                 };
 
-            if let Some(rom_rules) = self.uroman.rom_rules.get(&orig_s) {
+            if let Some(rom_rules) = self.rules_for_span(start, end) {
+                let lcode = self.span_lcode(rom_rules, start, end);
                 for rom_rule in rom_rules {
-                    if !self.cand_is_valid(rom_rule, start, end) {
+                    if !self.cand_is_valid(rom_rule, start, end, lcode) {
                         continue;
                     }
 
                     let Some(rom_t) = &rom_rule.t else {
                         continue;
                     };
+                    let rom_t = self.carry_case(Cow::Borrowed(rom_t), start, end);
+                    let alt_edge = |rom: String, r#type: &str| {
+                        let mut edge = Edge::new_regular(start, end, rom, r#type);
+                        if self.uroman.rule_conditions {
+                            edge.get_data_mut().conditions = Some(rom_rule.conditions());
+                        }
+                        edge
+                    };
 
-                    if (rom_t == old_rom || old_rom_core == Some(rom_t.as_str()))
+                    if (rom_t == old_rom || old_rom_core == Some(rom_t.as_ref()))
                         && !rom_rule.t_alts.is_empty()
                     {
                         for rom_alt_base in &rom_rule.t_alts {
-                            let mut rom_alt = rom_alt_base.clone();
+                            let mut rom_alt = self
+                                .carry_case(Cow::Borrowed(rom_alt_base), start, end)
+                                .into_owned();
                             if let (Some(core), Some(suffix)) = (old_rom_core, old_rom_suffix)
                                 && rom_t == core
                             {
@@ -2149,35 +2469,24 @@ impl<'a> Lattice<'a> {
                             }
 
                             if existing_edges.insert((start, end, rom_alt.clone())) {
-                                new_edges_to_add.push(Edge::new_regular(
-                                    start,
-                                    end,
-                                    rom_alt,
-                                    "rom-alt".to_string(),
-                                ));
+                                new_edges_to_add.push(alt_edge(rom_alt, "rom-alt"));
                             }
                         }
                     }
 
                     if let Some(rom_end_of_syllable) = &rom_rule.t_at_end_of_syllable {
-                        if rom_t == old_rom && existing_edges.insert((start, end, rom_t.clone())) {
-                            new_edges_to_add.push(Edge::new_regular(
-                                start,
-                                end,
-                                rom_t.clone(),
-                                "rom-alt2".to_string(),
-                            ));
+                        let rom_end_of_syllable =
+                            self.carry_case(Cow::Borrowed(rom_end_of_syllable), start, end);
+                        if rom_t == old_rom
+                            && existing_edges.insert((start, end, rom_t.to_string()))
+                        {
+                            new_edges_to_add.push(alt_edge(rom_t.to_string(), "rom-alt2"));
                         }
 
                         if rom_end_of_syllable == old_rom
-                            && existing_edges.insert((start, end, rom_t.clone()))
+                            && existing_edges.insert((start, end, rom_t.to_string()))
                         {
-                            new_edges_to_add.push(Edge::new_regular(
-                                start,
-                                end,
-                                rom_t.clone(),
-                                "rom-alt3".to_string(),
-                            ));
+                            new_edges_to_add.push(alt_edge(rom_t.to_string(), "rom-alt3"));
                         }
                     }
                 }
@@ -2187,7 +2496,42 @@ impl<'a> Lattice<'a> {
         edges.extend(new_edges_to_add);
     }
 
-    pub fn best_rom_edge_path(&mut self, start: usize, end: usize, skip_num_edge: bool) -> Vec<Edge> {
+    /// Romanizes the string by the longest matching rule at each position, without
+    /// consulting the edges. This is the fallback for lattices that exceeded the edge cap.
+    pub fn greedy_rom_edge_path(&mut self) -> Vec<Edge<'a>> {
+        let uroman = self.uroman;
+        let mut result = Vec::new();
+        let mut start = 0;
+        while start < self.max_vertex {
+            let mut longest = None;
+            let mut node = RuleTrie::ROOT;
+            for end in start..self.max_vertex {
+                let Some(child) = uroman.rule_trie.child(node, self.s_chars[end]) else {
+                    break;
+                };
+                node = child;
+                if let Some(rom) = uroman
+                    .rule_trie
+                    .value(node)
+                    .and_then(|index| uroman.rom_rules.get_index(index))
+                    .and_then(|(_, rules)| rules.first())
+                    .and_then(|rule| rule.t.as_deref())
+                {
+                    longest = Some((end + 1, Cow::Borrowed(rom)));
+                }
+            }
+            let (end, rom) = longest.unwrap_or_else(|| {
+                let (rom, _) =
+                    self.get_fallback_rom_and_annot(self.s_chars[start], start, start + 1);
+                (start + 1, rom)
+            });
+            result.push(Edge::new_regular(start, end, rom, "greedy"));
+            start = end;
+        }
+        result
+    }
+
+    pub fn best_rom_edge_path(&mut self, start: usize, end: usize, skip_num_edge: bool) -> Vec<Edge<'a>> {
         let mut result = Vec::new();
         let mut current_pos = start;
         while current_pos < end {
@@ -2211,7 +2555,7 @@ impl<'a> Lattice<'a> {
     pub fn _print_all_edges_for_debug(&self, step_name: &str) {
         println!("\n--- Rust: After {step_name} ---");
 
-        let mut all_edges: Vec<Edge> = self.edge_lattice.values().flatten().cloned().collect();
+        let mut all_edges: Vec<Edge<'a>> = self.edge_lattice.values().flatten().cloned().collect();
 
         all_edges.sort_by(|a, b| {
             let this = &a;
@@ -2237,6 +2581,131 @@ impl<'a> Lattice<'a> {
     }
 }
 
+/// The largest integer up to which all integers are exact as `f64`, 2^53.
+const MAX_EXACT_F64_INT: f64 = 9_007_199_254_740_992.0;
+
+/// The value of a numeric edge combined from those of other edges.
+///
+/// Values are combined as decimals with checked arithmetic, and those beyond the precision of
+/// `f64` are also kept as digits. A combination whose value overflows is not made, so that its
+/// parts are romanized one by one instead of as a wrong value.
+struct CombinedValue {
+    value: f64,
+    digits: Option<String>,
+}
+
+impl CombinedValue {
+    fn product(left: &Edge, right: &Edge) -> Option<Self> {
+        let (a, b) = (Decimal::of_edge(left)?, Decimal::of_edge(right)?);
+        Self::from_decimal(Decimal {
+            mantissa: a.mantissa.checked_mul(b.mantissa)?,
+            scale: a.scale.checked_add(b.scale)?,
+        })
+    }
+
+    fn sum(edges: &[Edge]) -> Option<Self> {
+        let mut sum = Decimal {
+            mantissa: 0,
+            scale: 0,
+        };
+        for edge in edges {
+            let value = Decimal::of_edge(edge)?;
+            let scale = sum.scale.max(value.scale);
+            sum = Decimal {
+                mantissa: sum.rescaled(scale)?.checked_add(value.rescaled(scale)?)?,
+                scale,
+            };
+        }
+        Self::from_decimal(sum)
+    }
+
+    fn from_decimal(decimal: Decimal) -> Option<Self> {
+        let s = decimal.normalized().to_string();
+        let value: f64 = s.parse().ok()?;
+        let digits = (value.abs() > MAX_EXACT_F64_INT).then_some(s);
+        Some(Self { value, digits })
+    }
+
+    /// Sets the digits of a value beyond the precision of `f64` as the text of `edge`.
+    fn set_digits(self, edge: &mut Edge) {
+        if let Some(digits) = self.digits {
+            edge.update(NumDataUpdates {
+                value_s: Some(digits),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// An exact decimal number, `mantissa / 10^scale`.
+#[derive(Debug, Clone, Copy)]
+struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Returns the value of a numeric edge, from its digits where it has them. Edges without a
+    /// value count as 0.
+    fn of_edge(edge: &Edge) -> Option<Self> {
+        let Some(num_data) = edge.get_num_data() else {
+            return Self::parse("0");
+        };
+        match (&num_data.value_s, num_data.value) {
+            (Some(value_s), _) => Self::parse(value_s),
+            (None, Some(value)) if value.is_finite() => Self::parse(&value.to_string()),
+            (None, Some(_)) => None,
+            (None, None) => Self::parse("0"),
+        }
+    }
+
+    /// Returns the value of a numeric edge if it is an integer.
+    fn integer_of_edge(edge: &Edge) -> Option<i128> {
+        edge.value()?;
+        let decimal = Self::of_edge(edge)?.normalized();
+        (decimal.scale == 0).then_some(decimal.mantissa)
+    }
+
+    /// Parses a decimal number such as `-12.50`.
+    fn parse(s: &str) -> Option<Self> {
+        let (int_part, fract_part) = s.split_once('.').unwrap_or((s, ""));
+        let scale = u32::try_from(fract_part.len()).ok()?;
+        let mantissa = format!("{int_part}{fract_part}").parse().ok()?;
+        Some(Self { mantissa, scale })
+    }
+
+    /// Returns the mantissa of the number with `scale` decimals, if it fits.
+    fn rescaled(self, scale: u32) -> Option<i128> {
+        self.mantissa
+            .checked_mul(10_i128.checked_pow(scale - self.scale)?)
+    }
+
+    /// Drops the trailing zeros of the decimals.
+    fn normalized(mut self) -> Self {
+        while self.scale > 0 && self.mantissa % 10 == 0 {
+            self.mantissa /= 10;
+            self.scale -= 1;
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.scale == 0 {
+            return write!(f, "{}", self.mantissa);
+        }
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = format!(
+            "{:0>width$}",
+            self.mantissa.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (int_part, fract_part) = digits.split_at(digits.len() - self.scale as usize);
+        write!(f, "{sign}{int_part}.{fract_part}")
+    }
+}
+
 fn is_power_of_10(mut n: i64) -> bool {
     if n <= 0 {
         return false;
@@ -0,0 +1,89 @@
+//! Guessing the language of a text from its scripts and the chars that language-specific
+//! rules romanize, without a language identification model.
+
+use crate::Uroman;
+use crate::hash::HashMap;
+use crate::lang_tag::lcode_of_language_name;
+
+/// The longest sources of rules, in chars, that are looked up as evidence for a language.
+const MAX_EVIDENCE_CHARS: usize = 4;
+
+impl Uroman {
+    /// Guesses the language codes of `s`, with the confidence of each, best first.
+    ///
+    /// The letters of each script in `s` are shared among the languages that the script is
+    /// written in, by `Scripts.txt`, and the languages of the rules that match them, e.g.
+    /// Ukrainian for `ї`. Rules for fewer languages count more. The confidence of a language is
+    /// the share of the letters of `s` that are attributed to it, so the confidences add up to
+    /// at most 1: letters of scripts without known languages, such as Latin letters without
+    /// language-specific rules, are attributed to none. Variants of a code in the rules, such
+    /// as `srp2`, count for the code itself.
+    ///
+    /// This is much cruder than [`Uroman::detect_lcode`], which needs the `detect-lcode`
+    /// feature, and tells apart only languages with different letters or rules.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// assert_eq!(uroman.guess_lcode("Київ")[0].0, "ukr");
+    /// assert_eq!(uroman.guess_lcode("Қазақстан")[0].0, "kaz");
+    /// assert_eq!(uroman.guess_lcode("ひらがな"), [("jpn", 1.0)]);
+    /// assert!(uroman.guess_lcode("hello").is_empty());
+    /// ```
+    pub fn guess_lcode(&self, s: &str) -> Vec<(&str, f64)> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut n_letters = 0;
+        // The letters of each script, and the weight of each language for them.
+        let mut scripts: HashMap<&str, (usize, HashMap<&str, f64>)> = HashMap::default();
+        for (i, &c) in chars.iter().enumerate() {
+            let script_name = self.chr_script_name(c);
+            if script_name.is_empty() || !c.is_alphabetic() {
+                continue;
+            }
+            n_letters += 1;
+            let (n_script_letters, weights) = scripts.entry(script_name).or_default();
+            *n_script_letters += 1;
+            for end in i + 1..=chars.len().min(i + MAX_EVIDENCE_CHARS) {
+                let Some(rules) = self.rom_rules_for_chars(&chars[i..end]) else {
+                    continue;
+                };
+                let mut lcodes: Vec<&str> = rules
+                    .iter()
+                    .flat_map(|rule| &rule.lcodes)
+                    .map(|lcode| lcode.trim_end_matches(|c: char| c.is_ascii_digit()))
+                    .filter(|lcode| !lcode.is_empty())
+                    .collect();
+                lcodes.sort_unstable();
+                lcodes.dedup();
+                for lcode in &lcodes {
+                    *weights.entry(lcode).or_default() += 1.0 / lcodes.len() as f64;
+                }
+            }
+        }
+
+        let mut confidences: HashMap<&str, f64> = HashMap::default();
+        for (script_name, (n_script_letters, mut weights)) in scripts {
+            let languages = self
+                .scripts
+                .get(&script_name.to_lowercase())
+                .map(|script| script.languages.as_slice())
+                .unwrap_or_default();
+            for lcode in languages
+                .iter()
+                .filter_map(|name| lcode_of_language_name(name))
+            {
+                *weights.entry(lcode).or_default() += 1.0;
+            }
+            let total_weight: f64 = weights.values().sum();
+            let share = n_script_letters as f64 / n_letters as f64;
+            for (lcode, weight) in weights {
+                *confidences.entry(lcode).or_default() += share * weight / total_weight;
+            }
+        }
+        let mut confidences: Vec<(&str, f64)> = confidences.into_iter().collect();
+        confidences.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        confidences
+    }
+}
@@ -0,0 +1,169 @@
+//! Romanizing text that mixes languages, with language codes for spans of it.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::{RomFormatType, RomanizationOutput, RomanizationResult, RomanizeOptions, Uroman};
+
+/// Starts a span of `romanize_file` input with its own language code, e.g.
+/// `⟦lcode:ell⟧Καλημέρα⟦/lcode⟧`. The code ends at the next `⟧`.
+const INLINE_LCODE_START: &str = "⟦lcode:";
+/// Ends a span started by [`INLINE_LCODE_START`], or the line does.
+const INLINE_LCODE_END: &str = "⟦/lcode⟧";
+
+impl Uroman {
+    /// Romanizes `s` with `lcode`, except for the char ranges of `spans`, which are romanized
+    /// with their own language codes.
+    ///
+    /// This is for text that mixes languages within a sentence. Rules don't match across the
+    /// boundaries of spans, and edge offsets are char offsets in `s`. Spans are taken in the
+    /// order of their starts, and a span that overlaps the one before it starts where that
+    /// one ends. Ranges past the end of `s` are cut at its end.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new();
+    /// let spans = [(6..10, "ukr")];
+    /// let result = uroman.romanize_with_lcode_spans::<rom_format::Str>("Київ, Київ", None, &spans);
+    /// assert_eq!(result.to_output_string(), "Kiyiv, Kyiv");
+    /// ```
+    pub fn romanize_with_lcode_spans<F: RomFormatType + 'static>(
+        &self,
+        s: &str,
+        lcode: Option<&str>,
+        spans: &[(Range<usize>, &str)],
+    ) -> RomanizationOutput<F> {
+        let mut spans = spans.to_vec();
+        spans.sort_by_key(|(range, _)| range.start);
+        let byte_offsets: Vec<usize> = s.char_indices().map(|(i, _)| i).chain([s.len()]).collect();
+        let n_chars = byte_offsets.len() - 1;
+
+        let mut segments = Vec::with_capacity(2 * spans.len() + 1);
+        let mut pos = 0;
+        for (range, span_lcode) in &spans {
+            let start = range.start.clamp(pos, n_chars);
+            let end = range.end.clamp(start, n_chars);
+            segments.push((&s[byte_offsets[pos]..byte_offsets[start]], lcode));
+            segments.push((
+                &s[byte_offsets[start]..byte_offsets[end]],
+                Some(*span_lcode),
+            ));
+            pos = end;
+        }
+        segments.push((&s[byte_offsets[pos]..], lcode));
+        self.romanize_segments(&segments, self.policies())
+    }
+
+    /// Romanizes each of `tokens` with its own language code, e.g. one found by language
+    /// identification of each token of code-switched text.
+    ///
+    /// Returns one output per token, in the order of `tokens`. Rules don't match across tokens,
+    /// and the edge offsets of each output are char offsets in its token.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new();
+    /// let tokens = [("Київ", None), ("Київ", Some("ukr"))];
+    /// let outputs = uroman.romanize_tokens_with_lcodes::<rom_format::Str>(&tokens);
+    /// let romanized: Vec<String> = outputs.into_iter().map(|out| out.to_output_string()).collect();
+    /// assert_eq!(romanized, ["Kiyiv", "Kyiv"]);
+    /// ```
+    pub fn romanize_tokens_with_lcodes<F: RomFormatType + 'static>(
+        &self,
+        tokens: &[(&str, Option<&str>)],
+    ) -> Vec<RomanizationOutput<F>> {
+        tokens
+            .iter()
+            .map(|&(token, lcode)| self.romanize_string(token, lcode))
+            .collect()
+    }
+
+    /// Romanizes a line of `romanize_file` input like [`Uroman::romanize_with_options`], with
+    /// the spans between inline language code markers romanized with their codes.
+    ///
+    /// The markers are dropped, and edge offsets are char offsets in the line without them.
+    pub(crate) fn romanize_with_inline_lcodes(
+        &self,
+        text: &str,
+        options: &RomanizeOptions,
+    ) -> RomanizationResult {
+        let Some(segments) = split_inline_lcodes(text, options.output.lcode.as_deref()) else {
+            return self.romanize_with_options(text, options);
+        };
+        let segments: Vec<(Cow<str>, Option<&str>)> = segments
+            .into_iter()
+            .map(|(segment, lcode)| (options.decode_escapes(segment), lcode))
+            .collect();
+        let segments: Vec<(&str, Option<&str>)> = segments
+            .iter()
+            .map(|(segment, lcode)| (segment.as_ref(), *lcode))
+            .collect();
+        let policies = options.policies(self);
+        let result =
+            self.romanize_segments_with_format(&segments, options.output.rom_format, policies);
+        if !options.output.display_positions {
+            return result;
+        }
+        let text: String = segments.iter().map(|(segment, _)| *segment).collect();
+        self.with_display_positions(result, &text, options)
+    }
+}
+
+/// The segments of `text` between its inline language code markers with their language codes,
+/// `options.output.lcode` outside the markers, and with their escapes decoded as `options` say.
+pub(crate) fn inline_lcode_segments<'a>(
+    text: &'a str,
+    options: &'a RomanizeOptions,
+) -> Vec<(Cow<'a, str>, Option<&'a str>)> {
+    let lcode = options.output.lcode.as_deref();
+    split_inline_lcodes(text, lcode)
+        .unwrap_or_else(|| vec![(text, lcode)])
+        .into_iter()
+        .map(|(segment, lcode)| (options.decode_escapes(segment), lcode))
+        .collect()
+}
+
+/// The text that the edges of [`Uroman::romanize_with_inline_lcodes`] index into: `text`
+/// without its inline language code markers and with its escapes decoded as `options` say.
+pub(crate) fn inline_lcodes_text<'a>(text: &'a str, options: &RomanizeOptions) -> Cow<'a, str> {
+    match split_inline_lcodes(text, None) {
+        Some(segments) => Cow::Owned(
+            segments
+                .iter()
+                .map(|(segment, _)| options.decode_escapes(segment))
+                .collect(),
+        ),
+        None => options.decode_escapes(text),
+    }
+}
+
+/// Splits `text` at its inline language code markers into segments with their language
+/// codes, `lcode` outside the markers. Returns `None` if `text` has no markers.
+///
+/// Spans don't nest, and a start marker without its `⟧` is left in the text.
+fn split_inline_lcodes<'a>(
+    text: &'a str,
+    lcode: Option<&'a str>,
+) -> Option<Vec<(&'a str, Option<&'a str>)>> {
+    if !text.contains(INLINE_LCODE_START) {
+        return None;
+    }
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(INLINE_LCODE_START) {
+        let after_start = &rest[start + INLINE_LCODE_START.len()..];
+        let Some((span_lcode, span)) = after_start.split_once('⟧') else {
+            break;
+        };
+        let (span, after_span) = span.split_once(INLINE_LCODE_END).unwrap_or((span, ""));
+        segments.push((&rest[..start], lcode));
+        segments.push((span, Some(span_lcode.trim())));
+        rest = after_span;
+    }
+    segments.push((rest, lcode));
+    Some(segments)
+}
@@ -6,52 +6,187 @@
 
 #![allow(clippy::too_many_arguments)]
 
-use indexmap::IndexMap;
 use regex::Regex;
-use serde::Serialize;
-use serde_json::Value as JsonValue;
+use num_rational::Ratio;
+use serde::{Deserialize, Serialize};
 use std::any::TypeId;
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
 use std::io::{self, BufRead, Write};
 use std::marker::PhantomData;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use unicode_normalization::UnicodeNormalization;
-use unicode_properties::UnicodeGeneralCategory;
 
+use crate::data_table::{PropsRecord, RomRecord, ScriptRecord};
+pub use crate::alignment::{Alignment, OffsetUnit};
+pub use crate::backend::RomanizerBackend;
+pub use crate::cache::{CacheStats, RomanizationCache};
+pub use crate::cancel::CancellationToken;
+pub use crate::compare::{Comparison, Disagreement, LineDivergence, compare_outputs};
+pub use crate::custom_rules::RULE_CONDITIONS;
+use crate::cache::MemoMap;
+use crate::cancel::Interrupt;
+use crate::chunks::LineChunks;
 pub use crate::edge::Edge;
+pub use crate::encoding::InputEncoding;
+pub use crate::explorer::{LatticeExplorer, LatticeSpan};
+pub use crate::gen_data::DataTables;
+pub use crate::incremental::IncrementalRomanization;
+pub use crate::input_format::InputFormat;
+pub use crate::lang_tag::normalize_lcode;
+pub use crate::options::{
+    CombiningMarkPolicy, Compat, FileContext, FormatCharPolicy, InvalidEscapePolicy,
+    InvalidUtf8Policy, LineEnding, OutputOptions, RomanizeOptions, RulePriority, Subsystems,
+    UnmappableCharPolicy, WhitespacePolicy,
+};
+pub use crate::overrides::{OVERRIDES_DIR_ENV, default_overrides_dir};
+#[cfg(feature = "parallel")]
+pub use crate::parallel::ParallelRomanize;
+#[cfg(feature = "profiling")]
+pub use crate::profiling::StageTimings;
+pub use crate::progress::{FileSummary, LineError, Progress};
+pub use crate::report::{HtmlReport, ReportStats};
+pub use crate::script_spans::COMMON_SCRIPT;
+#[cfg(feature = "tower")]
+pub use crate::service::RomanizeService;
+pub use crate::writer::RomanizingWriter;
+use crate::directives::split_directives;
+use crate::hash::{HashMap, HashSet, IndexMap, RandomState};
+use crate::intern::intern;
 use crate::lattice::Lattice;
-use crate::utils::{decode_unicode_escapes, slot_value_in_double_colon_del_list};
-
+use crate::options::Policies;
+use crate::profiling::Stage;
+use crate::sentences::Sentences;
+use crate::utils::{
+    InputWindows, LossyLines, decode_unicode_escapes, diagnostic, split_ascii_margins,
+};
+
+mod alignment;
+// Shared with `build.rs`, which uses the compile half of it.
+#[allow(dead_code)]
+mod data_table;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod backend;
+mod bidi;
+mod cache;
+mod cancel;
+mod case;
+mod chunks;
+mod compat;
+mod compare;
+mod custom_rules;
 mod decompositions;
+#[cfg(feature = "detect-lcode")]
+mod detect;
+mod directives;
 mod edge;
+mod edge_path;
+mod encoding;
+mod explorer;
+mod filename;
+mod gen_data;
+mod hash;
+mod homoglyphs;
+mod html_entities;
+mod icu;
+mod idempotency;
+mod incremental;
+mod input_format;
+mod intern;
+mod lang_tag;
 mod lattice;
+mod lcode_guess;
+mod lcode_spans;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod options;
+mod overrides;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod profiling;
+mod progress;
+mod protected;
+mod report;
 mod rom_rule;
+mod rule_matcher;
+mod rule_trie;
+mod rule_weights;
+mod script_spans;
+mod sentences;
+#[cfg(feature = "tower")]
+mod service;
+#[cfg(feature = "snapshot")]
+mod snapshot;
+mod strict;
+mod unigrams;
 mod utils;
+mod writer;
 
 use rom_rule::{RomRule, RomRules};
+use rule_matcher::RuleMatcher;
+use rule_trie::RuleTrie;
 
 static KAYAH_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"kayah\s+(\S+)\s*$").unwrap());
 static MENDE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"m\d+\s+(\S+)\s*$").unwrap());
 static SPACE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\S\s+\S").unwrap());
-static HANGUL_LEADS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
-    "g gg n d dd r m b bb s ss - j jj c k t p h"
-        .split_whitespace()
-        .collect()
-});
-static HANGUL_VOWELS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
-    "a ae ya yae eo e yeo ye o wa wai oe yo u weo we wi yu eu yi i"
-        .split_whitespace()
-        .collect()
-});
-static HANGUL_TAILS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
-    "- g gg gs n nj nh d l lg lm lb ls lt lp lh m b bs s ss ng j c k t p h"
-        .split_whitespace()
-        .collect()
+#[cfg(feature = "hangul")]
+const HANGUL_LEADS: [&str; 19] = [
+    "g", "gg", "n", "d", "dd", "r", "m", "b", "bb", "s", "ss", "", "j", "jj", "c", "k", "t", "p",
+    "h",
+];
+#[cfg(feature = "hangul")]
+const HANGUL_VOWELS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wai", "oe", "yo", "u", "weo", "we",
+    "wi", "yu", "eu", "yi", "i",
+];
+#[cfg(feature = "hangul")]
+const HANGUL_TAILS: [&str; 28] = [
+    "", "g", "gg", "gs", "n", "nj", "nh", "d", "l", "lg", "lm", "lb", "ls", "lt", "lp", "lh", "m",
+    "b", "bs", "s", "ss", "ng", "j", "c", "k", "t", "p", "h",
+];
+/// The romanization of every Hangul syllable (U+AC00–U+D7A3), in code point order.
+#[cfg(feature = "hangul")]
+static HANGUL_SYLLABLES: LazyLock<Box<[String]>> = LazyLock::new(|| {
+    let mut syllables =
+        Vec::with_capacity(HANGUL_LEADS.len() * HANGUL_VOWELS.len() * HANGUL_TAILS.len());
+    for lead in HANGUL_LEADS {
+        for vowel in HANGUL_VOWELS {
+            for tail in HANGUL_TAILS {
+                syllables.push(format!("{lead}{vowel}{tail}"));
+            }
+        }
+    }
+    syllables.into_boxed_slice()
 });
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The default of `Uroman::with_max_edges_per_span`. Regular text has only a few
+/// edges per span, so this is only reached by adversarial or garbage input.
+const DEFAULT_MAX_EDGES_PER_SPAN: usize = 64;
+
+/// The default of `Uroman::with_max_edges`, 64 edges per char of the longest window of a line.
+/// Regular text has about two.
+const DEFAULT_MAX_EDGES: usize = 64 * LONG_INPUT_WINDOW_CHARS;
+
+/// The estimated memory of an edge of a lattice, including the strings it owns and its share
+/// of the maps that index it, see `Uroman::with_memory_budget`.
+const ESTIMATED_EDGE_BYTES: usize = 2 * size_of::<Edge<'static>>();
+
+/// The estimated memory of an entry of the abugida cache.
+const ESTIMATED_ABUGIDA_ENTRY_BYTES: usize = 256;
+
+/// Lines longer than this many characters are romanized in windows of about this size, which
+/// bounds the size of the lattice.
+const LONG_INPUT_WINDOW_CHARS: usize = 4096;
+
+/// The provenance of the rules that romanize Chinese characters as pinyin.
+pub(crate) const PINYIN_PROV: &str = "rom pinyin";
+
+/// The provenance of the rules from a user overrides directory, see [`Uroman::load_overrides`].
+pub(crate) const OVERRIDE_PROV: &str = "user";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum RomFormat {
     #[default]
     Str,
@@ -62,6 +197,31 @@ pub enum RomFormat {
 
 pub(crate) use rom_format::RomFormatType;
 
+/// Embeds a data table compiled by `build.rs` and returns its text.
+macro_rules! embedded_table {
+    ($name:literal) => {
+        crate::decode_embedded_table(include_bytes!(concat!(env!("OUT_DIR"), "/", $name)))
+    };
+}
+
+/// Decodes an embedded data table, inflating it if it was stored compressed.
+#[cfg(feature = "compressed-data")]
+fn decode_embedded_table(bytes: &'static [u8]) -> std::borrow::Cow<'static, str> {
+    let inflated =
+        miniz_oxide::inflate::decompress_to_vec(bytes).expect("embedded table is corrupted");
+    String::from_utf8(inflated)
+        .expect("embedded table is not UTF-8")
+        .into()
+}
+
+/// Decodes an embedded data table, which is stored as plain text.
+#[cfg(not(feature = "compressed-data"))]
+fn decode_embedded_table(bytes: &'static [u8]) -> std::borrow::Cow<'static, str> {
+    std::str::from_utf8(bytes)
+        .expect("embedded table is not UTF-8")
+        .into()
+}
+
 pub mod rom_format {
     use crate::RomanizationError;
 
@@ -93,10 +253,18 @@ pub mod rom_format {
 
 pub struct RomanizationOutput<F: RomFormatType> {
     result: RomanizationResult,
+    greedy_fallback: bool,
     _marker: PhantomData<F>,
 }
 
 impl<F: RomFormatType> RomanizationOutput<F> {
+    /// Returns `true` if the lattice exceeded `Uroman::with_max_edges_per_span`,
+    /// `Uroman::with_max_edges` or `Uroman::with_time_limit` and the string was romanized
+    /// greedily instead. In the edge formats, such edges have the type `greedy`.
+    pub fn used_greedy_fallback(&self) -> bool {
+        self.greedy_fallback
+    }
+
     pub fn to_output_string(self) -> F::Output
     where
         F::Output: From<RomanizationResult>,
@@ -129,7 +297,7 @@ impl From<RomanizationResult> for Result<String, RomanizationError> {
 #[serde(untagged)]
 pub enum RomanizationResult {
     Str(String),
-    Edges(Vec<Edge>),
+    Edges(Vec<Edge<'static>>),
 }
 
 impl RomanizationResult {
@@ -142,6 +310,7 @@ impl RomanizationResult {
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum RomanizationError {
     #[error("Failed to serialize the result to JSON: {0}")]
     SerializationFailed(#[from] serde_json::Error),
@@ -151,35 +320,233 @@ pub enum RomanizationError {
 
     #[error("Internal logic error: {0}")]
     InternalError(String),
+
+    /// A language code that is neither an ISO 639-3 code nor a BCP-47 tag with a known
+    /// language subtag.
+    #[error(
+        "Unknown language tag {0:?}: expected an ISO 639-3 code such as \"ukr\" or a BCP-47 tag such as \"uk\" or \"sr-Latn\""
+    )]
+    UnknownLanguageTag(String),
+
+    /// A language code that is not an ISO 639-3 code and that no rule is conditioned on, see
+    /// [`Uroman::validate_lcode`].
+    #[error(
+        "Unknown language code {lcode:?}{}. The codes that change the romanization are: {}",
+        lang_tag::did_you_mean(.suggestions),
+        .effective_lcodes.join(", ")
+    )]
+    UnknownLanguageCode {
+        lcode: String,
+        /// Close matches of `lcode`, best first.
+        suggestions: Vec<String>,
+        /// The codes of [`Uroman::effective_lcodes`].
+        effective_lcodes: Vec<String>,
+    },
+
+    /// A rule weight for a source and target that no rule romanizes, see
+    /// [`Uroman::set_rule_weight`].
+    #[error("No rule romanizes {s:?} as {t:?}")]
+    UnknownRule { s: String, t: String },
+
+    /// A line of a rule weight file without a source, target or numeric weight, see
+    /// [`Uroman::load_rule_weights`].
+    #[error("Invalid rule weight {0:?}: expected e.g. \"::s ий ::t yi ::lcode ukr ::weight -1\"")]
+    InvalidRuleWeight(String),
+
+    /// A line of custom rules without a source, or with a condition on the context that rules
+    /// don't have, see [`Uroman::load_rules`].
+    #[error("Invalid rule {line:?}: {reason}")]
+    InvalidRule { line: String, reason: String },
+
+    /// A line of a unigram file without a word and a count, see [`Uroman::load_unigrams`].
+    #[error("Invalid unigram {0:?}: expected a word and its count, e.g. \"kitab 1520\"")]
+    InvalidUnigram(String),
+
+    /// An invalid regex of a file of strings that are not romanized, see
+    /// [`Uroman::load_protected`].
+    #[error("Invalid protected pattern {pattern:?}: {reason}")]
+    InvalidProtectedPattern { pattern: String, reason: String },
+
+    /// A line of JSON Lines input that is not valid JSON, see [`InputFormat::Jsonl`].
+    #[error("Invalid JSON line: {0}")]
+    InvalidJsonLine(String),
+
+    /// Text with chars that can't be romanized, see [`Uroman::romanize_strict`].
+    #[error("Can't romanize {}", strict::describe_chars(.0))]
+    Unromanizable(Vec<char>),
+
+    /// Edges that are not a path over their text, see [`Edge::validate_path`].
+    #[error("Invalid edge path at edge {index}: {reason}")]
+    InvalidEdgePath { index: usize, reason: String },
+
+    /// A romanization that passed its deadline, see [`Uroman::romanize_string_with_deadline`].
+    #[error("The romanization passed its deadline")]
+    DeadlineExceeded,
+
+    /// A romanization that was cancelled, see [`Uroman::romanize_string_with_cancellation`].
+    #[error("The romanization was cancelled")]
+    Cancelled,
+
+    /// A line of a `UnicodeData.txt` or `Scripts.txt` that can't be parsed, see
+    /// [`Uroman::generate_data_tables`].
+    #[error("Invalid Unicode Character Database line {0:?}")]
+    InvalidUnicodeData(String),
+
+    /// An error while reading or romanizing a line of a file, with where to find the line.
+    #[error("Line {line} (byte offset {byte_offset}, {snippet:?}): {source}")]
+    AtLine {
+        /// The 1-based number of the line.
+        line: usize,
+        /// The offset of the start of the line in the input.
+        byte_offset: u64,
+        /// The start of the line, with invalid UTF-8 replaced.
+        snippet: String,
+        source: Box<RomanizationError>,
+    },
+
+    #[cfg(feature = "snapshot")]
+    #[error("Failed to encode the state snapshot: {0}")]
+    SnapshotEncodeFailed(#[from] bincode::error::EncodeError),
+
+    #[cfg(feature = "snapshot")]
+    #[error("Failed to decode the state snapshot: {0}")]
+    SnapshotDecodeFailed(#[from] bincode::error::DecodeError),
+
+    #[cfg(feature = "snapshot")]
+    #[error("Incompatible state snapshot: expected version {expected}, found {found}")]
+    IncompatibleSnapshot { expected: String, found: String },
 }
 
 /// Represents a value that can be an integer, float, or string.
+#[allow(unused)]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 enum Value {
     Int(i64),
     Float(f64),
     String(String),
-    Array(Vec<Value>),
+}
+
+/// Keys of the boolean character/string properties stored in `dict_bool`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+enum BoolProp {
+    LargePower,
+    VowelSign,
+    MedialConsonantSign,
+    Virama,
+}
+
+/// Keys of the per-character string properties stored in `dict_str`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+enum StrProp {
+    Script,
+    ToneMark,
+    SyllableInfo,
+    Pic,
+    Name,
 }
 
 /// Represents a script with its properties.
 #[allow(unused)]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 struct Script {
     pub script_name: String,
     pub direction: Option<String>,
     pub abugida_default_vowels: Vec<String>,
     pub alt_script_names: Vec<String>,
     pub languages: Vec<String>,
-    pub abugida_regexes: Option<(Regex, Regex)>,
 }
 
-// #[derive(Default, Debug)]
-// struct NumPropDefaults {
-//     pub value: Option<f64>,
-//     pub num_base: Option<i64>,
-//     pub is_large_power: Option<bool>,
-// }
+impl Script {
+    /// Splits the romanization of an abugida syllable ending in `y` and inherent vowels,
+    /// e.g. `kyaa` into `("ky", "aa")`. A trailing `-` is ignored.
+    ///
+    /// This matches like the Python version's `([cfghkmnqrstxy]?y)(a+|...)-?$`, including
+    /// which match is found first, without building a regex per script.
+    fn split_y_vowels<'r>(&self, rom: &'r str) -> Option<(&'r str, &'r str)> {
+        let body = rom.strip_suffix('-').unwrap_or(rom);
+        let bytes = body.as_bytes();
+        body.char_indices().find_map(|(i, _)| {
+            let after_consonant = (b"cfghkmnqrstxy".contains(&bytes[i])
+                && bytes.get(i + 1) == Some(&b'y'))
+            .then_some(i + 2);
+            let after_y = (bytes[i] == b'y').then_some(i + 1);
+            [after_consonant, after_y]
+                .into_iter()
+                .flatten()
+                .find(|&j| self.is_repeated_vowel(&body[j..]))
+                .map(|j| (&body[i..j], &body[j..]))
+        })
+    }
+
+    /// Splits the romanization of an abugida syllable into its consonants and inherent vowel,
+    /// e.g. `kta` into `("kt", "a")`. A trailing `-` is ignored.
+    ///
+    /// This matches like the Python version's `([bcdfghjklmnpqrstvwxyz]+)(a|...)-?$`,
+    /// including which match is found first, without building a regex per script.
+    fn split_consonant_vowel<'r>(&self, rom: &'r str) -> Option<(&'r str, &'r str)> {
+        let body = rom.strip_suffix('-').unwrap_or(rom);
+        let bytes = body.as_bytes();
+        body.char_indices().find_map(|(i, _)| {
+            let n_consonants = bytes[i..]
+                .iter()
+                .take_while(|b| b"bcdfghjklmnpqrstvwxyz".contains(b))
+                .count();
+            (i + 1..=i + n_consonants).rev().find_map(|j| {
+                self.abugida_default_vowels
+                    .iter()
+                    .any(|vowel| body[j..] == **vowel)
+                    .then(|| (&body[i..j], &body[j..]))
+            })
+        })
+    }
+
+    /// Returns `true` if `s` is one of the default vowels, repeated one or more times.
+    fn is_repeated_vowel(&self, s: &str) -> bool {
+        !s.is_empty()
+            && self.abugida_default_vowels.iter().any(|vowel| {
+                // A shorter last chunk doesn't match either.
+                !vowel.is_empty()
+                    && s.as_bytes()
+                        .chunks(vowel.len())
+                        .all(|chunk| chunk == vowel.as_bytes())
+            })
+    }
+}
+
+/// Numeric properties of a character, loaded from `NumProps.jsonl`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+struct NumProp {
+    rom: Option<String>,
+    value: Option<f64>,
+    fraction: Option<Ratio<i64>>,
+    r#type: Arc<str>,
+    num_base: Option<i64>,
+    base_multiplier: Option<f64>,
+    script: Option<Arc<str>>,
+    is_large_power: bool,
+}
+
+/// One line of `NumProps.jsonl`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct NumPropRecord {
+    txt: String,
+    rom: Option<String>,
+    value: Option<f64>,
+    fraction: Option<(i64, i64)>,
+    #[serde(default)]
+    r#type: String,
+    base: Option<i64>,
+    mult: Option<f64>,
+    script: Option<String>,
+    #[serde(default)]
+    is_large_power: bool,
+}
 
 #[derive(Debug, Clone)]
 struct AbugidaCacheEntry {
@@ -195,348 +562,637 @@ struct AbugidaCacheEntry {
 #[derive(Debug, Default, Clone)]
 pub struct Uroman {
     rom_rules: RomRules,
+    rule_trie: RuleTrie,
+    rule_matcher: Option<RuleMatcher>,
     scripts: HashMap<String, Script>,
-    dict_bool: HashMap<(String, String), bool>,
-    dict_str: HashMap<(String, String), String>,
-    num_props: HashMap<String, HashMap<String, Value>>,
+    dict_bool: HashMap<BoolProp, HashSet<String>>,
+    /// The values repeat across many chars, and are interned except for `StrProp::Name`.
+    dict_str: HashMap<(StrProp, char), Arc<str>>,
+    num_props: HashMap<char, NumProp>,
     percentage_markers: HashSet<String>,
     fraction_connectors: HashSet<String>,
     plus_signs: HashSet<String>,
     minus_signs: HashSet<String>,
-    hangul_rom: RefCell<HashMap<char, String>>,
-    abugida_cache: RefCell<HashMap<(String, String), AbugidaCacheEntry>>,
+    abugida_cache: MemoMap<(String, String), AbugidaCacheEntry>,
+    max_edges_per_span: Option<usize>,
+    max_edges: Option<usize>,
+    sentence_chunk_chars: Option<usize>,
+    max_alternatives: Option<usize>,
+    time_limit: Option<Duration>,
+    memory_budget: Option<usize>,
+    subsystems: Subsystems,
+    format_chars: FormatCharPolicy,
+    combining_marks: CombiningMarkPolicy,
+    unmappable_chars: UnmappableCharPolicy,
+    whitespace: WhitespacePolicy,
+    normalize_homoglyphs: bool,
+    /// Whether edges are annotated with the conditions of their rules, see
+    /// [`Uroman::with_rule_conditions`].
+    rule_conditions: bool,
+    /// Whether romanizations take the case of the romanized chars, see
+    /// [`Uroman::with_case_carrying`].
+    carry_case: bool,
+    /// The strings that are not romanized, see [`Uroman::load_protected`].
+    protected: protected::Protected,
+    rule_priority: Vec<RulePriority>,
+    /// The frequencies of romanized words of each language, see [`Uroman::load_unigrams`].
+    unigrams: HashMap<String, HashMap<String, u64>>,
+    #[cfg(feature = "profiling")]
+    timings: profiling::TimingsCell,
 }
 
 impl Uroman {
     pub fn new() -> Self {
         let mut uroman = Self {
-            rom_rules: IndexMap::with_capacity(42979),
-            scripts: HashMap::with_capacity(179),
-            dict_bool: HashMap::with_capacity(44366),
-            dict_str: HashMap::with_capacity(122770),
-            num_props: HashMap::with_capacity(1599),
-            percentage_markers: HashSet::with_capacity(1),
-            fraction_connectors: HashSet::with_capacity(1),
-            minus_signs: HashSet::with_capacity(2),
-            plus_signs: HashSet::new(),
-            hangul_rom: HashMap::new().into(),
-            abugida_cache: HashMap::new().into(),
+            rom_rules: IndexMap::with_capacity_and_hasher(42979, RandomState::default()),
+            rule_trie: RuleTrie::with_capacity(44366),
+            scripts: HashMap::with_capacity_and_hasher(179, RandomState::default()),
+            dict_bool: HashMap::with_capacity_and_hasher(4, RandomState::default()),
+            dict_str: HashMap::with_capacity_and_hasher(122770, RandomState::default()),
+            num_props: HashMap::with_capacity_and_hasher(1599, RandomState::default()),
+            percentage_markers: HashSet::with_capacity_and_hasher(1, RandomState::default()),
+            fraction_connectors: HashSet::with_capacity_and_hasher(1, RandomState::default()),
+            minus_signs: HashSet::with_capacity_and_hasher(2, RandomState::default()),
+            ..Self::unloaded()
         };
         uroman.load_resource_files();
         uroman
     }
 
-    /// Registers all prefixes of a string `s` for efficient lookup later.
-    fn register_s_prefix(&mut self, s: &str) {
-        let mut prefix = String::with_capacity(s.chars().count());
-        for c in s.chars() {
-            prefix.push(c);
-            self.dict_bool
-                .insert(("s-prefix".to_string(), prefix.clone()), true);
+    /// Returns a `Uroman` with the settings of [`Uroman::new`] but no tables loaded. Both
+    /// `new` and [`Uroman::load_state`] start from it, so that they romanize alike.
+    pub(crate) fn unloaded() -> Self {
+        Self {
+            rom_rules: RomRules::default(),
+            rule_trie: RuleTrie::default(),
+            rule_matcher: None,
+            scripts: HashMap::default(),
+            dict_bool: HashMap::default(),
+            dict_str: HashMap::default(),
+            num_props: HashMap::default(),
+            percentage_markers: HashSet::default(),
+            fraction_connectors: HashSet::default(),
+            minus_signs: HashSet::default(),
+            plus_signs: HashSet::default(),
+            abugida_cache: MemoMap::default(),
+            max_edges_per_span: Some(DEFAULT_MAX_EDGES_PER_SPAN),
+            max_edges: Some(DEFAULT_MAX_EDGES),
+            sentence_chunk_chars: None,
+            max_alternatives: None,
+            time_limit: None,
+            memory_budget: None,
+            subsystems: Subsystems::default(),
+            format_chars: FormatCharPolicy::default(),
+            combining_marks: CombiningMarkPolicy::default(),
+            unmappable_chars: UnmappableCharPolicy::default(),
+            whitespace: WhitespacePolicy::default(),
+            normalize_homoglyphs: false,
+            rule_conditions: false,
+            carry_case: false,
+            protected: protected::Protected::default(),
+            rule_priority: RulePriority::DEFAULT.to_vec(),
+            unigrams: HashMap::default(),
+            #[cfg(feature = "profiling")]
+            timings: Default::default(),
         }
     }
 
-    // /// Retrieves the numerical properties for a given character.
-    // ///
-    // /// This method looks up the character in the `num_props` map.
-    // /// If the character is not found, it returns default values.
-    // fn get_num_props(&self, c: char) -> NumPropDefaults {
-    //     self.num_props
-    //         .get(&c)
-    //         .map_or_else(NumPropDefaults::default, |props| {
-    //             let value = props.get("value").and_then(|v| match v {
-    //                 Value::Int(i) => Some(*i as f64),
-    //                 Value::Float(f) => Some(*f),
-    //                 _ => None,
-    //             });
-    //             let num_base = props.get("base").and_then(|v| match v {
-    //                 Value::Int(i) => Some(*i),
-    //                 _ => None,
-    //             });
-    //             let is_large_power = props.get("is-large-power").and_then(|v| match v {
-    //                 Value::Int(1) => Some(true),
-    //                 _ => None,
-    //             });
-
-    //             NumPropDefaults {
-    //                 value,
-    //                 num_base,
-    //                 is_large_power,
-    //             }
-    //         })
-    // }
+    /// Sets the maximum number of competing edges for a single span of the lattice, or removes
+    /// the limit with `None`. The default is 64.
+    ///
+    /// When a string exceeds it, the lattice is abandoned and the string is romanized greedily
+    /// by the longest matching rule at each position instead. Such output is marked, see
+    /// [`RomanizationOutput::used_greedy_fallback`].
+    ///
+    /// # Example
+    /// ```
+    /// # use uroman::Uroman;
+    /// let uroman = Uroman::new().with_max_edges_per_span(Some(16));
+    /// ```
+    pub fn with_max_edges_per_span(mut self, max: Option<usize>) -> Self {
+        self.max_edges_per_span = max;
+        self
+    }
 
-    fn load_resource_files(&mut self) {
-        self.load_rom_file(
-            include_str!("../data/romanization-auto-table.txt"),
-            "ud",
-            "rom",
-        );
-        self.load_rom_file(
-            include_str!("../data/UnicodeDataOverwrite.txt"),
-            "ow",
-            "u2r",
-        );
-        self.load_rom_file(
-            include_str!("../data/romanization-table.txt"),
-            "man",
-            "rom",
-        );
-        self.load_chinese_pinyin_file(include_str!("../data/Chinese_to_Pinyin.txt"));
-        self.load_script_file(include_str!("../data/Scripts.txt"));
-        self.load_unicode_data_props(include_str!("../data/UnicodeDataProps.txt"));
-        self.load_unicode_data_props(include_str!("../data/UnicodeDataPropsCJK.txt"));
-        self.load_unicode_data_props(include_str!("../data/UnicodeDataPropsHangul.txt"));
-        self.load_num_props(include_str!("../data/NumProps.jsonl"));
-        self.add_thai_cancellation_rules();
+    /// Sets the maximum number of edges in the lattice of a string, or removes the limit with
+    /// `None`. The default is 262144, and lines longer than 4096 chars are romanized in windows
+    /// with a lattice each.
+    ///
+    /// Like [`Uroman::with_max_edges_per_span`], a string that exceeds it is romanized
+    /// greedily instead.
+    ///
+    /// # Example
+    /// ```
+    /// # use uroman::Uroman;
+    /// let uroman = Uroman::new().with_max_edges(Some(10_000));
+    /// ```
+    pub fn with_max_edges(mut self, max: Option<usize>) -> Self {
+        self.max_edges = max;
+        self
     }
 
-    /// Loads numerical properties from a JSONL file (e.g., NumProps.jsonl).
-    fn load_num_props(&mut self, file_content: &'static str) {
-        for line in file_content.lines() {
-            if line.starts_with('#') || line.trim().is_empty() {
-                continue;
-            }
+    /// Returns the maximum number of edges in the lattice of a string, see
+    /// [`Uroman::with_max_edges`].
+    pub fn max_edges(&self) -> Option<usize> {
+        self.max_edges
+    }
 
-            let json: JsonValue = serde_json::from_str(line).unwrap();
+    /// Splits strings longer than `min_chars` chars into sentences, which are romanized one by
+    /// one, or romanizes strings whole with `None`, the default.
+    ///
+    /// This bounds the size of the lattice of a long paragraph by that of its longest sentence,
+    /// and with the `parallel` feature, the sentences of a string are romanized in parallel in
+    /// the `str` format. A sentence ends after `.`, `!`, `?` or a terminator of another script,
+    /// such as `։`, `؟` or `।`, followed by whitespace, or after `。`. The output is put back
+    /// together with the separators between the sentences as they are. Rules don't match across
+    /// the end of a sentence.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_sentence_chunks(Some(10));
+    /// let text = "Привіт!  Як справи? Добре।";
+    /// let result = uroman.romanize_string::<rom_format::Str>(text, Some("ukr"));
+    /// assert_eq!(result.to_output_string(), "Pryvit!  Yak spravy? Dobre.");
+    /// ```
+    pub fn with_sentence_chunks(mut self, min_chars: Option<usize>) -> Self {
+        self.sentence_chunk_chars = min_chars;
+        self
+    }
 
-            if let Some(obj) = json.as_object()
-                && let Some(txt) = obj.get("txt").and_then(|v| v.as_str()) {
-                    let txt_key = txt.to_string();
+    /// Sets the maximum number of alternatives that the `alts` format adds to the edges of a
+    /// call, or removes the limit with `None`, the default. Those beyond it are left out.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomanizationResult, Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_max_alternatives(Some(0));
+    /// let output = uroman.romanize_string::<rom_format::Alts>("ایران", None);
+    /// assert!(!output.to_output_string().unwrap().contains("rom-alt"));
+    /// ```
+    pub fn with_max_alternatives(mut self, max: Option<usize>) -> Self {
+        self.max_alternatives = max;
+        self
+    }
 
-                    for bool_key in ["is-large-power"] {
-                        if obj.get(bool_key).and_then(|v| v.as_bool()).unwrap_or(false) {
-                            self.dict_bool.insert((bool_key.to_string(), txt_key.clone()), true);
-                        }
-                    }
+    /// Sets the time that a call may spend on building lattices, or removes the limit with
+    /// `None`, the default. Once it is spent, the rest of the string is romanized greedily,
+    /// which takes linear time, so the output is still complete.
+    ///
+    /// The output of a string can then depend on the load of the machine. Without a limit,
+    /// the clock is never read, e.g. on WebAssembly targets without one.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use uroman::Uroman;
+    /// let uroman = Uroman::new().with_time_limit(Some(Duration::from_millis(100)));
+    /// ```
+    pub fn with_time_limit(mut self, limit: Option<Duration>) -> Self {
+        self.time_limit = limit;
+        self
+    }
 
-                    let mut prop_map: HashMap<String, Value> = HashMap::new();
-                    for (key, val) in obj {
-                        match val {
-                            JsonValue::Number(n) => {
-                                if let Some(i) = n.as_i64() {
-                                    prop_map.insert(key.clone(), Value::Int(i));
-                                } else if let Some(f) = n.as_f64() {
-                                    prop_map.insert(key.clone(), Value::Float(f));
-                                }
-                            }
-                            JsonValue::String(s) => {
-                                prop_map.insert(key.clone(), Value::String(s.clone()));
-                            }
-                            JsonValue::Bool(b) => {
-                                prop_map.insert(key.clone(), Value::Int(if *b { 1 } else { 0 }));
-                            }
-                            JsonValue::Array(arr) => {
-                                let mut values = Vec::new();
-                                for item in arr {
-                                    if let Some(i) = item.as_i64() {
-                                        values.push(Value::Int(i));
-                                    }
-                                }
-                                prop_map.insert(key.clone(), Value::Array(values));
-                            }
-                            _ => {}
-                        }
-                    }
+    /// Sets a soft limit in bytes on the memory that romanizing takes, or removes it with
+    /// `None`, the default, for running in memory-limited containers.
+    ///
+    /// The memory is estimated from the number of edges of a lattice and the entries of the
+    /// internal caches. The caches get a quarter of the budget and are emptied once they
+    /// reach it, and a string whose lattice would exceed the rest is romanized greedily, as
+    /// with [`Uroman::with_max_edges`]. The rule tables aren't counted, nor is a
+    /// [`RomanizationCache`], which has a budget of its own, see
+    /// [`RomanizationCache::with_memory_budget`].
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_memory_budget(Some(1 << 20));
+    /// let output = uroman.romanize_string::<rom_format::Str>(&"Київ ".repeat(1000), Some("ukr"));
+    /// assert!(output.used_greedy_fallback());
+    /// assert!(output.to_output_string().starts_with("Kyiv Kyiv"));
+    /// ```
+    pub fn with_memory_budget(mut self, bytes: Option<usize>) -> Self {
+        self.memory_budget = bytes;
+        self
+    }
 
-                    self.num_props.insert(txt_key, prop_map);
-                }
+    /// Returns the maximum number of edges of a lattice, from `max_edges` and the part of the
+    /// memory budget that isn't for the caches.
+    pub(crate) fn lattice_edge_cap(&self) -> Option<usize> {
+        let budget_edges = self
+            .memory_budget
+            .map(|budget| (budget - budget / 4) / ESTIMATED_EDGE_BYTES);
+        match (self.max_edges, budget_edges) {
+            (Some(max), Some(budget_edges)) => Some(max.min(budget_edges)),
+            (max, budget_edges) => max.or(budget_edges),
         }
     }
 
-    /// Loads Unicode data properties from a file (e.g., UnicodeDataProps.txt).
-    fn load_unicode_data_props(&mut self, file: &'static str) {
-        for line in file.lines() {
-            if line.starts_with('#') || line.trim().is_empty() {
-                continue;
-            }
+    /// Returns the maximum number of entries of the abugida cache, from the memory budget.
+    pub(crate) fn abugida_cache_cap(&self) -> Option<usize> {
+        self.memory_budget
+            .map(|budget| budget / 4 / ESTIMATED_ABUGIDA_ENTRY_BYTES)
+    }
 
-            if let Some(script_name) =
-                utils::slot_value_in_double_colon_del_list(line, "script-name")
-            {
-                if let Some(chars_str) = utils::slot_value_in_double_colon_del_list(line, "char") {
-                    for c in chars_str.chars() {
-                        self.dict_str.insert(
-                            ("script".to_string(), c.to_string()),
-                            script_name.to_string(),
-                        );
-                    }
-                }
-                if let Some(vowel_sign_str) =
-                    utils::slot_value_in_double_colon_del_list(line, "vowel-sign")
-                {
-                    for c in vowel_sign_str.chars() {
-                        self.dict_bool
-                            .insert(("is-vowel-sign".to_string(), c.to_string()), true);
-                    }
-                }
-                if let Some(medial_consonant_sign_str) =
-                    utils::slot_value_in_double_colon_del_list(line, "medial-consonant-sign")
-                {
-                    for c in medial_consonant_sign_str.chars() {
-                        self.dict_bool.insert(
-                            ("is-medial-consonant-sign".to_string(), c.to_string()),
-                            true,
-                        );
-                    }
-                }
-                if let Some(virama_str) =
-                    utils::slot_value_in_double_colon_del_list(line, "sign-virama")
-                {
-                    for c in virama_str.chars() {
-                        self.dict_bool
-                            .insert(("is-virama".to_string(), c.to_string()), true);
-                    }
-                }
-            }
-        }
+    /// Sets whether numbers are converted, which they are by default.
+    ///
+    /// Numbers in other scripts are converted to Arabic numerals, e.g. `۱۳۹۸` to `1398` and
+    /// `三百` to `300`, and so are Braille numbers. Without the conversion, digits pass through
+    /// untouched and numeral words are romanized like other words (`三百` to `sanbai`), for
+    /// pipelines that only want letters transliterated.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_numbers(false);
+    /// let result = uroman.romanize_string::<rom_format::Str>("۱۳۹۸", None);
+    /// assert_eq!(result.to_output_string(), "۱۳۹۸");
+    /// ```
+    pub fn with_numbers(mut self, enabled: bool) -> Self {
+        self.subsystems.numbers = enabled;
+        self
     }
 
-    /// Loads a script definition file (e.g., Scripts.txt).
-    fn load_script_file(&mut self, file: &'static str) {
-        for line in file.lines() {
-            if line.starts_with('#') || line.trim().is_empty() {
-                continue;
-            }
+    /// Sets the stages of romanization to run, all of them by default. Calls can override
+    /// them with [`OutputOptions::subsystems`].
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Subsystems, Uroman};
+    ///
+    /// let uroman = Uroman::new().with_subsystems(Subsystems::default().with_pinyin(false));
+    /// ```
+    pub fn with_subsystems(mut self, subsystems: Subsystems) -> Self {
+        self.subsystems = subsystems;
+        self
+    }
 
-            if let Some(script_name) =
-                utils::slot_value_in_double_colon_del_list(line, "script-name")
-            {
-                let lc_script_name = script_name.to_lowercase();
-                if self.scripts.contains_key(&lc_script_name) {
-                    // Handle duplicate script names (Python version warns and ignores)
-                    continue;
-                }
+    /// Sets what to do with format characters such as ZWNJ and ZWJ, which are dropped by
+    /// default.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{FormatCharPolicy, Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_format_chars(FormatCharPolicy::Keep);
+    /// let result = uroman.romanize_string::<rom_format::Str>("می\u{200c}خواهم", None);
+    /// assert_eq!(result.to_output_string(), "mi\u{200c}khwahm");
+    /// ```
+    pub fn with_format_chars(mut self, policy: FormatCharPolicy) -> Self {
+        self.format_chars = policy;
+        self
+    }
 
-                let direction = utils::slot_value_in_double_colon_del_list(line, "direction")
-                    .map(|s| s.to_string());
-                let abugida_default_vowel_s =
-                    utils::slot_value_in_double_colon_del_list(line, "abugida-default-vowel")
-                        .unwrap_or("");
-                let abugida_default_vowels = if abugida_default_vowel_s.is_empty() {
-                    vec![]
-                } else {
-                    abugida_default_vowel_s
-                        .split([',', ';'])
-                        .map(|s| s.trim().to_string())
-                        .collect()
-                };
-                let alt_script_name_s =
-                    utils::slot_value_in_double_colon_del_list(line, "alt-script-name")
-                        .unwrap_or("");
-                let alt_script_names = if alt_script_name_s.is_empty() {
-                    vec![]
-                } else {
-                    alt_script_name_s
-                        .split([',', ';'])
-                        .map(|s| s.trim().to_string())
-                        .collect()
-                };
-                let language_s =
-                    utils::slot_value_in_double_colon_del_list(line, "language").unwrap_or("");
-                let languages = if language_s.is_empty() {
-                    vec![]
-                } else {
-                    language_s
-                        .split([',', ';'])
-                        .map(|s| s.trim().to_string())
-                        .collect()
-                };
+    /// Sets what to do with combining marks that no rule romanizes in their context, which
+    /// are dropped by default.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{CombiningMarkPolicy, Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_combining_marks(CombiningMarkPolicy::Name);
+    /// let result = uroman.romanize_string::<rom_format::Str>("e\u{301}", None);
+    /// assert_eq!(result.to_output_string(), "e[COMBINING ACUTE ACCENT]");
+    /// ```
+    pub fn with_combining_marks(mut self, policy: CombiningMarkPolicy) -> Self {
+        self.combining_marks = policy;
+        self
+    }
 
-                let abugida_regexes = if !abugida_default_vowels.is_empty() {
-                    let vowels_regex1 = abugida_default_vowels.join("|");
-                    let vowels_regex2 = abugida_default_vowels
-                        .iter()
-                        .map(|v| format!("{v}+"))
-                        .collect::<Vec<_>>()
-                        .join("|");
-
-                    let re1 =
-                        Regex::new(&format!(r"([cfghkmnqrstxy]?y)({vowels_regex2})-?$")).unwrap();
-                    let re2 =
-                        Regex::new(&format!(r"([bcdfghjklmnpqrstvwxyz]+)({vowels_regex1})-?$"))
-                            .unwrap();
-
-                    Some((re1, re2))
-                } else {
-                    None
-                };
+    /// Sets what to do with non-ASCII chars that can't be romanized, which are kept as they
+    /// are by default.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{UnmappableCharPolicy, Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_unmappable_chars(UnmappableCharPolicy::Escape);
+    /// let result = uroman.romanize_string::<rom_format::Str>("Київ ☃", None);
+    /// assert_eq!(result.to_output_string(), "Kiyiv \\u{2603}");
+    /// ```
+    pub fn with_unmappable_chars(mut self, policy: UnmappableCharPolicy) -> Self {
+        self.unmappable_chars = policy;
+        self
+    }
 
-                let new_script = Script {
-                    script_name: script_name.to_string(),
-                    direction,
-                    abugida_default_vowels,
-                    alt_script_names: alt_script_names.clone(),
-                    languages: languages.clone(),
-                    abugida_regexes,
-                };
+    /// Sets what to do with whitespace other than ASCII spaces, tabs and line breaks, which is
+    /// replaced with an ASCII space by default.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, WhitespacePolicy, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_whitespace(WhitespacePolicy::Preserve);
+    /// let result = uroman.romanize_string::<rom_format::Str>("Київ\u{a0}\u{3000}Львів", None);
+    /// assert_eq!(result.to_output_string(), "Kiyiv\u{a0}\u{3000}Lviv");
+    /// ```
+    pub fn with_whitespace(mut self, policy: WhitespacePolicy) -> Self {
+        self.whitespace = policy;
+        self
+    }
+
+    /// Sets whether the homoglyphs of Latin letters are normalized before romanizing, which
+    /// they are not by default.
+    ///
+    /// Spoofed text mixes Cyrillic and Greek letters that look like Latin ones into Latin
+    /// words, which are then romanized one by one, e.g. the Cyrillic `р` of `рaypal` as `r`.
+    /// With the normalization, such letters in words with ASCII letters are taken for the
+    /// Latin letters they look like, and fullwidth forms such as `ｐ` for ASCII. Words
+    /// without ASCII letters are romanized as they are. Edge offsets don't change.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_normalized_homoglyphs(true);
+    /// let result = uroman.romanize_string::<rom_format::Str>("рaypal, рау", None);
+    /// assert_eq!(result.to_output_string(), "paypal, rau");
+    /// ```
+    pub fn with_normalized_homoglyphs(mut self, enabled: bool) -> Self {
+        self.normalize_homoglyphs = enabled;
+        self
+    }
 
-                self.scripts.insert(lc_script_name, new_script.clone());
+    /// Annotates the edges of the edge formats that rules romanize with the conditions on the
+    /// context of their rules, in the `conditions` field of [`Edge::get_data`], e.g. to check
+    /// across a corpus that language-specific rules apply where they should.
+    ///
+    /// The conditions are `lcode: ` and the language codes of the rule, the
+    /// [`RULE_CONDITIONS`] of the rule, and `t-end-of-syllable` if the edge has the
+    /// romanization of the rule for the end of a syllable. Edges of rules without conditions
+    /// have none, and edges that no single rule romanizes, such as numbers, are not annotated.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomFormat, RomanizationResult, Uroman};
+    ///
+    /// let uroman = Uroman::new().with_rule_conditions(true);
+    /// let result = uroman.romanize_with_format("Київ", Some("ukr"), Some(RomFormat::Edges));
+    /// let RomanizationResult::Edges(edges) = result else {
+    ///     unreachable!();
+    /// };
+    /// assert_eq!(edges[0].txt(), "K");
+    /// assert_eq!(edges[0].get_data().conditions, Some(vec![]));
+    /// assert_eq!(edges[1].txt(), "yi");
+    /// assert_eq!(edges[1].get_data().conditions, Some(vec!["lcode: ukr".to_string()]));
+    /// ```
+    pub fn with_rule_conditions(mut self, enabled: bool) -> Self {
+        self.rule_conditions = enabled;
+        self
+    }
 
-                for alt_script_name in alt_script_names {
-                    self.scripts
-                        .insert(alt_script_name.to_lowercase(), new_script.clone());
-                }
-            }
+    /// Makes romanizations take the case of the chars they romanize in bicameral scripts such
+    /// as Greek, Cyrillic and Armenian, char by char, instead of the case of the romanizations
+    /// of the rules. Uppercase words become uppercase, e.g. `ЯБЛОКО` becomes `YABLOKO` rather
+    /// than `YaBLOKO`, and the rules for lowercase chars also romanize their uppercase forms
+    /// where those have no rules of their own, e.g. `ντ` in `ΝΤΟΜΑΤΑ`. A single uppercase
+    /// char next to lowercase ones is capitalized, as in `Яблоко`, and spans of mixed case
+    /// carry the case of each char over to its share of the romanization.
+    ///
+    /// It is off by default, as in the Python uroman.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let uroman = Uroman::new().with_case_carrying(true);
+    /// let result = uroman.romanize_string::<rom_format::Str>("ЖУК Жук жук", None);
+    /// assert_eq!(result.to_output_string(), "ZHUK Zhuk zhuk");
+    /// ```
+    pub fn with_case_carrying(mut self, enabled: bool) -> Self {
+        self.carry_case = enabled;
+        self
+    }
+
+    /// Sets the criteria for choosing among the rules that can romanize the same span, most
+    /// important first, instead of [`RulePriority::DEFAULT`].
+    ///
+    /// Rules that tie on all of them are taken in the order they are loaded, so setting the
+    /// criteria explicitly keeps the output the same when a data file adds rules that tie
+    /// with others in a different way.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RulePriority, Uroman};
+    ///
+    /// let uroman = Uroman::new()
+    ///     .with_rule_priority(&[RulePriority::LanguageMatch, RulePriority::Restrictions]);
+    /// ```
+    pub fn with_rule_priority(mut self, priority: &[RulePriority]) -> Self {
+        self.rule_priority = priority.to_vec();
+        self
+    }
+
+    /// Registers the rule source `s` in `rule_trie` for efficient prefix lookup later.
+    ///
+    /// The rules for `s` must already have an entry in `rom_rules`.
+    fn register_s_prefix(&mut self, s: &str) {
+        if let Some(index) = self.rom_rules.get_index_of(s) {
+            self.rule_trie.insert(s, index);
+        }
+    }
+
+    /// Returns the rules whose source is exactly `chars`.
+    fn rom_rules_for_chars(&self, chars: &[char]) -> Option<&Vec<RomRule>> {
+        let index = self.rule_trie.get(chars)?;
+        self.rom_rules.get_index(index).map(|(_, rules)| rules)
+    }
+
+    fn load_resource_files(&mut self) {
+        // Later rom tables can overwrite the rules of earlier ones, so they are loaded in order
+        // into a `Uroman` of their own. The property tables don't depend on them, so with more
+        // than one core they are loaded on this thread in the meantime.
+        let mut rules = Self {
+            rom_rules: std::mem::take(&mut self.rom_rules),
+            rule_trie: std::mem::take(&mut self.rule_trie),
+            ..Default::default()
+        };
+        if std::thread::available_parallelism().is_ok_and(|n| n.get() > 1) {
+            rules = std::thread::scope(|scope| {
+                let rules = scope.spawn(move || {
+                    rules.load_rom_tables();
+                    rules
+                });
+                self.load_property_tables();
+                rules.join().expect("loading the rom tables panicked")
+            });
+        } else {
+            rules.load_rom_tables();
+            self.load_property_tables();
+        }
+        self.merge_rules(rules);
+    }
+
+    fn load_rom_tables(&mut self) {
+        self.load_rom_file(&embedded_table!("romanization-auto-table.tbl"), "ud");
+        self.load_rom_file(&embedded_table!("UnicodeDataOverwrite.tbl"), "ow");
+        self.load_rom_file(&embedded_table!("romanization-table.tbl"), "man");
+        #[cfg(feature = "cjk")]
+        self.load_chinese_pinyin_file(&embedded_table!("Chinese_to_Pinyin.tbl"));
+        self.add_thai_cancellation_rules();
+        self.rule_matcher = RuleMatcher::new(&self.rom_rules);
+    }
+
+    fn load_property_tables(&mut self) {
+        self.load_script_file(&embedded_table!("Scripts.tbl"));
+        self.load_unicode_data_props(&embedded_table!("UnicodeDataProps.tbl"));
+        #[cfg(feature = "cjk")]
+        self.load_unicode_data_props(&embedded_table!("UnicodeDataPropsCJK.tbl"));
+        #[cfg(feature = "hangul")]
+        self.load_unicode_data_props(&embedded_table!("UnicodeDataPropsHangul.tbl"));
+        self.load_num_props(&embedded_table!("NumProps.tbl"));
+    }
+
+    /// Moves the rules and the properties that come with them out of `rules`, which loaded
+    /// only the rom tables.
+    fn merge_rules(&mut self, rules: Self) {
+        self.rom_rules = rules.rom_rules;
+        self.rule_trie = rules.rule_trie;
+        self.rule_matcher = rules.rule_matcher;
+        self.percentage_markers = rules.percentage_markers;
+        self.fraction_connectors = rules.fraction_connectors;
+        self.plus_signs = rules.plus_signs;
+        self.minus_signs = rules.minus_signs;
+        // The rom tables only set other `StrProp`s than the property tables.
+        self.dict_str.extend(rules.dict_str);
+        for (prop, keys) in rules.dict_bool {
+            self.dict_bool.entry(prop).or_default().extend(keys);
         }
     }
 
-    fn load_rom_file(&mut self, file: &'static str, provenance: &str, file_format: &str) {
-        for line in file.lines() {
+    /// Loads numerical properties from a JSONL file (e.g., NumProps.jsonl).
+    fn load_num_props(&mut self, file_content: &str) {
+        for line in file_content.lines() {
             if line.starts_with('#') || line.trim().is_empty() {
                 continue;
             }
 
-            if file_format == "u2r" {
-                let u_str = match slot_value_in_double_colon_del_list(line, "u") {
-                    Some(s) => s,
-                    None => continue,
-                };
-
-                let s = match u32::from_str_radix(u_str, 16)
-                    .ok()
-                    .and_then(std::char::from_u32)
-                {
-                    Some(c) => c,
-                    None => continue,
-                };
-
-                if let Some(tone_mark) = slot_value_in_double_colon_del_list(line, "tone-mark") {
-                    self.dict_str.insert(
-                        ("tone-mark".to_string(), s.to_string()),
-                        tone_mark.to_string(),
-                    );
+            let record: NumPropRecord = match serde_json::from_str(line) {
+                Ok(record) => record,
+                Err(e) => {
+                    diagnostic!("Warning: skipping malformed numeric properties {line:?}: {e}");
+                    continue;
                 }
+            };
+            if record.is_large_power {
+                self.dict_bool_insert(BoolProp::LargePower, record.txt.clone());
+            }
 
-                if let Some(syllable_info) =
-                    slot_value_in_double_colon_del_list(line, "syllable-info")
-                {
-                    self.dict_str.insert(
-                        ("syllable-info".to_string(), s.to_string()),
-                        syllable_info.to_string(),
+            let mut chars = record.txt.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                continue;
+            };
+            let fraction = record.fraction.and_then(|(num, den)| {
+                let fraction = edge::checked_ratio(num, den);
+                if fraction.is_none() {
+                    diagnostic!(
+                        "Warning: ignoring the invalid fraction {num}/{den} of {:?}",
+                        record.txt
                     );
                 }
+                fraction
+            });
+            let prop = NumProp {
+                rom: record.rom,
+                value: record.value,
+                fraction,
+                r#type: intern(&record.r#type),
+                num_base: record.base,
+                base_multiplier: record.mult,
+                script: record.script.as_deref().map(intern),
+                is_large_power: record.is_large_power,
+            };
+            self.num_props.insert(c, prop);
+        }
+    }
 
-                if let Some(syllable_info) = slot_value_in_double_colon_del_list(line, "pic") {
-                    self.dict_str.insert(
-                        ("pic".to_string(), s.to_string()),
-                        syllable_info.to_string(),
-                    );
-                }
+    /// Loads Unicode data properties from a compiled table (e.g., UnicodeDataProps.txt).
+    fn load_unicode_data_props(&mut self, table: &str) {
+        for rec in table.lines().filter_map(PropsRecord::parse) {
+            let script_name = intern(rec.script_name);
+            for c in rec.chars.unwrap_or("").chars() {
+                self.dict_str
+                    .insert((StrProp::Script, c), script_name.clone());
+            }
+            for c in rec.vowel_signs.unwrap_or("").chars() {
+                self.dict_bool_insert(BoolProp::VowelSign, c.to_string());
+            }
+            for c in rec.medial_consonant_signs.unwrap_or("").chars() {
+                self.dict_bool_insert(BoolProp::MedialConsonantSign, c.to_string());
+            }
+            for c in rec.viramas.unwrap_or("").chars() {
+                self.dict_bool_insert(BoolProp::Virama, c.to_string());
+            }
+        }
+    }
 
-                if let Some(syllable_info) = slot_value_in_double_colon_del_list(line, "name") {
-                    self.dict_str.insert(
-                        ("name".to_string(), s.to_string()),
-                        syllable_info.to_string(),
-                    );
+    /// Loads a script definition file from its compiled table (e.g., Scripts.txt).
+    fn load_script_file(&mut self, table: &str) {
+        let split_list = |s: Option<&str>| -> Vec<String> {
+            match s {
+                Some(s) if !s.is_empty() => {
+                    s.split([',', ';']).map(|s| s.trim().to_string()).collect()
                 }
+                _ => vec![],
+            }
+        };
+
+        for rec in table.lines().filter_map(ScriptRecord::parse) {
+            let script_name = rec.script_name;
+            let lc_script_name = script_name.to_lowercase();
+            if self.scripts.contains_key(&lc_script_name) {
+                // Handle duplicate script names (Python version warns and ignores)
+                continue;
+            }
+
+            let direction = rec.direction.map(|s| s.to_string());
+            let abugida_default_vowels = split_list(rec.abugida_default_vowels);
+            let alt_script_names = split_list(rec.alt_script_names);
+            let languages = split_list(rec.languages);
 
-                if let Some(rule) = RomRule::from_line(line, provenance, file_format, self) {
-                    self.add_rom_rule(rule);
+            let new_script = Script {
+                script_name: script_name.to_string(),
+                direction,
+                abugida_default_vowels,
+                alt_script_names: alt_script_names.clone(),
+                languages: languages.clone(),
+            };
+
+            self.scripts.insert(lc_script_name, new_script.clone());
+
+            for alt_script_name in alt_script_names {
+                self.scripts
+                    .insert(alt_script_name.to_lowercase(), new_script.clone());
+            }
+        }
+    }
+
+    fn load_rom_file(&mut self, table: &str, provenance: &str) {
+        for rec in table.lines().filter_map(RomRecord::parse) {
+            if let Some(c) = rec.s.chars().next() {
+                for (prop, value) in [
+                    (StrProp::ToneMark, rec.tone_mark),
+                    (StrProp::SyllableInfo, rec.syllable_info),
+                    (StrProp::Pic, rec.pic),
+                    (StrProp::Name, rec.name),
+                ] {
+                    if let Some(value) = value {
+                        let value = if prop == StrProp::Name {
+                            Arc::from(value)
+                        } else {
+                            intern(value)
+                        };
+                        self.dict_str.insert((prop, c), value);
+                    }
                 }
-            } else if let Some(rule) = RomRule::from_line(line, provenance, file_format, self) {
-                self.add_rom_rule(rule);
             }
+
+            let rule = RomRule::from_record(&rec, provenance, self);
+            self.add_rom_rule(rule);
         }
     }
 
@@ -555,20 +1211,23 @@ impl Uroman {
         }
 
         if rule.is_large_power {
-            self.dict_bool
-                .insert(("is-large-power".to_string(), rule.s.clone()), true);
+            self.dict_bool_insert(BoolProp::LargePower, rule.s.clone());
         }
 
-        self.register_s_prefix(&rule.s);
-
-        let old_rules = self.rom_rules.entry(rule.s.clone()).or_default();
+        let s = rule.s.clone();
+        let old_rules = self.rom_rules.entry(s.clone()).or_default();
 
         // Python: `and not (lcodes or ...)`
         let is_unconditional = rule.is_unconditional();
 
+        // Overrides replace the rules for the same conditions, whichever table they came from.
+        if &*rule.prov == OVERRIDE_PROV {
+            old_rules.retain(|old| &*old.prov == OVERRIDE_PROV || !old.has_same_conditions(&rule));
+        }
+
         let should_overwrite = old_rules.len() == 1 && {
             let old_rule = &old_rules[0];
-            (old_rule.prov == "ud" || old_rule.prov == "ow") && is_unconditional
+            (&*old_rule.prov == "ud" || &*old_rule.prov == "ow") && is_unconditional
         };
 
         // println!(
@@ -581,29 +1240,18 @@ impl Uroman {
         } else {
             old_rules.push(rule);
         }
-    }
-
-    /// Loads and processes the Chinese to Pinyin mapping file.
-    fn load_chinese_pinyin_file(&mut self, file: &'static str) {
-        for line in file.lines() {
-            if line.starts_with('#') || line.trim().is_empty() {
-                continue;
-            }
 
-            if let Some((chinese, pinyin_with_accent)) = line.split_once(char::is_whitespace) {
-                // `de_accent_pinyin` logic: NFD decomposition to separate base chars and accents.
-                let rom: String = pinyin_with_accent
-                    .nfd()
-                    .filter(|c| {
-                        !matches!(
-                            c.general_category_group(),
-                            unicode_properties::GeneralCategoryGroup::Mark
-                        )
-                    })
-                    .collect::<String>()
-                    .replace('ü', "u");
+        self.register_s_prefix(&s);
+    }
 
-                let rule = RomRule::new_simple(chinese.to_string(), &rom, "rom pinyin");
+    /// Loads the Chinese to Pinyin mapping from its compiled table.
+    ///
+    /// The pinyin is already de-accented by `build.rs`.
+    #[cfg(feature = "cjk")]
+    fn load_chinese_pinyin_file(&mut self, table: &str) {
+        for line in table.lines() {
+            if let Some((chinese, rom)) = line.split_once(data_table::FIELD_SEP) {
+                let rule = RomRule::new_simple(chinese.to_string(), rom, PINYIN_PROV);
                 self.rom_rules
                     .entry(chinese.to_string())
                     .or_default()
@@ -614,20 +1262,28 @@ impl Uroman {
     }
 
     /// A helper to get a string value from `dict_str`, returning `""` if not found.
-    fn dict_str_get(&self, k1: &str, k2_char: char) -> &str {
+    fn dict_str_get(&self, prop: StrProp, c: char) -> &str {
         self.dict_str
-            .get(&(k1.to_string(), k2_char.to_string()))
-            .map(|s| s.as_str()) // Option<&String> -> Option<&str>
+            .get(&(prop, c))
+            .map(|s| &**s) // Option<&Arc<str>> -> Option<&str>
             .unwrap_or("") // None -> ""
     }
 
     /// A helper to get a boolean value from `dict_bool`, returning `false` if not found.
     /// This mimics the behavior of Python's `defaultdict(bool)`.
-    fn dict_bool_get(&self, k1: &str, k2: &str) -> bool {
+    fn dict_bool_get(&self, prop: BoolProp, k2: &str) -> bool {
         self.dict_bool
-            .get(&(k1.to_string(), k2.to_string()))
-            .copied()
-            .unwrap_or(false)
+            .get(&prop)
+            .is_some_and(|set| set.contains(k2))
+    }
+
+    /// Same as `dict_bool_get`, for single-character keys, without allocating.
+    fn dict_bool_get_char(&self, prop: BoolProp, c: char) -> bool {
+        self.dict_bool_get(prop, c.encode_utf8(&mut [0; 4]))
+    }
+
+    fn dict_bool_insert(&mut self, prop: BoolProp, k2: String) {
+        self.dict_bool.entry(prop).or_default().insert(k2);
     }
 
     fn second_rom_filter(&self, c: &str, rom: Option<&str>) -> Option<String> {
@@ -694,8 +1350,8 @@ impl Uroman {
 
     fn chr_name(&self, c: char) -> String {
         // Check for an overridden name in dict_str.
-        if let Some(name) = self.dict_str.get(&("name".to_string(), c.to_string())) {
-            return name.clone();
+        if let Some(name) = self.dict_str.get(&(StrProp::Name, c)) {
+            return name.to_string();
         }
         unicode_names2::name(c)
             .map(|n| n.to_string())
@@ -706,37 +1362,12 @@ impl Uroman {
     ///
     /// This is a special algorithmic romanization that decomposes a Hangul syllable
     /// into its constituent Jamo (lead, vowel, tail) and maps them to roman characters.
-    /// The results are cached for performance.
-    fn unicode_hangul_romanization(&self, c: char) -> Option<String> {
-        if let Some(cached_rom) = self.hangul_rom.borrow().get(&c) {
-            return Some(cached_rom.clone());
-        }
-
-        let cp = c as u32;
-
-        // Check if the codepoint is within the Hangul Syllables range (AC00–D7A3).
-        if (0xAC00..=0xD7A3).contains(&cp) {
-            let code = cp - 0xAC00;
-
-            // Calculate the indices for the lead, vowel, and tail (Jamo).
-            let lead_index = (code / (28 * 21)) as usize;
-            let vowel_index = ((code / 28) % 21) as usize;
-            let tail_index = (code % 28) as usize;
-
-            let rom = format!(
-                "{}{}{}",
-                HANGUL_LEADS[lead_index], HANGUL_VOWELS[vowel_index], HANGUL_TAILS[tail_index]
-            );
-
-            // Remove the placeholder hyphen '-'.
-            let rom = rom.replace('-', "");
-
-            self.hangul_rom.borrow_mut().insert(c, rom.clone());
-
-            Some(rom)
-        } else {
-            None
-        }
+    /// All syllables are romanized once, on first use.
+    #[cfg(feature = "hangul")]
+    fn unicode_hangul_romanization(c: char) -> Option<&'static str> {
+        // The syllables are ordered by lead, then vowel, then tail, like the table.
+        let index = (c as u32).checked_sub(0xAC00)? as usize;
+        HANGUL_SYLLABLES.get(index).map(String::as_str)
     }
 
     // fn unicode_hangul_romanization_str(&mut self, s: &str, pass_through_p: bool) -> String {
@@ -755,11 +1386,8 @@ impl Uroman {
     ///
     /// This is derived from `UnicodeDataProps*.txt` and stored in `dict_str`.
     /// Returns an empty string if not found.
-    fn chr_script_name(&self, c: char) -> String {
-        self.dict_str
-            .get(&("script".to_string(), c.to_string()))
-            .cloned()
-            .unwrap_or_default()
+    fn chr_script_name(&self, c: char) -> &str {
+        self.dict_str_get(StrProp::Script, c)
     }
 
     /// Adds automatic cancellation rules for the Thai script.
@@ -825,44 +1453,355 @@ impl Uroman {
         s: &str,
         lcode: Option<&str>,
     ) -> RomanizationOutput<F> {
-        let mut lat = Lattice::new(s, self, lcode);
+        self.romanize_segments(&[(s, lcode)], self.policies())
+    }
 
-        lat.pick_tibetan_vowel_edge();
-        lat.prep_braille();
-        lat.add_romanization();
-        lat.add_numbers();
-        lat.add_braille_numbers();
-        lat.add_rom_fall_back_singles();
+    /// Romanizes each of `tokens`, a pre-tokenized text, into one string, for taggers and
+    /// parsers whose tokenization is fixed.
+    ///
+    /// Returns exactly one string per token, in the order of `tokens`. Each token is romanized
+    /// on its own as a whole word, so tokens are never merged or split, even where a rule would
+    /// match across their boundary, and an empty token stays empty.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// // The rule for "иї" doesn't match across the tokens.
+    /// assert_eq!(uroman.romanize_tokens(&["Ки", "їв", ""], Some("ukr")), ["Ky", "yiv", ""]);
+    /// assert_eq!(uroman.romanize_tokens(&["Київ"], Some("ukr")), ["Kyiv"]);
+    /// ```
+    pub fn romanize_tokens(&self, tokens: &[&str], lcode: Option<&str>) -> Vec<String> {
+        tokens
+            .iter()
+            .map(|token| {
+                self.romanize_string::<rom_format::Str>(token, lcode)
+                    .to_output_string()
+            })
+            .collect()
+    }
 
-        let type_id = TypeId::of::<F>();
+    /// Romanizes the concatenation of `segments`, each with its own language code.
+    ///
+    /// Rules never match across segments, and the edge offsets are char offsets in the
+    /// concatenation.
+    pub(crate) fn romanize_segments<F: RomFormatType + 'static>(
+        &self,
+        segments: &[(&str, Option<&str>)],
+        policies: Policies,
+    ) -> RomanizationOutput<F> {
+        self.romanize_segments_until(segments, policies, None)
+            .expect("only a stop condition interrupts romanization")
+    }
 
-        let result = if type_id == TypeId::of::<rom_format::Str>() {
-            let best_edges = lat.best_rom_edge_path(0, s.chars().count(), false);
-            RomanizationResult::Str(
-                best_edges.iter().map(|edge| edge.txt()).collect::<String>(),
-            )
-        } else if type_id == TypeId::of::<rom_format::Edges>() {
-            RomanizationResult::Edges(
-                lat.best_rom_edge_path(0, s.chars().count(), false)
-            )
-        } else if type_id == TypeId::of::<rom_format::Alts>() {
-            let mut best_edges = lat.best_rom_edge_path(0, s.chars().count(), false);
-            lat.add_alternatives(&mut best_edges);
-
-            RomanizationResult::Edges(best_edges)
-        } else if type_id == TypeId::of::<rom_format::Lattice>() {
-            let mut all_edges = lat.all_edges(0, s.chars().count());
-            lat.add_alternatives(&mut all_edges);
-
-            RomanizationResult::Edges(all_edges)
-        } else {
-            unreachable!("Unknown RomFormatType provided");
+    /// Splits `s` into the windows that a lattice is built for one by one: its sentences if
+    /// they are split, see [`Uroman::with_sentence_chunks`], cut further into windows of about
+    /// [`LONG_INPUT_WINDOW_CHARS`] chars.
+    fn input_windows<'s>(&self, s: &'s str) -> impl Iterator<Item = &'s str> {
+        Sentences::new(s, self.sentence_chunk_chars)
+            .flat_map(|sentence| InputWindows::new(sentence, LONG_INPUT_WINDOW_CHARS))
+    }
+
+    /// Romanizes like [`Uroman::romanize_segments`], but fails once `stop` is due.
+    pub(crate) fn romanize_segments_until<F: RomFormatType + 'static>(
+        &self,
+        segments: &[(&str, Option<&str>)],
+        policies: Policies,
+        stop: Option<&Interrupt>,
+    ) -> Result<RomanizationOutput<F>, RomanizationError> {
+        let type_id = TypeId::of::<F>();
+        let interrupt = Interrupt {
+            deadline: self
+                .time_limit
+                .map(|limit| Instant::now() + limit)
+                .into_iter()
+                .chain(stop.and_then(|stop| stop.deadline))
+                .min(),
+            token: stop.and_then(|stop| stop.token.clone()),
+        };
+        let check_stop = || match stop {
+            Some(stop) => stop.check(),
+            None => Ok(()),
         };
+        // Protected strings pass through as they are, so they are split off first, and the
+        // homoglyphs of the parts between them are normalized.
+        let parts: Vec<(Cow<str>, Option<&str>, bool)> = segments
+            .iter()
+            .flat_map(|&(s, lcode)| {
+                self.protected
+                    .split(s)
+                    .into_iter()
+                    .map(move |(part, is_protected)| (part, lcode, is_protected))
+            })
+            .map(|(part, lcode, is_protected)| {
+                let part = if self.normalize_homoglyphs && !is_protected {
+                    homoglyphs::normalize_homoglyphs(part)
+                } else {
+                    Cow::Borrowed(part)
+                };
+                (part, lcode, is_protected)
+            })
+            .collect();
+
+        if type_id == TypeId::of::<rom_format::Str>() {
+            // Appends the romanization of `window` to `str`, and returns whether it was
+            // romanized greedily.
+            let romanize_window = |window: &str,
+                                   lcode: Option<&str>,
+                                   str: &mut String|
+             -> Result<bool, RomanizationError> {
+                check_stop()?;
+                let mut lat = self.build_lattice(window, lcode, policies, &interrupt);
+                check_stop()?;
+                let best_edges = self.timed(Stage::BestPath, || {
+                    if lat.exceeded_edge_cap {
+                        lat.greedy_rom_edge_path()
+                    } else {
+                        let mut path = lat.best_rom_edge_path(0, lat.max_vertex, false);
+                        lat.prefer_frequent_words(&mut path);
+                        path
+                    }
+                });
+                #[cfg(feature = "tracing")]
+                if let Err(e) = Edge::validate_path(&best_edges, lat.max_vertex) {
+                    tracing::warn!("{e}, romanizing {window:?}");
+                }
+                str.extend(best_edges.iter().map(|edge| edge.txt()));
+                Ok(lat.exceeded_edge_cap)
+            };
+
+            let mut str = String::with_capacity(parts.iter().map(|(s, ..)| s.len()).sum());
+            let mut greedy_fallback = false;
+            for (s, lcode, is_protected) in &parts {
+                let (s, lcode) = (s.as_ref(), *lcode);
+                if *is_protected {
+                    str.push_str(s);
+                    continue;
+                }
+                // ASCII romanizes to itself, so only the part with non-ASCII text goes through the lattice.
+                let (head, rest, tail) = split_ascii_margins(s);
+                str.push_str(head);
+                if !rest.is_empty() {
+                    let windows = self.input_windows(rest);
+                    #[cfg(feature = "parallel")]
+                    if self.sentence_chunk_chars.is_some() {
+                        use rayon::prelude::*;
+
+                        let windows: Vec<&str> = windows.collect();
+                        let romanized = windows
+                            .par_iter()
+                            .map(|window| {
+                                let mut window_str = String::new();
+                                let greedy = romanize_window(window, lcode, &mut window_str)?;
+                                Ok((window_str, greedy))
+                            })
+                            .collect::<Result<Vec<_>, RomanizationError>>()?;
+                        for (window_str, greedy) in romanized {
+                            str.push_str(&window_str);
+                            greedy_fallback |= greedy;
+                        }
+                        str.push_str(tail);
+                        continue;
+                    }
+                    for window in windows {
+                        greedy_fallback |= romanize_window(window, lcode, &mut str)?;
+                    }
+                }
+                str.push_str(tail);
+            }
+            return Ok(RomanizationOutput {
+                result: RomanizationResult::Str(str),
+                greedy_fallback,
+                _marker: PhantomData,
+            });
+        }
+
+        // `add_alternatives` appends the alternatives after the edges, so they are collected
+        // separately to keep that order across windows.
+        let mut edges = Vec::new();
+        let mut alternatives = Vec::new();
+        let mut offset = 0;
+        let mut greedy_fallback = false;
+        for (s, lcode, is_protected) in &parts {
+            let (s, lcode) = (s.as_ref(), *lcode);
+            if *is_protected {
+                let end = offset + s.chars().count();
+                edges.push(Edge::new_regular(offset, end, s.to_string(), "protected"));
+                offset = end;
+                continue;
+            }
+            for window in self.input_windows(s) {
+                check_stop()?;
+                let mut lat = self.build_lattice(window, lcode, policies, &interrupt);
+                check_stop()?;
+                greedy_fallback |= lat.exceeded_edge_cap;
+
+                let mut window_edges = if lat.exceeded_edge_cap {
+                    self.timed(Stage::BestPath, || lat.greedy_rom_edge_path())
+                } else if type_id == TypeId::of::<rom_format::Lattice>() {
+                    lat.all_edges(0, lat.max_vertex)
+                } else if type_id == TypeId::of::<rom_format::Edges>()
+                    || type_id == TypeId::of::<rom_format::Alts>()
+                {
+                    self.timed(Stage::BestPath, || {
+                        let mut path = lat.best_rom_edge_path(0, lat.max_vertex, false);
+                        lat.prefer_frequent_words(&mut path);
+                        path
+                    })
+                } else {
+                    unreachable!("Unknown RomFormatType provided");
+                };
+                let n_edges = window_edges.len();
+                // A lattice lists every edge, so only a best path is checked to cover the text.
+                #[cfg(feature = "tracing")]
+                if (type_id != TypeId::of::<rom_format::Lattice>() || lat.exceeded_edge_cap)
+                    && let Err(e) = Edge::validate_path(&window_edges, lat.max_vertex)
+                {
+                    tracing::warn!("{e}, romanizing {window:?}");
+                }
+                if type_id != TypeId::of::<rom_format::Edges>() && !lat.exceeded_edge_cap {
+                    lat.add_alternatives(&mut window_edges);
+                }
+
+                // Edge offsets are relative to the window.
+                if offset > 0 {
+                    for edge in &mut window_edges {
+                        let data = edge.get_data_mut();
+                        data.start += offset;
+                        data.end += offset;
+                    }
+                }
+                offset += lat.max_vertex;
+                // The lattice borrows from `window`, so only the output edges are copied.
+                let n_alternatives = self
+                    .max_alternatives
+                    .map_or(usize::MAX, |max| max.saturating_sub(alternatives.len()));
+                alternatives.extend(
+                    window_edges
+                        .drain(n_edges..)
+                        .take(n_alternatives)
+                        .map(Edge::into_owned),
+                );
+                edges.extend(window_edges.into_iter().map(Edge::into_owned));
+            }
+        }
+        edges.append(&mut alternatives);
 
-        RomanizationOutput {
-            result,
+        Ok(RomanizationOutput {
+            result: RomanizationResult::Edges(edges),
+            greedy_fallback,
             _marker: PhantomData,
+        })
+    }
+
+    /// Builds the fully populated lattice for `s`, ready for path extraction, unless
+    /// `interrupt` becomes due.
+    fn build_lattice<'a>(
+        &'a self,
+        s: &'a str,
+        lcode: Option<&str>,
+        policies: Policies<'a>,
+        interrupt: &Interrupt,
+    ) -> Lattice<'a> {
+        let subsystems = policies.subsystems;
+        let mut lat = Lattice::new(s, self, lcode);
+        lat.subsystems = subsystems;
+        lat.interrupt = interrupt.clone();
+        self.count_timed_call();
+
+        self.timed(Stage::RuleMatching, || lat.pick_tibetan_vowel_edge());
+        #[cfg(feature = "braille")]
+        if subsystems.braille {
+            self.timed(Stage::Braille, || lat.prep_braille());
+        }
+        self.timed(Stage::RuleMatching, || lat.add_romanization());
+        if subsystems.numbers {
+            self.timed(Stage::Numbers, || lat.add_numbers());
+            #[cfg(feature = "braille")]
+            if subsystems.braille {
+                self.timed(Stage::Braille, || lat.add_braille_numbers());
+            }
         }
+        self.timed(Stage::RuleMatching, || lat.add_rom_fall_back_singles());
+        if policies.format_chars != FormatCharPolicy::Drop {
+            lat.replace_format_chars(policies.format_chars);
+        }
+        if policies.combining_marks != CombiningMarkPolicy::Drop {
+            lat.replace_combining_marks(policies.combining_marks);
+        }
+        if *policies.unmappable_chars != UnmappableCharPolicy::Keep {
+            lat.replace_unmappable_chars(policies.unmappable_chars);
+        }
+        if policies.whitespace == WhitespacePolicy::Preserve {
+            lat.preserve_whitespace();
+        }
+
+        lat
+    }
+
+    /// Builds the romanization lattice for `s` and returns an explorer over its edges.
+    ///
+    /// This is intended for inspecting how competing edges affect the best path,
+    /// e.g. when authoring new rules.
+    ///
+    /// # Example
+    /// ```
+    /// # use uroman::Uroman;
+    /// # let uroman = Uroman::new();
+    /// let mut explorer = uroman.explore_lattice("ᚺᚨᛚᛚᛟ", None);
+    ///
+    /// for span in explorer.spans() {
+    ///     println!("[{}, {}) {} edge(s)", span.start, span.end, span.edges.len());
+    /// }
+    ///
+    /// println!("{}", explorer.best_path_string());
+    /// ```
+    pub fn explore_lattice<'a>(&'a self, s: &'a str, lcode: Option<&str>) -> LatticeExplorer<'a> {
+        LatticeExplorer::new(self.build_lattice(s, lcode, self.policies(), &Interrupt::default()))
+    }
+
+    /// Romanizes `text` as a starting point for romanizing it incrementally while it is edited.
+    ///
+    /// # Example
+    /// ```
+    /// # use uroman::Uroman;
+    /// # let uroman = Uroman::new();
+    /// let mut romanization = uroman.incremental("Привет мир", None);
+    /// assert_eq!(romanization.romanized(), "Privet mir");
+    ///
+    /// // Only "мир" and its replacement are romanized again.
+    /// romanization.apply_edit(7..10, "друг");
+    /// assert_eq!(romanization.romanized(), "Privet drug");
+    /// ```
+    pub fn incremental<'a>(&'a self, text: &str, lcode: Option<&str>) -> IncrementalRomanization<'a> {
+        IncrementalRomanization::new(self, text, lcode)
+    }
+
+    /// Wraps `inner` in a writer that romanizes the text written into it with `options`, line
+    /// by line, and writes the romanization into `inner`, see [`RomanizingWriter`].
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// use uroman::{RomanizeOptions, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let options = RomanizeOptions::new().with_lcode("ukr");
+    /// let mut writer = uroman.romanizing_writer(Vec::new(), options);
+    /// write!(writer, "Привіт, ").unwrap();
+    /// writeln!(writer, "Київ!").unwrap();
+    /// write!(writer, "Львів").unwrap();
+    /// let output = writer.finish().unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), "Pryvit, Kyiv!\nLviv\n");
+    /// ```
+    pub fn romanizing_writer<W: Write>(
+        &self,
+        inner: W,
+        options: RomanizeOptions,
+    ) -> RomanizingWriter<'_, W> {
+        RomanizingWriter::new(self, inner, options)
     }
 
     /// Decodes Unicode escape sequences before performing romanization.
@@ -888,7 +1827,7 @@ impl Uroman {
         s: &str,
         lcode: Option<&str>,
     ) -> RomanizationOutput<F> {
-        let s = decode_unicode_escapes(s);
+        let s = decode_unicode_escapes(s, InvalidEscapePolicy::default());
         self.romanize_string::<F>(s.as_str(), lcode)
     }
 
@@ -920,7 +1859,7 @@ impl Uroman {
         lcode: Option<&str>,
         rom_format: Option<RomFormat>,
     ) -> RomanizationResult {
-        let s = decode_unicode_escapes(s);
+        let s = decode_unicode_escapes(s, InvalidEscapePolicy::default());
         self.romanize_with_format(&s, lcode, rom_format)
     }
 
@@ -966,6 +1905,33 @@ impl Uroman {
         }
     }
 
+    /// Romanizes `segments` like [`Uroman::romanize_segments`], in `rom_format`.
+    pub(crate) fn romanize_segments_with_format(
+        &self,
+        segments: &[(&str, Option<&str>)],
+        rom_format: RomFormat,
+        policies: Policies,
+    ) -> RomanizationResult {
+        match rom_format {
+            RomFormat::Str => RomanizationResult::Str(
+                self.romanize_segments::<rom_format::Str>(segments, policies)
+                    .to_output_string(),
+            ),
+            RomFormat::Edges => {
+                self.romanize_segments::<rom_format::Edges>(segments, policies)
+                    .result
+            }
+            RomFormat::Alts => {
+                self.romanize_segments::<rom_format::Alts>(segments, policies)
+                    .result
+            }
+            RomFormat::Lattice => {
+                self.romanize_segments::<rom_format::Lattice>(segments, policies)
+                    .result
+            }
+        }
+    }
+
     /// Romanizes a stream of text line by line and writes the output to another stream.
     ///
     /// This method efficiently processes large amounts of text by reading from a buffered
@@ -982,107 +1948,277 @@ impl Uroman {
     /// reading from the `reader` or writing to the `writer`.
     pub fn romanize_file<R: BufRead, W: Write>(
         &self,
-        mut reader: R,
-        mut writer: W,
+        reader: R,
+        writer: W,
         lcode: Option<&str>,
         rom_format: RomFormat,
         max_lines: Option<usize>,
         decode_unicode: bool,
         silent: bool,
     ) -> Result<(), RomanizationError> {
-        let mut line_number = 0;
-        let mut non_utf8_chars_total = 0;
-        let mut n_error_messages_output = 0;
-        let max_n_error_messages = 10;
-
-        let mut buffer = vec![];
-        let default_lcode = lcode;
-        let lcode_directive = "::lcode ";
-
-        while reader.read_until(b'\n', &mut buffer)? > 0 {
-            line_number += 1;
-
-            let original_len = buffer.len();
-            let line_str = String::from_utf8_lossy(&buffer);
-            let replaced_len = line_str.len();
-            if replaced_len < original_len {
-                non_utf8_chars_total += 1;
-                if n_error_messages_output < max_n_error_messages {
-                    eprintln!(
-                        "Detected encoding error on line {line_number}: non-UTF-8 characters were replaced."
-                    );
-                    n_error_messages_output += 1;
-                } else if n_error_messages_output == max_n_error_messages {
-                    eprintln!("Too many encoding errors. No further errors reported.");
-                    n_error_messages_output += 1;
-                }
-            }
-            let mut line_trimmed = &*line_str;
+        let options =
+            RomanizeOptions::for_file(lcode, rom_format, max_lines, decode_unicode, silent);
+        let lines = LossyLines::decoded(reader, options.input_encoding);
+        self.romanize_file_inner(lines, writer, &options, None, None)?;
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(lcode = ?options.output.lcode, rom_format = ?options.output.rom_format)
+        )
+    )]
+    pub(crate) fn romanize_file_inner<R: BufRead, W: Write>(
+        &self,
+        lines: LossyLines<R>,
+        mut writer: W,
+        options: &RomanizeOptions,
+        mut cache: Option<&mut RomanizationCache>,
+        mut progress: Option<&mut dyn FnMut(Progress)>,
+    ) -> Result<FileSummary, RomanizationError> {
+        let mut lines = lines
+            .with_invalid_utf8(options.invalid_utf8)
+            .with_max_chunk_bytes(
+                // Structured lines are romanized field by field, and escapes are decoded in
+                // whole lines, which chunks would cut.
+                options.max_chunk_bytes.filter(|_| {
+                    options.output.input_format == InputFormat::Text
+                        && !options.output.decodes_escapes()
+                }),
+            );
+        if options.keep_bom && lines.starts_with_bom()? {
+            writer.write_all("\u{FEFF}".as_bytes())?;
+        }
 
-            if line_trimmed.ends_with('\n') {
-                line_trimmed = &line_trimmed[..line_trimmed.len() - 1];
+        let mut changed_lines = 0;
+        let mut per_script_counts: HashMap<&str, usize> = HashMap::default();
+        let mut chunks = LineChunks::default();
+        let mut line_changed = false;
+        while lines.next_line()?.is_some() {
+            let line_trimmed = lines.current_line();
+            let line_end = options.line_ending.terminator(lines.current_line_ending());
+            let chunk_position = (lines.chunk_starts_line(), lines.chunk_ends_line());
+            let is_whole_line = chunk_position == (true, true);
+            self.count_scripts(line_trimmed, &mut per_script_counts);
+            let changed = match cache.as_deref_mut() {
+                _ if !is_whole_line => self.write_file_chunk(
+                    &mut writer,
+                    line_trimmed,
+                    chunk_position,
+                    line_end,
+                    &mut chunks,
+                    options,
+                ),
+                Some(cache) => cache
+                    .try_get_or_insert_with(line_trimmed, options, || {
+                        self.romanize_file_line(line_trimmed, options)
+                    })
+                    .and_then(|output| {
+                        write!(writer, "{output}{line_end}")?;
+                        Ok(output != line_trimmed)
+                    }),
+                None => self.write_file_line(&mut writer, line_trimmed, line_end, options),
             }
-            if line_trimmed.ends_with('\r') {
-                line_trimmed = &line_trimmed[..line_trimmed.len() - 1];
+            .map_err(|e| lines.error_at_current_line(e))?;
+            line_changed |= changed;
+            if !lines.chunk_ends_line() {
+                continue;
             }
-
-            if let Some(rest_of_line) = line_trimmed.strip_prefix(lcode_directive) {
-                let parts: Vec<&str> = rest_of_line.splitn(2, char::is_whitespace).collect();
-                let (lcode, text_to_romanize) =
-                    (parts.first().cloned(), parts.get(1).cloned().unwrap_or(""));
-
-                let result = if decode_unicode {
-                    self.romanize_escaped_with_format(text_to_romanize, lcode, Some(rom_format))
-                } else {
-                    self.romanize_with_format(text_to_romanize, lcode, Some(rom_format))
-                };
-
-                match rom_format {
-                    RomFormat::Str => {
-                        let prefix = format!("{}{}{} ", lcode_directive, lcode.unwrap_or(""), "");
-                        let output = prefix + &result.to_output_string().unwrap();
-                        writeln!(writer, "{output}")?;
-                    }
-                    _ => {
-                        let meta_edge = format!(r#"[0,0,"","lcode: {}"]"#, lcode.unwrap_or(""));
-                        let result_json = result.to_output_string().unwrap();
-                        if let Some(stripped) = result_json.strip_prefix('[') {
-                            writeln!(writer, "[{meta_edge},{stripped}")?;
-                        } else {
-                            writeln!(writer, "{result_json}")?;
-                        }
-                    }
-                }
-            } else {
-                let result = if decode_unicode {
-                    self.romanize_escaped_with_format(line_trimmed, default_lcode, Some(rom_format))
-                } else {
-                    self.romanize_with_format(line_trimmed, default_lcode, Some(rom_format))
-                };
-                let output = result
-                    .to_output_string()
-                    .expect("JSON serialization failed");
-                writeln!(writer, "{output}")?;
+            changed_lines += usize::from(line_changed);
+            line_changed = false;
+
+            if let Some(progress) = progress.as_deref_mut() {
+                progress(Progress {
+                    lines: lines.line_number(),
+                    bytes: lines.bytes_read(),
+                    error: lines.last_line_non_utf8().then_some(LineError::NonUtf8),
+                });
             }
 
-            if let Some(max) = max_lines
-                && line_number >= max
+            if let Some(max) = options.max_lines
+                && lines.line_number() >= max
             {
                 break;
             }
-            buffer.clear();
         }
 
-        if !silent && line_number > 0 {
-            eprintln!();
+        lines.finish(options.silent);
+
+        writer.flush()?;
+        Ok(lines.summary(changed_lines, per_script_counts))
+    }
+
+    /// Adds the chars of `line` to the counts of their scripts, see
+    /// [`FileSummary::per_script_counts`].
+    pub(crate) fn count_scripts<'a>(&'a self, line: &str, counts: &mut HashMap<&'a str, usize>) {
+        for c in line.chars() {
+            let script = self.chr_script_name(c);
+            if !script.is_empty() {
+                *counts.entry(script).or_default() += 1;
+            }
         }
-        if non_utf8_chars_total > 0 {
-            eprintln!(
-                "Total number of lines with non-UTF-8 characters: {non_utf8_chars_total}"
-            );
+    }
+
+    /// Romanizes one line of `romanize_file` input and writes its output line to `writer`,
+    /// ended with `line_end`.
+    ///
+    /// The edge formats are serialized straight into `writer`, instead of building the JSON
+    /// of the line first, which takes most of the memory for the lattice of a long line.
+    /// Returns whether the output line differs from `line`.
+    pub(crate) fn write_file_line<W: Write>(
+        &self,
+        writer: &mut W,
+        line: &str,
+        line_end: &str,
+        options: &RomanizeOptions,
+    ) -> Result<bool, RomanizationError> {
+        // Meta edges are spliced into the JSON, so such lines are built as a string, as are
+        // those formatted for compatibility.
+        if options.output.rom_format == RomFormat::Str
+            || options.output.utt_ids
+            || options.output.compat.is_some()
+            || options.output.input_format != InputFormat::Text
+            || line.starts_with("::")
+        {
+            let output = self.romanize_file_line(line, options)?;
+            write!(writer, "{output}{line_end}")?;
+            return Ok(output != line);
         }
 
-        writer.flush()?;
+        let RomanizationResult::Edges(edges) = self.romanize_checked(line, options)? else {
+            unreachable!("the edge formats always yield edges");
+        };
+        // Failures of `writer` come back wrapped in a JSON error, so unwrap them.
+        serde_json::to_writer_pretty(&mut *writer, &edges).map_err(|e| {
+            if e.is_io() {
+                RomanizationError::Io(e.into())
+            } else {
+                e.into()
+            }
+        })?;
+        writer.write_all(line_end.as_bytes())?;
+        Ok(true)
+    }
+
+    /// Romanizes one line of `romanize_file` input into its output line.
+    ///
+    /// With `options.output.utt_ids`, the first field of the line is an utterance ID, which is kept
+    /// as it is. It is followed by a meta edge in the edge formats. Lines of the structured
+    /// formats are romanized field by field instead, see [`InputFormat`].
+    pub(crate) fn romanize_file_line(
+        &self,
+        line: &str,
+        options: &RomanizeOptions,
+    ) -> Result<String, RomanizationError> {
+        if options.output.input_format != InputFormat::Text {
+            return self.romanize_structured_line(line, options);
+        }
+        if !options.output.utt_ids {
+            return Ok(self.romanize_file_text(line, options)?.0);
+        }
+        let (utt_id, rest) = line.split_at(line.find(char::is_whitespace).unwrap_or(line.len()));
+        let (separator, text) = rest.split_at(rest.chars().next().map_or(0, char::len_utf8));
+        let (output, rom_format) = self.romanize_file_text(text, options)?;
+        Ok(match rom_format {
+            RomFormat::Str => format!("{utt_id}{separator}{output}"),
+            _ if utt_id.is_empty() => output,
+            _ => splice_meta_edge(&output, &format!("utt-id: {utt_id}"), options.output.compat),
+        })
+    }
+
+    /// Calls `f` with each text of a line of `romanize_file` input that
+    /// [`Uroman::romanize_file_line`] romanizes, and the options it is romanized with: the
+    /// text after any utterance ID and directives, or each field of a structured format.
+    pub(crate) fn for_each_file_line_text(
+        &self,
+        line: &str,
+        options: &RomanizeOptions,
+        mut f: impl FnMut(&str, &RomanizeOptions),
+    ) -> Result<(), RomanizationError> {
+        if options.output.input_format != InputFormat::Text {
+            input_format::map_structured_fields(line, options.output.input_format, |field| {
+                f(field, options);
+                Ok(String::new())
+            })?;
+            return Ok(());
+        }
+        let text = if options.output.utt_ids {
+            let rest = &line[line.find(char::is_whitespace).unwrap_or(line.len())..];
+            &rest[rest.chars().next().map_or(0, char::len_utf8)..]
+        } else {
+            line
+        };
+        match split_directives(text) {
+            Some((_, directives, text)) => f(text, &directives.apply(options, self)),
+            None => f(text, options),
+        }
         Ok(())
     }
+
+    /// Romanizes the text of a line of `romanize_file` input, after any utterance ID, and
+    /// returns the output with its format.
+    ///
+    /// Text starting with directives, such as `::lcode <code> ` or `::format edges `, is
+    /// romanized as they say, and they are echoed in the output: as they are in the string
+    /// format, or as meta edges such as `lcode: ukr` in the edge formats. As in the Python
+    /// uroman, any whitespace may surround the code of `::lcode`.
+    fn romanize_file_text(
+        &self,
+        text: &str,
+        options: &RomanizeOptions,
+    ) -> Result<(String, RomFormat), RomanizationError> {
+        let Some((prefix, directives, text_to_romanize)) = split_directives(text) else {
+            let output = self
+                .romanize_checked(text, options)?
+                .to_compat_output_string(options.output.compat)?;
+            return Ok((output, options.output.rom_format));
+        };
+        let options = directives.apply(options, self);
+        let output = self
+            .romanize_checked(text_to_romanize, &options)?
+            .to_compat_output_string(options.output.compat)?;
+        let output = match options.output.rom_format {
+            RomFormat::Str => format!("{prefix}{output}"),
+            _ => directives
+                .meta_edges()
+                .iter()
+                .rev()
+                .fold(output, |output, meta| {
+                    splice_meta_edge(&output, meta, options.output.compat)
+                }),
+        };
+        Ok((output, options.output.rom_format))
+    }
+}
+
+/// Returns the language codes of `lcode`, which may be a comma-separated fallback chain such
+/// as `aze,tur,fas`, in priority order. BCP-47 tags and ISO 639-1 codes are normalized to
+/// ISO 639-3 codes, and codes that can't be are kept as they are.
+pub(crate) fn lcode_chain(lcode: Option<&str>) -> impl Iterator<Item = &str> {
+    lcode
+        .into_iter()
+        .flat_map(|lcode| lcode.split(','))
+        .map(str::trim)
+        .filter(|lcode| !lcode.is_empty())
+        .map(|lcode| lang_tag::normalize_lang_tag(lcode).unwrap_or(lcode))
+}
+
+/// Inserts a meta edge `[0,0,"",meta]` before the first edge of the edges JSON `json`,
+/// formatted for `compat` like the edges.
+fn splice_meta_edge(json: &str, meta: &str, compat: Option<Compat>) -> String {
+    let (meta_edge, separator) = match compat {
+        Some(Compat::PythonV1_3) => (compat::python_edge_json(0, 0, "", meta), ", "),
+        None => (
+            format!(r#"[0,0,"",{}]"#, serde_json::Value::from(meta)),
+            ",",
+        ),
+    };
+    match json.strip_prefix('[') {
+        Some(rest) if rest.trim_start().starts_with(']') => format!("[{meta_edge}]"),
+        Some(rest) => format!("[{meta_edge}{separator}{rest}"),
+        None => json.to_string(),
+    }
 }
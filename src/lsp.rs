@@ -0,0 +1,233 @@
+//! A minimal language server that shows the romanization of non-Latin text in editors.
+
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+use serde_json::{Value, json};
+
+use crate::hash::HashMap;
+use crate::{RomanizationError, Uroman, rom_format};
+
+/// The JSON-RPC error code of messages that are not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// The JSON-RPC error code of requests for methods the server doesn't implement.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+impl Uroman {
+    /// Serves the [language server protocol](https://microsoft.github.io/language-server-protocol/)
+    /// on `reader` and `writer`, usually stdin and stdout, until the client sends `exit` or
+    /// closes `reader`.
+    ///
+    /// Each span of non-Latin text in the open documents gets its romanization as an inlay
+    /// hint after it and as hover text, so editor plugins only need to start the server.
+    /// Documents are synchronized in full, and positions are in UTF-16 code units.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if reading from `reader` or writing to
+    /// `writer` fails, or if a message has no `Content-Length` header.
+    pub fn serve_language_server<R: BufRead, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        lcode: Option<&str>,
+    ) -> Result<(), RomanizationError> {
+        let mut documents: HashMap<String, String> = HashMap::default();
+
+        while let Some(content) = read_message(&mut reader)? {
+            let Ok(message) = serde_json::from_slice::<Value>(&content) else {
+                write_error(&mut writer, &Value::Null, PARSE_ERROR, "invalid JSON")?;
+                continue;
+            };
+            let params = &message["params"];
+            let uri = || params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+            let result = match message["method"].as_str().unwrap_or_default() {
+                "initialize" => json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "hoverProvider": true,
+                        "inlayHintProvider": true,
+                    },
+                    "serverInfo": { "name": "uroman-rs", "version": env!("CARGO_PKG_VERSION") },
+                }),
+                "shutdown" => Value::Null,
+                "exit" => break,
+                "textDocument/didOpen" => {
+                    let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                    documents.insert(uri().to_string(), text.to_string());
+                    continue;
+                }
+                "textDocument/didChange" => {
+                    let changes = params["contentChanges"].as_array();
+                    if let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) {
+                        documents.insert(uri().to_string(), text.to_string());
+                    }
+                    continue;
+                }
+                "textDocument/didClose" => {
+                    documents.remove(uri());
+                    continue;
+                }
+                "textDocument/hover" => {
+                    let text = documents.get(uri()).map_or("", String::as_str);
+                    self.hover(text, &params["position"], lcode)
+                }
+                "textDocument/inlayHint" => {
+                    let text = documents.get(uri()).map_or("", String::as_str);
+                    self.inlay_hints(text, &params["range"], lcode)
+                }
+                // Notifications, such as `initialized`, need no response.
+                _ if message.get("id").is_none() => continue,
+                method => {
+                    let error = format!("method not found: {method}");
+                    write_error(&mut writer, &message["id"], METHOD_NOT_FOUND, &error)?;
+                    continue;
+                }
+            };
+            write_message(
+                &mut writer,
+                &json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the hover of the non-Latin span at `position` of `text`, or null.
+    fn hover(&self, text: &str, position: &Value, lcode: Option<&str>) -> Value {
+        let (Some(line), Some(character)) =
+            (position["line"].as_u64(), position["character"].as_u64())
+        else {
+            return Value::Null;
+        };
+        let Some(line_text) = text.lines().nth(line as usize) else {
+            return Value::Null;
+        };
+        self.romanized_spans(line_text, lcode)
+            .into_iter()
+            .find(|(span, _)| span.contains(&(character as usize)))
+            .map_or(Value::Null, |(span, romanized)| {
+                json!({
+                    "contents": { "kind": "plaintext", "value": romanized },
+                    "range": lsp_range(line, &span),
+                })
+            })
+    }
+
+    /// Returns the inlay hints after the non-Latin spans on the lines of `range` in `text`.
+    fn inlay_hints(&self, text: &str, range: &Value, lcode: Option<&str>) -> Value {
+        let first_line = range["start"]["line"].as_u64().unwrap_or(0);
+        let last_line = range["end"]["line"].as_u64().unwrap_or(u64::MAX);
+        let hints: Vec<Value> = (0..)
+            .zip(text.lines())
+            .filter(|(line, _)| (first_line..=last_line).contains(line))
+            .flat_map(|(line, line_text)| {
+                self.romanized_spans(line_text, lcode)
+                    .into_iter()
+                    .map(move |(span, romanized)| {
+                        json!({
+                            "position": { "line": line, "character": span.end },
+                            "label": romanized,
+                            "paddingLeft": true,
+                        })
+                    })
+            })
+            .collect();
+        Value::from(hints)
+    }
+
+    /// Returns the spans of non-Latin text in `line`, in UTF-16 code units, with their
+    /// romanizations.
+    ///
+    /// A span starts with a non-Latin letter and continues over whitespace and other non-ASCII,
+    /// non-Latin chars, so that a sentence in one script is romanized as a whole.
+    fn romanized_spans(&self, line: &str, lcode: Option<&str>) -> Vec<(Range<usize>, String)> {
+        let is_non_latin = |c: char| !c.is_ascii() && self.chr_script_name(c) != "Latin";
+
+        let mut spans = Vec::new();
+        // The byte and UTF-16 offsets of the start and end of the current span.
+        let mut current: Option<(usize, usize, usize, usize)> = None;
+        let mut utf16_offset = 0;
+        for (byte_offset, c) in line.char_indices() {
+            let utf16_end = utf16_offset + c.len_utf16();
+            let byte_end = byte_offset + c.len_utf8();
+            match &mut current {
+                Some((_, _, end, utf16_span_end)) if is_non_latin(c) && !c.is_whitespace() => {
+                    (*end, *utf16_span_end) = (byte_end, utf16_end);
+                }
+                Some(_) if c.is_whitespace() => {}
+                _ => {
+                    spans.extend(current.take());
+                    if is_non_latin(c) && c.is_alphabetic() {
+                        current = Some((byte_offset, utf16_offset, byte_end, utf16_end));
+                    }
+                }
+            }
+            utf16_offset = utf16_end;
+        }
+        spans.extend(current);
+
+        spans
+            .into_iter()
+            .map(|(start, utf16_start, end, utf16_end)| {
+                let romanized = self
+                    .romanize_string::<rom_format::Str>(&line[start..end], lcode)
+                    .to_output_string();
+                (utf16_start..utf16_end, romanized)
+            })
+            .collect()
+    }
+}
+
+/// Returns the LSP range of `span` on `line`.
+fn lsp_range(line: u64, span: &Range<usize>) -> Value {
+    json!({
+        "start": { "line": line, "character": span.start },
+        "end": { "line": line, "character": span.end },
+    })
+}
+
+/// Reads the content of the next message, or returns `None` at the end of `reader`.
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            content_length = value.trim().parse().ok();
+        }
+    }
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "language server message without a Content-Length header",
+        ));
+    };
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+/// Writes `message` with its `Content-Length` header and flushes it to the client.
+fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()
+}
+
+/// Writes an error response to the request with `id`.
+fn write_error(writer: &mut impl Write, id: &Value, code: i64, message: &str) -> io::Result<()> {
+    write_message(
+        writer,
+        &json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+    )
+}
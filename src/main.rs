@@ -1,14 +1,25 @@
 //! Command-line interface for uroman-rs.
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
 use rustyline::DefaultEditor;
 use rustyline::error::ReadlineError;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
-use std::path::PathBuf;
-use std::{fs, time};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::{fs, process, time};
 use thiserror::Error;
 use unicode_width::UnicodeWidthStr;
-use uroman::{RomFormat, RomanizationError, Uroman, rom_format};
+use uroman::{
+    CombiningMarkPolicy, Comparison, Compat, Edge, FileContext, FileSummary, FormatCharPolicy,
+    HtmlReport, InputEncoding, InputFormat, InvalidEscapePolicy, InvalidUtf8Policy, LineEnding,
+    RomFormat, RomanizationCache, RomanizationError, RomanizeOptions, RulePriority,
+    UnmappableCharPolicy, Uroman, WhitespacePolicy, compare_outputs, rom_format,
+};
 
 #[derive(ValueEnum, Clone, Copy, Debug, Default)]
 enum CliRomFormat {
@@ -30,6 +41,149 @@ impl From<CliRomFormat> for RomFormat {
     }
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliInvalidUtf8 {
+    Replace,
+    SkipLine,
+    Error,
+}
+
+impl From<CliInvalidUtf8> for InvalidUtf8Policy {
+    fn from(cli_policy: CliInvalidUtf8) -> Self {
+        match cli_policy {
+            CliInvalidUtf8::Replace => InvalidUtf8Policy::Replace,
+            CliInvalidUtf8::SkipLine => InvalidUtf8Policy::SkipLine,
+            CliInvalidUtf8::Error => InvalidUtf8Policy::Error,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliInvalidEscapes {
+    Replace,
+    Keep,
+}
+
+impl From<CliInvalidEscapes> for InvalidEscapePolicy {
+    fn from(cli_policy: CliInvalidEscapes) -> Self {
+        match cli_policy {
+            CliInvalidEscapes::Replace => InvalidEscapePolicy::Replace,
+            CliInvalidEscapes::Keep => InvalidEscapePolicy::Keep,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliFormatChars {
+    Drop,
+    Keep,
+    Boundary,
+}
+
+impl From<CliFormatChars> for FormatCharPolicy {
+    fn from(cli_policy: CliFormatChars) -> Self {
+        match cli_policy {
+            CliFormatChars::Drop => FormatCharPolicy::Drop,
+            CliFormatChars::Keep => FormatCharPolicy::Keep,
+            CliFormatChars::Boundary => FormatCharPolicy::Boundary,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliWhitespace {
+    Normalize,
+    Preserve,
+}
+
+impl From<CliWhitespace> for WhitespacePolicy {
+    fn from(cli_policy: CliWhitespace) -> Self {
+        match cli_policy {
+            CliWhitespace::Normalize => WhitespacePolicy::Normalize,
+            CliWhitespace::Preserve => WhitespacePolicy::Preserve,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliCombiningMarks {
+    Drop,
+    Attach,
+    Name,
+}
+
+impl From<CliCombiningMarks> for CombiningMarkPolicy {
+    fn from(cli_policy: CliCombiningMarks) -> Self {
+        match cli_policy {
+            CliCombiningMarks::Drop => CombiningMarkPolicy::Drop,
+            CliCombiningMarks::Attach => CombiningMarkPolicy::Attach,
+            CliCombiningMarks::Name => CombiningMarkPolicy::Name,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliRulePriority {
+    LanguageMatch,
+    Restrictions,
+    Provenance,
+}
+
+impl From<CliRulePriority> for RulePriority {
+    fn from(cli_priority: CliRulePriority) -> Self {
+        match cli_priority {
+            CliRulePriority::LanguageMatch => RulePriority::LanguageMatch,
+            CliRulePriority::Restrictions => RulePriority::Restrictions,
+            CliRulePriority::Provenance => RulePriority::Provenance,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliCompat {
+    #[value(name = "python-v1.3")]
+    PythonV1_3,
+}
+
+impl From<CliCompat> for Compat {
+    fn from(cli_compat: CliCompat) -> Self {
+        match cli_compat {
+            CliCompat::PythonV1_3 => Compat::PythonV1_3,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CliInputEncoding {
+    Auto,
+    #[value(name = "utf-8")]
+    Utf8,
+    #[value(name = "utf-16le")]
+    Utf16Le,
+    #[value(name = "utf-16be")]
+    Utf16Be,
+}
+
+impl From<CliInputEncoding> for InputEncoding {
+    fn from(cli_encoding: CliInputEncoding) -> Self {
+        match cli_encoding {
+            CliInputEncoding::Auto => InputEncoding::Auto,
+            CliInputEncoding::Utf8 => InputEncoding::Utf8,
+            CliInputEncoding::Utf16Le => InputEncoding::Utf16Le,
+            CliInputEncoding::Utf16Be => InputEncoding::Utf16Be,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CliInputFormat {
+    Auto,
+    Text,
+    Jsonl,
+    Tsv,
+    Srt,
+}
+
 #[derive(Error, Debug)]
 enum UromanError {
     #[error("Failed to open input file '{path}': {source}")]
@@ -38,6 +192,57 @@ enum UromanError {
     #[error("Failed to create output file '{path}': {source}")]
     OutputFileCreate { path: PathBuf, source: io::Error },
 
+    #[error("Failed to read config file '{path}': {source}")]
+    ConfigRead { path: PathBuf, source: io::Error },
+
+    #[error("Invalid config file '{path}': {source}")]
+    ConfigParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Failed to read Unicode data file '{path}': {source}")]
+    UnicodeDataRead { path: PathBuf, source: io::Error },
+
+    #[error("Failed to rename '{path}': {source}")]
+    Rename { path: PathBuf, source: io::Error },
+
+    #[error("Invalid rename manifest '{path}': {source}")]
+    ManifestParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("The outputs differ on {differing} of {lines} lines")]
+    OutputsDiffer { differing: usize, lines: usize },
+
+    #[error("Failed to run '{program}': {reason}")]
+    OtherUroman { program: PathBuf, reason: String },
+
+    #[error("Invalid rule file '{path}': {source}")]
+    Rules {
+        path: PathBuf,
+        source: RomanizationError,
+    },
+
+    #[error("Invalid protected string file '{path}': {source}")]
+    Protected {
+        path: PathBuf,
+        source: RomanizationError,
+    },
+
+    #[error("Invalid rule weight file '{path}': {source}")]
+    RuleWeights {
+        path: PathBuf,
+        source: RomanizationError,
+    },
+
+    #[error("Invalid unigram file '{path}': {source}")]
+    Unigrams {
+        path: PathBuf,
+        source: RomanizationError,
+    },
+
     #[error(transparent)]
     Io(#[from] io::Error),
 
@@ -48,37 +253,63 @@ enum UromanError {
     Romanization(#[from] RomanizationError),
 }
 
+// The flags also accept the spellings of the Python uroman (`--rom_format`, `--max_lines`, ...),
+// so that scripts written for it keep working.
 #[derive(Parser, Debug)]
-#[command(author, version)]
+#[command(
+    author,
+    version,
+    disable_version_flag = true,
+    args_conflicts_with_subcommands = true
+)]
 struct Cli {
-    /// Direct text input to be romanized.
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Direct text input to be romanized. Put it after '--' if it starts with the name of a
+    /// subcommand, as in 'uroman-rs -- compare'.
     #[arg(value_name = "DIRECT_INPUT")]
     direct_input: Vec<String>,
 
     /// Input file path (default: stdin).
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", alias = "input_filename")]
     input_filename: Option<PathBuf>,
 
     /// Output file path (default: stdout).
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", alias = "output_filename")]
     output_filename: Option<PathBuf>,
 
     /// [ISO 639-3 language code](https://www.loc.gov/standards/iso639-2/php/code_list.php) (e.g., 'eng').
+    /// A comma-separated list (e.g., 'aze,tur,fas') is tried in order. BCP-47 tags and ISO 639-1
+    /// codes (e.g., 'uk', 'sr-Latn') are normalized to ISO 639-3 codes.
     #[arg(short = 'l', long)]
     lcode: Option<String>,
 
-    /// Output format of romanization. 'edges' provides offsets.
-    #[arg(short = 'f', long, value_enum, default_value_t = CliRomFormat::default())]
-    rom_format: CliRomFormat,
+    /// Output format of romanization (default: str). 'edges' provides offsets.
+    #[arg(short = 'f', long, value_enum, alias = "rom_format")]
+    rom_format: Option<CliRomFormat>,
 
     /// Limit uroman to the first n lines of a file.
-    #[arg(long)]
+    #[arg(long, alias = "max_lines")]
     max_lines: Option<usize>,
 
     /// Decodes Unicode escape notation, e.g., \\u03B4 to δ.
-    #[arg(short = 'd', long, action = clap::ArgAction::SetTrue)]
+    #[arg(short = 'd', long, action = clap::ArgAction::SetTrue, alias = "decode_unicode")]
     decode_unicode: bool,
 
+    /// Decodes Python escapes of named characters, e.g., \N{GREEK SMALL LETTER DELTA} to δ.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    decode_named_escapes: bool,
+
+    /// Decodes HTML and XML character references, e.g., &#x3B4; and &eacute;.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    decode_entities: bool,
+
+    /// What to do with escapes that don't encode a character, such as the lone surrogate
+    /// \\ud800, when decoding them (default: replace with U+FFFD).
+    #[arg(long, value_enum, value_name = "POLICY")]
+    invalid_escapes: Option<CliInvalidEscapes>,
+
     /// Run and display a few samples.
     #[arg(long, action = clap::ArgAction::SetTrue)]
     sample: bool,
@@ -86,6 +317,266 @@ struct Cli {
     /// Suppress progress indicators.
     #[arg(long, action = clap::ArgAction::SetTrue)]
     silent: bool,
+
+    /// Print the romanization rules (for --lcode, if given) as ICU transform rules and exit.
+    #[arg(long, action = clap::ArgAction::SetTrue, alias = "icu_rules")]
+    icu_rules: bool,
+
+    /// Run as a language server on stdin/stdout, showing the romanization of non-Latin text in
+    /// editors as inlay hints and hover text.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    lsp: bool,
+
+    /// Also write a standalone HTML page to FILE that shows each line next to its
+    /// romanization, with the source and confidence of each span in tooltips, and statistics.
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Keep the first field of each line as an utterance ID, as in Kaldi/ESPnet text files.
+    #[arg(long, action = clap::ArgAction::SetTrue, alias = "utt_ids")]
+    utt_ids: bool,
+
+    /// What to do with input lines that are not valid UTF-8 (default: replace).
+    #[arg(long, value_enum, value_name = "POLICY")]
+    invalid_utf8: Option<CliInvalidUtf8>,
+
+    /// Start the output with a byte order mark if the input starts with one.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    keep_bom: bool,
+
+    /// Annotate edges with the span of positions their characters are displayed at, for
+    /// highlighting them over right-to-left text (edges, alts and lattice formats).
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    display_positions: bool,
+
+    /// Annotate edges with the conditions of the rules that romanized them, such as
+    /// 'lcode: ukr' or 'use-only-at-start-of-word' (edges, alts and lattice formats).
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    rule_conditions: bool,
+
+    /// Fail on the first line with characters that can't be romanized (other than Latin
+    /// letters and punctuation), listing them.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    strict: bool,
+
+    /// Format the output as another implementation does, to diff it against that one's.
+    /// 'python-v1.3' writes the edges as the Python uroman 1.3 does, as arrays
+    /// [start, end, "txt", "type"].
+    #[arg(long, value_enum, value_name = "IMPLEMENTATION")]
+    compat: Option<CliCompat>,
+
+    /// How to end the output lines (default: lf). 'crlf' ends them with \r\n, 'preserve' as the
+    /// input lines end, and any other value is a record separator to end them with, e.g. '\0'.
+    #[arg(long, value_name = "ENDING")]
+    line_ending: Option<String>,
+
+    /// Read input lines longer than N bytes in chunks of at most N bytes, cut at whitespace
+    /// where possible, so that huge lines such as minified JSON don't have to fit in memory.
+    /// Lines are read whole when escapes are decoded.
+    #[arg(long, value_name = "N")]
+    max_chunk_bytes: Option<usize>,
+
+    /// The encoding of the input (default: auto, which detects UTF-16 by its byte order mark).
+    #[arg(long, value_enum, value_name = "ENCODING")]
+    input_encoding: Option<CliInputEncoding>,
+
+    /// The format of the input (default: auto). 'jsonl' romanizes the strings of the JSON
+    /// values and keeps the keys, 'tsv' romanizes each tab-separated field, and 'srt' keeps the
+    /// cue numbers and timings of subtitles. 'auto' tells them by the extension of the input
+    /// file or by its first lines, for the str format without --utt-ids.
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    format: Option<CliInputFormat>,
+
+    /// JSON file with the romanization options (lcode, rom_format, max_lines, decode_unicode,
+    /// decode_named_escapes, decode_entities, invalid_escapes, silent, utt_ids, invalid_utf8,
+    /// keep_bom, input_encoding, subsystems, display_positions, strict, compat, line_ending,
+    /// max_chunk_bytes, input_format, format_chars, combining_marks, unmappable_chars,
+    /// whitespace). The flags override it.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Cache the romanization of up to N distinct lines, so that duplicate lines are served from the cache.
+    #[arg(long, value_name = "N", alias = "cache_size")]
+    cache_size: Option<NonZeroUsize>,
+
+    /// Soft limit on the estimated memory of romanization, in bytes with an optional K, M or G
+    /// suffix, e.g. '512M'. Lines whose lattice would exceed it are romanized greedily, and
+    /// the caches are shrunk to stay within it. With --cache-size, half of it is for the cache.
+    #[arg(long, value_name = "BYTES", value_parser = parse_bytes)]
+    memory_budget: Option<usize>,
+
+    /// Split lines longer than N characters into sentences, at terminators such as '.', '?',
+    /// '։', '؟' and '।', and romanize them one by one, which bounds the work on long paragraphs.
+    #[arg(long, value_name = "N")]
+    sentence_chunks: Option<usize>,
+
+    /// Leave numbers as they are instead of converting them to Arabic numerals (e.g., ۱۳۹۸ to 1398).
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    no_numbers: bool,
+
+    /// What to do with format characters such as ZWNJ and ZWJ (default: drop). 'boundary'
+    /// replaces those between words with a space.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    format_chars: Option<CliFormatChars>,
+
+    /// What to do with combining marks that no rule romanizes in context (default: drop).
+    /// 'attach' keeps them on the romanized character before them, 'name' prints their
+    /// Unicode names.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    combining_marks: Option<CliCombiningMarks>,
+
+    /// What to do with non-ASCII characters that can't be romanized (default: keep). 'escape'
+    /// replaces them with \u{XXXX} escapes, and any other value is a marker to replace them
+    /// with, e.g. '?'.
+    #[arg(long, value_name = "POLICY")]
+    unmappable_chars: Option<String>,
+
+    /// What to do with whitespace other than ASCII spaces, tabs and line breaks (default:
+    /// normalize). 'preserve' keeps e.g. no-break and ideographic spaces as they are.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    whitespace: Option<CliWhitespace>,
+
+    /// Take Cyrillic and Greek letters that look like Latin ones in words with ASCII letters,
+    /// e.g. the 'р' of 'рaypal', for those Latin letters, and fullwidth forms for ASCII.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    normalize_homoglyphs: bool,
+
+    /// Give romanizations the case of the characters they romanize in scripts with case, such
+    /// as Greek and Cyrillic, e.g. 'YABLOKO' rather than 'YaBLOKO' for 'ЯБЛОКО'.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    carry_case: bool,
+
+    /// How to choose among rules for the same characters, most important criterion first
+    /// (default: restrictions). Rules that tie are taken in the order they are loaded.
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "CRITERIA")]
+    rule_priority: Option<Vec<CliRulePriority>>,
+
+    /// File with custom rules in the format of the hand-written table, one per line, e.g.
+    /// '::s ντ ::t d ::lcode ell ::use-only-at-start-of-word'. They replace the rules for the
+    /// same chars and conditions.
+    #[arg(long, value_name = "FILE")]
+    rules: Option<PathBuf>,
+
+    /// File of strings to leave as they are, one per line, such as product names or codes
+    /// like 'ISO-8859'. Lines between slashes, e.g. '/[A-Z]{2}-\d+/', are regexes.
+    #[arg(long, value_name = "FILE")]
+    protect: Option<PathBuf>,
+
+    /// File with weights of rules, one per line, e.g. '::s γκ ::t ng ::weight -1'. Rules with
+    /// higher weights are preferred, and those with negative weights avoided.
+    #[arg(long, value_name = "FILE")]
+    rule_weights: Option<PathBuf>,
+
+    /// Language code and file of frequencies of romanized words in the language, one per line,
+    /// e.g. 'ara=ara-words.txt' with lines like 'kitab 1520'. Alternative romanizations of
+    /// rules are used where they make more frequent words. Can be repeated.
+    #[arg(long, value_name = "LCODE=FILE", value_parser = parse_unigrams_arg)]
+    unigrams: Vec<(String, PathBuf)>,
+
+    /// Print a summary of the time spent in each romanization stage to stderr.
+    #[cfg(feature = "profiling")]
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    timing: bool,
+
+    // Development flags of the Python uroman, accepted and ignored.
+    #[arg(long, hide = true, action = clap::ArgAction::SetTrue)]
+    dev: bool,
+    #[arg(long, hide = true, value_name = "FILE")]
+    profile: Option<PathBuf>,
+
+    /// Print version.
+    #[arg(short = 'v', short_alias = 'V', long, action = clap::ArgAction::Version)]
+    version: (),
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Regenerates romanization-auto-table.txt and UnicodeDataProps*.txt from a Unicode release,
+    /// to review and rebuild with.
+    GenData {
+        /// Directory with the UnicodeData.txt and Scripts.txt of the release.
+        #[arg(long, value_name = "DIR")]
+        ucd: PathBuf,
+
+        /// Directory to write the tables to.
+        #[arg(short, long, value_name = "DIR", default_value = "data")]
+        output_dir: PathBuf,
+    },
+
+    /// Compares a romanized output with a reference line by line, e.g. the output of another
+    /// version, and shows the lines that differ most. Fails if they differ.
+    Compare {
+        /// The reference output.
+        #[arg(value_name = "REF")]
+        reference: PathBuf,
+
+        /// The output to compare with the reference.
+        #[arg(value_name = "HYP")]
+        hypothesis: PathBuf,
+
+        /// Number of the most different lines to show.
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Romanizes a file with this and another uroman, such as the Python one, and summarizes
+    /// where they disagree by script and rule source. Fails if they disagree. The options
+    /// before the subcommand configure this uroman.
+    Diff {
+        /// The other uroman, which is run with the input on its stdin, as the Python uroman
+        /// reads it, and '-l LCODE' if a language code is given.
+        #[arg(long, value_name = "PROGRAM")]
+        other: PathBuf,
+
+        /// An argument to pass to the other uroman, e.g. the script for '--other python3'.
+        /// Can be repeated.
+        #[arg(long, value_name = "ARG", allow_hyphen_values = true)]
+        other_arg: Vec<String>,
+
+        /// The file to romanize.
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Language code of the input.
+        #[arg(short, long, value_name = "LCODE")]
+        lcode: Option<String>,
+
+        /// Number of the most different lines to show.
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Renames the files and directories with non-ASCII names in a directory tree to safe ASCII
+    /// romanizations of them, and records the renames in a manifest to undo them with.
+    Rename {
+        /// Directory whose contents to rename. The directory itself keeps its name.
+        #[arg(value_name = "DIR", required_unless_present = "undo")]
+        dir: Option<PathBuf>,
+
+        /// Print the renames without making them.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// File to append the renames to, one JSON object per line, or to read them from with
+        /// --undo.
+        #[arg(long, value_name = "FILE", default_value = "uroman-rename.jsonl")]
+        manifest: PathBuf,
+
+        /// Undo the renames of the manifest, the last one first.
+        #[arg(long, conflicts_with_all = ["dir", "dry_run"])]
+        undo: bool,
+
+        /// ISO 639-3 language code of the names (e.g., 'ukr').
+        #[arg(short, long, value_name = "LCODE")]
+        lcode: Option<String>,
+    },
+}
+
+/// A line of the manifest of `rename`.
+#[derive(Serialize, Deserialize)]
+struct RenameRecord {
+    from: PathBuf,
+    to: PathBuf,
 }
 
 fn main() {
@@ -103,14 +594,146 @@ fn main() {
 
 fn run() -> Result<(), UromanError> {
     let cli = Cli::parse();
-    let uroman = Uroman::new();
+    match &cli.command {
+        Some(Command::GenData { ucd, output_dir }) => return generate_data(ucd, output_dir),
+        Some(Command::Compare {
+            reference,
+            hypothesis,
+            top,
+        }) => return compare_files(reference, hypothesis, *top),
+        Some(Command::Rename {
+            dir,
+            dry_run,
+            manifest,
+            undo,
+            lcode,
+        }) => {
+            return match dir {
+                Some(dir) if !undo => rename_tree(dir, lcode.as_deref(), *dry_run, manifest),
+                _ => undo_renames(manifest),
+            };
+        }
+        Some(Command::Diff { .. }) | None => {}
+    }
+    let mut uroman = Uroman::new()
+        .with_default_overrides()
+        .with_numbers(!cli.no_numbers);
+    if let Some(memory_budget) = cli.memory_budget {
+        let cache_budget = cli.cache_size.map_or(0, |_| memory_budget / 2);
+        uroman = uroman.with_memory_budget(Some(memory_budget - cache_budget));
+    }
+    if cli.sentence_chunks.is_some() {
+        uroman = uroman.with_sentence_chunks(cli.sentence_chunks);
+    }
+    if let Some(format_chars) = cli.format_chars {
+        uroman = uroman.with_format_chars(format_chars.into());
+    }
+    if let Some(combining_marks) = cli.combining_marks {
+        uroman = uroman.with_combining_marks(combining_marks.into());
+    }
+    if let Some(unmappable_chars) = &cli.unmappable_chars {
+        uroman = uroman.with_unmappable_chars(match unmappable_chars.as_str() {
+            "keep" => UnmappableCharPolicy::Keep,
+            "escape" => UnmappableCharPolicy::Escape,
+            marker => UnmappableCharPolicy::Replace(marker.to_string()),
+        });
+    }
+    if let Some(whitespace) = cli.whitespace {
+        uroman = uroman.with_whitespace(whitespace.into());
+    }
+    if cli.normalize_homoglyphs {
+        uroman = uroman.with_normalized_homoglyphs(true);
+    }
+    if cli.carry_case {
+        uroman = uroman.with_case_carrying(true);
+    }
+    if cli.rule_conditions {
+        uroman = uroman.with_rule_conditions(true);
+    }
+    if let Some(rule_priority) = &cli.rule_priority {
+        let priority: Vec<RulePriority> = rule_priority.iter().map(|&p| p.into()).collect();
+        uroman = uroman.with_rule_priority(&priority);
+    }
+    if let Some(path) = &cli.protect {
+        let protected_error = |source| UromanError::Protected {
+            path: path.clone(),
+            source,
+        };
+        let file = fs::File::open(path).map_err(|e| protected_error(e.into()))?;
+        uroman
+            .load_protected(BufReader::new(file))
+            .map_err(protected_error)?;
+    }
+    if let Some(path) = &cli.rules {
+        let rules_error = |source| UromanError::Rules {
+            path: path.clone(),
+            source,
+        };
+        let file = fs::File::open(path).map_err(|e| rules_error(e.into()))?;
+        uroman
+            .load_rules(BufReader::new(file))
+            .map_err(rules_error)?;
+    }
+    if let Some(path) = &cli.rule_weights {
+        let rule_weights_error = |source| UromanError::RuleWeights {
+            path: path.clone(),
+            source,
+        };
+        let file = fs::File::open(path).map_err(|e| rule_weights_error(e.into()))?;
+        uroman
+            .load_rule_weights(BufReader::new(file))
+            .map_err(rule_weights_error)?;
+    }
+    for (lcode, path) in &cli.unigrams {
+        let unigrams_error = |source| UromanError::Unigrams {
+            path: path.clone(),
+            source,
+        };
+        let file = fs::File::open(path).map_err(|e| unigrams_error(e.into()))?;
+        uroman
+            .load_unigrams(lcode, BufReader::new(file))
+            .map_err(unigrams_error)?;
+    }
+    let options = romanize_options(&cli, &uroman)?;
+
+    if let Some(Command::Diff {
+        other,
+        other_arg,
+        input,
+        lcode,
+        top,
+    }) = &cli.command
+    {
+        let lcode = lcode.as_deref().or(options.output.lcode.as_deref());
+        return diff_with_other(&uroman, other, other_arg, input, lcode, *top);
+    }
+
+    if cli.icu_rules {
+        let mut writer = get_writer(&cli.output_filename)?;
+        writer.write_all(
+            uroman
+                .to_icu_rules(options.output.lcode.as_deref())
+                .as_bytes(),
+        )?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    if cli.lsp {
+        uroman.serve_language_server(
+            io::stdin().lock(),
+            io::stdout().lock(),
+            options.output.lcode.as_deref(),
+        )?;
+        return Ok(());
+    }
 
     if cli.direct_input.is_empty()
         && cli.input_filename.is_none()
         && !cli.sample
         && std::io::stdin().is_terminal()
     {
-        run_repl(&uroman, &cli)?;
+        run_repl(&uroman, &options)?;
         return Ok(());
     }
 
@@ -118,23 +741,44 @@ fn run() -> Result<(), UromanError> {
         && cli.direct_input.is_empty()
         && cli.input_filename.is_none()
         && cli.output_filename.is_none()
-        && !cli.silent
+        && !options.silent
     {
         show_samples(&uroman)?;
         return Ok(());
     }
 
     let mut writer = get_writer(&cli.output_filename)?;
+    let mut report = match &cli.report {
+        Some(path) => Some(create_report(path, &cli)?),
+        None => None,
+    };
 
     if !cli.direct_input.is_empty() {
-        process_direct_input(&uroman, &cli, &mut writer)?;
+        process_direct_input(
+            &uroman,
+            &cli.direct_input,
+            &options,
+            &mut writer,
+            report.as_mut(),
+        )?;
     }
 
     if cli.input_filename.is_some() || cli.direct_input.is_empty() {
-        process_stream(&uroman, &cli, &mut writer)?;
+        process_stream(&uroman, &cli, &options, &mut writer, report.as_mut())?;
     }
 
     writer.flush()?;
+    if let (Some(report), Some(path)) = (report, &cli.report) {
+        report.finish()?;
+        if !options.silent {
+            eprintln!("Wrote the report to '{}'.", path.display());
+        }
+    }
+
+    #[cfg(feature = "profiling")]
+    if cli.timing {
+        eprint!("{}", uroman.stage_timings());
+    }
 
     if cli.sample {
         println!(
@@ -145,39 +789,697 @@ fn run() -> Result<(), UromanError> {
     Ok(())
 }
 
+/// Regenerates the data tables from the Unicode Character Database files in `ucd`.
+fn generate_data(ucd: &Path, output_dir: &Path) -> Result<(), UromanError> {
+    let read = |file_name| {
+        let path = ucd.join(file_name);
+        fs::read_to_string(&path).map_err(|source| UromanError::UnicodeDataRead { path, source })
+    };
+    let tables =
+        Uroman::new().generate_data_tables(&read("UnicodeData.txt")?, &read("Scripts.txt")?)?;
+    tables
+        .write_to_dir(output_dir)
+        .map_err(|source| UromanError::OutputFileCreate {
+            path: output_dir.to_path_buf(),
+            source,
+        })?;
+    eprintln!(
+        "Wrote the data tables to '{}'. Review them with `git diff` and rebuild.",
+        output_dir.display()
+    );
+    Ok(())
+}
+
+/// Prints how `hypothesis` differs from `reference`, with the `top` lines that differ most.
+fn compare_files(reference: &Path, hypothesis: &Path, top: usize) -> Result<(), UromanError> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|source| UromanError::InputFileOpen {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    let comparison = compare_outputs(&read(reference)?, &read(hypothesis)?, top);
+    write_comparison(&mut io::stdout().lock(), &comparison)?;
+    if comparison.is_identical() {
+        Ok(())
+    } else {
+        Err(UromanError::OutputsDiffer {
+            differing: comparison.differing_lines,
+            lines: comparison.lines,
+        })
+    }
+}
+
+/// Writes the number of differing lines and the edit distance of `comparison`, and its lines
+/// that differ most.
+fn write_comparison(out: &mut impl Write, comparison: &Comparison) -> io::Result<()> {
+    writeln!(
+        out,
+        "Lines: {}, differing: {}",
+        comparison.lines, comparison.differing_lines
+    )?;
+    writeln!(
+        out,
+        "Edit distance: {} chars of {} (CER {:.2}%)",
+        comparison.distance,
+        comparison.reference_chars,
+        100.0 * comparison.char_error_rate()
+    )?;
+    for divergence in &comparison.divergences {
+        writeln!(
+            out,
+            "\nLine {} (distance {}):\n- {}\n+ {}",
+            divergence.line, divergence.distance, divergence.reference, divergence.hypothesis
+        )?;
+    }
+    Ok(())
+}
+
+/// Romanizes `input` with `uroman` and with the program `other`, and compares the outputs
+/// with those of `other` as the reference, with their disagreements counted by script and
+/// rule source.
+fn diff_with_other(
+    uroman: &Uroman,
+    other: &Path,
+    other_args: &[String],
+    input: &Path,
+    lcode: Option<&str>,
+    top: usize,
+) -> Result<(), UromanError> {
+    let text = fs::read_to_string(input).map_err(|source| UromanError::InputFileOpen {
+        path: input.to_path_buf(),
+        source,
+    })?;
+    let theirs = run_other_uroman(other, other_args, input, lcode)?;
+    let ours: String = text
+        .lines()
+        .map(|line| {
+            uroman
+                .romanize_string::<rom_format::Str>(line, lcode)
+                .to_output_string()
+                + "\n"
+        })
+        .collect();
+    let comparison = compare_outputs(&theirs, &ours, top);
+
+    let mut by_script: HashMap<String, usize> = HashMap::new();
+    let mut by_source: HashMap<String, usize> = HashMap::new();
+    let mut their_lines = theirs.lines();
+    for line in text.lines() {
+        let their_line = their_lines.next().unwrap_or_default();
+        for disagreement in uroman.disagreements(line, lcode, their_line) {
+            *by_script.entry(disagreement.script).or_default() += 1;
+            *by_source.entry(disagreement.source).or_default() += 1;
+        }
+    }
+
+    let mut out = io::stdout().lock();
+    write_comparison(&mut out, &comparison)?;
+    for (title, counts) in [("script", by_script), ("rule source", by_source)] {
+        if counts.is_empty() {
+            continue;
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        writeln!(out, "\nDisagreements by {title}:")?;
+        for (name, count) in counts {
+            let name = if name.is_empty() {
+                "(unaligned)"
+            } else {
+                &name
+            };
+            writeln!(out, "{count:>8}  {name}")?;
+        }
+    }
+    if comparison.is_identical() {
+        Ok(())
+    } else {
+        Err(UromanError::OutputsDiffer {
+            differing: comparison.differing_lines,
+            lines: comparison.lines,
+        })
+    }
+}
+
+/// Runs the uroman `other` with `input` on its stdin, and returns what it writes to stdout.
+fn run_other_uroman(
+    other: &Path,
+    other_args: &[String],
+    input: &Path,
+    lcode: Option<&str>,
+) -> Result<String, UromanError> {
+    let other_error = |reason: String| UromanError::OtherUroman {
+        program: other.to_path_buf(),
+        reason,
+    };
+    let stdin = fs::File::open(input).map_err(|source| UromanError::InputFileOpen {
+        path: input.to_path_buf(),
+        source,
+    })?;
+    let mut command = process::Command::new(other);
+    command.args(other_args);
+    if let Some(lcode) = lcode {
+        command.args(["-l", lcode]);
+    }
+    let output = command
+        .stdin(stdin)
+        .stderr(process::Stdio::piped())
+        .output()
+        .map_err(|e| other_error(e.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(other_error(format!("{}: {}", output.status, stderr.trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Renames the entries with non-ASCII names in `dir` and its subdirectories, or only prints
+/// the renames with `dry_run`. The renames are appended to `manifest` as they are made.
+fn rename_tree(
+    dir: &Path,
+    lcode: Option<&str>,
+    dry_run: bool,
+    manifest: &Path,
+) -> Result<(), UromanError> {
+    let uroman = Uroman::new().with_default_overrides();
+    let mut manifest_file = None;
+    let mut record = |from: &Path, to: &Path| -> Result<(), UromanError> {
+        println!("{} -> {}", from.display(), to.display());
+        if dry_run {
+            return Ok(());
+        }
+        if fs::symlink_metadata(to).is_ok() {
+            let source = io::Error::new(io::ErrorKind::AlreadyExists, "the new name is taken");
+            return Err(UromanError::Rename {
+                path: from.to_path_buf(),
+                source,
+            });
+        }
+        fs::rename(from, to).map_err(|source| UromanError::Rename {
+            path: from.to_path_buf(),
+            source,
+        })?;
+        let file = match &mut manifest_file {
+            Some(file) => file,
+            None => manifest_file.insert(
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(manifest)
+                    .map_err(|source| UromanError::OutputFileCreate {
+                        path: manifest.to_path_buf(),
+                        source,
+                    })?,
+            ),
+        };
+        let line = serde_json::to_string(&RenameRecord {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        })
+        .map_err(RomanizationError::from)?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    };
+    let n_renames = rename_dir_entries(&uroman, dir, lcode, &mut record)?;
+    if dry_run {
+        eprintln!("Would rename {n_renames} entries.");
+    } else if n_renames > 0 {
+        eprintln!(
+            "Renamed {n_renames} entries. Undo with `rename --undo --manifest {}`.",
+            manifest.display()
+        );
+    }
+    Ok(())
+}
+
+/// Renames the entries of `dir` with `rename`, those of its subdirectories first, so that the
+/// paths of the renames stay valid. Returns the number of renames.
+fn rename_dir_entries(
+    uroman: &Uroman,
+    dir: &Path,
+    lcode: Option<&str>,
+    rename: &mut impl FnMut(&Path, &Path) -> Result<(), UromanError>,
+) -> Result<usize, UromanError> {
+    let read_error = |source| UromanError::Rename {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .map_err(read_error)?;
+    entries.sort_by_key(|entry| entry.file_name());
+    // Names are compared ignoring case, for case-insensitive file systems.
+    let mut taken: HashSet<String> = entries
+        .iter()
+        .map(|entry| entry.file_name().to_string_lossy().to_lowercase())
+        .collect();
+    let mut n_renames = 0;
+    for entry in entries {
+        let path = entry.path();
+        // Symbolic links to directories are renamed, but not followed.
+        if entry.file_type().map_err(read_error)?.is_dir() {
+            n_renames += rename_dir_entries(uroman, &path, lcode, rename)?;
+        }
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            eprintln!(
+                "Warning: skipping '{}', whose name is not valid UTF-8",
+                path.display()
+            );
+            continue;
+        };
+        if name.is_ascii() {
+            continue;
+        }
+        let new_name = unique_name(&uroman.romanize_filename(&name, lcode), &mut taken);
+        rename(&path, &dir.join(new_name))?;
+        n_renames += 1;
+    }
+    Ok(n_renames)
+}
+
+/// Returns `name`, or if it is `taken`, the first of `name-1`, `name-2`, ... that is not,
+/// with the extension kept at the end, and takes it.
+fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let (stem, extension) = match name.rfind('.') {
+        Some(i) if i > 0 => name.split_at(i),
+        _ => (name, ""),
+    };
+    let mut unique = name.to_string();
+    for n in 1.. {
+        if !taken.contains(&unique.to_lowercase()) {
+            break;
+        }
+        unique = format!("{stem}-{n}{extension}");
+    }
+    taken.insert(unique.to_lowercase());
+    unique
+}
+
+/// Undoes the renames recorded in `manifest`, the last one first. Renames that were already
+/// undone are skipped.
+fn undo_renames(manifest: &Path) -> Result<(), UromanError> {
+    let content = fs::read_to_string(manifest).map_err(|source| UromanError::InputFileOpen {
+        path: manifest.to_path_buf(),
+        source,
+    })?;
+    let records = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<RenameRecord>, _>>()
+        .map_err(|source| UromanError::ManifestParse {
+            path: manifest.to_path_buf(),
+            source,
+        })?;
+    let exists = |path: &Path| fs::symlink_metadata(path).is_ok();
+    let mut n_undone = 0;
+    for RenameRecord { from, to } in records.iter().rev() {
+        if exists(from) {
+            if exists(to) {
+                let source = io::Error::new(io::ErrorKind::AlreadyExists, "the old name is taken");
+                return Err(UromanError::Rename {
+                    path: to.clone(),
+                    source,
+                });
+            }
+            continue;
+        }
+        println!("{} -> {}", to.display(), from.display());
+        fs::rename(to, from).map_err(|source| UromanError::Rename {
+            path: to.clone(),
+            source,
+        })?;
+        n_undone += 1;
+    }
+    eprintln!("Undid {n_undone} renames.");
+    Ok(())
+}
+
+/// Reads the options from the config file, if any, and overrides them with the flags.
+fn romanize_options(cli: &Cli, uroman: &Uroman) -> Result<RomanizeOptions, UromanError> {
+    let mut options = match &cli.config {
+        Some(path) => {
+            let config = fs::read_to_string(path).map_err(|e| UromanError::ConfigRead {
+                path: path.clone(),
+                source: e,
+            })?;
+            serde_json::from_str(&config).map_err(|e| UromanError::ConfigParse {
+                path: path.clone(),
+                source: e,
+            })?
+        }
+        None => RomanizeOptions::new(),
+    };
+    if let Some(lcode) = &cli.lcode {
+        options.output.lcode = Some(lcode.clone());
+    }
+    options.output.lcode = options
+        .output
+        .lcode
+        .as_deref()
+        .map(|lcode| uroman.validate_lcode(lcode))
+        .transpose()?;
+    if let Some(rom_format) = cli.rom_format {
+        options.output.rom_format = rom_format.into();
+    }
+    if cli.max_lines.is_some() {
+        options.max_lines = cli.max_lines;
+    }
+    options.output.decode_unicode |= cli.decode_unicode;
+    options.output.decode_named_escapes |= cli.decode_named_escapes;
+    options.output.decode_entities |= cli.decode_entities;
+    options.silent |= cli.silent;
+    options.output.utt_ids |= cli.utt_ids;
+    options.keep_bom |= cli.keep_bom;
+    options.output.display_positions |= cli.display_positions;
+    options.output.strict |= cli.strict;
+    if let Some(invalid_escapes) = cli.invalid_escapes {
+        options.output.invalid_escapes = invalid_escapes.into();
+    }
+    if let Some(invalid_utf8) = cli.invalid_utf8 {
+        options.invalid_utf8 = invalid_utf8.into();
+    }
+    if let Some(input_encoding) = cli.input_encoding {
+        options.input_encoding = input_encoding.into();
+    }
+    if let Some(compat) = cli.compat {
+        options.output.compat = Some(compat.into());
+    }
+    if cli.max_chunk_bytes.is_some() {
+        options.max_chunk_bytes = cli.max_chunk_bytes;
+    }
+    if let Some(line_ending) = &cli.line_ending {
+        options.line_ending = match line_ending.as_str() {
+            "lf" => LineEnding::Lf,
+            "crlf" => LineEnding::CrLf,
+            "preserve" => LineEnding::Preserve,
+            "\\0" => LineEnding::Separator("\0".to_string()),
+            separator => LineEnding::Separator(separator.to_string()),
+        };
+    }
+    // The policy flags are set on the `Uroman`, so that they apply to every mode, and the
+    // policies of the config file would override them.
+    if cli.format_chars.is_some() {
+        options.output.format_chars = None;
+    }
+    if cli.combining_marks.is_some() {
+        options.output.combining_marks = None;
+    }
+    if cli.unmappable_chars.is_some() {
+        options.output.unmappable_chars = None;
+    }
+    if cli.whitespace.is_some() {
+        options.output.whitespace = None;
+    }
+    if let Some(lcode) = &options.output.lcode
+        && !options.silent
+    {
+        let effective_lcodes = uroman.effective_lcodes();
+        for code in lcode
+            .split(',')
+            .filter(|code| !effective_lcodes.contains(code))
+        {
+            eprintln!("Warning: no rules depend on the language code '{code}', so it is ignored");
+        }
+    }
+    Ok(options)
+}
+
+/// An HTML report of the romanization of the input, see `--report`.
+type Report = HtmlReport<BufWriter<fs::File>>;
+
+fn create_report(path: &Path, cli: &Cli) -> Result<Report, UromanError> {
+    let file = fs::File::create(path).map_err(|source| UromanError::OutputFileCreate {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let input = match &cli.input_filename {
+        Some(input) => input.display().to_string(),
+        None if !cli.direct_input.is_empty() => "command-line input".to_string(),
+        None => "standard input".to_string(),
+    };
+    let title = format!("Romanization of {input}");
+    Ok(HtmlReport::new(BufWriter::new(file), &title)?)
+}
+
 fn process_direct_input(
+    uroman: &Uroman,
+    direct_input: &[String],
+    options: &RomanizeOptions,
+    writer: &mut dyn Write,
+    mut report: Option<&mut Report>,
+) -> Result<(), UromanError> {
+    // Direct input is romanized whole, as plain text.
+    let direct_options = options
+        .clone()
+        .with_utt_ids(false)
+        .with_input_format(InputFormat::Text);
+    for s in direct_input {
+        if options.output.strict {
+            uroman.check_romanizable(s, options)?;
+        }
+        if let Some(report) = report.as_deref_mut() {
+            report.add_line(uroman, s, &direct_options)?;
+        }
+        let result = uroman.romanize_with_options(s, options);
+        writeln!(
+            writer,
+            "{}",
+            result.to_compat_output_string(options.output.compat)?
+        )?;
+    }
+    Ok(())
+}
+
+fn process_stream(
     uroman: &Uroman,
     cli: &Cli,
+    options: &RomanizeOptions,
     writer: &mut dyn Write,
+    report: Option<&mut Report>,
 ) -> Result<(), UromanError> {
-    let rom_format = Some(cli.rom_format.into());
-    let lcode = cli.lcode.as_deref();
-    for s in &cli.direct_input {
-        let result = if !cli.decode_unicode {
-            uroman.romanize_with_format(s, lcode, rom_format)
-        } else {
-            uroman.romanize_escaped_with_format(s, lcode, rom_format)
-        };
-        writeln!(writer, "{}", result.to_output_string()?)?;
+    let mut reader = get_reader(&cli.input_filename)?;
+    let mut options = Cow::Borrowed(options);
+    let input_format = match cli.format {
+        Some(CliInputFormat::Auto) | None => detect_input_format(cli, &options, &mut reader)?,
+        Some(CliInputFormat::Text) => Some(InputFormat::Text),
+        Some(CliInputFormat::Jsonl) => Some(InputFormat::Jsonl),
+        Some(CliInputFormat::Tsv) => Some(InputFormat::Tsv),
+        Some(CliInputFormat::Srt) => Some(InputFormat::Srt),
+    };
+    if let Some(input_format) = input_format {
+        options.to_mut().output.input_format = input_format;
     }
+    let options = options.as_ref();
+    let Some(report) = report else {
+        return romanize_stream(uroman, cli, options, writer, reader);
+    };
+    let mut reader = ReportingReader::new(reader, uroman, options, report);
+    romanize_stream(uroman, cli, options, writer, &mut reader)?;
+    reader.finish()?;
     Ok(())
 }
 
-fn process_stream(uroman: &Uroman, cli: &Cli, writer: &mut dyn Write) -> Result<(), UromanError> {
-    let reader = get_reader(&cli.input_filename)?;
+/// Tells the format of the input by the extension of the input file, or else by its first
+/// lines, which are left in `reader`. `None` keeps the format of the options, which is only
+/// guessed for plain text in the str format without utterance IDs.
+fn detect_input_format(
+    cli: &Cli,
+    options: &RomanizeOptions,
+    reader: &mut impl BufRead,
+) -> Result<Option<InputFormat>, UromanError> {
+    if options.output.input_format != InputFormat::Text
+        || options.output.rom_format != RomFormat::Str
+        || options.output.utt_ids
+    {
+        return Ok(None);
+    }
+    if let Some(input_format) = cli
+        .input_filename
+        .as_deref()
+        .and_then(InputFormat::from_extension)
+    {
+        return Ok(Some(input_format));
+    }
+    let buffer = reader.fill_buf()?;
+    let sample = match buffer.iter().rposition(|&b| b == b'\n') {
+        Some(end) => &buffer[..=end],
+        None => buffer,
+    };
+    let input_format = InputFormat::detect(&String::from_utf8_lossy(sample));
+    // A guess from the content may be wrong, so it is not made silently.
+    let name = match input_format {
+        InputFormat::Jsonl => Some("JSON Lines"),
+        InputFormat::Tsv => Some("tab-separated values"),
+        InputFormat::Srt => Some("SubRip subtitles"),
+        _ => None,
+    };
+    if let Some(name) = name
+        && !options.silent
+    {
+        eprintln!("Reading the input as {name}. Pass '--format text' to romanize whole lines.");
+    }
+    Ok(Some(input_format))
+}
 
-    uroman.romanize_file(
-        reader,
-        writer,
-        cli.lcode.as_deref(),
-        cli.rom_format.into(),
-        cli.max_lines,
-        cli.decode_unicode,
-        cli.silent,
-    )?;
+fn romanize_stream(
+    uroman: &Uroman,
+    cli: &Cli,
+    options: &RomanizeOptions,
+    writer: &mut dyn Write,
+    reader: impl BufRead,
+) -> Result<(), UromanError> {
+    if let Some(cache_size) = cli.cache_size {
+        let mut cache = RomanizationCache::new(cache_size);
+        if let Some(memory_budget) = cli.memory_budget {
+            cache = cache.with_memory_budget(memory_budget / 2);
+        }
+        let context = FileContext::new().with_cache(&mut cache);
+        let summary = uroman.romanize_file_with_context(reader, writer, options, context)?;
+        report_non_utf8_lines(&summary, options);
+        if !options.silent {
+            let stats = cache.stats();
+            eprintln!(
+                "Cache: {} hits, {} misses ({:.1}% hit rate)",
+                stats.hits,
+                stats.misses,
+                stats.hit_rate() * 100.0
+            );
+        }
+        return Ok(());
+    }
+
+    let summary = uroman.romanize_file_with_options(reader, writer, options)?;
+    report_non_utf8_lines(&summary, options);
     Ok(())
 }
 
+/// Passes the input through, and adds each line of it to a report as it is read.
+struct ReportingReader<'a, R> {
+    inner: R,
+    uroman: &'a Uroman,
+    options: &'a RomanizeOptions,
+    report: &'a mut Report,
+    /// The start of the line being read.
+    line: Vec<u8>,
+    /// The first error while adding a line, returned by the next read.
+    error: Option<io::Error>,
+}
+
+impl<'a, R: BufRead> ReportingReader<'a, R> {
+    fn new(
+        inner: R,
+        uroman: &'a Uroman,
+        options: &'a RomanizeOptions,
+        report: &'a mut Report,
+    ) -> Self {
+        Self {
+            inner,
+            uroman,
+            options,
+            report,
+            line: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn add_line(&mut self) {
+        let line = String::from_utf8_lossy(&self.line);
+        let line = line
+            .trim_start_matches('\u{FEFF}')
+            .trim_end_matches(['\n', '\r']);
+        if let Err(e) = self.report.add_line(self.uroman, line, self.options) {
+            self.error.get_or_insert(match e {
+                RomanizationError::Io(e) => e,
+                e => io::Error::other(e),
+            });
+        }
+        self.line.clear();
+    }
+
+    /// Adds the last line if it has no line terminator, and returns the first error.
+    fn finish(mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.add_line();
+        }
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+impl<R: BufRead> io::Read for ReportingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for ReportingReader<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The buffer is still filled from the `fill_buf` call that `amt` is from.
+        let consumed = match self.inner.fill_buf() {
+            Ok(buf) => buf[..amt.min(buf.len())].to_vec(),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                Vec::new()
+            }
+        };
+        self.inner.consume(amt);
+        for chunk in consumed.split_inclusive(|&b| b == b'\n') {
+            self.line.extend_from_slice(chunk);
+            if chunk.ends_with(b"\n") {
+                self.add_line();
+            }
+        }
+    }
+}
+
+/// Parses an `LCODE=FILE` argument of `--unigrams`.
+fn parse_unigrams_arg(s: &str) -> Result<(String, PathBuf), String> {
+    match s.split_once('=') {
+        Some((lcode, path)) if !lcode.is_empty() && !path.is_empty() => {
+            Ok((lcode.to_string(), PathBuf::from(path)))
+        }
+        _ => Err(format!(
+            "expected LCODE=FILE, e.g. 'ara=ara-words.txt', not '{s}'"
+        )),
+    }
+}
+
+/// Parses a number of bytes with an optional K, M or G suffix, e.g. `512M`.
+fn parse_bytes(s: &str) -> Result<usize, String> {
+    let (digits, shift) = match s.trim().to_ascii_uppercase() {
+        s if s.ends_with('K') => (s[..s.len() - 1].to_string(), 10),
+        s if s.ends_with('M') => (s[..s.len() - 1].to_string(), 20),
+        s if s.ends_with('G') => (s[..s.len() - 1].to_string(), 30),
+        s => (s, 0),
+    };
+    digits
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("'{s}' is not a number of bytes, e.g. 512M"))
+}
+
+fn report_non_utf8_lines(summary: &FileSummary, options: &RomanizeOptions) {
+    if summary.non_utf8_lines > 0 && !options.silent {
+        eprintln!(
+            "Total number of lines with non-UTF-8 characters: {}",
+            summary.non_utf8_lines
+        );
+    }
+}
+
 fn get_reader(path: &Option<PathBuf>) -> Result<Box<dyn BufRead>, UromanError> {
     match path {
         Some(p) => {
@@ -204,7 +1506,7 @@ fn get_writer(path: &Option<PathBuf>) -> Result<Box<dyn Write>, UromanError> {
     }
 }
 
-fn run_repl(uroman: &Uroman, cli: &Cli) -> Result<(), UromanError> {
+fn run_repl(uroman: &Uroman, options: &RomanizeOptions) -> Result<(), UromanError> {
     let mut rl = DefaultEditor::new()?;
 
     let history_path = || -> Option<std::path::PathBuf> {
@@ -219,7 +1521,7 @@ fn run_repl(uroman: &Uroman, cli: &Cli) -> Result<(), UromanError> {
         && rl.load_history(&path).is_err()
     {}
 
-    let lcode = cli.lcode.as_deref();
+    let lcode = options.output.lcode.as_deref();
 
     loop {
         let readline = rl.readline(">> ");
@@ -236,9 +1538,19 @@ fn run_repl(uroman: &Uroman, cli: &Cli) -> Result<(), UromanError> {
                     continue;
                 }
 
+                if let Some(text) = line.trim_start().strip_prefix(":lattice") {
+                    let text = text.trim();
+                    if text.is_empty() {
+                        println!("Usage: :lattice <text>");
+                    } else {
+                        run_lattice_explorer(uroman, text, lcode)?;
+                    }
+                    continue;
+                }
+
                 match uroman
-                    .romanize_with_format(&line, lcode, Some(cli.rom_format.into()))
-                    .to_output_string()
+                    .romanize_with_options(&line, options)
+                    .to_compat_output_string(options.output.compat)
                 {
                     Ok(output) => println!("{output}"),
                     Err(e) => eprintln!("Error formatting output: {e}"),
@@ -268,6 +1580,89 @@ fn run_repl(uroman: &Uroman, cli: &Cli) -> Result<(), UromanError> {
     Ok(())
 }
 
+/// Lists the competing edges of `text` and lets the user step through them with the arrow keys,
+/// showing how forcing each edge changes the best path.
+fn run_lattice_explorer(
+    uroman: &Uroman,
+    text: &str,
+    lcode: Option<&str>,
+) -> Result<(), UromanError> {
+    let mut explorer = uroman.explore_lattice(text, lcode);
+    let best_path = explorer.best_path();
+    let edges: Vec<Edge> = explorer
+        .spans()
+        .iter()
+        .flat_map(|span| span.edges.iter().cloned())
+        .collect();
+
+    if edges.is_empty() {
+        println!("(No edges in lattice)");
+        return Ok(());
+    }
+
+    println!("best: {}", format_path(&best_path));
+    for (i, edge) in edges.iter().enumerate() {
+        let marker = if best_path.contains(edge) { '*' } else { ' ' };
+        println!("{marker}#{i:<3} {}", format_edge(text, edge));
+    }
+    println!("Step through the edges with the arrow keys, Home and End. Press q or Esc to quit.");
+
+    // Keys are read one by one in raw mode, where lines have to be ended with "\r\n".
+    terminal::enable_raw_mode()?;
+    let mut selected: Option<usize> = None;
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        let next = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break Ok(()),
+            KeyCode::Char('c' | 'd') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Ok(());
+            }
+            KeyCode::Right | KeyCode::Down => selected.map_or(0, |i| (i + 1) % edges.len()),
+            KeyCode::Left | KeyCode::Up => {
+                selected.map_or(edges.len() - 1, |i| (i + edges.len() - 1) % edges.len())
+            }
+            KeyCode::Home => 0,
+            KeyCode::End => edges.len() - 1,
+            _ => continue,
+        };
+
+        selected = Some(next);
+        let edge = &edges[next];
+        let path = explorer.best_path_through(edge);
+        print!("#{next} {}\r\n", format_edge(text, edge));
+        print!("path: {}\r\n", format_path(&path));
+        if let Err(e) = io::stdout().flush() {
+            break Err(e);
+        }
+    };
+    terminal::disable_raw_mode()?;
+    Ok(result?)
+}
+
+fn format_edge(text: &str, edge: &Edge) -> String {
+    let orig: String = text
+        .chars()
+        .skip(edge.start())
+        .take(edge.end() - edge.start())
+        .collect();
+    format!(
+        "[{},{}) {orig:?} -> {:?} ({})",
+        edge.start(),
+        edge.end(),
+        edge.txt(),
+        edge.r#type()
+    )
+}
+
+fn format_path(path: &[Edge]) -> String {
+    let segments: Vec<&str> = path.iter().map(|e| e.txt()).collect();
+    format!("{}  =>  {:?}", segments.join("|"), segments.concat())
+}
+
 fn show_samples(uroman: &Uroman) -> Result<(), UromanError> {
     println!("Running sample conversions with uroman-rs:");
     println!("---------------------------------------");
@@ -325,8 +1720,7 @@ fn show_samples(uroman: &Uroman) -> Result<(), UromanError> {
     println!("---------------------------------------");
 
     let num_samples = samples.len() as u128;
-    if num_samples > 0 {
-        let avg_duration_ns = total_duration_ns / num_samples;
+    if let Some(avg_duration_ns) = total_duration_ns.checked_div(num_samples) {
         let avg_duration_us = avg_duration_ns as f64 / 1_000.0;
         let avg_duration_ms = avg_duration_us / 1_000.0;
 
@@ -0,0 +1,750 @@
+//! Options shared by the romanizing entry points.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    LossyLines, decode_html_entities, decode_named_escapes, decode_unicode_escapes,
+};
+use crate::{
+    FileSummary, InputEncoding, InputFormat, Progress, RomFormat, RomanizationCache,
+    RomanizationError, RomanizationResult, Uroman,
+};
+
+/// The options of a romanization, so that new options don't change the signatures of the
+/// entry points that take them.
+///
+/// The options that change the output of a line are in [`RomanizeOptions::output`], see
+/// [`OutputOptions`], and the others, which only say how a file is read and written, are
+/// fields of their own. Which options apply depends on the entry point:
+///
+/// - The options of the text, `lcode`, `rom_format`, `decode_unicode`, `decode_named_escapes`,
+///   `decode_entities`, `invalid_escapes`, `subsystems`, `display_positions`, `format_chars`,
+///   `combining_marks`, `unmappable_chars` and `whitespace`, apply to all of them, such as
+///   [`Uroman::romanize_with_options`].
+/// - The options of files, `max_lines`, `silent`, `utt_ids`, `invalid_utf8`, `keep_bom`,
+///   `input_encoding`, `strict`, `compat`, `line_ending`, `max_chunk_bytes` and
+///   `input_format`, only apply to the entry points for files and streams, such as
+///   [`Uroman::romanize_file_with_options`] and [`Uroman::romanizing_writer`]. For a single
+///   text, [`Uroman::check_romanizable`] checks what `strict` does, and
+///   [`RomanizationResult::to_compat_output_string`] formats the result for `compat`.
+///
+/// The options that are `None` by default, such as `subsystems` and `format_chars`, take the
+/// setting of the [`Uroman`], such as [`Uroman::with_format_chars`], unless they are set. How
+/// rules are matched, with homoglyphs, case, and the priority and conditions of rules, is only
+/// set on the [`Uroman`], and applies to every call.
+///
+/// Fields that are missing when deserializing keep their defaults, so a config file only needs
+/// the options it changes. The fields of `output` are read and written alongside the others:
+///
+/// ```
+/// use uroman::{RomFormat, RomanizeOptions};
+///
+/// let options: RomanizeOptions =
+///     serde_json::from_str(r#"{ "lcode": "ukr", "rom_format": "edges" }"#).unwrap();
+/// assert_eq!(
+///     options,
+///     RomanizeOptions::new().with_lcode("ukr").with_format(RomFormat::Edges)
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct RomanizeOptions {
+    /// The options that change the output of a line.
+    #[serde(flatten)]
+    pub output: OutputOptions,
+    /// Romanize only the first `max_lines` lines of a file.
+    pub max_lines: Option<usize>,
+    /// Don't send the progress output of a file to stderr.
+    pub silent: bool,
+    /// What to do with the lines of a file that are not valid UTF-8.
+    pub invalid_utf8: InvalidUtf8Policy,
+    /// Start the output of a file with a byte order mark if the file starts with one. It is
+    /// never romanized.
+    pub keep_bom: bool,
+    /// The encoding of a file. UTF-16 is transcoded to UTF-8 while the file is read.
+    pub input_encoding: InputEncoding,
+    /// How to end the output lines of a file.
+    pub line_ending: LineEnding,
+    /// Read lines of a file that are longer than this many bytes in chunks of at most this
+    /// size, which are romanized one by one and written as one output line, so that a huge
+    /// line such as minified JSON doesn't have to fit in memory. `None` reads whole lines, as
+    /// do `decode_unicode`, `decode_named_escapes` and `decode_entities`, since a chunk could
+    /// cut an escape.
+    pub max_chunk_bytes: Option<usize>,
+}
+
+/// The options of a [`RomanizeOptions`] that change the output of a line. A
+/// [`RomanizationCache`] keys its lines by them, so an option that changes the output must be
+/// one of these.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct OutputOptions {
+    /// The [ISO 639-3 language code](https://www.loc.gov/standards/iso639-2/php/code_list.php)
+    /// of the text (e.g., eng, jpn, hin, ara, zho), or a comma-separated fallback chain of
+    /// them (e.g., `aze,tur,fas`). BCP-47 tags and ISO 639-1 codes are normalized, see
+    /// [`normalize_lcode`](crate::normalize_lcode).
+    pub lcode: Option<String>,
+    /// The output format.
+    pub rom_format: RomFormat,
+    /// Decode Unicode escape notation, e.g. `\u03B4` to `δ`, before romanizing.
+    pub decode_unicode: bool,
+    /// Decode the Python escapes of named chars, e.g. `\N{GREEK SMALL LETTER DELTA}` to `δ`,
+    /// before romanizing.
+    pub decode_named_escapes: bool,
+    /// Decode HTML and XML character references, e.g. `&#x3B4;` and `&eacute;`, before
+    /// romanizing. They are decoded after the other escapes.
+    pub decode_entities: bool,
+    /// What to do with escapes that don't encode a char when decoding them.
+    pub invalid_escapes: InvalidEscapePolicy,
+    /// Treat the first field of each line of a file as an utterance ID, as in the
+    /// `utt_id transcript` text files of Kaldi and ESPnet. The ID is kept as it is, and only
+    /// the transcript is romanized.
+    pub utt_ids: bool,
+    /// The stages of romanization to run, or `None` for those of the [`Uroman`], see
+    /// [`Uroman::with_subsystems`].
+    pub subsystems: Option<Subsystems>,
+    /// Annotate the edges of the edge formats with the span of positions that their chars are
+    /// shown at, for highlighting them over right-to-left text, see
+    /// [`Uroman::add_display_positions`].
+    pub display_positions: bool,
+    /// Stop at the first line of a file with chars that can't be romanized, with a
+    /// [`RomanizationError::Unromanizable`] wrapped in [`RomanizationError::AtLine`], see
+    /// [`Uroman::romanize_strict`].
+    pub strict: bool,
+    /// Format the output as another implementation of uroman does, so that it can be diffed
+    /// against that of the implementation being migrated from, see [`Compat`].
+    pub compat: Option<Compat>,
+    /// The format of a file. In the structured formats, only the text in the fields of each
+    /// line is romanized, in the `str` format whatever `rom_format` is, and `utt_ids` and the
+    /// line directives don't apply. Their lines are never read in chunks.
+    pub input_format: InputFormat,
+    /// What to do with format chars, or `None` for the policy of the [`Uroman`], see
+    /// [`Uroman::with_format_chars`].
+    pub format_chars: Option<FormatCharPolicy>,
+    /// What to do with combining marks, or `None` for the policy of the [`Uroman`], see
+    /// [`Uroman::with_combining_marks`].
+    pub combining_marks: Option<CombiningMarkPolicy>,
+    /// What to do with chars that no rule romanizes, or `None` for the policy of the
+    /// [`Uroman`], see [`Uroman::with_unmappable_chars`].
+    pub unmappable_chars: Option<UnmappableCharPolicy>,
+    /// What to do with whitespace, or `None` for the policy of the [`Uroman`], see
+    /// [`Uroman::with_whitespace`].
+    pub whitespace: Option<WhitespacePolicy>,
+}
+
+/// What to do with format characters (Unicode category Cf), such as the zero-width non-joiner
+/// (ZWNJ) and joiner (ZWJ), soft hyphens and bidi marks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FormatCharPolicy {
+    /// Leave them out of the output.
+    #[default]
+    Drop,
+    /// Keep them in the output as they are, e.g. the ZWNJ of Persian `می‌خواهم` in `mi‌khwahm`.
+    Keep,
+    /// Replace those between two words with a space, e.g. `mi khwahm`, for tokenizers that
+    /// should split at them, and leave the others out.
+    Boundary,
+}
+
+/// What to do with a combining mark (Unicode category Mn) that no rule romanizes in its
+/// context, such as an accent on a char that it doesn't usually go with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CombiningMarkPolicy {
+    /// Leave it out of the output.
+    #[default]
+    Drop,
+    /// Keep it in the output as it is, so it stays on the romanized char before it.
+    Attach,
+    /// Replace it with its Unicode name in brackets, e.g. `[COMBINING CANDRABINDU]`, to see
+    /// which marks are lost.
+    Name,
+}
+
+/// What to do with whitespace other than ASCII spaces, tabs and line breaks, which always pass
+/// through as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum WhitespacePolicy {
+    /// Replace spaces such as the no-break space (U+00A0) and the ideographic space (U+3000)
+    /// with an ASCII space, as the Python uroman does.
+    #[default]
+    Normalize,
+    /// Keep every whitespace char as it is, each on an edge of its own, so that the output
+    /// lines up with the input for formats that depend on it.
+    Preserve,
+}
+
+/// What to do with a Unicode escape that doesn't encode a char, such as the lone surrogate
+/// `\ud800` or `\U00110000`, when decoding escapes, see [`OutputOptions::decode_unicode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum InvalidEscapePolicy {
+    /// Replace it with U+FFFD.
+    #[default]
+    Replace,
+    /// Keep it in the text as it is.
+    Keep,
+}
+
+/// What to do with a char that neither rules nor other stages romanize, and that would pass
+/// through as it is, such as `☃` or `€`. ASCII chars always pass through.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum UnmappableCharPolicy {
+    /// Keep it in the output as it is.
+    #[default]
+    Keep,
+    /// Replace it with a marker, such as `?` or `\u{FFFD}`, for systems that only take ASCII
+    /// or that should see where romanization failed.
+    Replace(String),
+    /// Replace it with its code point as an escape, e.g. `\u{2603}` for `☃`.
+    Escape,
+}
+
+/// How to end the output lines of a file, see [`RomanizeOptions::line_ending`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum LineEnding {
+    /// With `\n`, whatever the input lines end with, as the Python uroman does.
+    #[default]
+    Lf,
+    /// With `\r\n`.
+    CrLf,
+    /// As the input line does, with `\r\n` or `\n`, and a last line without a line terminator
+    /// without one either.
+    Preserve,
+    /// With another record separator, such as `\0`.
+    Separator(String),
+}
+
+impl LineEnding {
+    /// Returns the terminator of an output line whose input line ends with `input_ending`.
+    pub(crate) fn terminator<'a>(&'a self, input_ending: &'a str) -> &'a str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Preserve => input_ending,
+            LineEnding::Separator(separator) => separator,
+        }
+    }
+}
+
+/// Another implementation of uroman whose output formatting to match, see
+/// [`OutputOptions::compat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Compat {
+    /// The Python uroman 1.3. The edge formats are written as its `json.dumps` does: each edge
+    /// as an array `[start, end, "txt", "type"]`, separated by `, `, with non-ASCII chars
+    /// escaped. The `str` format is already the same.
+    #[serde(rename = "python-v1.3")]
+    PythonV1_3,
+}
+
+/// A criterion for choosing among the rules that can romanize the same span of text, see
+/// [`Uroman::with_rule_priority`].
+///
+/// Rules with a romanization are always preferred over those without one, and rules that tie
+/// on all criteria are taken in the order they are loaded: the tables generated from the
+/// Unicode data, then their overwrites, then the hand-written table, then pinyin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RulePriority {
+    /// Prefer rules conditioned on the language code of the text over rules for any language.
+    LanguageMatch,
+    /// Prefer rules with more restrictions: language codes, and positions in a word.
+    Restrictions,
+    /// Prefer rules from the user overrides, then the hand-written table, then the overwrites
+    /// of the Unicode data, then the tables generated from it, then pinyin.
+    Provenance,
+}
+
+impl RulePriority {
+    /// The priority that rules are chosen by unless [`Uroman::with_rule_priority`] changes
+    /// it: the rule with the most restrictions, and the first loaded one of those.
+    pub const DEFAULT: &[RulePriority] = &[RulePriority::Restrictions];
+}
+
+/// The stages of romanization that can be turned off, for pipelines that some of them cause
+/// trouble for. All of them are on by default.
+///
+/// ```
+/// use uroman::{RomanizeOptions, Subsystems, Uroman};
+///
+/// let uroman = Uroman::new();
+/// let subsystems = Subsystems::default().with_abugida_vowels(false);
+/// let options = RomanizeOptions::new().with_subsystems(subsystems);
+/// let result = uroman.romanize_with_options("नमस्ते", &options);
+/// assert_eq!(result.to_output_string().unwrap(), "nmste");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Subsystems {
+    /// Convert numbers to Arabic numerals, see [`Uroman::with_numbers`].
+    pub numbers: bool,
+    /// Handle Braille all-caps signs and numbers. Without it, their cells are romanized one
+    /// by one.
+    pub braille: bool,
+    /// Romanize Chinese characters as pinyin. Without it, they are left as they are, except
+    /// for the words and numbers with rules of their own.
+    pub pinyin: bool,
+    /// Romanize Hangul syllables by decomposing them into jamo. Without it, they are left as
+    /// they are.
+    pub hangul: bool,
+    /// Add the inherent vowel of abugida consonants, e.g. `क` to `ka` instead of `k`.
+    pub abugida_vowels: bool,
+}
+
+impl Default for Subsystems {
+    fn default() -> Self {
+        Self {
+            numbers: true,
+            braille: true,
+            pinyin: true,
+            hangul: true,
+            abugida_vowels: true,
+        }
+    }
+}
+
+impl Subsystems {
+    /// Sets whether numbers are converted to Arabic numerals.
+    pub fn with_numbers(mut self, numbers: bool) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    /// Sets whether Braille all-caps signs and numbers are handled.
+    pub fn with_braille(mut self, braille: bool) -> Self {
+        self.braille = braille;
+        self
+    }
+
+    /// Sets whether Chinese characters are romanized as pinyin.
+    pub fn with_pinyin(mut self, pinyin: bool) -> Self {
+        self.pinyin = pinyin;
+        self
+    }
+
+    /// Sets whether Hangul syllables are decomposed into jamo.
+    pub fn with_hangul(mut self, hangul: bool) -> Self {
+        self.hangul = hangul;
+        self
+    }
+
+    /// Sets whether the inherent vowel of abugida consonants is added.
+    pub fn with_abugida_vowels(mut self, abugida_vowels: bool) -> Self {
+        self.abugida_vowels = abugida_vowels;
+        self
+    }
+}
+
+/// What to do with a line of a file that is not valid UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum InvalidUtf8Policy {
+    /// Replace the invalid bytes with U+FFFD and romanize the line.
+    #[default]
+    Replace,
+    /// Leave the line out of the output. It still counts toward `max_lines`.
+    SkipLine,
+    /// Stop with an `io::Error` of kind `InvalidData`.
+    Error,
+}
+
+impl OutputOptions {
+    /// Returns `true` if any escapes are decoded before romanizing.
+    pub(crate) fn decodes_escapes(&self) -> bool {
+        self.decode_unicode || self.decode_named_escapes || self.decode_entities
+    }
+}
+
+impl RomanizeOptions {
+    /// Creates the default options: the `str` format, without a language code.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the language code.
+    pub fn with_lcode(mut self, lcode: &str) -> Self {
+        self.output.lcode = Some(lcode.to_string());
+        self
+    }
+
+    /// Sets the output format.
+    pub fn with_format(mut self, rom_format: RomFormat) -> Self {
+        self.output.rom_format = rom_format;
+        self
+    }
+
+    /// Limits a file to its first `max_lines` lines.
+    pub fn with_max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Decodes Unicode escape notation before romanizing.
+    pub fn with_decode_unicode(mut self, decode_unicode: bool) -> Self {
+        self.output.decode_unicode = decode_unicode;
+        self
+    }
+
+    /// Decodes the Python escapes of named chars before romanizing.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomanizeOptions, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let options = RomanizeOptions::new().with_decode_named_escapes(true);
+    /// let result = uroman.romanize_with_options(r"\N{GREEK SMALL LETTER DELTA}", &options);
+    /// assert_eq!(result.to_output_string().unwrap(), "d");
+    /// ```
+    pub fn with_decode_named_escapes(mut self, decode_named_escapes: bool) -> Self {
+        self.output.decode_named_escapes = decode_named_escapes;
+        self
+    }
+
+    /// Decodes HTML and XML character references before romanizing.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomanizeOptions, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let options = RomanizeOptions::new().with_decode_entities(true);
+    /// let text = "&#x41C;&#1086;&#x441;&#x43A;&#x432;&#x430; &amp;";
+    /// let result = uroman.romanize_with_options(text, &options);
+    /// assert_eq!(result.to_output_string().unwrap(), "Moskva &");
+    /// ```
+    pub fn with_decode_entities(mut self, decode_entities: bool) -> Self {
+        self.output.decode_entities = decode_entities;
+        self
+    }
+
+    /// Sets whether not to send the progress output of a file to stderr.
+    pub fn with_silent(mut self, silent: bool) -> Self {
+        self.silent = silent;
+        self
+    }
+
+    /// Sets whether the lines of a file start with an utterance ID.
+    pub fn with_utt_ids(mut self, utt_ids: bool) -> Self {
+        self.output.utt_ids = utt_ids;
+        self
+    }
+
+    /// Sets what to do with escapes that don't encode a char.
+    pub fn with_invalid_escapes(mut self, invalid_escapes: InvalidEscapePolicy) -> Self {
+        self.output.invalid_escapes = invalid_escapes;
+        self
+    }
+
+    /// Sets what to do with the lines of a file that are not valid UTF-8.
+    pub fn with_invalid_utf8(mut self, invalid_utf8: InvalidUtf8Policy) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// Sets whether to reproduce the byte order mark of a file in its output.
+    pub fn with_keep_bom(mut self, keep_bom: bool) -> Self {
+        self.keep_bom = keep_bom;
+        self
+    }
+
+    /// Sets the encoding of a file.
+    pub fn with_input_encoding(mut self, input_encoding: InputEncoding) -> Self {
+        self.input_encoding = input_encoding;
+        self
+    }
+
+    /// Sets the stages of romanization to run instead of those of the [`Uroman`].
+    pub fn with_subsystems(mut self, subsystems: Subsystems) -> Self {
+        self.output.subsystems = Some(subsystems);
+        self
+    }
+
+    /// Sets whether to annotate edges with their display positions.
+    pub fn with_display_positions(mut self, display_positions: bool) -> Self {
+        self.output.display_positions = display_positions;
+        self
+    }
+
+    /// Sets whether to stop at lines with chars that can't be romanized.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.output.strict = strict;
+        self
+    }
+
+    /// Sets the implementation of uroman whose output formatting to match.
+    pub fn with_compat(mut self, compat: Compat) -> Self {
+        self.output.compat = Some(compat);
+        self
+    }
+
+    /// Sets how to end the output lines of a file.
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Sets the size of the chunks that long lines of a file are read in.
+    pub fn with_max_chunk_bytes(mut self, max_chunk_bytes: usize) -> Self {
+        self.max_chunk_bytes = Some(max_chunk_bytes);
+        self
+    }
+
+    /// Sets the format of a file.
+    pub fn with_input_format(mut self, input_format: InputFormat) -> Self {
+        self.output.input_format = input_format;
+        self
+    }
+
+    /// Sets what to do with format chars instead of the policy of the [`Uroman`].
+    pub fn with_format_chars(mut self, policy: FormatCharPolicy) -> Self {
+        self.output.format_chars = Some(policy);
+        self
+    }
+
+    /// Sets what to do with combining marks instead of the policy of the [`Uroman`].
+    pub fn with_combining_marks(mut self, policy: CombiningMarkPolicy) -> Self {
+        self.output.combining_marks = Some(policy);
+        self
+    }
+
+    /// Sets what to do with unmappable chars instead of the policy of the [`Uroman`].
+    pub fn with_unmappable_chars(mut self, policy: UnmappableCharPolicy) -> Self {
+        self.output.unmappable_chars = Some(policy);
+        self
+    }
+
+    /// Sets what to do with whitespace instead of the policy of the [`Uroman`].
+    pub fn with_whitespace(mut self, policy: WhitespacePolicy) -> Self {
+        self.output.whitespace = Some(policy);
+        self
+    }
+
+    /// Decodes the escapes in `s` that the options ask for.
+    pub(crate) fn decode_escapes<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let mut s = Cow::Borrowed(s);
+        if self.output.decode_unicode {
+            s = Cow::Owned(decode_unicode_escapes(&s, self.output.invalid_escapes));
+        }
+        if self.output.decode_named_escapes {
+            s = Cow::Owned(decode_named_escapes(&s, self.output.invalid_escapes));
+        }
+        if self.output.decode_entities {
+            s = Cow::Owned(decode_html_entities(&s, self.output.invalid_escapes));
+        }
+        s
+    }
+
+    /// The options of the `romanize_file*` functions that take them one by one.
+    pub(crate) fn for_file(
+        lcode: Option<&str>,
+        rom_format: RomFormat,
+        max_lines: Option<usize>,
+        decode_unicode: bool,
+        silent: bool,
+    ) -> Self {
+        Self {
+            output: OutputOptions {
+                lcode: lcode.map(String::from),
+                rom_format,
+                decode_unicode,
+                ..OutputOptions::default()
+            },
+            max_lines,
+            silent,
+            ..Self::default()
+        }
+    }
+
+    /// The policies that the lattices of `uroman` are built with for these options.
+    pub(crate) fn policies<'a>(&'a self, uroman: &'a Uroman) -> Policies<'a> {
+        Policies {
+            subsystems: self.output.subsystems.unwrap_or(uroman.subsystems),
+            format_chars: self.output.format_chars.unwrap_or(uroman.format_chars),
+            combining_marks: self
+                .output
+                .combining_marks
+                .unwrap_or(uroman.combining_marks),
+            unmappable_chars: self
+                .output
+                .unmappable_chars
+                .as_ref()
+                .unwrap_or(&uroman.unmappable_chars),
+            whitespace: self.output.whitespace.unwrap_or(uroman.whitespace),
+        }
+    }
+}
+
+/// The policies that a lattice is built with: those of the [`Uroman`], or those of the
+/// [`RomanizeOptions`] of a call where they are set.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Policies<'a> {
+    pub subsystems: Subsystems,
+    pub format_chars: FormatCharPolicy,
+    pub combining_marks: CombiningMarkPolicy,
+    pub unmappable_chars: &'a UnmappableCharPolicy,
+    pub whitespace: WhitespacePolicy,
+}
+
+impl Uroman {
+    /// The policies that the lattices of this `Uroman` are built with by default.
+    pub(crate) fn policies(&self) -> Policies<'_> {
+        Policies {
+            subsystems: self.subsystems,
+            format_chars: self.format_chars,
+            combining_marks: self.combining_marks,
+            unmappable_chars: &self.unmappable_chars,
+            whitespace: self.whitespace,
+        }
+    }
+
+    /// Romanizes `s` like [`Uroman::romanize_with_format`], or like
+    /// [`Uroman::romanize_escaped_with_format`] if `options.output.decode_unicode` is set.
+    ///
+    /// Only the options of the text apply, and those of files, such as `options.output.strict`
+    /// and `options.output.compat`, are ignored, see [`RomanizeOptions`].
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomFormat, RomanizationResult, RomanizeOptions, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let options = RomanizeOptions::new()
+    ///     .with_format(RomFormat::Edges)
+    ///     .with_display_positions(true);
+    /// let RomanizationResult::Edges(edges) = uroman.romanize_with_options("abc שלום", &options)
+    /// else {
+    ///     unreachable!();
+    /// };
+    /// // `ש` is the 5th char of the text, and is shown last.
+    /// assert_eq!(edges[4].get_data().display, Some((7, 8)));
+    /// ```
+    pub fn romanize_with_options(&self, s: &str, options: &RomanizeOptions) -> RomanizationResult {
+        let lcode = options.output.lcode.as_deref();
+        let s = options.decode_escapes(s);
+        let policies = options.policies(self);
+        let result =
+            self.romanize_segments_with_format(&[(&s, lcode)], options.output.rom_format, policies);
+        self.with_display_positions(result, &s, options)
+    }
+
+    /// Romanizes a stream of text like [`Uroman::romanize_file`], and supports
+    /// `options.output.utt_ids` and `options.invalid_utf8`.
+    ///
+    /// Lines that are not valid UTF-8 are counted in the returned summary instead of being
+    /// reported on stderr.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an `io::Error` if any I/O operation fails during
+    /// reading from the `reader` or writing to the `writer`, or if a line is not valid UTF-8
+    /// and `options.invalid_utf8` is [`InvalidUtf8Policy::Error`]. Such errors are wrapped in
+    /// [`RomanizationError::AtLine`] with the location of the line.
+    pub fn romanize_file_with_options<R: BufRead, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+        options: &RomanizeOptions,
+    ) -> Result<FileSummary, RomanizationError> {
+        self.romanize_file_with_context(reader, writer, options, FileContext::new())
+    }
+
+    /// Same as [`Uroman::romanize_file_with_options`], but serves repeated lines from the cache
+    /// of `context` and reports the progress to its callback, see [`FileContext`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Uroman::romanize_file_with_options`].
+    ///
+    /// # Example
+    /// ```
+    /// use std::num::NonZeroUsize;
+    /// use uroman::{FileContext, Progress, RomanizationCache, RomanizeOptions, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let options = RomanizeOptions::new().with_lcode("ukr");
+    /// let mut cache = RomanizationCache::new(NonZeroUsize::new(100).unwrap());
+    /// let mut lines = 0;
+    /// let mut progress = |progress: Progress| lines = progress.lines;
+    /// let context = FileContext::new()
+    ///     .with_cache(&mut cache)
+    ///     .with_progress(&mut progress);
+    /// let mut output = Vec::new();
+    /// uroman
+    ///     .romanize_file_with_context("Київ\nКиїв\n".as_bytes(), &mut output, &options, context)
+    ///     .unwrap();
+    /// assert_eq!(output, b"Kyiv\nKyiv\n");
+    /// assert_eq!(cache.stats().hits, 1);
+    /// assert_eq!(lines, 2);
+    /// ```
+    pub fn romanize_file_with_context<R: BufRead, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+        options: &RomanizeOptions,
+        context: FileContext,
+    ) -> Result<FileSummary, RomanizationError> {
+        let lines = LossyLines::decoded(reader, options.input_encoding).quiet();
+        self.romanize_file_inner(lines, writer, options, context.cache, context.progress)
+    }
+}
+
+/// What [`Uroman::romanize_file_with_context`] uses besides the [`RomanizeOptions`], which
+/// can't be serialized with them: a cache of repeated lines and a callback for the progress.
+#[derive(Default)]
+#[non_exhaustive]
+pub struct FileContext<'a> {
+    /// The cache that repeated lines are served from, see [`RomanizationCache`].
+    pub cache: Option<&'a mut RomanizationCache>,
+    /// Called with the progress after each line, see [`Progress`].
+    pub progress: Option<&'a mut dyn FnMut(Progress)>,
+}
+
+impl<'a> FileContext<'a> {
+    /// Creates a context without a cache or a progress callback.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the cache that repeated lines are served from.
+    pub fn with_cache(mut self, cache: &'a mut RomanizationCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Sets the callback that is called with the progress after each line.
+    pub fn with_progress(mut self, progress: &'a mut dyn FnMut(Progress)) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+}
+
+impl fmt::Debug for FileContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileContext")
+            .field("cache", &self.cache)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,129 @@
+//! User overrides of the embedded data tables, kept in a directory of their own so that
+//! corrections survive upgrades.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::cache::MemoMap;
+use crate::utils::diagnostic;
+use crate::{RomanizationError, Uroman};
+
+/// The environment variable with the overrides directory that [`Uroman::with_default_overrides`]
+/// loads. If it is set but empty, no overrides are loaded.
+pub const OVERRIDES_DIR_ENV: &str = "UROMAN_OVERRIDES_DIR";
+
+/// The rules, in the format of `romanization-table.txt`. Rules for whole words make a lexicon.
+const RULES_FILE: &str = "romanization-table.txt";
+
+/// The numeric properties, in the format of `NumProps.jsonl`.
+const NUM_PROPS_FILE: &str = "NumProps.jsonl";
+
+/// The overrides directory that [`Uroman::with_default_overrides`] loads: the one in
+/// [`OVERRIDES_DIR_ENV`], or else `uroman-rs/overrides` in `$XDG_DATA_HOME`, which defaults to
+/// `~/.local/share`.
+pub fn default_overrides_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(OVERRIDES_DIR_ENV) {
+        return (!dir.is_empty()).then(|| PathBuf::from(dir));
+    }
+    // Relative paths in `XDG_DATA_HOME` are invalid and to be ignored, as the spec says.
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            let home = PathBuf::from(std::env::var_os("HOME")?);
+            Some(home.join(".local").join("share"))
+        })?;
+    Some(data_home.join("uroman-rs").join("overrides"))
+}
+
+impl Uroman {
+    /// Loads the overrides of [`default_overrides_dir`] on top of the rules and properties loaded
+    /// so far, if there are any. The `uroman-rs` binary loads them, while [`Uroman::new`] only
+    /// uses the embedded tables, so that its output doesn't depend on the environment.
+    ///
+    /// A directory that can't be loaded is reported as a warning and skipped, unless it was set
+    /// in [`OVERRIDES_DIR_ENV`], where it has to exist.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new().with_default_overrides();
+    /// ```
+    pub fn with_default_overrides(mut self) -> Self {
+        self.load_default_overrides();
+        self
+    }
+
+    /// Loads the overrides in `dir` on top of the rules and properties loaded so far, see
+    /// [`Uroman::load_overrides`].
+    ///
+    /// # Errors
+    /// Returns the errors of [`Uroman::load_overrides`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new().with_overrides_dir("/etc/uroman-rs/overrides").unwrap();
+    /// ```
+    pub fn with_overrides_dir(mut self, dir: impl AsRef<Path>) -> Result<Self, RomanizationError> {
+        self.load_overrides(dir.as_ref())?;
+        Ok(self)
+    }
+
+    /// Loads the overrides in `dir`, which can hold either of these files:
+    ///
+    /// - `romanization-table.txt`: rules in the format of the hand-written table, e.g.
+    ///   `::s ий ::t iy ::lcode ukr`, as [`Uroman::load_rules`] loads them.
+    /// - `NumProps.jsonl`: numeric properties of characters, which replace those of the same
+    ///   characters.
+    ///
+    /// [`Uroman::with_default_overrides`] loads the overrides in the directory of
+    /// [`OVERRIDES_DIR_ENV`], or `$XDG_DATA_HOME/uroman-rs/overrides`, if there is one.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::Io`] if `dir` or one of its files can't be read, and the
+    /// errors of [`Uroman::load_rules`] for the rules. Missing files are skipped.
+    pub fn load_overrides(&mut self, dir: &Path) -> Result<(), RomanizationError> {
+        if !dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No overrides directory {}", dir.display()),
+            )
+            .into());
+        }
+        if let Some(rules) = read_optional(&dir.join(RULES_FILE))? {
+            self.load_rules(rules.as_bytes())?;
+        }
+        if let Some(num_props) = read_optional(&dir.join(NUM_PROPS_FILE))? {
+            self.load_num_props(&num_props);
+        }
+        // Abugida romanizations are derived from the rules and properties.
+        self.abugida_cache = MemoMap::default();
+        Ok(())
+    }
+
+    /// Loads the overrides of [`default_overrides_dir`], reporting errors as warnings.
+    fn load_default_overrides(&mut self) {
+        let Some(dir) = default_overrides_dir() else {
+            return;
+        };
+        // Only a directory that was asked for explicitly has to exist.
+        if !dir.is_dir() && std::env::var_os(OVERRIDES_DIR_ENV).is_none() {
+            return;
+        }
+        if let Err(e) = self.load_overrides(&dir) {
+            diagnostic!("Warning: ignoring the overrides in {}: {e}", dir.display());
+        }
+    }
+}
+
+/// Reads the file at `path`, or returns `None` if there is none.
+fn read_optional(path: &Path) -> io::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
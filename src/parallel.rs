@@ -0,0 +1,170 @@
+//! Romanizing streams on a rayon thread pool.
+
+use std::io::{BufRead, Write};
+
+use rayon::prelude::*;
+
+use crate::hash::HashMap;
+use crate::utils::{LossyLines, error_at_line};
+use crate::{FileSummary, RomanizationError, RomanizationResult, RomanizeOptions, Uroman};
+
+/// The number of lines read and romanized as one batch.
+const CHUNK_LINES: usize = 4096;
+
+/// A line of a batch, with the terminator of its output line and its location for errors.
+struct ChunkLine {
+    text: String,
+    line_end: String,
+    number: usize,
+    byte_offset: u64,
+}
+
+impl Uroman {
+    /// Romanizes a stream of text like [`Uroman::romanize_file_with_options`], but spreads the
+    /// lines over the global rayon thread pool.
+    ///
+    /// Lines are read in chunks, and each chunk is romanized in parallel and written in the
+    /// original order before the next one is read, so memory use stays bounded by the chunk
+    /// size rather than the size of the input. The output is the same as that of
+    /// [`Uroman::romanize_file_with_options`], except that lines are always read whole, whatever
+    /// `options.max_chunk_bytes` is.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomanizeOptions, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// let options = RomanizeOptions::new().with_lcode("ukr").with_silent(true);
+    /// let mut output = Vec::new();
+    /// let summary = uroman
+    ///     .romanize_file_parallel("Київ\nЛьвів\n".as_bytes(), &mut output, &options)
+    ///     .unwrap();
+    /// assert_eq!(output, b"Kyiv\nLviv\n");
+    /// assert_eq!(summary.lines, 2);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Uroman::romanize_file_with_options`]. Errors in the middle of the stream come
+    /// as [`RomanizationError::AtLine`], with the location of the line.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(lcode = ?options.output.lcode, rom_format = ?options.output.rom_format)
+        )
+    )]
+    pub fn romanize_file_parallel<R: BufRead, W: Write>(
+        &self,
+        reader: R,
+        mut writer: W,
+        options: &RomanizeOptions,
+    ) -> Result<FileSummary, RomanizationError> {
+        let mut lines = LossyLines::decoded(reader, options.input_encoding)
+            .with_invalid_utf8(options.invalid_utf8)
+            .quiet();
+        if options.keep_bom && lines.starts_with_bom()? {
+            writer.write_all("\u{FEFF}".as_bytes())?;
+        }
+        let mut changed_lines = 0;
+        let mut per_script_counts: HashMap<&str, usize> = HashMap::default();
+        let mut chunk = Vec::with_capacity(CHUNK_LINES);
+        let mut output = Vec::with_capacity(CHUNK_LINES);
+        let mut done = false;
+
+        while !done {
+            chunk.clear();
+            while chunk.len() < CHUNK_LINES {
+                let Some(line) = lines.next_line()? else {
+                    done = true;
+                    break;
+                };
+                self.count_scripts(line, &mut per_script_counts);
+                chunk.push(ChunkLine {
+                    text: line.to_string(),
+                    line_end: options
+                        .line_ending
+                        .terminator(lines.current_line_ending())
+                        .to_string(),
+                    number: lines.line_number(),
+                    byte_offset: lines.current_line_start(),
+                });
+                if options
+                    .max_lines
+                    .is_some_and(|max| lines.line_number() >= max)
+                {
+                    done = true;
+                    break;
+                }
+            }
+
+            chunk
+                .par_iter()
+                .map(|line| {
+                    let mut buf = Vec::new();
+                    self.write_file_line(&mut buf, &line.text, &line.line_end, options)
+                        .map(|changed| (buf, changed))
+                        .map_err(|e| error_at_line(line.number, line.byte_offset, &line.text, e))
+                })
+                .collect_into_vec(&mut output);
+            for line in output.drain(..) {
+                let (line, changed) = line?;
+                writer.write_all(&line)?;
+                changed_lines += usize::from(changed);
+            }
+        }
+
+        lines.finish(options.silent);
+
+        writer.flush()?;
+        Ok(lines.summary(changed_lines, per_script_counts))
+    }
+}
+
+/// Romanizes the texts of an indexed rayon iterator, e.g. `lines.par_iter()`.
+///
+/// Implemented for every [`IndexedParallelIterator`] over `AsRef<str>` items, so the results
+/// keep the order of the input.
+///
+/// # Example
+/// ```
+/// use rayon::prelude::*;
+/// use uroman::{ParallelRomanize, RomanizeOptions, Uroman};
+///
+/// let uroman = Uroman::new();
+/// let lines = vec!["Ελληνικά", "Київ"];
+/// let romanized = lines
+///     .par_iter()
+///     .romanize_to_strings(&uroman, &RomanizeOptions::new());
+/// assert_eq!(romanized[1].as_ref().unwrap(), "Kiyiv");
+/// ```
+pub trait ParallelRomanize: IndexedParallelIterator<Item: AsRef<str>> {
+    /// Romanizes each item like [`Uroman::romanize_with_options`].
+    fn romanize_with<'a>(
+        self,
+        uroman: &'a Uroman,
+        options: &'a RomanizeOptions,
+    ) -> impl IndexedParallelIterator<Item = RomanizationResult> + 'a
+    where
+        Self: 'a,
+    {
+        self.map(move |s| uroman.romanize_with_options(s.as_ref(), options))
+    }
+
+    /// Romanizes each item to its output string, and collects them in the order of the input.
+    ///
+    /// An item whose edges cannot be serialized yields its own error without affecting the
+    /// others.
+    fn romanize_to_strings(
+        self,
+        uroman: &Uroman,
+        options: &RomanizeOptions,
+    ) -> Vec<Result<String, RomanizationError>> {
+        self.romanize_with(uroman, options)
+            .map(|result| result.to_output_string())
+            .collect()
+    }
+}
+
+impl<I: IndexedParallelIterator<Item: AsRef<str>>> ParallelRomanize for I {}
@@ -0,0 +1,140 @@
+//! Per-stage timing of romanization, recorded with the `profiling` feature.
+//!
+//! Without the feature, [`Uroman::timed`] just runs the stage, so the call sites
+//! cost nothing.
+
+use crate::Uroman;
+#[cfg(feature = "profiling")]
+use std::fmt;
+#[cfg(feature = "profiling")]
+use std::sync::{Mutex, PoisonError};
+#[cfg(feature = "profiling")]
+use std::time::{Duration, Instant};
+
+/// The stages of romanization that are timed separately.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Stage {
+    /// Matching the romanization rules and adding the fallback edges.
+    RuleMatching,
+    /// Combining digits and number words into numeric edges.
+    Numbers,
+    /// Preparing Braille text and combining Braille numbers.
+    #[cfg_attr(not(feature = "braille"), allow(dead_code))]
+    Braille,
+    /// Searching the lattice for the best path.
+    BestPath,
+}
+
+/// Time spent in each stage of romanization, accumulated over all calls since the
+/// `Uroman` was created or [`Uroman::reset_stage_timings`] was called.
+#[cfg(feature = "profiling")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageTimings {
+    /// The number of strings romanized through the lattice. Strings that are all ASCII
+    /// skip it and are not counted.
+    pub calls: u64,
+    pub rule_matching: Duration,
+    pub numbers: Duration,
+    pub braille: Duration,
+    pub best_path: Duration,
+}
+
+#[cfg(feature = "profiling")]
+impl StageTimings {
+    /// Returns the time spent in all stages together.
+    pub fn total(&self) -> Duration {
+        self.rule_matching + self.numbers + self.braille + self.best_path
+    }
+
+    fn stage_mut(&mut self, stage: Stage) -> &mut Duration {
+        match stage {
+            Stage::RuleMatching => &mut self.rule_matching,
+            Stage::Numbers => &mut self.numbers,
+            Stage::Braille => &mut self.braille,
+            Stage::BestPath => &mut self.best_path,
+        }
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        writeln!(f, "{} lattices, {total:.2?} in total", self.calls)?;
+        for (name, time) in [
+            ("rule matching", self.rule_matching),
+            ("numbers", self.numbers),
+            ("braille", self.braille),
+            ("best path", self.best_path),
+        ] {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            writeln!(f, "  {name:<14}{time:>12.2?} {share:>5.1}%")?;
+        }
+        Ok(())
+    }
+}
+
+/// The accumulated [`StageTimings`] of a `Uroman`, shared between threads.
+#[cfg(feature = "profiling")]
+#[derive(Debug, Default)]
+pub(crate) struct TimingsCell(Mutex<StageTimings>);
+
+#[cfg(feature = "profiling")]
+impl TimingsCell {
+    fn update(&self, f: impl FnOnce(&mut StageTimings)) {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    fn get(&self) -> StageTimings {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "profiling")]
+impl Clone for TimingsCell {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.get()))
+    }
+}
+
+impl Uroman {
+    /// Runs one stage of romanization, adding its duration to the stage timings.
+    #[cfg(feature = "profiling")]
+    pub(crate) fn timed<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        self.timings
+            .update(|timings| *timings.stage_mut(stage) += elapsed);
+        result
+    }
+
+    #[cfg(not(feature = "profiling"))]
+    #[inline(always)]
+    pub(crate) fn timed<T>(&self, _stage: Stage, f: impl FnOnce() -> T) -> T {
+        f()
+    }
+
+    /// Counts one romanized string in the stage timings.
+    pub(crate) fn count_timed_call(&self) {
+        #[cfg(feature = "profiling")]
+        self.timings.update(|timings| timings.calls += 1);
+    }
+
+    /// Returns the time spent in each stage of romanization so far.
+    #[cfg(feature = "profiling")]
+    pub fn stage_timings(&self) -> StageTimings {
+        self.timings.get()
+    }
+
+    /// Resets the stage timings to zero.
+    #[cfg(feature = "profiling")]
+    pub fn reset_stage_timings(&self) {
+        self.timings
+            .update(|timings| *timings = StageTimings::default());
+    }
+}
@@ -0,0 +1,47 @@
+//! Progress reports of `Uroman::romanize_file_with_context`, and summaries of the
+//! `romanize_file_with_*` functions that take `RomanizeOptions`.
+
+use std::collections::BTreeMap;
+
+/// The progress of [`Uroman::romanize_file_with_context`](crate::Uroman::romanize_file_with_context),
+/// reported after each line to the callback of
+/// [`FileContext::with_progress`](crate::FileContext::with_progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Progress {
+    /// The number of lines read so far.
+    pub lines: usize,
+    /// The number of bytes read so far, including line terminators. For UTF-16 input, these
+    /// are the bytes transcoded to UTF-8.
+    pub bytes: u64,
+    /// The problem with the line just read, if any. The line is romanized anyway.
+    pub error: Option<LineError>,
+}
+
+/// A problem with a line of input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LineError {
+    /// The line had non-UTF-8 bytes, which were replaced with U+FFFD.
+    NonUtf8,
+}
+
+/// What [`Uroman::romanize_file_with_options`](crate::Uroman::romanize_file_with_options) read
+/// and wrote, for callers to log or assert on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileSummary {
+    /// The number of lines read, including skipped ones.
+    pub lines: usize,
+    /// The number of lines that were not valid UTF-8. They were replaced or skipped according to
+    /// [`RomanizeOptions::invalid_utf8`](crate::RomanizeOptions::invalid_utf8).
+    pub non_utf8_lines: usize,
+    /// The number of bytes read, including line terminators.
+    pub bytes: u64,
+    /// The number of lines whose output differs from the line. In the `str` format, these are
+    /// the lines that had text to romanize.
+    pub changed_lines: usize,
+    /// The number of chars of each script in the romanized lines, e.g. `"Cyrillic"`. Chars
+    /// without a script, such as digits and punctuation, are not counted.
+    pub per_script_counts: BTreeMap<String, usize>,
+}
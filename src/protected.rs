@@ -0,0 +1,121 @@
+//! Strings that are not romanized, such as product names and codes like `ISO-8859`, which pass
+//! through as they are even if they contain chars that rules romanize.
+
+use std::io::BufRead;
+
+use regex::Regex;
+
+use crate::data_table::is_skipped_line;
+use crate::utils::LossyLines;
+use crate::{InvalidUtf8Policy, RomanizationError, Uroman};
+
+/// The strings and regexes of [`Uroman::load_protected`], with the regex that finds them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Protected {
+    strings: Vec<String>,
+    regexes: Vec<String>,
+    regex: Option<Regex>,
+}
+
+impl Protected {
+    /// Builds the regex of the strings and regexes, with the strings longest first so that a
+    /// string wins over its prefixes.
+    fn build(&mut self) -> Result<(), regex::Error> {
+        let mut strings: Vec<&String> = self.strings.iter().collect();
+        strings.sort_by_key(|s| std::cmp::Reverse(s.chars().count()));
+        let alternatives: Vec<String> = strings
+            .into_iter()
+            .map(|s| regex::escape(s))
+            .chain(self.regexes.iter().map(|regex| format!("(?:{regex})")))
+            .collect();
+        self.regex = if alternatives.is_empty() {
+            None
+        } else {
+            Some(Regex::new(&alternatives.join("|"))?)
+        };
+        Ok(())
+    }
+
+    /// Splits `s` into its protected matches and the text between them, with whether each
+    /// part is protected.
+    pub fn split<'s>(&self, s: &'s str) -> Vec<(&'s str, bool)> {
+        let Some(regex) = &self.regex else {
+            return vec![(s, false)];
+        };
+        let mut parts = Vec::new();
+        let mut end = 0;
+        for m in regex.find_iter(s).filter(|m| !m.is_empty()) {
+            if m.start() > end {
+                parts.push((&s[end..m.start()], false));
+            }
+            parts.push((m.as_str(), true));
+            end = m.end();
+        }
+        if end < s.len() || parts.is_empty() {
+            parts.push((&s[end..], false));
+        }
+        parts
+    }
+}
+
+impl Uroman {
+    /// Loads strings that are not romanized, such as product names, codes like `ISO-8859` and
+    /// identifiers, one per line, on top of those loaded so far. `#` starts a comment line. A
+    /// line between slashes, such as `/[A-Z]{2}-\d+/`, is a regex, and other lines are exact
+    /// strings, without the whitespace around them.
+    ///
+    /// The strings and the matches of the regexes are found in the text before rules are
+    /// matched, and pass through as they are, as edges of type `protected` in the edge
+    /// formats. Rules don't match across them. Where several of them match at the same
+    /// position, strings win over regexes, longer strings first, and then the first regex.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::InvalidProtectedPattern`] for an invalid regex, wrapped in
+    /// [`RomanizationError::AtLine`]. Nothing is loaded then.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let mut uroman = Uroman::new();
+    /// uroman.load_protected("Ωmega\n/№\\d+/\n".as_bytes()).unwrap();
+    /// let result = uroman.romanize_string::<rom_format::Str>("Ωmega №12 Ωμέγα", None);
+    /// assert_eq!(result.to_output_string(), "Ωmega №12 Omega");
+    /// ```
+    pub fn load_protected<R: BufRead>(&mut self, reader: R) -> Result<(), RomanizationError> {
+        let mut lines = LossyLines::new(reader)
+            .with_invalid_utf8(InvalidUtf8Policy::Error)
+            .quiet();
+        let mut protected = self.protected.clone();
+        while let Some(line) = lines.next_line()? {
+            if is_skipped_line(line) {
+                continue;
+            }
+            let line = line.trim();
+            match line
+                .strip_prefix('/')
+                .and_then(|line| line.strip_suffix('/'))
+            {
+                Some(regex) => {
+                    if let Err(e) = Regex::new(regex) {
+                        let e = RomanizationError::InvalidProtectedPattern {
+                            pattern: line.to_string(),
+                            reason: e.to_string(),
+                        };
+                        return Err(lines.error_at_current_line(e));
+                    }
+                    protected.regexes.push(regex.to_string());
+                }
+                None => protected.strings.push(line.to_string()),
+            }
+        }
+        protected
+            .build()
+            .map_err(|e| RomanizationError::InvalidProtectedPattern {
+                pattern: protected.regexes.join("|"),
+                reason: e.to_string(),
+            })?;
+        self.protected = protected;
+        Ok(())
+    }
+}
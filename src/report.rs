@@ -0,0 +1,301 @@
+//! HTML reports of romanizations, for reviewing large conversion jobs.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::hash::{HashMap, HashSet};
+use crate::lcode_spans::inline_lcode_segments;
+use crate::options::Policies;
+use crate::{
+    OVERRIDE_PROV, PINYIN_PROV, RomanizationError, RomanizationResult, RomanizeOptions, Uroman,
+    rom_format,
+};
+
+/// Spans romanized with a confidence below this are highlighted.
+const LOW_CONFIDENCE: f64 = 0.5;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+table{border-collapse:collapse;width:100%}\
+td,th{border:1px solid #ccc;padding:.3em .5em;text-align:left;vertical-align:top}\
+td.n{color:#888;text-align:right}\
+span.s{border-bottom:1px dotted #888;cursor:help}\
+span.alt{background:#fff3c4}span.low{background:#ffd6a5}span.unrom{background:#ffc9c9}";
+
+/// The statistics of an [`HtmlReport`], which it ends with.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ReportStats {
+    pub lines: usize,
+    pub chars: usize,
+    /// The spans of the romanizations, one for each edge of their best paths.
+    pub spans: usize,
+    /// The spans that the lattice had other romanizations for.
+    pub ambiguous_spans: usize,
+    /// The spans with a confidence below 0.5, i.e. with at least two other romanizations.
+    pub low_confidence_spans: usize,
+    /// The non-ASCII chars that were left as they are.
+    pub unromanized_chars: usize,
+    /// The lines that were romanized greedily, see
+    /// [`RomanizationOutput::used_greedy_fallback`](crate::RomanizationOutput::used_greedy_fallback).
+    pub greedy_lines: usize,
+    /// The number of spans romanized by the rules of each provenance, e.g. `man` for the
+    /// hand-written table, and by each other kind of edge, e.g. `num` for numbers.
+    pub spans_by_source: BTreeMap<String, usize>,
+}
+
+/// A standalone HTML page that shows lines next to their romanizations, for linguists to
+/// review conversions.
+///
+/// Each span of a romanization has a tooltip with the text it romanizes, the rule table it
+/// comes from and the confidence of the romanization, which is 1 divided by the number of
+/// different romanizations that the lattice had for the span. Ambiguous spans and text that
+/// was left as it is are highlighted. The page ends with [`ReportStats`].
+///
+/// Call [`HtmlReport::finish`] to end the page; a report that is dropped without it is cut
+/// off.
+///
+/// # Example
+/// ```
+/// use uroman::{HtmlReport, RomanizeOptions, Uroman};
+///
+/// let uroman = Uroman::new();
+/// let mut report = HtmlReport::new(Vec::new(), "Review").unwrap();
+/// report.add_line(&uroman, "αγκάθι", &RomanizeOptions::default()).unwrap();
+/// assert_eq!(report.stats().ambiguous_spans, 1);
+/// let html = String::from_utf8(report.finish().unwrap()).unwrap();
+/// assert!(html.contains("confidence: 0.50"));
+/// ```
+#[derive(Debug)]
+pub struct HtmlReport<W: Write> {
+    writer: W,
+    stats: ReportStats,
+}
+
+impl<W: Write> HtmlReport<W> {
+    /// Starts a report with the title `title` in `writer`.
+    pub fn new(mut writer: W, title: &str) -> io::Result<Self> {
+        let title = escape_html(title);
+        write!(
+            writer,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+             <table>\n<tr><th>#</th><th>Original</th><th>Romanized</th></tr>\n"
+        )?;
+        Ok(Self {
+            writer,
+            stats: ReportStats::default(),
+        })
+    }
+
+    /// Romanizes `line`, a line of a file, with `options` as
+    /// [`Uroman::romanize_file_with_options`] does, and adds it to the report. Only the text
+    /// that is romanized shows in its romanization: utterance IDs, directives and the markup
+    /// of structured formats are left out, and each field of a structured format is shown on
+    /// a line of its own.
+    ///
+    /// # Errors
+    /// Returns the errors of writing the report, and [`RomanizationError::InvalidJsonLine`] for
+    /// lines that can't be romanized.
+    pub fn add_line(
+        &mut self,
+        uroman: &Uroman,
+        line: &str,
+        options: &RomanizeOptions,
+    ) -> Result<(), RomanizationError> {
+        let mut texts = Vec::new();
+        let mut greedy = false;
+        uroman.for_each_file_line_text(line, options, |text, options| {
+            let (romanized, text_greedy) = self.add_text(uroman, text, options);
+            texts.push(romanized);
+            greedy |= text_greedy;
+        })?;
+        self.stats.lines += 1;
+        self.stats.greedy_lines += usize::from(greedy);
+        writeln!(
+            self.writer,
+            "<tr><td class=\"n\">{}</td><td dir=\"auto\">{}</td><td>{}</td></tr>",
+            self.stats.lines,
+            escape_html(line),
+            texts.join("<br>")
+        )?;
+        Ok(())
+    }
+
+    /// Romanizes `text` with `options`, counts its spans in the statistics and returns its
+    /// romanization as HTML, and whether it was romanized greedily.
+    fn add_text(
+        &mut self,
+        uroman: &Uroman,
+        text: &str,
+        options: &RomanizeOptions,
+    ) -> (String, bool) {
+        let segments = inline_lcode_segments(text, options);
+        let segments: Vec<(&str, Option<&str>)> = segments
+            .iter()
+            .map(|(segment, lcode)| (segment.as_ref(), *lcode))
+            .collect();
+        let policies = options.policies(uroman);
+        let output = uroman.romanize_segments::<rom_format::Edges>(&segments, policies);
+        let greedy = output.used_greedy_fallback();
+        let RomanizationResult::Edges(edges) = output.result else {
+            unreachable!("the edges format returns edges");
+        };
+        let candidates = if greedy {
+            HashMap::default()
+        } else {
+            span_candidates(uroman, &segments, policies)
+        };
+        // The edges index into the text without its markers and with its escapes decoded.
+        let chars: Vec<char> = segments
+            .iter()
+            .flat_map(|(segment, _)| segment.chars())
+            .collect();
+
+        self.stats.chars += chars.len();
+        let mut romanized = String::new();
+        for edge in &edges {
+            let orig: String = chars[edge.start()..edge.end()].iter().collect();
+            let n_candidates = candidates
+                .get(&(edge.start(), edge.end()))
+                .map_or(1, HashSet::len)
+                .max(1);
+            let confidence = 1.0 / n_candidates as f64;
+            let unromanized = edge.txt() == orig && !orig.is_ascii();
+            let source = uroman
+                .rule_provenance(&orig, edge.txt())
+                .unwrap_or_else(|| edge.r#type());
+
+            self.stats.spans += 1;
+            self.stats.ambiguous_spans += usize::from(n_candidates > 1);
+            self.stats.low_confidence_spans += usize::from(confidence < LOW_CONFIDENCE);
+            if unromanized {
+                self.stats.unromanized_chars += orig.chars().filter(|c| !c.is_ascii()).count();
+            }
+            *self
+                .stats
+                .spans_by_source
+                .entry(source.to_string())
+                .or_default() += 1;
+
+            let class = if unromanized {
+                "s unrom"
+            } else if confidence < LOW_CONFIDENCE {
+                "s low"
+            } else if n_candidates > 1 {
+                "s alt"
+            } else {
+                "s"
+            };
+            let tooltip = format!(
+                "{orig} → {}\nsource: {}\nconfidence: {confidence:.2} ({n_candidates} {})",
+                edge.txt(),
+                describe_source(source),
+                if n_candidates == 1 {
+                    "candidate"
+                } else {
+                    "candidates"
+                },
+            );
+            romanized.push_str(&format!(
+                "<span class=\"{class}\" title=\"{}\">{}</span>",
+                escape_html(&tooltip),
+                escape_html(edge.txt())
+            ));
+        }
+        (romanized, greedy)
+    }
+
+    /// The statistics of the lines added so far.
+    pub fn stats(&self) -> &ReportStats {
+        &self.stats
+    }
+
+    /// Ends the report with its statistics, and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let stats = &self.stats;
+        writeln!(self.writer, "</table>\n<h2>Summary</h2>\n<table>")?;
+        for (name, value) in [
+            ("Lines", stats.lines),
+            ("Characters", stats.chars),
+            ("Spans", stats.spans),
+            ("Spans with alternatives", stats.ambiguous_spans),
+            ("Spans with low confidence", stats.low_confidence_spans),
+            ("Characters left unromanized", stats.unromanized_chars),
+            ("Lines romanized greedily", stats.greedy_lines),
+        ] {
+            writeln!(self.writer, "<tr><th>{name}</th><td>{value}</td></tr>")?;
+        }
+        writeln!(self.writer, "</table>\n<h2>Spans by source</h2>\n<table>")?;
+        for (source, count) in &stats.spans_by_source {
+            writeln!(
+                self.writer,
+                "<tr><th>{}</th><td>{count}</td></tr>",
+                escape_html(&describe_source(source))
+            )?;
+        }
+        writeln!(self.writer, "</table>\n</body>\n</html>")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl Uroman {
+    /// Returns the provenance of the first rule that romanizes `s` as `t`, if any.
+    pub(crate) fn rule_provenance(&self, s: &str, t: &str) -> Option<&str> {
+        self.rom_rules
+            .get(s)?
+            .iter()
+            .find(|rule| rule.t.as_deref() == Some(t) || rule.t_alts.iter().any(|alt| alt == t))
+            .map(|rule| &*rule.prov)
+    }
+}
+
+/// Returns the different romanizations that the lattice of `segments` has for each of its
+/// spans.
+fn span_candidates(
+    uroman: &Uroman,
+    segments: &[(&str, Option<&str>)],
+    policies: Policies,
+) -> HashMap<(usize, usize), HashSet<String>> {
+    let output = uroman.romanize_segments::<rom_format::Lattice>(segments, policies);
+    let RomanizationResult::Edges(edges) = output.result else {
+        unreachable!("the lattice format returns edges");
+    };
+    let mut candidates: HashMap<(usize, usize), HashSet<String>> = HashMap::default();
+    for edge in edges.iter().filter(|edge| edge.is_active()) {
+        candidates
+            .entry((edge.start(), edge.end()))
+            .or_default()
+            .insert(edge.txt().to_string());
+    }
+    candidates
+}
+
+/// Describes a provenance of rules, or another kind of edge, for the report.
+fn describe_source(source: &str) -> String {
+    let description = match source {
+        "ud" => "table generated from the Unicode data",
+        "ow" => "overwrites of the Unicode data",
+        "man" => "hand-written table",
+        OVERRIDE_PROV => "user overrides",
+        PINYIN_PROV => "pinyin table",
+        _ => return source.to_string(),
+    };
+    format!("{source} ({description})")
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
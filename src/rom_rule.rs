@@ -1,21 +1,24 @@
 //! Defines the `RomRule` struct and related parsing logic.
 
-use indexmap::IndexMap;
+use std::cmp::Ordering;
+use std::sync::Arc;
 
+use crate::hash::IndexMap;
 use crate::{
-    Uroman,
-    utils::{
-        dequote_string, has_value_in_double_colon_del_list, slot_value_in_double_colon_del_list,
-    },
+    OVERRIDE_PROV, PINYIN_PROV, RulePriority, Uroman,
+    data_table::{self, RomRecord},
+    intern::intern,
+    utils::dequote_string,
 };
 
 #[allow(unused)]
 /// Represents a single romanization rule parsed from the data files.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub(super) struct RomRule {
     pub s: String,
     pub t: Option<String>,
-    pub prov: String,
+    pub prov: Arc<str>,
     pub lcodes: Vec<String>,
     pub use_only_at_start_of_word: bool,
     pub dont_use_at_start_of_word: bool,
@@ -33,15 +36,44 @@ pub(super) struct RomRule {
     pub int_frac_connector: bool,
     pub is_large_power: bool,
     pub t_at_end_of_syllable: Option<String>,
+    /// See [`Uroman::set_rule_weight`].
+    pub weight: f64,
 }
 
 impl RomRule {
+    /// Compares two rules that can romanize the same span by their weights and then by
+    /// `priority`, with the one to prefer first. Rules that tie are `Equal`, so the first
+    /// loaded one is kept.
+    pub fn cmp_priority(&self, other: &RomRule, priority: &[RulePriority]) -> Ordering {
+        let by_priority = priority.iter().map(|criterion| match criterion {
+            RulePriority::LanguageMatch => self.lcodes.is_empty().cmp(&other.lcodes.is_empty()),
+            RulePriority::Restrictions => other.n_restr.cmp(&self.n_restr),
+            RulePriority::Provenance => self.provenance_rank().cmp(&other.provenance_rank()),
+        });
+        std::iter::once(other.weight.total_cmp(&self.weight))
+            .chain(by_priority)
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// The rank of the provenance of the rule for [`RulePriority::Provenance`], lowest first.
+    fn provenance_rank(&self) -> u8 {
+        match &*self.prov {
+            OVERRIDE_PROV => 0,
+            "man" => 1,
+            "ow" => 2,
+            "ud" => 3,
+            PINYIN_PROV => 4,
+            _ => 5,
+        }
+    }
+
     /// Creates a simple `RomRule` with default values for most fields.
     pub fn new_simple(s: String, t: &str, provenance: &str) -> Self {
         Self {
             s,
             t: Some(t.to_string()),
-            prov: provenance.to_string(),
+            prov: intern(provenance),
             lcodes: Vec::new(),
             use_only_at_start_of_word: false,
             dont_use_at_start_of_word: false,
@@ -59,6 +91,7 @@ impl RomRule {
             int_frac_connector: false,
             is_large_power: false,
             t_at_end_of_syllable: None,
+            weight: 0.0,
         }
     }
 
@@ -73,32 +106,46 @@ impl RomRule {
             && !self.use_only_for_whole_word
     }
 
-    pub fn from_line(
-        line: &str,
-        provenance: &str,
-        file_format: &str,
-        uroman: &mut Uroman,
-    ) -> Option<Self> {
-        let (s, t) = if file_format == "u2r" {
-            let u_str = slot_value_in_double_colon_del_list(line, "u")?;
-            let cp = u32::from_str_radix(u_str, 16).ok()?;
-            let s = std::char::from_u32(cp)?.to_string();
-            let t = slot_value_in_double_colon_del_list(line, "r")
-                .map(|s_val| dequote_string(s_val).to_string());
-            (s, t)
-        } else {
-            let s = slot_value_in_double_colon_del_list(line, "s")
-                .map(|s_val| dequote_string(s_val).to_string())?;
-            let t = slot_value_in_double_colon_del_list(line, "t")
-                .map(|s_val| dequote_string(s_val).to_string());
-            (s, t)
-        };
-
-        // println!("DEBUG: RomRule::from_line - s: '{}', t: {:?}, prov: {}", s, t, provenance);
-
-        let t = uroman.second_rom_filter(&s, t.as_deref());
-
-        let lcodes: Vec<String> = slot_value_in_double_colon_del_list(line, "lcode")
+    /// Whether the rule applies in the same contexts as `other`: for the same language codes
+    /// and positions in a word.
+    pub fn has_same_conditions(&self, other: &RomRule) -> bool {
+        self.lcodes == other.lcodes
+            && self.use_only_at_start_of_word == other.use_only_at_start_of_word
+            && self.dont_use_at_start_of_word == other.dont_use_at_start_of_word
+            && self.use_only_at_end_of_word == other.use_only_at_end_of_word
+            && self.dont_use_at_end_of_word == other.dont_use_at_end_of_word
+            && self.use_only_for_whole_word == other.use_only_for_whole_word
+    }
+
+    /// The conditions on the context of the rule, e.g. `["lcode: ukr,bel",
+    /// "use-only-at-start-of-word"]`, see [`EdgeData::conditions`](crate::edge::EdgeData).
+    pub fn conditions(&self) -> Vec<String> {
+        let positions = [
+            self.use_only_at_start_of_word,
+            self.dont_use_at_start_of_word,
+            self.use_only_at_end_of_word,
+            self.dont_use_at_end_of_word,
+            self.use_only_for_whole_word,
+        ];
+        let lcodes = (!self.lcodes.is_empty()).then(|| format!("lcode: {}", self.lcodes.join(",")));
+        lcodes
+            .into_iter()
+            .chain(
+                crate::RULE_CONDITIONS
+                    .iter()
+                    .zip(positions)
+                    .filter(|&(_, holds)| holds)
+                    .map(|(condition, _)| condition.to_string()),
+            )
+            .collect()
+    }
+
+    /// Builds a rule from a record of a compiled romanization table.
+    pub fn from_record(rec: &RomRecord, provenance: &str, uroman: &mut Uroman) -> Self {
+        let t = uroman.second_rom_filter(rec.s, rec.t);
+
+        let lcodes: Vec<String> = rec
+            .lcode
             .map(|s| {
                 s.split([',', ';'])
                     .map(|part| part.trim().to_string())
@@ -106,18 +153,14 @@ impl RomRule {
             })
             .unwrap_or_default();
 
-        let use_only_at_start_of_word =
-            has_value_in_double_colon_del_list(line, "use-only-at-start-of-word");
-        let dont_use_at_start_of_word =
-            has_value_in_double_colon_del_list(line, "dont-use-at-start-of-word");
-        let use_only_at_end_of_word =
-            has_value_in_double_colon_del_list(line, "use-only-at-end-of-word");
-        let dont_use_at_end_of_word =
-            has_value_in_double_colon_del_list(line, "dont-use-at-end-of-word");
-        let use_only_for_whole_word =
-            has_value_in_double_colon_del_list(line, "use-only-for-whole-word");
-
-        let t_alts: Vec<String> = slot_value_in_double_colon_del_list(line, "t-alt")
+        let use_only_at_start_of_word = rec.has_flag(data_table::USE_ONLY_AT_START_OF_WORD);
+        let dont_use_at_start_of_word = rec.has_flag(data_table::DONT_USE_AT_START_OF_WORD);
+        let use_only_at_end_of_word = rec.has_flag(data_table::USE_ONLY_AT_END_OF_WORD);
+        let dont_use_at_end_of_word = rec.has_flag(data_table::DONT_USE_AT_END_OF_WORD);
+        let use_only_for_whole_word = rec.has_flag(data_table::USE_ONLY_FOR_WHOLE_WORD);
+
+        let t_alts: Vec<String> = rec
+            .t_alt
             .map(|s| {
                 s.split([',', ';'])
                     .map(|part| dequote_string(part).to_string())
@@ -125,18 +168,7 @@ impl RomRule {
             })
             .unwrap_or_default();
 
-        let num = slot_value_in_double_colon_del_list(line, "num")
-            .and_then(crate::utils::robust_str_to_num);
-
-        let is_minus_sign = has_value_in_double_colon_del_list(line, "is-minus-sign");
-        let is_plus_sign = has_value_in_double_colon_del_list(line, "is-plus-sign");
-        let is_decimal_point = has_value_in_double_colon_del_list(line, "is-decimal-point");
-        let fraction_connector = has_value_in_double_colon_del_list(line, "fraction-connector");
-        let percentage_marker = has_value_in_double_colon_del_list(line, "percentage-marker");
-        let int_frac_connector = has_value_in_double_colon_del_list(line, "int-frac-connector");
-        let is_large_power = has_value_in_double_colon_del_list(line, "is-large-power");
-        let t_at_end_of_syllable = slot_value_in_double_colon_del_list(line, "t-end-of-syllable")
-            .map(|s_val| dequote_string(s_val).to_string());
+        let num = rec.num.and_then(crate::utils::robust_str_to_num);
 
         // Calculate the number of restrictions in a more declarative way
         let n_restr = [
@@ -151,10 +183,10 @@ impl RomRule {
         .filter(|&&is_restr| is_restr)
         .count();
 
-        Some(RomRule {
-            s,
+        RomRule {
+            s: rec.s.to_string(),
             t,
-            prov: provenance.to_string(),
+            prov: intern(provenance),
             lcodes,
             use_only_at_start_of_word,
             dont_use_at_start_of_word,
@@ -164,15 +196,16 @@ impl RomRule {
             n_restr,
             t_alts,
             num,
-            is_minus_sign,
-            is_plus_sign,
-            is_decimal_point,
-            fraction_connector,
-            percentage_marker,
-            int_frac_connector,
-            is_large_power,
-            t_at_end_of_syllable,
-        })
+            is_minus_sign: rec.has_flag(data_table::IS_MINUS_SIGN),
+            is_plus_sign: rec.has_flag(data_table::IS_PLUS_SIGN),
+            is_decimal_point: rec.has_flag(data_table::IS_DECIMAL_POINT),
+            fraction_connector: rec.has_flag(data_table::FRACTION_CONNECTOR),
+            percentage_marker: rec.has_flag(data_table::PERCENTAGE_MARKER),
+            int_frac_connector: rec.has_flag(data_table::INT_FRAC_CONNECTOR),
+            is_large_power: rec.has_flag(data_table::IS_LARGE_POWER),
+            t_at_end_of_syllable: rec.t_at_end_of_syllable.map(|s| s.to_string()),
+            weight: 0.0,
+        }
     }
 }
 
@@ -0,0 +1,59 @@
+//! Defines `RuleMatcher`, an Aho-Corasick automaton over multi-character rule sources.
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+
+use crate::rom_rule::RomRules;
+
+/// Finds all occurrences of multi-character rule sources in a line in a single pass.
+///
+/// Complements `RuleTrie`: instead of extending a prefix at every start position,
+/// the lattice asks for all candidate spans at once.
+#[derive(Debug, Clone)]
+pub(crate) struct RuleMatcher {
+    automaton: AhoCorasick,
+}
+
+impl RuleMatcher {
+    /// Builds the automaton over all rule sources longer than one character.
+    pub fn new(rom_rules: &RomRules) -> Option<Self> {
+        let patterns: Vec<&str> = rom_rules
+            .iter()
+            .filter(|(s, rules)| !rules.is_empty() && s.chars().nth(1).is_some())
+            .map(|(s, _)| s.as_str())
+            .collect();
+
+        let automaton = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::Standard)
+            .build(patterns)
+            .ok()?;
+
+        Some(Self { automaton })
+    }
+
+    /// Returns, for every char position of `s`, the char end positions of all
+    /// multi-character rule sources starting there, in ascending order.
+    ///
+    /// `char_offsets` holds the byte offset of each char of `s`, followed by `s.len()`.
+    pub fn candidate_ends(&self, s: &str, char_offsets: &[usize]) -> Vec<Vec<usize>> {
+        let n_chars = char_offsets.len() - 1;
+        let mut ends: Vec<Vec<usize>> = vec![Vec::new(); n_chars];
+        if n_chars < 2 {
+            return ends;
+        }
+
+        // Byte offset -> char index, including the end-of-string boundary.
+        let mut char_index = vec![0; s.len() + 1];
+        for (i, &byte_pos) in char_offsets.iter().enumerate() {
+            char_index[byte_pos] = i;
+        }
+
+        for m in self.automaton.find_overlapping_iter(s) {
+            ends[char_index[m.start()]].push(char_index[m.end()]);
+        }
+        for e in &mut ends {
+            e.sort_unstable();
+            e.dedup();
+        }
+        ends
+    }
+}
@@ -0,0 +1,76 @@
+//! Defines `RuleTrie`, a character trie over the source strings of the romanization rules.
+
+use crate::hash::{HashMap, RandomState};
+
+/// A character trie mapping each rule source string `s` to its index in `RomRules`.
+///
+/// This replaces registering every prefix of every rule source as a separate string key.
+/// Walking the trie one character at a time tells the lattice both whether a longer match
+/// is still possible and, if the current prefix is itself a rule source, where its rules are.
+#[derive(Debug, Clone)]
+pub(crate) struct RuleTrie {
+    /// `(node, char) -> child node`
+    children: HashMap<(u32, char), u32>,
+    /// `node -> index into RomRules`, set if the path to the node is a rule source.
+    values: Vec<Option<usize>>,
+}
+
+impl Default for RuleTrie {
+    fn default() -> Self {
+        Self {
+            children: HashMap::default(),
+            values: vec![None],
+        }
+    }
+}
+
+impl RuleTrie {
+    pub const ROOT: u32 = 0;
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut values = Vec::with_capacity(capacity + 1);
+        values.push(None);
+        Self {
+            children: HashMap::with_capacity_and_hasher(capacity, RandomState::default()),
+            values,
+        }
+    }
+
+    /// Inserts `s`, associating it with the rule index `value`.
+    pub fn insert(&mut self, s: &str, value: usize) {
+        let mut node = Self::ROOT;
+        for c in s.chars() {
+            node = match self.children.get(&(node, c)) {
+                Some(&child) => child,
+                None => {
+                    let child = self.values.len() as u32;
+                    self.values.push(None);
+                    self.children.insert((node, c), child);
+                    child
+                }
+            };
+        }
+        self.values[node as usize] = Some(value);
+    }
+
+    /// Returns the child of `node` reached via `c`, if any rule source continues that way.
+    #[inline]
+    pub fn child(&self, node: u32, c: char) -> Option<u32> {
+        self.children.get(&(node, c)).copied()
+    }
+
+    /// Returns the rule index stored at `node`, if the path to it is a rule source.
+    #[inline]
+    pub fn value(&self, node: u32) -> Option<usize> {
+        self.values.get(node as usize).copied().flatten()
+    }
+
+    /// Looks up the rule index of the exact source `chars`.
+    pub fn get(&self, chars: &[char]) -> Option<usize> {
+        let mut node = Self::ROOT;
+        for &c in chars {
+            node = self.child(node, c)?;
+        }
+        self.value(node)
+    }
+}
@@ -0,0 +1,90 @@
+//! Weights of romanization rules, to nudge the choice between them without deleting any.
+
+use std::io::BufRead;
+
+use crate::data_table::{is_skipped_line, slot_value_in_double_colon_del_list};
+use crate::utils::LossyLines;
+use crate::{InvalidUtf8Policy, RomanizationError, Uroman};
+
+impl Uroman {
+    /// Sets the weight of the rules that romanize `s` as `t`, and that are conditioned on
+    /// `lcode` if it is given. Rules weigh 0 by default.
+    ///
+    /// Of the rules for the same span of text, those with higher weights are used first, before
+    /// [`Uroman::with_rule_priority`] decides. Rules with negative weights are also avoided by
+    /// the search for the best path: a shorter span is romanized instead where one can be.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::UnknownRule`] if no rule matches.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let mut uroman = Uroman::new();
+    /// let romanize = |uroman: &Uroman| {
+    ///     uroman.romanize_string::<rom_format::Str>("αγκάθι", None).to_output_string()
+    /// };
+    /// assert_eq!(romanize(&uroman), "angathi");
+    /// // Romanize `γ` and `κ` one by one instead.
+    /// uroman.set_rule_weight("γκ", "ng", None, -1.0).unwrap();
+    /// assert_eq!(romanize(&uroman), "agkathi");
+    /// ```
+    pub fn set_rule_weight(
+        &mut self,
+        s: &str,
+        t: &str,
+        lcode: Option<&str>,
+        weight: f64,
+    ) -> Result<(), RomanizationError> {
+        let rules = self
+            .rom_rules
+            .get_mut(s)
+            .into_iter()
+            .flatten()
+            .filter(|rule| rule.t.as_deref() == Some(t))
+            .filter(|rule| lcode.is_none_or(|lcode| rule.lcodes.iter().any(|lc| lc == lcode)));
+        let mut n_rules = 0;
+        for rule in rules {
+            rule.weight = weight;
+            n_rules += 1;
+        }
+        if n_rules == 0 {
+            return Err(RomanizationError::UnknownRule {
+                s: s.to_string(),
+                t: t.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Sets the weights of rules from an overlay file, with a line for each in the format of
+    /// the romanization tables, e.g. `::s ий ::t yi ::lcode ukr ::weight -1`, see
+    /// [`Uroman::set_rule_weight`]. `::lcode` is optional, and `#` starts a comment line.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::InvalidRuleWeight`] for a line without a source, target or
+    /// numeric weight, and [`RomanizationError::UnknownRule`] for a line that matches no rule,
+    /// wrapped in [`RomanizationError::AtLine`]. The weights of the lines before it are set.
+    pub fn load_rule_weights<R: BufRead>(&mut self, reader: R) -> Result<(), RomanizationError> {
+        let mut lines = LossyLines::new(reader)
+            .with_invalid_utf8(InvalidUtf8Policy::Error)
+            .quiet();
+        while let Some(line) = lines.next_line()? {
+            if is_skipped_line(line) {
+                continue;
+            }
+            let slot = |name| slot_value_in_double_colon_del_list(line, name);
+            let weight = slot("weight").and_then(|weight| weight.parse().ok());
+            let result = match (slot("s"), slot("t"), weight) {
+                (Some(s), Some(t), Some(weight)) => {
+                    let lcode = slot("lcode").filter(|lcode| !lcode.is_empty());
+                    self.set_rule_weight(s, t, lcode, weight)
+                }
+                _ => Err(RomanizationError::InvalidRuleWeight(line.to_string())),
+            };
+            result.map_err(|e| lines.error_at_current_line(e))?;
+        }
+        Ok(())
+    }
+}
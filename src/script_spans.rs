@@ -0,0 +1,119 @@
+//! Segmenting text by script, without romanizing it, and flagging words that mix scripts.
+
+use std::ops::Range;
+
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+use crate::Uroman;
+
+/// The script name of the spans of chars that belong to no script, such as spaces,
+/// punctuation and digits, see [`Uroman::script_spans`].
+pub const COMMON_SCRIPT: &str = "Common";
+
+/// Scripts that are written together within words, so that mixing them is not flagged by
+/// [`Uroman::mixed_script_tokens`].
+const SCRIPTS_WRITTEN_TOGETHER: [&[&str]; 3] = [
+    &["CJK", "Hiragana", "Katakana"],
+    &["CJK", "Hangul"],
+    &["CJK", "Bopomofo"],
+];
+
+impl Uroman {
+    /// Splits `s` into spans of chars of the same script, e.g. to find the Cyrillic in mixed
+    /// text. Ranges are char offsets, like edge offsets, and the spans cover all of `s` in order.
+    ///
+    /// Chars without a script of their own, such as spaces, punctuation and digits, are
+    /// [`COMMON_SCRIPT`]. They are part of the span around them if the script on both sides is
+    /// the same, and a span of their own otherwise. Combining marks and joiners are part of the
+    /// span of the char before them. Script names are those of the `script-name` slots of the
+    /// data tables, e.g. `Cyrillic` or `Devanagari`. Chinese characters and Hangul only have
+    /// their scripts with the `cjk` and `hangul` features.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// let spans = uroman.script_spans("Київ, Львів (Kyiv) 2024");
+    /// assert_eq!(
+    ///     spans,
+    ///     [(0..11, "Cyrillic"), (11..13, "Common"), (13..17, "Latin"), (17..23, "Common")]
+    /// );
+    /// ```
+    pub fn script_spans(&self, s: &str) -> Vec<(Range<usize>, &str)> {
+        let mut spans: Vec<(Range<usize>, &str)> = Vec::new();
+        // Common chars whose span depends on the script after them.
+        let mut common: Option<Range<usize>> = None;
+        for (i, c) in s.chars().enumerate() {
+            let script = self.chr_script_name(c);
+            if script.is_empty() {
+                match (&mut common, spans.last_mut()) {
+                    (None, Some((range, _))) if is_inherited(c) => range.end = i + 1,
+                    (common, _) => common.get_or_insert(i..i).end = i + 1,
+                }
+                continue;
+            }
+            match spans.last_mut() {
+                Some((range, last_script)) if *last_script == script => range.end = i + 1,
+                _ => {
+                    spans.extend(common.take().map(|range| (range, COMMON_SCRIPT)));
+                    spans.push((i..i + 1, script));
+                }
+            }
+            common = None;
+        }
+        spans.extend(common.map(|range| (range, COMMON_SCRIPT)));
+        spans
+    }
+
+    /// Finds the words of `s` that mix scripts, such as `pаypal` with a Cyrillic `а`, which
+    /// are a sign of spoofing. Returns the char range of each, with its scripts in the order
+    /// they first occur.
+    ///
+    /// Words are runs of letters, digits and the combining marks on them. Chars without a
+    /// script, such as digits, mix with any script, and so do Chinese characters with kana,
+    /// Hangul and Bopomofo, which are written together.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::Uroman;
+    ///
+    /// let uroman = Uroman::new();
+    /// let tokens = uroman.mixed_script_tokens("Log in to pаypal2 at 東京タワー");
+    /// assert_eq!(tokens, [(10..17, vec!["Latin", "Cyrillic"])]);
+    /// ```
+    pub fn mixed_script_tokens(&self, s: &str) -> Vec<(Range<usize>, Vec<&str>)> {
+        let mut tokens = Vec::new();
+        let mut token: Option<(Range<usize>, Vec<&str>)> = None;
+        // A trailing space ends the last word.
+        for (i, c) in s.chars().chain([' ']).enumerate() {
+            if c.is_alphanumeric() || is_inherited(c) {
+                let (range, scripts) = token.get_or_insert_with(|| (i..i, Vec::new()));
+                range.end = i + 1;
+                let script = self.chr_script_name(c);
+                if !script.is_empty() && !scripts.contains(&script) {
+                    scripts.push(script);
+                }
+            } else if let Some((range, scripts)) = token.take()
+                && is_mixed(&scripts)
+            {
+                tokens.push((range, scripts));
+            }
+        }
+        tokens
+    }
+}
+
+/// Whether the scripts of a word are a mix that is not usually written together.
+fn is_mixed(scripts: &[&str]) -> bool {
+    scripts.len() > 1
+        && !SCRIPTS_WRITTEN_TOGETHER
+            .iter()
+            .any(|together| scripts.iter().all(|script| together.contains(script)))
+}
+
+/// Whether `c` takes the script of the char before it: a combining mark, or a zero-width
+/// (non-)joiner.
+fn is_inherited(c: char) -> bool {
+    matches!(c, '\u{200C}' | '\u{200D}') || c.general_category_group() == GeneralCategoryGroup::Mark
+}
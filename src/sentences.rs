@@ -0,0 +1,81 @@
+//! Splitting long text into sentences that are romanized one by one, see
+//! [`Uroman::with_sentence_chunks`](crate::Uroman::with_sentence_chunks).
+
+/// Chars that end a sentence when whitespace or the end of the text follows them: the Latin
+/// ones, the Armenian full stop `։` and question mark `՞`, the Arabic question mark `؟` and
+/// full stop `۔`, the Devanagari dandas `।` and `॥`, the Ethiopic full stop `።` and question
+/// mark `፧`, the Myanmar section mark `။` and the Khmer signs `។` and `៕`.
+const TERMINATORS: &[char] = &[
+    '.', '!', '?', '…', '։', '՞', '؟', '۔', '।', '॥', '።', '፧', '။', '។', '៕',
+];
+
+/// Chars that end a sentence wherever they are, as in scripts written without spaces: the
+/// ideographic full stop `。` and the fullwidth `！` and `？`.
+const FULLWIDTH_TERMINATORS: &[char] = &['。', '！', '？', '｡'];
+
+/// Whether `c` stays with the end of a sentence after its terminator, as closing quotes do.
+fn is_closing(c: char) -> bool {
+    matches!(
+        c,
+        '"' | '\'' | ')' | ']' | '}' | '»' | '”' | '’' | '」' | '』' | '）' | '》'
+    )
+}
+
+/// Splits text into sentences whose concatenation is the text, so that the separators between
+/// them are kept exactly. A sentence ends after a run of terminators, the closing quotes and
+/// brackets after them and the whitespace after those.
+///
+/// Text of at most `min_chars` chars, and all text if `min_chars` is `None`, is a single
+/// sentence.
+pub(crate) struct Sentences<'a> {
+    rest: Option<&'a str>,
+    split: bool,
+}
+
+impl<'a> Sentences<'a> {
+    pub(crate) fn new(s: &'a str, min_chars: Option<usize>) -> Self {
+        Self {
+            rest: Some(s),
+            split: min_chars.is_some_and(|min| s.len() > min && s.chars().nth(min).is_some()),
+        }
+    }
+}
+
+impl<'a> Iterator for Sentences<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.rest?;
+        if !self.split {
+            return self.rest.take();
+        }
+
+        let mut chars = rest.char_indices().peekable();
+        while let Some((_, c)) = chars.next() {
+            let is_fullwidth = FULLWIDTH_TERMINATORS.contains(&c);
+            if !is_fullwidth && !TERMINATORS.contains(&c) {
+                continue;
+            }
+            while chars
+                .next_if(|&(_, c)| {
+                    TERMINATORS.contains(&c) || FULLWIDTH_TERMINATORS.contains(&c) || is_closing(c)
+                })
+                .is_some()
+            {}
+            let is_end = match chars.peek() {
+                Some(&(_, c)) => is_fullwidth || c.is_whitespace(),
+                None => true,
+            };
+            if !is_end {
+                continue;
+            }
+            while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+            let Some(&(end, _)) = chars.peek() else {
+                break;
+            };
+            self.rest = Some(&rest[end..]);
+            return Some(&rest[..end]);
+        }
+        self.rest.take()
+    }
+}
@@ -0,0 +1,105 @@
+//! Integration with tower and axum.
+
+use std::convert::Infallible;
+use std::future::{Ready, ready};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tower_service::Service;
+
+use crate::{RomFormat, RomanizationResult, RomanizerBackend, Uroman};
+
+/// A [`tower_service::Service`] that romanizes the request text, so that the romanizer can be
+/// wrapped with tower middleware such as timeouts, rate limits and metrics.
+///
+/// The service is cheap to clone and shares one `Uroman`, or another [`RomanizerBackend`]. The
+/// text is romanized synchronously in `call`, which blocks the calling task, so limit the
+/// request size if clients may send large texts.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use tower_service::Service;
+/// use uroman::{RomanizationResult, RomanizeService, Uroman};
+///
+/// let mut service = RomanizeService::new(Arc::new(Uroman::new())).with_lcode("ukr");
+/// let result = service.call("Київ".to_string()).into_inner().unwrap();
+/// assert_eq!(result, RomanizationResult::Str("Kyiv".to_string()));
+/// ```
+pub struct RomanizeService<B: RomanizerBackend + ?Sized = Uroman> {
+    backend: Arc<B>,
+    lcode: Option<Arc<str>>,
+    rom_format: RomFormat,
+}
+
+impl<B: RomanizerBackend + ?Sized> Clone for RomanizeService<B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            lcode: self.lcode.clone(),
+            rom_format: self.rom_format,
+        }
+    }
+}
+
+impl<B: RomanizerBackend + ?Sized> RomanizeService<B> {
+    /// Creates a service that romanizes to [`RomFormat::Str`] without a language code.
+    pub fn new(backend: Arc<B>) -> Self {
+        Self {
+            backend,
+            lcode: None,
+            rom_format: RomFormat::Str,
+        }
+    }
+
+    /// Romanizes all requests with the ISO 639-3 language code `lcode`.
+    pub fn with_lcode(mut self, lcode: &str) -> Self {
+        self.lcode = Some(lcode.into());
+        self
+    }
+
+    /// Romanizes all requests to `rom_format`.
+    pub fn with_format(mut self, rom_format: RomFormat) -> Self {
+        self.rom_format = rom_format;
+        self
+    }
+}
+
+impl<B: RomanizerBackend + ?Sized> Service<String> for RomanizeService<B> {
+    type Response = RomanizationResult;
+    type Error = Infallible;
+    type Future = Ready<Result<RomanizationResult, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, text: String) -> Self::Future {
+        ready(Ok(self.backend.romanize_format(
+            &text,
+            self.lcode.as_deref(),
+            self.rom_format,
+        )))
+    }
+}
+
+#[cfg(feature = "axum")]
+impl axum_core::response::IntoResponse for RomanizationResult {
+    /// Responds with the romanized string as plain text, or with the edges as JSON.
+    fn into_response(self) -> axum_core::response::Response {
+        use http::{HeaderValue, StatusCode, header};
+
+        let content_type = match self {
+            RomanizationResult::Str(_) => "text/plain; charset=utf-8",
+            RomanizationResult::Edges(_) => "application/json",
+        };
+        match self.to_output_string() {
+            Ok(body) => (
+                [(header::CONTENT_TYPE, HeaderValue::from_static(content_type))],
+                body,
+            )
+                .into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Saving and restoring the fully loaded `Uroman` state.
+//!
+//! Only the loaded tables are stored. The derived lookup structures (`RuleTrie` and
+//! `RuleMatcher`) are rebuilt on load.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hash::{HashMap, HashSet};
+use crate::intern::intern;
+use crate::rom_rule::RomRules;
+use crate::rule_matcher::RuleMatcher;
+use crate::rule_trie::RuleTrie;
+use crate::{BoolProp, NumProp, RomanizationError, Script, StrProp, Uroman};
+
+/// Identifies the crate version and the data tables a snapshot was built from.
+const SNAPSHOT_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "+",
+    env!("UROMAN_DATA_FINGERPRINT")
+);
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    rom_rules: &'a RomRules,
+    scripts: &'a HashMap<String, Script>,
+    dict_bool: &'a HashMap<BoolProp, HashSet<String>>,
+    dict_str: &'a HashMap<(StrProp, char), Arc<str>>,
+    num_props: &'a HashMap<char, NumProp>,
+    percentage_markers: &'a HashSet<String>,
+    fraction_connectors: &'a HashSet<String>,
+    plus_signs: &'a HashSet<String>,
+    minus_signs: &'a HashSet<String>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    rom_rules: RomRules,
+    scripts: HashMap<String, Script>,
+    dict_bool: HashMap<BoolProp, HashSet<String>>,
+    dict_str: HashMap<(StrProp, char), Arc<str>>,
+    num_props: HashMap<char, NumProp>,
+    percentage_markers: HashSet<String>,
+    fraction_connectors: HashSet<String>,
+    plus_signs: HashSet<String>,
+    minus_signs: HashSet<String>,
+}
+
+impl Uroman {
+    /// Writes the fully loaded state, so that it can be restored with [`Uroman::load_state`]
+    /// instead of loading the data tables again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state cannot be encoded or written to `writer`.
+    pub fn save_state<W: Write>(&self, mut writer: W) -> Result<(), RomanizationError> {
+        let config = bincode::config::standard();
+        bincode::serde::encode_into_std_write(SNAPSHOT_VERSION, &mut writer, config)?;
+        let snapshot = SnapshotRef {
+            rom_rules: &self.rom_rules,
+            scripts: &self.scripts,
+            dict_bool: &self.dict_bool,
+            dict_str: &self.dict_str,
+            num_props: &self.num_props,
+            percentage_markers: &self.percentage_markers,
+            fraction_connectors: &self.fraction_connectors,
+            plus_signs: &self.plus_signs,
+            minus_signs: &self.minus_signs,
+        };
+        bincode::serde::encode_into_std_write(snapshot, &mut writer, config)?;
+        Ok(())
+    }
+
+    /// Restores a state written by [`Uroman::save_state`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RomanizationError::IncompatibleSnapshot`] if the snapshot was written by a
+    /// different version of the crate or with different data tables, and an error if it
+    /// cannot be read or decoded.
+    pub fn load_state<R: Read>(mut reader: R) -> Result<Self, RomanizationError> {
+        let config = bincode::config::standard();
+        let version: String = bincode::serde::decode_from_std_read(&mut reader, config)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(RomanizationError::IncompatibleSnapshot {
+                expected: SNAPSHOT_VERSION.to_string(),
+                found: version,
+            });
+        }
+        let mut snapshot: Snapshot = bincode::serde::decode_from_std_read(&mut reader, config)?;
+
+        // Deserialization allocates every provenance and property value separately, so share
+        // them again.
+        for rule in snapshot.rom_rules.values_mut().flatten() {
+            rule.prov = intern(&rule.prov);
+        }
+        for ((prop, _), value) in &mut snapshot.dict_str {
+            if *prop != StrProp::Name {
+                *value = intern(value);
+            }
+        }
+        let mut rule_trie = RuleTrie::with_capacity(snapshot.rom_rules.len());
+        for (index, s) in snapshot.rom_rules.keys().enumerate() {
+            rule_trie.insert(s, index);
+        }
+        let rule_matcher = RuleMatcher::new(&snapshot.rom_rules);
+
+        Ok(Self {
+            rom_rules: snapshot.rom_rules,
+            rule_trie,
+            rule_matcher,
+            scripts: snapshot.scripts,
+            dict_bool: snapshot.dict_bool,
+            dict_str: snapshot.dict_str,
+            num_props: snapshot.num_props,
+            percentage_markers: snapshot.percentage_markers,
+            fraction_connectors: snapshot.fraction_connectors,
+            plus_signs: snapshot.plus_signs,
+            minus_signs: snapshot.minus_signs,
+            ..Self::unloaded()
+        })
+    }
+}
@@ -0,0 +1,139 @@
+//! Romanizing text that must be romanized completely.
+
+use std::borrow::Cow;
+
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+use crate::lcode_spans::inline_lcodes_text;
+use crate::{
+    Edge, RomFormat, RomanizationError, RomanizationResult, RomanizeOptions, Uroman, rom_format,
+};
+
+impl Uroman {
+    /// Romanizes `s` like [`Uroman::romanize_string`] into a string, or fails if it has chars
+    /// that can't be romanized.
+    ///
+    /// Chars that pass through as they are count as romanized if they are ASCII, Latin or
+    /// punctuation, such as `é` or `«`, and not otherwise, such as `☃` or `€`.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::Unromanizable`] with the chars that can't be romanized.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{RomanizationError, Uroman};
+    ///
+    /// let uroman = Uroman::new();
+    /// assert_eq!(uroman.romanize_strict("«Київ»", Some("ukr")).unwrap(), "«Kyiv»");
+    /// let Err(RomanizationError::Unromanizable(chars)) = uroman.romanize_strict("Київ ☃ €", None)
+    /// else {
+    ///     panic!("expected unromanizable chars");
+    /// };
+    /// assert_eq!(chars, ['☃', '€']);
+    /// ```
+    pub fn romanize_strict(
+        &self,
+        s: &str,
+        lcode: Option<&str>,
+    ) -> Result<String, RomanizationError> {
+        let RomanizationResult::Edges(edges) =
+            self.romanize_string::<rom_format::Edges>(s, lcode).result
+        else {
+            unreachable!("the edge formats always yield edges");
+        };
+        self.check_edges_romanized(s, &edges)?;
+        Ok(edges.iter().map(Edge::txt).collect())
+    }
+
+    /// Checks that the text `s` can be romanized completely with `options`, such as its language
+    /// code and subsystems, like [`Uroman::romanize_strict`]. Inline language code markers
+    /// apply as they do in files.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::Unromanizable`] with the chars that can't be romanized.
+    pub fn check_romanizable(
+        &self,
+        s: &str,
+        options: &RomanizeOptions,
+    ) -> Result<(), RomanizationError> {
+        let options = options
+            .clone()
+            .with_format(RomFormat::Edges)
+            .with_strict(true);
+        self.romanize_checked(s, &options).map(drop)
+    }
+
+    /// Romanizes a line of `romanize_file` input like
+    /// [`Uroman::romanize_with_inline_lcodes`], and with `options.strict` fails if the
+    /// romanization has chars that can't be romanized. This is what [`RomanizeOptions::strict`]
+    /// checks for the text of each line of a file.
+    ///
+    /// The string format is the text of the edges of the best path, so those are checked
+    /// instead of romanizing the line twice, and the path that the alternatives of the alts
+    /// format follow is. The lattice format has no single path, so the best one is found
+    /// apart from it.
+    pub(crate) fn romanize_checked(
+        &self,
+        text: &str,
+        options: &RomanizeOptions,
+    ) -> Result<RomanizationResult, RomanizationError> {
+        if !options.output.strict {
+            return Ok(self.romanize_with_inline_lcodes(text, options));
+        }
+        let path_options = match options.output.rom_format {
+            RomFormat::Edges | RomFormat::Alts => Cow::Borrowed(options),
+            RomFormat::Str | RomFormat::Lattice => {
+                Cow::Owned(options.clone().with_format(RomFormat::Edges))
+            }
+        };
+        let result = self.romanize_with_inline_lcodes(text, &path_options);
+        let RomanizationResult::Edges(edges) = &result else {
+            unreachable!("the edge formats always yield edges");
+        };
+        self.check_edges_romanized(&inline_lcodes_text(text, options), edges)?;
+        Ok(match options.output.rom_format {
+            RomFormat::Str => RomanizationResult::Str(edges.iter().map(Edge::txt).collect()),
+            RomFormat::Lattice => self.romanize_with_inline_lcodes(text, options),
+            RomFormat::Edges | RomFormat::Alts => result,
+        })
+    }
+
+    /// Fails with the distinct chars of `s` that the best path `edges` pass through without
+    /// romanizing them, in the order they first appear. Edges after the path, which overlap
+    /// it, are left out.
+    fn check_edges_romanized(&self, s: &str, edges: &[Edge]) -> Result<(), RomanizationError> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut unromanizable: Vec<char> = Vec::new();
+        let mut path_end = 0;
+        for edge in edges {
+            if edge.start() < path_end {
+                continue;
+            }
+            path_end = edge.end();
+            if edge.r#type() != "orig" {
+                continue;
+            }
+            let c = chars[edge.start()];
+            let is_allowed = c.is_ascii()
+                || c.general_category_group() == GeneralCategoryGroup::Punctuation
+                || self.chr_script_name(c) == "Latin";
+            if !is_allowed && !unromanizable.contains(&c) {
+                unromanizable.push(c);
+            }
+        }
+        if unromanizable.is_empty() {
+            Ok(())
+        } else {
+            Err(RomanizationError::Unromanizable(unromanizable))
+        }
+    }
+}
+
+/// Formats the chars of an [`RomanizationError::Unromanizable`], e.g. `'☃' (U+2603)`.
+pub(crate) fn describe_chars(chars: &[char]) -> String {
+    chars
+        .iter()
+        .map(|&c| format!("{c:?} (U+{:04X})", c as u32))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
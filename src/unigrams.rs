@@ -0,0 +1,182 @@
+//! Frequencies of romanized words, to choose between the alternative romanizations of rules,
+//! e.g. of Arabic `و` as `w`, `u` or `o`, by which makes the more frequent word.
+
+use std::borrow::Cow;
+use std::io::BufRead;
+
+use crate::data_table::is_skipped_line;
+use crate::edge::Edge;
+use crate::hash::HashMap;
+use crate::lang_tag::normalize_lcode;
+use crate::lattice::Lattice;
+use crate::utils::LossyLines;
+use crate::{InvalidUtf8Policy, RomanizationError, Uroman};
+
+/// The most combinations of alternatives that are looked up for a word. Words with more are
+/// left as they are.
+const MAX_WORD_CANDIDATES: usize = 256;
+
+impl Uroman {
+    /// Loads the frequencies of romanized words in the language `lcode` from a file with a
+    /// word and its count on each line, e.g. `kitab 1520`. `#` starts a comment line, words
+    /// are case-insensitive and the counts of repeated words add up.
+    ///
+    /// When text in `lcode` is romanized, a rule with alternatives, such as `::s و ::t w
+    /// ::t-alt o, u`, romanizes a word with the alternative that makes the most frequent word
+    /// of the list. Words that are not in the list, and those that are as frequent as with the
+    /// first romanization of each rule, are left as they are. Only the rule alternatives that
+    /// [`rom_format::Alts`](crate::rom_format::Alts) shows are considered.
+    ///
+    /// # Errors
+    /// Returns [`RomanizationError::UnknownLanguageTag`] for an unknown `lcode`, and
+    /// [`RomanizationError::InvalidUnigram`] for a line without a word and a count, wrapped in
+    /// [`RomanizationError::AtLine`]. The words of the lines before it are loaded.
+    ///
+    /// # Example
+    /// ```
+    /// use uroman::{Uroman, rom_format};
+    ///
+    /// let mut uroman = Uroman::new();
+    /// let romanize = |uroman: &Uroman| {
+    ///     uroman.romanize_string::<rom_format::Str>("בית", Some("heb")).to_output_string()
+    /// };
+    /// assert_eq!(romanize(&uroman), "vyt");
+    /// uroman.load_unigrams("heb", "byt 120\nvyt 3\n".as_bytes()).unwrap();
+    /// assert_eq!(romanize(&uroman), "byt");
+    /// ```
+    pub fn load_unigrams<R: BufRead>(
+        &mut self,
+        lcode: &str,
+        reader: R,
+    ) -> Result<(), RomanizationError> {
+        let lcode = normalize_lcode(lcode)?;
+        let unigrams = self.unigrams.entry(lcode).or_default();
+        let mut lines = LossyLines::new(reader)
+            .with_invalid_utf8(InvalidUtf8Policy::Error)
+            .quiet();
+        while let Some(line) = lines.next_line()? {
+            if is_skipped_line(line) {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some((word, count)) = (match fields[..] {
+                [word, count] => count.parse::<u64>().ok().map(|count| (word, count)),
+                _ => None,
+            }) else {
+                let e = RomanizationError::InvalidUnigram(line.to_string());
+                return Err(lines.error_at_current_line(e));
+            };
+            *unigrams.entry(word.to_lowercase()).or_default() += count;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Lattice<'a> {
+    /// Replaces the romanizations of the edges of `path` by alternatives of their rules where
+    /// that makes a word that is more frequent in the unigrams of the language of the lattice.
+    pub(crate) fn prefer_frequent_words(&mut self, path: &mut [Edge<'a>]) {
+        let uroman = self.uroman;
+        let Some(unigrams) = self
+            .lcode
+            .as_deref()
+            .and_then(|lcode| uroman.unigrams.get(lcode))
+        else {
+            return;
+        };
+
+        let mut edges = path.to_vec();
+        self.add_alternatives(&mut edges);
+        let mut alternatives: HashMap<(usize, usize), Vec<String>> = HashMap::default();
+        // Alternatives after the first in `::t-alt` lists keep the space after the comma.
+        for edge in &edges[path.len()..] {
+            alternatives
+                .entry((edge.start(), edge.end()))
+                .or_default()
+                .push(edge.txt().trim().to_string());
+        }
+        if alternatives.is_empty() {
+            return;
+        }
+
+        let mut word_start = 0;
+        for i in 0..=path.len() {
+            if i < path.len() && self.is_word_edge(&path[i]) {
+                continue;
+            }
+            let word = &mut path[word_start..i];
+            if let Some(best) = most_frequent_candidate(word, &alternatives, unigrams) {
+                for (edge, txt) in word.iter_mut().zip(best) {
+                    if txt != edge.txt() {
+                        edge.get_data_mut().txt = Cow::Owned(txt);
+                    }
+                }
+            }
+            word_start = i + 1;
+        }
+    }
+
+    /// Whether `edge` romanizes part of a word, rather than spaces or punctuation.
+    fn is_word_edge(&self, edge: &Edge) -> bool {
+        self.s_chars[edge.start()..edge.end()]
+            .iter()
+            .any(|c| c.is_alphanumeric())
+    }
+}
+
+/// Returns the romanizations of the edges of `word` that make the most frequent word of
+/// `unigrams` with the `alternatives` of the edges, if that is more frequent than the word as
+/// it is.
+fn most_frequent_candidate(
+    word: &[Edge],
+    alternatives: &HashMap<(usize, usize), Vec<String>>,
+    unigrams: &HashMap<String, u64>,
+) -> Option<Vec<String>> {
+    let options: Vec<Vec<&str>> = word
+        .iter()
+        .map(|edge| {
+            let alternatives = alternatives.get(&(edge.start(), edge.end()));
+            std::iter::once(edge.txt())
+                .chain(alternatives.into_iter().flatten().map(String::as_str))
+                .collect()
+        })
+        .collect();
+    let n_candidates = options
+        .iter()
+        .try_fold(1usize, |n, options| n.checked_mul(options.len()))?;
+    if n_candidates <= 1 || n_candidates > MAX_WORD_CANDIDATES {
+        return None;
+    }
+
+    let frequency = |choice: &[usize]| {
+        let word: String = options
+            .iter()
+            .zip(choice)
+            .map(|(options, &i)| options[i])
+            .collect();
+        unigrams.get(&word.to_lowercase()).copied().unwrap_or(0)
+    };
+    // The index of the option of each edge, counted up like a number.
+    let mut choice = vec![0; options.len()];
+    let mut best = (frequency(&choice), choice.clone());
+    for _ in 1..n_candidates {
+        for (i, options) in choice.iter_mut().zip(&options) {
+            *i = (*i + 1) % options.len();
+            if *i != 0 {
+                break;
+            }
+        }
+        let frequency = frequency(&choice);
+        if frequency > best.0 {
+            best = (frequency, choice.clone());
+        }
+    }
+    let (_, choice) = best;
+    choice.iter().any(|&i| i != 0).then(|| {
+        options
+            .iter()
+            .zip(choice)
+            .map(|(options, i)| options[i].to_string())
+            .collect()
+    })
+}
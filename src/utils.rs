@@ -1,60 +1,28 @@
 //! Utility functions for parsing uroman data files.
 
 use regex::Regex;
-use std::sync::{LazyLock, OnceLock};
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::sync::LazyLock;
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
 
-use crate::Value;
+pub use crate::data_table::dequote_string;
+use crate::encoding::DecodedReader;
+use crate::html_entities::HTML_ENTITIES;
+use crate::hash::HashMap;
+use crate::{
+    FileSummary, InputEncoding, InvalidEscapePolicy, InvalidUtf8Policy, RomanizationError, Value,
+};
 
 static HAS_ESCAPE_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\\(x[0-9a-fA-F]{2}|u[0-9a-fA-F]{4}|U[0-9a-fA-F]{8})").unwrap());
 
-/// Captures the value associated with a `::slot` in a line.
-///
-/// This function is a Rust port of the Python version's `slot_value_in_double_colon_del_list`.
-/// It uses a dynamically generated regex to find the slot and extract its value.
-///
-/// # Example
-/// `slot_value_in_double_colon_del_list("::s1 of course ::s2 ::cost 0.3", "cost")` returns `Some("0.3")`.
-pub fn slot_value_in_double_colon_del_list<'a>(line: &'a str, slot: &'a str) -> Option<&'a str> {
-    let search_str = format!("::{slot}");
-    if let Some(start_index) = line.find(&search_str) {
-        let remaining = &line[start_index + search_str.len()..];
-        if let Some(end_index) = remaining.find("::") {
-            Some(remaining[..end_index].trim())
-        } else {
-            Some(remaining.trim())
-        }
-    } else {
-        None
-    }
-}
-
-/// Checks if a slot exists in the line, even if it has no value.
-pub fn has_value_in_double_colon_del_list(line: &str, slot: &str) -> bool {
-    slot_value_in_double_colon_del_list(line, slot).is_some()
-}
-
-/// Removes matching quotes from the start and end of a string.
-///
-/// Handles single quotes, double quotes, and curly double quotes.
-pub fn dequote_string(s: &str) -> &str {
-    static DEQUOTE_RE: OnceLock<Regex> = OnceLock::new();
-    let re = DEQUOTE_RE.get_or_init(|| Regex::new(r#"^\s*(['"“])(.*)(['"”])\s*$"#).unwrap());
-
-    if let Some(m) = re.captures(s) {
-        let open_quote = m.get(1).map_or("", |m| m.as_str());
-        let content = m.get(2).map_or("", |m| m.as_str());
-        let close_quote = m.get(3).map_or("", |m| m.as_str());
+static NAMED_ESCAPE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\\N\{([A-Za-z0-9 \-]+)\}").unwrap());
 
-        if (open_quote == "'" && close_quote == "'")
-            || (open_quote == "\"" && close_quote == "\"")
-            || (open_quote == "“" && close_quote == "”")
-        {
-            return content;
-        }
-    }
-    s
-}
+static HTML_ENTITY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"&(?:#([0-9]{1,8})|#[xX]([0-9a-fA-F]{1,8})|([A-Za-z][A-Za-z0-9]{1,31}));").unwrap()
+});
 
 pub fn robust_str_to_num(s: &str) -> Option<Value> {
     if let Ok(i) = s.parse::<i64>() {
@@ -66,32 +34,604 @@ pub fn robust_str_to_num(s: &str) -> Option<Value> {
     }
 }
 
-pub fn decode_unicode_escapes(s: &str) -> String {
+/// Decodes the escapes `\xXX`, `\uXXXX` and `\UXXXXXXXX` in `s`, as the Python uroman does.
+///
+/// Escapes of ASCII chars are kept as they are. A `\u` escape of a high surrogate followed by
+/// one of a low surrogate, as in `\ud83d\ude00`, is decoded as the char of the pair. Escapes
+/// that don't encode a char, such as lone surrogates or values beyond U+10FFFF, are handled
+/// according to `invalid`.
+pub fn decode_unicode_escapes(s: &str, invalid: InvalidEscapePolicy) -> String {
     if !HAS_ESCAPE_RE.is_match(s) {
         return s.to_string();
     }
 
     let mut result = String::with_capacity(s.len());
     let mut last_end = 0;
+    let mut escapes = HAS_ESCAPE_RE.find_iter(s).peekable();
 
-    for m in HAS_ESCAPE_RE.find_iter(s) {
+    while let Some(m) = escapes.next() {
         result.push_str(&s[last_end..m.start()]);
+        last_end = m.end();
 
         let full_escape_sequence = m.as_str();
-        let hex_part = &full_escape_sequence[2..];
-
-        let codepoint = u32::from_str_radix(hex_part, 16).unwrap();
-
-        if codepoint > 0x80 {
-            result.push(std::char::from_u32(codepoint).unwrap_or(std::char::REPLACEMENT_CHARACTER));
-        } else {
+        let Ok(codepoint) = u32::from_str_radix(&full_escape_sequence[2..], 16) else {
             result.push_str(full_escape_sequence);
+            continue;
+        };
+        if codepoint <= 0x80 {
+            result.push_str(full_escape_sequence);
+            continue;
         }
 
-        last_end = m.end();
+        let is_u_escape = |escape: &str| escape.starts_with("\\u");
+        let low_surrogate = escapes
+            .peek()
+            .filter(|next| {
+                is_u_escape(full_escape_sequence)
+                    && (0xD800..0xDC00).contains(&codepoint)
+                    && next.start() == m.end()
+                    && is_u_escape(next.as_str())
+            })
+            .and_then(|next| u32::from_str_radix(&next.as_str()[2..], 16).ok())
+            .filter(|low| (0xDC00..0xE000).contains(low));
+        let decoded = match low_surrogate {
+            Some(low) => {
+                last_end = escapes.next().map_or(last_end, |next| next.end());
+                char::from_u32(0x10000 + ((codepoint - 0xD800) << 10) + (low - 0xDC00))
+            }
+            None => char::from_u32(codepoint),
+        };
+
+        match (decoded, invalid) {
+            (Some(c), _) => result.push(c),
+            (None, InvalidEscapePolicy::Keep) => result.push_str(&s[m.start()..last_end]),
+            (None, _) => result.push(char::REPLACEMENT_CHARACTER),
+        }
     }
 
     result.push_str(&s[last_end..]);
 
     result
 }
+
+/// Decodes the Python escapes of named chars, such as `\N{GREEK SMALL LETTER DELTA}`, in `s`.
+///
+/// Names are matched as by Python, ignoring case. Escapes of unknown names are handled
+/// according to `invalid`.
+pub fn decode_named_escapes(s: &str, invalid: InvalidEscapePolicy) -> String {
+    NAMED_ESCAPE_RE
+        .replace_all(s, |caps: &regex::Captures| {
+            match (unicode_names2::character(&caps[1]), invalid) {
+                (Some(c), _) => c.to_string(),
+                (None, InvalidEscapePolicy::Keep) => caps[0].to_string(),
+                (None, _) => char::REPLACEMENT_CHARACTER.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Decodes the HTML and XML character references in `s`: the numeric `&#948;` and `&#x3B4;`,
+/// and the named ones of HTML 4 and XML, such as `&eacute;` and `&amp;`.
+///
+/// Unknown names, such as `&foo;`, are kept as they are, since they are mostly not meant as
+/// references. Numeric references that don't encode a char, such as `&#xD800;` and `&#0;`,
+/// are handled according to `invalid`.
+pub fn decode_html_entities(s: &str, invalid: InvalidEscapePolicy) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    HTML_ENTITY_RE
+        .replace_all(s, |caps: &regex::Captures| {
+            let codepoint = match (caps.get(1), caps.get(2), caps.get(3)) {
+                (Some(dec), _, _) => dec.as_str().parse::<u32>().ok(),
+                (_, Some(hex), _) => u32::from_str_radix(hex.as_str(), 16).ok(),
+                (_, _, Some(name)) => {
+                    return HTML_ENTITIES
+                        .get(name.as_str())
+                        .map_or_else(|| caps[0].to_string(), char::to_string);
+                }
+                _ => None,
+            };
+            match (
+                codepoint.filter(|&cp| cp != 0).and_then(char::from_u32),
+                invalid,
+            ) {
+                (Some(c), _) => c.to_string(),
+                (None, InvalidEscapePolicy::Keep) => caps[0].to_string(),
+                (None, _) => char::REPLACEMENT_CHARACTER.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Reports a diagnostic as a `tracing` warning with the `tracing` feature, and on stderr
+/// otherwise.
+macro_rules! diagnostic {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+        #[cfg(not(feature = "tracing"))]
+        eprintln!($($arg)*);
+    };
+}
+pub(crate) use diagnostic;
+
+/// The byte order mark of UTF-8, which some editors write at the start of a file.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The number of chars of a line quoted in errors.
+const SNIPPET_CHARS: usize = 40;
+
+/// The smallest maximum size of a chunk, which holds at least one char.
+const MIN_CHUNK_BYTES: usize = 4;
+
+/// Wraps `error` with the location of line `line` of a stream, which starts at `byte_offset`
+/// and has the text `text`, of which the start is kept.
+pub fn error_at_line(
+    line: usize,
+    byte_offset: u64,
+    text: &str,
+    error: RomanizationError,
+) -> RomanizationError {
+    let text = text.trim_end_matches(['\r', '\n']);
+    let mut snippet: String = text.chars().take(SNIPPET_CHARS).collect();
+    if snippet.len() < text.len() {
+        snippet.push('…');
+    }
+    RomanizationError::AtLine {
+        line,
+        byte_offset,
+        snippet,
+        source: Box::new(error),
+    }
+}
+
+/// Reads lines from a byte stream, replacing non-UTF-8 characters and reporting them on stderr.
+///
+/// A byte order mark at the start of the stream is dropped. Lines longer than the maximum chunk
+/// size, if there is one, are read in chunks, see [`LossyLines::with_max_chunk_bytes`].
+pub struct LossyLines<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: String,
+    line_number: usize,
+    bytes_read: u64,
+    last_line_non_utf8: bool,
+    non_utf8_lines: usize,
+    n_error_messages_output: usize,
+    /// Don't report the encoding errors, for callers that report them themselves.
+    quiet: bool,
+    invalid_utf8: InvalidUtf8Policy,
+    max_chunk_bytes: Option<usize>,
+    /// The bytes read after the end of the current chunk, which start the next one.
+    carry: Vec<u8>,
+    chunk_starts_line: bool,
+    chunk_ends_line: bool,
+}
+
+impl<R> LossyLines<R> {
+    const MAX_N_ERROR_MESSAGES: usize = 10;
+
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            line: String::new(),
+            line_number: 0,
+            bytes_read: 0,
+            last_line_non_utf8: false,
+            non_utf8_lines: 0,
+            n_error_messages_output: 0,
+            quiet: false,
+            invalid_utf8: InvalidUtf8Policy::Replace,
+            max_chunk_bytes: None,
+            carry: Vec::new(),
+            chunk_starts_line: true,
+            chunk_ends_line: true,
+        }
+    }
+
+    /// Sets what to do with lines that are not valid UTF-8.
+    pub fn with_invalid_utf8(mut self, invalid_utf8: InvalidUtf8Policy) -> Self {
+        self.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// Reads lines longer than `max_chunk_bytes` bytes, but at least four, in chunks of at
+    /// most that size. A chunk ends before the last ASCII whitespace within it, or else at a
+    /// char boundary, so that each chunk is valid UTF-8 and most words stay in one chunk.
+    pub fn with_max_chunk_bytes(mut self, max_chunk_bytes: Option<usize>) -> Self {
+        self.max_chunk_bytes = max_chunk_bytes.map(|max| max.max(MIN_CHUNK_BYTES));
+        self
+    }
+
+    /// Stops reporting the encoding errors on stderr.
+    pub fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    /// The number of lines read so far.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Returns `true` unless the chunk just read continues a line that was cut into chunks.
+    pub fn chunk_starts_line(&self) -> bool {
+        self.chunk_starts_line
+    }
+
+    /// Returns `true` unless the line of the chunk just read continues in the next chunk.
+    pub fn chunk_ends_line(&self) -> bool {
+        self.chunk_ends_line
+    }
+
+    /// The number of bytes read so far, including line terminators.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns `true` if non-UTF-8 characters were replaced in the last line read.
+    pub fn last_line_non_utf8(&self) -> bool {
+        self.last_line_non_utf8
+    }
+
+    /// Returns the summary of the lines read so far, of which `changed_lines` were changed and
+    /// which have the chars of each script of `per_script_counts`.
+    pub fn summary(
+        &self,
+        changed_lines: usize,
+        per_script_counts: HashMap<&str, usize>,
+    ) -> FileSummary {
+        FileSummary {
+            lines: self.line_number,
+            non_utf8_lines: self.non_utf8_lines,
+            bytes: self.bytes_read,
+            changed_lines,
+            per_script_counts: per_script_counts
+                .into_iter()
+                .map(|(script, count)| (script.to_string(), count))
+                .collect(),
+        }
+    }
+
+    /// Ends the progress output unless `silent`, and reports the total number of lines with
+    /// non-UTF-8 characters, if there were any.
+    pub fn finish(&self, silent: bool) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(lines = self.line_number, silent, "finished reading");
+        #[cfg(not(feature = "tracing"))]
+        if !silent && self.line_number > 0 {
+            eprintln!();
+        }
+        if self.non_utf8_lines > 0 && !self.quiet {
+            diagnostic!(
+                "Total number of lines with non-UTF-8 characters: {}",
+                self.non_utf8_lines
+            );
+        }
+    }
+
+    /// Wraps `error` with the number, byte offset and start of the line just read.
+    pub fn error_at_current_line(&self, error: impl Into<RomanizationError>) -> RomanizationError {
+        self.error_at_line(self.line_number, self.current_line_start(), error.into())
+    }
+
+    /// The byte offset of the start of the line just read.
+    pub fn current_line_start(&self) -> u64 {
+        self.bytes_read - (self.buffer.len() + self.carry.len()) as u64
+    }
+
+    /// Wraps `error` with the location of line `line`, which starts at `byte_offset` and of
+    /// which `buffer` holds what was read.
+    fn error_at_line(
+        &self,
+        line: usize,
+        byte_offset: u64,
+        error: RomanizationError,
+    ) -> RomanizationError {
+        let text = String::from_utf8_lossy(&self.buffer);
+        error_at_line(line, byte_offset, &text, error)
+    }
+
+    /// Moves the end of a chunk that doesn't end its line into `carry`, so that the chunk ends
+    /// before its last ASCII whitespace, or else before its last char.
+    fn split_chunk(&mut self) {
+        let split = self
+            .buffer
+            .iter()
+            .rposition(u8::is_ascii_whitespace)
+            .filter(|&i| i > 0)
+            .or_else(|| {
+                // The last byte that isn't a UTF-8 continuation byte starts the last char.
+                let is_char_start = |b: &u8| b & 0xC0 != 0x80;
+                self.buffer
+                    .iter()
+                    .rposition(is_char_start)
+                    .filter(|&i| i > 0)
+            })
+            .unwrap_or(self.buffer.len());
+        self.carry.extend_from_slice(&self.buffer[split..]);
+        self.buffer.truncate(split);
+    }
+
+    /// Decodes the line or chunk just read into `buffer`, of which `n_bytes` bytes were read
+    /// by this call. Returns `false` if it is to be skipped, and an error if it must not be
+    /// replaced.
+    fn decode_line(&mut self, n_bytes: usize) -> io::Result<bool> {
+        if self.chunk_starts_line {
+            self.line_number += 1;
+        }
+        self.bytes_read += n_bytes as u64;
+
+        let bytes = match self.buffer.strip_prefix(UTF8_BOM) {
+            Some(rest) if self.line_number == 1 && self.chunk_starts_line => rest,
+            _ => &self.buffer,
+        };
+        let line_str = String::from_utf8_lossy(bytes);
+        // The lossy decoding only allocates if it had to replace something.
+        self.last_line_non_utf8 = matches!(line_str, Cow::Owned(_));
+        if self.last_line_non_utf8 {
+            self.non_utf8_lines += 1;
+            match self.invalid_utf8 {
+                InvalidUtf8Policy::Replace => {}
+                InvalidUtf8Policy::SkipLine => return Ok(false),
+                InvalidUtf8Policy::Error => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8"));
+                }
+            }
+        }
+        if self.last_line_non_utf8 && !self.quiet {
+            if self.n_error_messages_output < Self::MAX_N_ERROR_MESSAGES {
+                diagnostic!(
+                    "Detected encoding error on line {}: non-UTF-8 characters were replaced.",
+                    self.line_number
+                );
+                self.n_error_messages_output += 1;
+            } else if self.n_error_messages_output == Self::MAX_N_ERROR_MESSAGES {
+                diagnostic!("Too many encoding errors. No further errors reported.");
+                self.n_error_messages_output += 1;
+            }
+        }
+        self.line.clear();
+        self.line.push_str(&line_str);
+        Ok(true)
+    }
+
+    /// Returns the line or chunk just decoded without its line terminator.
+    pub fn current_line(&self) -> &str {
+        let mut line_trimmed = self.line.as_str();
+        if !self.chunk_ends_line {
+            return line_trimmed;
+        }
+        if line_trimmed.ends_with('\n') {
+            line_trimmed = &line_trimmed[..line_trimmed.len() - 1];
+        }
+        if line_trimmed.ends_with('\r') {
+            line_trimmed = &line_trimmed[..line_trimmed.len() - 1];
+        }
+        line_trimmed
+    }
+
+    /// Returns the line terminator of the line just read: `\r\n`, `\n`, `\r` before the end
+    /// of the stream, or an empty string for a last line without one.
+    pub fn current_line_ending(&self) -> &str {
+        &self.line[self.current_line().len()..]
+    }
+}
+
+impl<R: BufRead> LossyLines<DecodedReader<R>> {
+    /// Reads lines from a byte stream in `encoding`.
+    ///
+    /// The byte counts are of the stream transcoded to UTF-8.
+    pub fn decoded(reader: R, encoding: InputEncoding) -> Self {
+        Self::new(DecodedReader::new(reader, encoding))
+    }
+}
+
+impl<R: BufRead> LossyLines<R> {
+    /// Returns `true` if the stream starts with a byte order mark, without reading it.
+    pub fn starts_with_bom(&mut self) -> io::Result<bool> {
+        Ok(self.line_number == 0 && self.reader.fill_buf()?.starts_with(UTF8_BOM))
+    }
+
+    /// Reads the next line, or the next chunk of a line, without its line terminator, or
+    /// `None` at the end of the stream.
+    ///
+    /// Errors are wrapped with the location of the line.
+    pub fn next_line(&mut self) -> Result<Option<&str>, RomanizationError> {
+        loop {
+            self.chunk_starts_line = self.chunk_ends_line;
+            self.buffer.clear();
+            self.buffer.append(&mut self.carry);
+            let n_carried = self.buffer.len();
+            let read = match self.max_chunk_bytes {
+                Some(max) => {
+                    let limit = max.saturating_sub(n_carried).max(1) as u64;
+                    io::Read::take(&mut self.reader, limit).read_until(b'\n', &mut self.buffer)
+                }
+                None => self.reader.read_until(b'\n', &mut self.buffer),
+            };
+            let n_bytes = read.map_err(|e| {
+                let line = self.line_number + usize::from(self.chunk_starts_line);
+                self.error_at_line(line, self.bytes_read, e.into())
+            })?;
+            // A line that was cut into chunks right before the end of the stream still ends
+            // with an empty chunk.
+            if n_bytes == 0 && n_carried == 0 && self.chunk_starts_line {
+                return Ok(None);
+            }
+            self.chunk_ends_line = !self
+                .max_chunk_bytes
+                .is_some_and(|max| self.buffer.len() >= max && !self.buffer.ends_with(b"\n"));
+            if !self.chunk_ends_line {
+                // A full chunk right before the end of its line needn't be cut.
+                let next_byte = self.reader.fill_buf().map(|bytes| bytes.first().copied());
+                let next_byte = next_byte.map_err(|e| {
+                    let line = self.line_number + usize::from(self.chunk_starts_line);
+                    self.error_at_line(line, self.bytes_read, e.into())
+                })?;
+                match next_byte {
+                    None => self.chunk_ends_line = true,
+                    Some(b'\r' | b'\n') => {}
+                    Some(_) => self.split_chunk(),
+                }
+            }
+            if self
+                .decode_line(n_bytes)
+                .map_err(|e| self.error_at_current_line(e))?
+            {
+                return Ok(Some(self.current_line()));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncBufRead + Unpin> LossyLines<R> {
+    /// Returns `true` if the stream starts with a byte order mark, without reading it.
+    pub async fn starts_with_bom_async(&mut self) -> io::Result<bool> {
+        use tokio::io::AsyncBufReadExt;
+
+        Ok(self.line_number == 0 && self.reader.fill_buf().await?.starts_with(UTF8_BOM))
+    }
+
+    /// Reads the next line like [`LossyLines::next_line`], from an async stream.
+    pub async fn next_line_async(&mut self) -> Result<Option<&str>, RomanizationError> {
+        use tokio::io::AsyncBufReadExt;
+
+        loop {
+            self.buffer.clear();
+            let n_bytes = self
+                .reader
+                .read_until(b'\n', &mut self.buffer)
+                .await
+                .map_err(|e| self.error_at_line(self.line_number + 1, self.bytes_read, e.into()))?;
+            if n_bytes == 0 {
+                return Ok(None);
+            }
+            if self
+                .decode_line(n_bytes)
+                .map_err(|e| self.error_at_current_line(e))?
+            {
+                return Ok(Some(self.current_line()));
+            }
+        }
+    }
+}
+
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// Returns the index of the first non-ASCII byte, checking eight bytes at a time.
+pub fn first_non_ascii_byte(bytes: &[u8]) -> Option<usize> {
+    let mut chunks = bytes.chunks_exact(8);
+    for (i, chunk) in chunks.by_ref().enumerate() {
+        let high = u64::from_le_bytes(chunk.try_into().unwrap()) & HIGH_BITS;
+        if high != 0 {
+            return Some(i * 8 + high.trailing_zeros() as usize / 8);
+        }
+    }
+    let rest = chunks.remainder();
+    let offset = bytes.len() - rest.len();
+    rest.iter().position(|b| !b.is_ascii()).map(|i| offset + i)
+}
+
+/// Returns the index of the last non-ASCII byte, checking eight bytes at a time.
+pub fn last_non_ascii_byte(bytes: &[u8]) -> Option<usize> {
+    let mut chunks = bytes.rchunks_exact(8);
+    for (i, chunk) in chunks.by_ref().enumerate() {
+        let high = u64::from_le_bytes(chunk.try_into().unwrap()) & HIGH_BITS;
+        if high != 0 {
+            return Some(bytes.len() - (i + 1) * 8 + 7 - high.leading_zeros() as usize / 8);
+        }
+    }
+    chunks.remainder().iter().rposition(|b| !b.is_ascii())
+}
+
+/// Splits `s` into a leading ASCII part, the part that needs romanization, and a trailing
+/// ASCII part.
+///
+/// The middle part starts and ends at ASCII whitespace (or the ends of `s`), so that its
+/// first and last words keep their context.
+pub fn split_ascii_margins(s: &str) -> (&str, &str, &str) {
+    let bytes = s.as_bytes();
+    let (Some(first), Some(last)) = (first_non_ascii_byte(bytes), last_non_ascii_byte(bytes))
+    else {
+        return (s, "", "");
+    };
+    let start = bytes[..first]
+        .iter()
+        .rposition(u8::is_ascii_whitespace)
+        .unwrap_or(0);
+    let end = bytes[last..]
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .map_or(s.len(), |i| last + i + 1);
+    (&s[..start], &s[start..end], &s[end..])
+}
+
+/// Splits a long string into windows of roughly `window_chars` characters, so that the lattice
+/// of a pathologically long line can be built one window at a time.
+///
+/// A window is extended up to the next whitespace, which then starts the next window. If there
+/// is none within another `window_chars` characters, the window is cut before the next
+/// character that is not a mark or format character. Strings of at most `window_chars`
+/// characters, including the empty string, are a single window.
+pub struct InputWindows<'a> {
+    rest: Option<&'a str>,
+    window_chars: usize,
+}
+
+impl<'a> InputWindows<'a> {
+    pub fn new(s: &'a str, window_chars: usize) -> Self {
+        Self {
+            rest: Some(s),
+            window_chars,
+        }
+    }
+}
+
+impl<'a> Iterator for InputWindows<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.rest?;
+        let split = rest
+            .char_indices()
+            .skip(self.window_chars)
+            .enumerate()
+            .find(|&(n, (_, c))| {
+                c.is_whitespace()
+                    || (n >= self.window_chars
+                        && !matches!(
+                            c.general_category_group(),
+                            GeneralCategoryGroup::Mark | GeneralCategoryGroup::Other
+                        ))
+            })
+            .map(|(_, (i, _))| i);
+
+        match split {
+            Some(i) => {
+                self.rest = Some(&rest[i..]);
+                Some(&rest[..i])
+            }
+            None => self.rest.take(),
+        }
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`, in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
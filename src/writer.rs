@@ -0,0 +1,132 @@
+//! Romanizing the text written into an `io::Write`, the inverse of the reader-driven
+//! `romanize_file`.
+
+use std::io::{self, Write};
+
+use crate::{RomanizationError, RomanizeOptions, Uroman};
+
+/// A writer that romanizes the UTF-8 text written into it line by line and writes the
+/// romanization into another writer, so that code that writes text, such as a logger or an
+/// exporter, can be routed through romanization.
+///
+/// Created by [`Uroman::romanizing_writer`](crate::Uroman::romanizing_writer). Text is buffered
+/// until a line is complete, so that a line written in pieces is romanized as a whole, and
+/// each line is romanized and ended as a line of [`Uroman::romanize_file_with_options`] is,
+/// with `options.line_ending`. [`Write::flush`] writes the complete lines and flushes the
+/// inner writer, and the rest of the last line is romanized by [`RomanizingWriter::finish`],
+/// or when the writer is dropped, which ignores errors. Invalid UTF-8 is replaced with U+FFFD.
+///
+/// If romanizing or writing a line fails, the lines before it stay written, and
+/// [`Write::write`] returns the number of bytes of its buffer that they took, or the error if
+/// they took none. The text that isn't written stays buffered, except for the bytes of the
+/// failed write.
+pub struct RomanizingWriter<'a, W: Write> {
+    uroman: &'a Uroman,
+    /// `None` once [`RomanizingWriter::finish`] has taken it.
+    inner: Option<W>,
+    options: RomanizeOptions,
+    /// The start of the line being written.
+    buffer: Vec<u8>,
+}
+
+impl<'a, W: Write> RomanizingWriter<'a, W> {
+    pub(crate) fn new(uroman: &'a Uroman, inner: W, options: RomanizeOptions) -> Self {
+        Self {
+            uroman,
+            inner: Some(inner),
+            options,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.inner
+            .as_ref()
+            .expect("the inner writer is only taken by `finish`")
+    }
+
+    /// Romanizes the rest of the last line, flushes the inner writer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of the inner writer, and those of romanizing the line, such as a
+    /// [`RomanizationError::Unromanizable`] with `options.strict`, wrapped in an `io::Error`.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_lines(self.buffer.len())?;
+        let mut inner = self
+            .inner
+            .take()
+            .expect("the inner writer is only taken here");
+        inner.flush()?;
+        Ok(inner)
+    }
+
+    /// Romanizes the lines in the first `end` bytes of the buffer, which end at the end of a
+    /// line or are the rest of the last line, and writes them into the inner writer. Each
+    /// line leaves the buffer once it is written, so that after an error the buffer starts
+    /// with the line that failed.
+    fn write_lines(&mut self, end: usize) -> io::Result<()> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(());
+        };
+        let mut written = 0;
+        let result = self.buffer[..end]
+            .split_inclusive(|&b| b == b'\n')
+            .try_for_each(|line| {
+                let text = String::from_utf8_lossy(line);
+                let content = text.trim_end_matches(['\n', '\r']);
+                let line_end = self.options.line_ending.terminator(&text[content.len()..]);
+                let output = romanize_line(self.uroman, content, &self.options)?;
+                write!(inner, "{output}{line_end}")?;
+                written += line.len();
+                Ok(())
+            });
+        self.buffer.drain(..written);
+        result
+    }
+}
+
+impl<W: Write> Write for RomanizingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let pending = self.buffer.len();
+        self.buffer.extend_from_slice(buf);
+        if let Some(last_line_end) = self.buffer.iter().rposition(|&b| b == b'\n')
+            && let Err(e) = self.write_lines(last_line_end + 1)
+        {
+            // Only the bytes of `buf` in the lines that were written are taken from it.
+            let written = pending + buf.len() - self.buffer.len();
+            self.buffer.truncate(pending.saturating_sub(written));
+            return match written.checked_sub(pending) {
+                Some(taken) if taken > 0 => Ok(taken),
+                _ => Err(e),
+            };
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for RomanizingWriter<'_, W> {
+    fn drop(&mut self) {
+        if !self.buffer.is_empty() {
+            let _ = self.write_lines(self.buffer.len());
+        }
+    }
+}
+
+/// Romanizes a line as `romanize_file` does, with its errors as `io::Error`s.
+fn romanize_line(uroman: &Uroman, line: &str, options: &RomanizeOptions) -> io::Result<String> {
+    uroman
+        .romanize_file_line(line, options)
+        .map_err(|e| match e {
+            RomanizationError::Io(e) => e,
+            e => io::Error::other(e),
+        })
+}
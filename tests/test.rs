@@ -30,3 +30,312 @@ fn test_multi_script_romanization() {
         "tests/test/multi-script.uroman-ref.txt",
     );
 }
+
+#[test]
+fn test_config_file() {
+    let config_path =
+        std::env::temp_dir().join(format!("uroman-config-{}.json", std::process::id()));
+    fs::write(
+        &config_path,
+        r#"{ "lcode": "ukr", "decode_unicode": true }"#,
+    )
+    .unwrap();
+
+    // The flags override the config file.
+    for (args, expected) in [(vec![], "Kyiv\n"), (vec!["--lcode", "rus"], "Kiyiv\n")] {
+        let output = Command::cargo_bin("uroman-rs")
+            .unwrap()
+            .arg("--config")
+            .arg(&config_path)
+            .args(args)
+            .arg(r"\u041a\u0438\u0457\u0432")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    fs::write(&config_path, r#"{ "lcode": "ukr", "format": "edges" }"#).unwrap();
+    Command::cargo_bin("uroman-rs")
+        .unwrap()
+        .arg("--config")
+        .arg(&config_path)
+        .arg("Київ")
+        .assert()
+        .failure();
+
+    fs::remove_file(&config_path).unwrap();
+}
+
+#[test]
+fn test_python_uroman_flags() {
+    let input_path =
+        std::env::temp_dir().join(format!("uroman-python-flags-{}.txt", std::process::id()));
+    fs::write(&input_path, "::lcode\tukr  Київ\nКиїв\nКиїв\n").unwrap();
+
+    let output = Command::cargo_bin("uroman-rs")
+        .unwrap()
+        .args(["-l", "rus", "--rom_format", "str"])
+        .args(["--max_lines", "2", "--dev"])
+        .arg("--input_filename")
+        .arg(&input_path)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "::lcode\tukr  Kyiv\nKiyiv\n"
+    );
+
+    Command::cargo_bin("uroman-rs")
+        .unwrap()
+        .arg("-v")
+        .assert()
+        .success();
+
+    fs::remove_file(&input_path).unwrap();
+}
+
+#[test]
+fn test_rename() {
+    let dir = std::env::temp_dir().join(format!("uroman-rename-{}", std::process::id()));
+    let manifest = dir.with_extension("jsonl");
+    fs::create_dir_all(dir.join("Звіт")).unwrap();
+    for name in ["Звіт/Київ.txt", "Kyiv.txt", "Київ.txt"] {
+        fs::write(dir.join(name), name).unwrap();
+    }
+    let list = || {
+        let mut paths: Vec<String> = ["", "Звіт", "Zvit"]
+            .iter()
+            .filter_map(|sub| fs::read_dir(dir.join(sub)).ok())
+            .flatten()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        paths.sort();
+        paths
+    };
+    let rename = |args: &[&str]| {
+        Command::cargo_bin("uroman-rs")
+            .unwrap()
+            .arg("rename")
+            .args(args)
+            .arg("--manifest")
+            .arg(&manifest)
+            .args(["-l", "ukr"])
+            .assert()
+            .success();
+    };
+
+    rename(&["--dry-run", dir.to_str().unwrap()]);
+    assert_eq!(list(), ["Kyiv.txt", "Звіт", "Київ.txt", "Київ.txt"]);
+    rename(&[dir.to_str().unwrap()]);
+    assert_eq!(list(), ["Kyiv-1.txt", "Kyiv.txt", "Kyiv.txt", "Zvit"]);
+    assert_eq!(
+        fs::read_to_string(dir.join("Kyiv-1.txt")).unwrap(),
+        "Київ.txt"
+    );
+    rename(&["--undo"]);
+    assert_eq!(list(), ["Kyiv.txt", "Звіт", "Київ.txt", "Київ.txt"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_file(&manifest).unwrap();
+}
+
+#[test]
+fn test_report() {
+    let input = std::env::temp_dir().join(format!("uroman-report-{}.txt", std::process::id()));
+    let report = input.with_extension("html");
+    fs::write(&input, "Київ\r\nЛьвів").unwrap();
+    let output = Command::cargo_bin("uroman-rs")
+        .unwrap()
+        .arg("--report")
+        .arg(&report)
+        .args(["--silent", "-l", "ukr", "-i"])
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(String::from_utf8(output).unwrap(), "Kyiv\nLviv\n");
+
+    let html = fs::read_to_string(&report).unwrap();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&report).unwrap();
+    assert!(html.contains("<td dir=\"auto\">Київ</td>"));
+    assert!(html.contains("<td dir=\"auto\">Львів</td>"));
+    assert!(html.contains("<tr><th>Lines</th><td>2</td></tr>"));
+}
+
+#[test]
+fn test_compare() {
+    let reference = std::env::temp_dir().join(format!("uroman-compare-{}.txt", std::process::id()));
+    let hypothesis = reference.with_extension("hyp");
+    fs::write(&reference, "Kyiv\nLviv\n").unwrap();
+    let compare = || {
+        Command::cargo_bin("uroman-rs")
+            .unwrap()
+            .arg("compare")
+            .args([&reference, &hypothesis])
+            .assert()
+    };
+
+    fs::write(&hypothesis, "Kyiv\r\nLviv\r\n").unwrap();
+    compare().success();
+    fs::write(&hypothesis, "Kyiv\nL'viv\n").unwrap();
+    let output = compare().failure().get_output().stdout.clone();
+    fs::remove_file(&reference).unwrap();
+    fs::remove_file(&hypothesis).unwrap();
+    assert!(
+        String::from_utf8(output)
+            .unwrap()
+            .contains("Line 2 (distance 1):\n- Lviv\n+ L'viv\n")
+    );
+}
+
+#[test]
+fn test_diff() {
+    let input = std::env::temp_dir().join(format!("uroman-diff-{}.txt", std::process::id()));
+    let rules = input.with_extension("rules");
+    fs::write(&input, "Київ\nЛьвів 12\n").unwrap();
+    fs::write(&rules, "::s в ::t w ::lcode ukr\n").unwrap();
+    let uroman = assert_cmd::cargo::cargo_bin("uroman-rs");
+    let diff = |other_args: &[&std::ffi::OsStr]| {
+        let mut cmd = Command::cargo_bin("uroman-rs").unwrap();
+        cmd.args(["diff", "-l", "ukr", "--other"])
+            .arg(&uroman)
+            .arg(&input);
+        for arg in other_args {
+            cmd.arg("--other-arg").arg(arg);
+        }
+        cmd.assert()
+    };
+
+    diff(&[]).success();
+    let output = diff(&["--rules".as_ref(), rules.as_os_str()])
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&rules).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Line 2 (distance 2):\n- Lwiw 12\n+ Lviv 12\n"));
+    assert!(output.contains("Disagreements by script:\n       3  Cyrillic\n"));
+}
+
+#[test]
+fn test_input_format_detection() {
+    let srt = std::env::temp_dir().join(format!("uroman-format-{}.txt", std::process::id()));
+    let jsonl = srt.with_extension("jsonl");
+    let directives = srt.with_extension("lcode.txt");
+    fs::write(&srt, "1\n00:00:01,000 --> 00:00:02,500\nКиїв\n").unwrap();
+    fs::write(&jsonl, "{\"Ключ\": \"Київ\"}\n").unwrap();
+    fs::write(&directives, "::lcode ukr Київ\tЛьвів\n").unwrap();
+    let romanize = |input: &std::path::Path, args: &[&str]| {
+        let mut cmd = Command::cargo_bin("uroman-rs").unwrap();
+        cmd.args(args).arg("-i").arg(input);
+        String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+    };
+
+    let srt_output = romanize(&srt, &[]);
+    let jsonl_output = romanize(&jsonl, &[]);
+    let text_output = romanize(&jsonl, &["--format", "text"]);
+    // Tabs in lines with directives don't make them tab-separated values.
+    let directives_output = romanize(&directives, &[]);
+    fs::remove_file(&srt).unwrap();
+    fs::remove_file(&jsonl).unwrap();
+    fs::remove_file(&directives).unwrap();
+    assert_eq!(srt_output, "1\n00:00:01,000 --> 00:00:02,500\nKiyiv\n");
+    assert_eq!(jsonl_output, "{\"Ключ\": \"Kiyiv\"}\n");
+    assert_eq!(text_output, "{\"Klyuch\": \"Kiyiv\"}\n");
+    assert_eq!(directives_output, "::lcode ukr Kyiv\tLviv\n");
+}
+
+#[test]
+fn test_overrides_dir() {
+    let dir = std::env::temp_dir().join(format!("uroman-cli-overrides-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("romanization-table.txt"), "::s Київ ::t Kyiv\n").unwrap();
+    fs::write(dir.join("input.txt"), "Київ\n").unwrap();
+    let output = Command::cargo_bin("uroman-rs")
+        .unwrap()
+        .env("UROMAN_OVERRIDES_DIR", &dir)
+        .arg("-i")
+        .arg(dir.join("input.txt"))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "Kyiv\n");
+}
+
+#[test]
+fn test_sniffed_format_note() {
+    let srt = std::env::temp_dir().join(format!("uroman-sniffed-{}.txt", std::process::id()));
+    let text = srt.with_extension("numbered.txt");
+    fs::write(&srt, "1\n00:00:01,000 --> 00:00:02,500\nКиїв\n").unwrap();
+    fs::write(&text, "2024\nКиїв\n").unwrap();
+    let romanize = |input: &std::path::Path, args: &[&str]| {
+        let mut cmd = Command::cargo_bin("uroman-rs").unwrap();
+        let output = cmd.args(args).arg("-i").arg(input).assert().success();
+        let output = output.get_output();
+        (
+            String::from_utf8(output.stdout.clone()).unwrap(),
+            String::from_utf8(output.stderr.clone()).unwrap(),
+        )
+    };
+
+    let (_, srt_note) = romanize(&srt, &[]);
+    let (_, silent_note) = romanize(&srt, &["--silent"]);
+    // A leading number line doesn't make plain text subtitles.
+    let (text_output, text_note) = romanize(&text, &[]);
+    fs::remove_file(&srt).unwrap();
+    fs::remove_file(&text).unwrap();
+    assert!(srt_note.contains("Reading the input as SubRip subtitles."));
+    assert_eq!(silent_note, "");
+    assert_eq!(text_output, "2024\nKiyiv\n");
+    assert!(!text_note.contains("Reading the input as"));
+}
+
+#[test]
+fn test_silent_non_utf8_lines() {
+    let input = std::env::temp_dir().join(format!("uroman-non-utf8-{}.txt", std::process::id()));
+    fs::write(&input, b"a\xffb\n").unwrap();
+    let stderr = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("uroman-rs").unwrap();
+        let output = cmd.args(args).arg("-i").arg(&input).assert().success();
+        String::from_utf8(output.get_output().stderr.clone()).unwrap()
+    };
+
+    let note = stderr(&[]);
+    let silent_note = stderr(&["--silent"]);
+    fs::remove_file(&input).unwrap();
+    assert!(note.contains("Total number of lines with non-UTF-8 characters: 1\n"));
+    assert_eq!(silent_note, "");
+}
+
+#[test]
+fn test_direct_input_named_like_a_subcommand() {
+    let output = Command::cargo_bin("uroman-rs")
+        .unwrap()
+        .args(["--", "compare", "Київ"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(String::from_utf8(output).unwrap(), "compare\nKiyiv\n");
+    // Without '--', the first word is the subcommand.
+    Command::cargo_bin("uroman-rs")
+        .unwrap()
+        .arg("compare")
+        .assert()
+        .failure();
+}
@@ -1,4 +1,4 @@
-use uroman::{Uroman, rom_format};
+use uroman::{LineError, RomFormat, RomanizationCache, RomanizationResult, Uroman, rom_format};
 
 #[track_caller]
 fn assert_romanizes_to_str(uroman: &Uroman, input: &str, lcode: Option<&str>, expected_str: &str) {
@@ -64,6 +64,20 @@ fn test_ascii_passthrough() {
     assert_romanizes_to_str(&uroman, "Hello World!", None, "Hello World!");
     assert_romanizes_to_str(&uroman, "12345", None, "12345");
     assert_romanizes_to_str(&uroman, "!@#$%^&*()", None, "!@#$%^&*()");
+
+    // ASCII around non-ASCII text is copied through without changing the romanization.
+    assert_romanizes_to_str(
+        &uroman,
+        "Room 12 in Ελληνικά-speaking (北京) area, phone 555-0199.",
+        None,
+        "Room 12 in Ellenika-speaking (beijing) area, phone 555-0199.",
+    );
+    assert_romanizes_to_str(
+        &uroman,
+        "Ελληνικά and some ASCII after it",
+        None,
+        "Ellenika and some ASCII after it",
+    );
 }
 
 #[test]
@@ -442,6 +456,18 @@ fn test_generic_tibetan() {
     assert_romanizes_to_str(&uroman, "ལྷ་ས་གྲོང་ཁྱེར", None, "lha·sa·grong·khyer");
 }
 
+#[test]
+fn test_tibetan_syllable_ending_in_subjoined_letters() {
+    // A syllable whose last letter is considered for the vowel has no suffix to check.
+    let uroman = Uroman::new();
+    assert_romanizes_to_str(
+        &uroman,
+        "ཆའ༟8\u{fe2}\u{fcd}\u{fcf}\u{f9c}\u{f97}\u{fad}",
+        None,
+        "cha༟8\u{fe2}\u{fcd}\u{fcf}ddjwa",
+    );
+}
+
 #[test]
 fn test_generic_inuktitut() {
     let uroman = Uroman::new();
@@ -463,3 +489,2667 @@ fn test_generic_tifinagh() {
         "amagrad 1 ar d ttlalan middn gan ilellitn mgaddan gh waddur d izrfan, yili ak darsn unlli d ufrak, illa flla sn ad ttmyawasn ngratsn s tagmat.",
     );
 }
+
+#[test]
+fn test_long_line_windows() {
+    let uroman = Uroman::new();
+    let word = "Ελληνικά ";
+    let input = word.repeat(2000);
+    let n_chars = input.chars().count();
+
+    let str = uroman
+        .romanize_string::<rom_format::Str>(&input, None)
+        .to_output_string();
+    assert_eq!(str, "Ellenika ".repeat(2000));
+
+    // Edge offsets continue across windows.
+    let RomanizationResult::Edges(edges) =
+        uroman.romanize_with_format(&input, None, Some(RomFormat::Edges))
+    else {
+        panic!("expected edges");
+    };
+    assert!(edges.windows(2).all(|w| w[0].end() <= w[1].start()));
+    assert_eq!(edges.last().unwrap().end(), n_chars);
+}
+
+#[test]
+fn test_edge_cap_greedy_fallback() {
+    let uroman = Uroman::new();
+    let output = uroman.romanize_string::<rom_format::Str>("三千五百", None);
+    assert!(!output.used_greedy_fallback());
+    assert_eq!(output.to_output_string(), "3500");
+
+    // The number edges exceed a cap of one edge per span.
+    let uroman = uroman.with_max_edges_per_span(Some(1));
+    let output = uroman.romanize_string::<rom_format::Str>("三千五百", None);
+    assert!(output.used_greedy_fallback());
+    assert_eq!(output.to_output_string(), "sanqianwubai");
+
+    let output = uroman.romanize_string::<rom_format::Edges>("三千五百", None);
+    assert!(output.used_greedy_fallback());
+    assert!(output.to_output_string().unwrap().contains("\"greedy\""));
+}
+
+#[test]
+fn test_lattice_explorer() {
+    let uroman = Uroman::new();
+    let mut explorer = uroman.explore_lattice("チェコ", None);
+
+    assert_eq!(explorer.len(), 3);
+    assert_eq!(explorer.best_path_string(), "cheko");
+
+    let spans = explorer.spans().to_vec();
+    assert!(spans.iter().all(|s| !s.edges.is_empty()));
+
+    // Forcing the single-character edge for "チ" must change the path.
+    let single = spans
+        .iter()
+        .find(|s| s.start == 0 && s.end == 1)
+        .map(|s| s.edges[0].clone())
+        .unwrap();
+    let forced: String = explorer
+        .best_path_through(&single)
+        .iter()
+        .map(|e| e.txt())
+        .collect();
+    assert_ne!(forced, "cheko");
+    assert!(forced.ends_with("ko"));
+}
+
+#[test]
+fn test_romanize_file_with_cache() {
+    let uroman = Uroman::new();
+    let input = "Ελληνικά\nहिन्दी\nΕλληνικά\n::lcode hin हिन्दी\nΕλληνικά\n";
+
+    let mut expected = Vec::new();
+    uroman
+        .romanize_file(
+            input.as_bytes(),
+            &mut expected,
+            None,
+            RomFormat::Str,
+            None,
+            false,
+            true,
+        )
+        .unwrap();
+
+    let mut cache = RomanizationCache::new(2.try_into().unwrap());
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_context(
+            input.as_bytes(),
+            &mut output,
+            &uroman::RomanizeOptions::new(),
+            uroman::FileContext::new().with_cache(&mut cache),
+        )
+        .unwrap();
+
+    assert_eq!(output, expected);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.stats().hits, 2);
+    assert_eq!(cache.stats().misses, 3);
+}
+
+#[test]
+fn test_romanize_file_edges() {
+    let uroman = Uroman::new();
+    let input = "Ελληνικά 12\n::lcode hin हिन्दी\n";
+
+    // Without a cache the edges are written straight to the output, with one they are
+    // serialized to a string first.
+    let mut streamed = Vec::new();
+    uroman
+        .romanize_file(
+            input.as_bytes(),
+            &mut streamed,
+            None,
+            RomFormat::Edges,
+            None,
+            false,
+            true,
+        )
+        .unwrap();
+
+    let mut cache = RomanizationCache::new(2.try_into().unwrap());
+    let mut buffered = Vec::new();
+    uroman
+        .romanize_file_with_context(
+            input.as_bytes(),
+            &mut buffered,
+            &uroman::RomanizeOptions::new().with_format(RomFormat::Edges),
+            uroman::FileContext::new().with_cache(&mut cache),
+        )
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(streamed).unwrap(),
+        String::from_utf8(buffered).unwrap()
+    );
+}
+
+#[test]
+fn test_romanize_file_with_progress() {
+    let uroman = Uroman::new();
+    let input = ["Ελληνικά\r\n".as_bytes(), b"\xff\n", b"ok"].concat();
+
+    let mut reports = Vec::new();
+    let mut progress = |progress| reports.push(progress);
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_context(
+            input.as_slice(),
+            &mut output,
+            &uroman::RomanizeOptions::new(),
+            uroman::FileContext::new().with_progress(&mut progress),
+        )
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "Ellenika\n\u{fffd}\nok\n"
+    );
+    let summary: Vec<_> = reports
+        .iter()
+        .map(|p| (p.lines, p.bytes, p.error))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, 18, None),
+            (2, 20, Some(LineError::NonUtf8)),
+            (3, 22, None)
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_utf8_policy() {
+    use uroman::{InvalidUtf8Policy, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    let input = ["Ελληνικά\n".as_bytes(), b"\xff\n", b"ok\n"].concat();
+    for (invalid_utf8, expected) in [
+        (InvalidUtf8Policy::Replace, "Ellenika\n\u{fffd}\nok\n"),
+        (InvalidUtf8Policy::SkipLine, "Ellenika\nok\n"),
+    ] {
+        let options = RomanizeOptions::new().with_invalid_utf8(invalid_utf8);
+        let mut output = Vec::new();
+        let summary = uroman
+            .romanize_file_with_options(input.as_slice(), &mut output, &options)
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+        assert_eq!((summary.lines, summary.non_utf8_lines), (3, 1));
+    }
+
+    let options = RomanizeOptions::new().with_invalid_utf8(InvalidUtf8Policy::Error);
+    let mut output = Vec::new();
+    let result = uroman.romanize_file_with_options(input.as_slice(), &mut output, &options);
+    let Err(uroman::RomanizationError::AtLine {
+        line,
+        byte_offset,
+        snippet,
+        source,
+    }) = result
+    else {
+        panic!("expected an error at a line, got {result:?}");
+    };
+    assert_eq!((line, byte_offset, snippet.as_str()), (2, 17, "\u{fffd}"));
+    assert!(matches!(
+        *source,
+        uroman::RomanizationError::Io(e) if e.kind() == std::io::ErrorKind::InvalidData
+    ));
+    assert_eq!(output, b"Ellenika\n");
+}
+
+#[test]
+fn test_romanize_file_error_location() {
+    struct FailingReader;
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk failure"))
+        }
+    }
+
+    let uroman = Uroman::new();
+    let long_line = "ab".repeat(30);
+    let input = format!("Київ\n{long_line}");
+    let reader = std::io::Read::chain(input.as_bytes(), FailingReader);
+    let error = uroman
+        .romanize_file_with_options(
+            std::io::BufReader::new(reader),
+            std::io::sink(),
+            &uroman::RomanizeOptions::new(),
+        )
+        .unwrap_err();
+    let snippet = &long_line[..40];
+    assert_eq!(
+        error.to_string(),
+        format!("Line 2 (byte offset 9, \"{snippet}…\"): disk failure")
+    );
+}
+
+#[test]
+fn test_romanize_file_failing_writer() {
+    /// Accepts `capacity` bytes, then fails.
+    struct FailingWriter {
+        capacity: usize,
+    }
+
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.capacity == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::StorageFull,
+                    "disk full",
+                ));
+            }
+            let n = buf.len().min(self.capacity);
+            self.capacity -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let uroman = Uroman::new();
+    let input = "Київ\nΕλληνικά\n";
+    for rom_format in [RomFormat::Str, RomFormat::Edges, RomFormat::Lattice] {
+        for cache_size in [None, std::num::NonZeroUsize::new(8)] {
+            let options = uroman::RomanizeOptions::new().with_format(rom_format);
+            let writer = FailingWriter { capacity: 6 };
+            let result = match cache_size {
+                Some(size) => uroman.romanize_file_with_context(
+                    input.as_bytes(),
+                    writer,
+                    &options,
+                    uroman::FileContext::new().with_cache(&mut RomanizationCache::new(size)),
+                ),
+                None => uroman.romanize_file_with_options(input.as_bytes(), writer, &options),
+            };
+            let Err(uroman::RomanizationError::AtLine { line, source, .. }) = result else {
+                panic!("expected an error at a line, got {result:?}");
+            };
+            // Even the first line of the edge formats is longer than 6 bytes.
+            let expected_line = if rom_format == RomFormat::Str { 2 } else { 1 };
+            assert_eq!(line, expected_line, "{rom_format:?}");
+            assert!(
+                matches!(
+                    *source,
+                    uroman::RomanizationError::Io(ref e)
+                        if e.kind() == std::io::ErrorKind::StorageFull
+                ),
+                "{rom_format:?}: {source:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_romanize_file_summary() {
+    let uroman = Uroman::new();
+    let input = "Київ\nhello, world\nΑθήνα 2024\n";
+    let mut output = Vec::new();
+    let summary = uroman
+        .romanize_file_with_options(
+            input.as_bytes(),
+            &mut output,
+            &uroman::RomanizeOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(summary.lines, 3);
+    assert_eq!(summary.non_utf8_lines, 0);
+    assert_eq!(summary.bytes, input.len() as u64);
+    assert_eq!(summary.changed_lines, 2);
+    assert_eq!(
+        summary.per_script_counts,
+        [("Cyrillic", 4), ("Greek", 5), ("Latin", 10)]
+            .into_iter()
+            .map(|(script, count)| (script.to_string(), count))
+            .collect()
+    );
+}
+
+#[test]
+fn test_romanize_file_strips_bom() {
+    let uroman = Uroman::new();
+    let input = "\u{FEFF}Київ\n";
+
+    // The edges of the first line start at its first char after the byte order mark.
+    let options = uroman::RomanizeOptions::new().with_format(RomFormat::Edges);
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_options(input.as_bytes(), &mut output, &options)
+        .unwrap();
+    let RomanizationResult::Edges(edges) =
+        uroman.romanize_with_format("Київ", None, Some(RomFormat::Edges))
+    else {
+        panic!("expected edges");
+    };
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&output).unwrap(),
+        serde_json::to_value(edges).unwrap()
+    );
+
+    let options = uroman::RomanizeOptions::new().with_keep_bom(true);
+    for (input, expected) in [(input, "\u{FEFF}Kiyiv\n"), ("Київ\n", "Kiyiv\n")] {
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_options(input.as_bytes(), &mut output, &options)
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_romanize_file_utf16() {
+    let uroman = Uroman::new();
+    // The emoji is a surrogate pair in UTF-16.
+    let text = "Київ\r\nΕλληνικά 😀\n";
+    let mut expected = Vec::new();
+    uroman
+        .romanize_file_with_options(
+            text.as_bytes(),
+            &mut expected,
+            &uroman::RomanizeOptions::new(),
+        )
+        .unwrap();
+
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    let le: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+    let be: Vec<u8> = utf16.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+    for (bom, bytes, encoding) in [
+        (&[0xFF, 0xFE][..], &le, uroman::InputEncoding::Auto),
+        (&[0xFE, 0xFF][..], &be, uroman::InputEncoding::Auto),
+        (&[][..], &le, uroman::InputEncoding::Utf16Le),
+        (&[][..], &be, uroman::InputEncoding::Utf16Be),
+    ] {
+        let input = [bom, bytes].concat();
+        // A 3-byte buffer splits code units and surrogate pairs between chunks.
+        let reader = std::io::BufReader::with_capacity(3, input.as_slice());
+        let options = uroman::RomanizeOptions::new().with_input_encoding(encoding);
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_options(reader, &mut output, &options)
+            .unwrap();
+        assert_eq!(output, expected, "{encoding:?}");
+    }
+
+    // An unpaired surrogate and a trailing odd byte are replaced with U+FFFD.
+    let input = [0xFF, 0xFE, 0x00, 0xD8, b'a', 0x00, b'\n', 0x00, b'b'];
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_options(&input[..], &mut output, &uroman::RomanizeOptions::new())
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "\u{FFFD}a\n\u{FFFD}\n");
+}
+
+#[test]
+fn test_romanize_file_with_utt_ids() {
+    let uroman = Uroman::new();
+    let input = "utt1 Київ є столицею\nutt_2\tПривет\nutt3\n\n";
+    let options = uroman::RomanizeOptions::new()
+        .with_lcode("ukr")
+        .with_utt_ids(true);
+
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_options(input.as_bytes(), &mut output, &options)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "utt1 Kyiv ie stolytseyu\nutt_2\tPryvet\nutt3\n\n"
+    );
+
+    // The edges of the transcript follow a meta edge with the ID, and are the same with a cache.
+    let options = options.with_format(RomFormat::Edges);
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_options("utt1 Київ\n".as_bytes(), &mut output, &options)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let edges = uroman
+        .romanize_with_format("Київ", Some("ukr"), Some(RomFormat::Edges))
+        .to_output_string()
+        .unwrap();
+    assert_eq!(
+        output,
+        format!(
+            "[[0,0,\"\",\"utt-id: utt1\"],{}\n",
+            edges.strip_prefix('[').unwrap()
+        )
+    );
+
+    let mut cache = RomanizationCache::new(2.try_into().unwrap());
+    let mut cached = Vec::new();
+    uroman
+        .romanize_file_with_context(
+            "utt1 Київ\n".as_bytes(),
+            &mut cached,
+            &options,
+            uroman::FileContext::new().with_cache(&mut cache),
+        )
+        .unwrap();
+    assert_eq!(String::from_utf8(cached).unwrap(), output);
+}
+
+#[test]
+fn test_romanize_with_lcode_spans() {
+    let uroman = Uroman::new();
+    let text = "Київ, Київ, Київ";
+    let spans = [(12..99, "ukr"), (6..10, "ukr")];
+    let result = uroman.romanize_with_lcode_spans::<rom_format::Str>(text, None, &spans);
+    assert_eq!(result.to_output_string(), "Kiyiv, Kyiv, Kyiv");
+
+    // Edge offsets are char offsets in the whole text.
+    let result = uroman.romanize_with_lcode_spans::<rom_format::Edges>(text, None, &spans);
+    let edges: Vec<serde_json::Value> =
+        serde_json::from_str(&result.to_output_string().unwrap()).unwrap();
+    let yi_spans: Vec<_> = edges
+        .iter()
+        .map(|edge| &edge["Regular"])
+        .filter(|edge| edge["txt"] == "yi")
+        .map(|edge| (edge["start"].as_u64(), edge["end"].as_u64()))
+        .collect();
+    // Without `ukr`, only `ї` is romanized as `yi`.
+    assert_eq!(
+        yi_spans,
+        [(Some(2), Some(3)), (Some(7), Some(9)), (Some(13), Some(15))]
+    );
+}
+
+#[test]
+fn test_with_numbers() {
+    let uroman = Uroman::new().with_numbers(false);
+    let romanize = |s| {
+        uroman
+            .romanize_string::<rom_format::Str>(s, None)
+            .to_output_string()
+    };
+    assert_eq!(romanize("۱۳۹۸"), "۱۳۹۸");
+    assert_eq!(romanize("٣٤٥ كتاب"), "٣٤٥ ktab");
+    assert_eq!(romanize("三百二十"), "sanbaiershi");
+
+    let uroman = uroman.with_numbers(true);
+    let result = uroman.romanize_string::<rom_format::Str>("۱۳۹۸", None);
+    assert_eq!(result.to_output_string(), "1398");
+}
+
+#[test]
+fn test_subsystems() {
+    use uroman::{RomanizeOptions, Subsystems};
+
+    let uroman = Uroman::new();
+    let romanize = |s, subsystems| {
+        let options = RomanizeOptions::new().with_subsystems(subsystems);
+        uroman
+            .romanize_with_options(s, &options)
+            .to_output_string()
+            .unwrap()
+    };
+    let all = Subsystems::default();
+    assert_eq!(romanize("你好", all.with_pinyin(false)), "你好");
+    assert_eq!(romanize("한국어", all.with_hangul(false)), "한국어");
+    assert_eq!(romanize("नमस्ते", all.with_abugida_vowels(false)), "nmste");
+    assert_eq!(
+        romanize("नमस्ते 你好", all.with_hangul(false)),
+        "namaste nihao"
+    );
+
+    let options: RomanizeOptions =
+        serde_json::from_str(r#"{ "subsystems": { "pinyin": false } }"#).unwrap();
+    assert_eq!(options.output.subsystems, Some(all.with_pinyin(false)));
+    assert_eq!(
+        uroman.romanize_with_options("你好 한국어", &options),
+        RomanizationResult::Str("你好 hangugeo".to_string())
+    );
+
+    let uroman = Uroman::new().with_subsystems(all.with_hangul(false));
+    let result = uroman.romanize_string::<rom_format::Str>("한국어", None);
+    assert_eq!(result.to_output_string(), "한국어");
+}
+
+#[test]
+fn test_format_char_policy() {
+    use uroman::FormatCharPolicy;
+
+    let text = "\u{200e}می\u{200c}خواهم";
+    let romanize = |policy| {
+        Uroman::new()
+            .with_format_chars(policy)
+            .romanize_string::<rom_format::Str>(text, None)
+            .to_output_string()
+    };
+    assert_eq!(romanize(FormatCharPolicy::Drop), "mikhwahm");
+    assert_eq!(romanize(FormatCharPolicy::Keep), "\u{200e}mi\u{200c}khwahm");
+    // The bidi mark at the start is not between two words.
+    assert_eq!(romanize(FormatCharPolicy::Boundary), "mi khwahm");
+}
+
+#[test]
+fn test_romanize_tokens_with_lcodes() {
+    let uroman = Uroman::new();
+    let tokens = [
+        ("lol", Some("eng")),
+        ("щука", Some("bul")),
+        ("Києві", Some("uk")),
+        ("", None),
+    ];
+    let outputs = uroman.romanize_tokens_with_lcodes::<rom_format::Str>(&tokens);
+    let romanized: Vec<String> = outputs
+        .into_iter()
+        .map(|out| out.to_output_string())
+        .collect();
+    assert_eq!(romanized, ["lol", "shtuka", "Kyievi", ""]);
+
+    // Edge offsets start over at each token.
+    let outputs = uroman.romanize_tokens_with_lcodes::<rom_format::Edges>(&tokens[1..3]);
+    for output in outputs {
+        let edges: Vec<serde_json::Value> =
+            serde_json::from_str(&output.to_output_string().unwrap()).unwrap();
+        assert_eq!(edges[0]["Regular"]["start"], 0);
+    }
+}
+
+#[test]
+fn test_romanize_file_inline_lcodes() {
+    let uroman = Uroman::new();
+    let input = "Київ ⟦lcode:ukr⟧Київ⟦/lcode⟧ Київ\n\
+                 ::lcode ukr Київ ⟦lcode:rus⟧Київ\n\
+                 ⟦lcode:ukr Київ\n";
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_options(
+            input.as_bytes(),
+            &mut output,
+            &uroman::RomanizeOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "Kiyiv Kyiv Kiyiv\n::lcode ukr Kyiv Kiyiv\n⟦lcode:ukr Kiyiv\n"
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_romanize_file_parallel() {
+    use uroman::RomanizeOptions;
+
+    let uroman = Uroman::new();
+    let samples = [
+        "Ελληνικά",
+        "हिन्दी",
+        "::lcode jpn 日本",
+        "ⴰⵎⴰⴳⵔⴰⴷ",
+        "한국어",
+        "",
+    ];
+    let input: String = samples
+        .iter()
+        .cycle()
+        .take(5000)
+        .map(|s| format!("{s}\n"))
+        .collect();
+
+    let options = RomanizeOptions::new().with_silent(true);
+    for options in [
+        options.clone(),
+        options.with_format(RomFormat::Edges).with_max_lines(4100),
+    ] {
+        let mut expected = Vec::new();
+        let expected_summary = uroman
+            .romanize_file_with_options(input.as_bytes(), &mut expected, &options)
+            .unwrap();
+
+        let mut output = Vec::new();
+        let summary = uroman
+            .romanize_file_parallel(input.as_bytes(), &mut output, &options)
+            .unwrap();
+
+        assert_eq!(output, expected);
+        assert_eq!(summary, expected_summary);
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_romanize_file_parallel_crlf() {
+    use uroman::{LineEnding, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    let romanize = |input: &str, options: &RomanizeOptions, parallel: bool| {
+        let mut output = Vec::new();
+        let result = if parallel {
+            uroman.romanize_file_parallel(input.as_bytes(), &mut output, options)
+        } else {
+            uroman.romanize_file_with_options(input.as_bytes(), &mut output, options)
+        };
+        result.map(|_| String::from_utf8(output).unwrap())
+    };
+
+    let input = "Ελληνικά\r\n::lcode ukr Київ\r\n\r\nहिन्दी";
+    for rom_format in [RomFormat::Str, RomFormat::Edges] {
+        for line_ending in [LineEnding::Lf, LineEnding::Preserve] {
+            let options = RomanizeOptions::new()
+                .with_format(rom_format)
+                .with_line_ending(line_ending.clone());
+            let expected = romanize(input, &options, false).unwrap();
+            assert_eq!(expected.contains('\r'), line_ending == LineEnding::Preserve);
+            assert_eq!(romanize(input, &options, true).unwrap(), expected);
+        }
+    }
+
+    let input = "Київ\r\n☃ Київ\r\n";
+    let options = RomanizeOptions::new().with_strict(true);
+    let expected = romanize(input, &options, false).unwrap_err();
+    let error = romanize(input, &options, true).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("Line 2 (byte offset 10, \"☃ Київ\")")
+    );
+    assert_eq!(error.to_string(), expected.to_string());
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_romanize() {
+    use rayon::prelude::*;
+    use uroman::ParallelRomanize;
+
+    let uroman = Uroman::new();
+    let lines: Vec<String> = ["Ελληνικά", "हिन्दी", "ⴰⵎⴰⴳⵔⴰⴷ", "한국어", ""]
+        .iter()
+        .cycle()
+        .take(1000)
+        .map(|s| s.to_string())
+        .collect();
+
+    let options = uroman::RomanizeOptions::new().with_format(RomFormat::Edges);
+    let romanized = lines.par_iter().romanize_to_strings(&uroman, &options);
+    assert_eq!(romanized.len(), lines.len());
+    for (line, result) in lines.iter().zip(romanized) {
+        assert_eq!(
+            result.unwrap(),
+            uroman
+                .romanize_with_format(line, None, Some(RomFormat::Edges))
+                .to_output_string()
+                .unwrap()
+        );
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "current_thread")]
+async fn test_romanize_file_async() {
+    let uroman = Uroman::new();
+    let input = "Ελληνικά\n::lcode ukr Київ\nहिन्दी\r\n한국어\n";
+
+    for rom_format in [RomFormat::Str, RomFormat::Alts] {
+        let options = uroman::RomanizeOptions::new()
+            .with_format(rom_format)
+            .with_line_ending(uroman::LineEnding::Preserve);
+        let mut expected = Vec::new();
+        let expected_summary = uroman
+            .romanize_file_with_options(input.as_bytes(), &mut expected, &options)
+            .unwrap();
+
+        let mut output = Vec::new();
+        let summary = uroman
+            .romanize_file_async(input.as_bytes(), &mut output, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(output, expected);
+        assert_eq!(summary, expected_summary);
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_encoding_errors_are_traced() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    /// Counts the warnings, and ignores everything else.
+    #[derive(Default)]
+    struct WarningCounter(AtomicUsize);
+
+    impl Subscriber for &'static WarningCounter {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            if *event.metadata().level() == Level::WARN {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let counter: &'static WarningCounter = Box::leak(Box::default());
+    let uroman = Uroman::new();
+    let input = [b"ok\n".as_slice(), b"\xff\n", b"\xfe\n"].concat();
+    tracing::subscriber::with_default(counter, || {
+        uroman
+            .romanize_file(
+                input.as_slice(),
+                Vec::new(),
+                None,
+                RomFormat::Str,
+                None,
+                false,
+                true,
+            )
+            .unwrap();
+    });
+
+    // One warning per broken line, and one with the total.
+    assert_eq!(counter.0.load(Ordering::Relaxed), 3);
+}
+
+#[cfg(feature = "detect-lcode")]
+#[test]
+fn test_detect_lcode() {
+    let uroman = Uroman::new();
+    let text = "Київ є столицею та найбільшим містом України";
+    assert_eq!(uroman.detect_lcode(text), Some("ukr"));
+    assert_eq!(
+        uroman.romanize_with_detected_lcode(text, None),
+        uroman.romanize_with_format(text, Some("ukr"), None)
+    );
+    assert_eq!(uroman.detect_lcode("ⴰⵎⴰⴳⵔⴰⴷ"), None);
+    assert_eq!(uroman.detect_lcode("12345"), None);
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn test_stage_timings() {
+    let uroman = Uroman::new();
+    uroman.reset_stage_timings();
+
+    uroman.romanize_string::<rom_format::Str>("三千五百 Ελληνικά", None);
+    uroman.romanize_string::<rom_format::Edges>("⠼⠁⠃", None);
+    uroman.romanize_string::<rom_format::Str>("ASCII only", None);
+
+    let timings = uroman.stage_timings();
+    assert_eq!(timings.calls, 2);
+    assert!(timings.rule_matching > std::time::Duration::ZERO);
+    assert_eq!(
+        timings.total(),
+        timings.rule_matching + timings.numbers + timings.braille + timings.best_path
+    );
+
+    uroman.reset_stage_timings();
+    assert_eq!(uroman.stage_timings(), Default::default());
+}
+
+#[cfg(feature = "snapshot")]
+#[test]
+fn test_state_snapshot_roundtrip() {
+    let uroman = Uroman::new();
+    let mut buf = Vec::new();
+    uroman.save_state(&mut buf).unwrap();
+
+    let restored = Uroman::load_state(buf.as_slice()).unwrap();
+    assert_eq!(restored.max_edges(), uroman.max_edges());
+    assert!(restored.max_edges().is_some());
+    // The last three depend on the rule priority of `Uroman::new`.
+    for (input, lcode) in [
+        ("ⴰⵎⴰⴳⵔⴰⴷ", None),
+        ("Ελληνικά", None),
+        ("हिन्दी", Some("hin")),
+        ("北京", Some("zho")),
+        ("ภาษาไทย", None),
+        ("Բարեւ", None),
+        ("Türkçe", None),
+        ("ґ", Some("ukr")),
+    ] {
+        assert_eq!(
+            restored
+                .romanize_string::<rom_format::Str>(input, lcode)
+                .to_output_string(),
+            uroman
+                .romanize_string::<rom_format::Str>(input, lcode)
+                .to_output_string(),
+        );
+    }
+
+    // A truncated snapshot must be rejected rather than yield a partial state.
+    assert!(Uroman::load_state(&buf[..buf.len() / 2]).is_err());
+}
+
+#[cfg(feature = "tower")]
+#[test]
+fn test_romanize_service() {
+    use tower_service::Service;
+
+    let uroman = std::sync::Arc::new(Uroman::new());
+    let mut service = uroman::RomanizeService::new(uroman.clone());
+    let result = service.call("Ελληνικά".to_string()).into_inner().unwrap();
+    assert_eq!(result, RomanizationResult::Str("Ellenika".to_string()));
+
+    let mut service = service.with_lcode("ukr").with_format(RomFormat::Edges);
+    let result = service.call("Київ".to_string()).into_inner().unwrap();
+    assert_eq!(
+        result,
+        uroman.romanize_with_format("Київ", Some("ukr"), Some(RomFormat::Edges))
+    );
+
+    #[cfg(feature = "axum")]
+    {
+        use axum_core::response::IntoResponse;
+
+        let response = result.into_response();
+        assert_eq!(response.headers()["content-type"], "application/json");
+    }
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn test_serve_language_server() {
+    use serde_json::{Value, json};
+
+    let messages = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {
+                "uri": "file:///a.txt",
+                "text": "Capital: Київ є столицею.\n😀 Ελληνικά",
+            },
+        } }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {
+            "textDocument": { "uri": "file:///a.txt" },
+            "position": { "line": 0, "character": 12 },
+        } }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/inlayHint", "params": {
+            "textDocument": { "uri": "file:///a.txt" },
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 1, "character": 0 },
+            },
+        } }),
+        json!({ "jsonrpc": "2.0", "id": 4, "method": "textDocument/definition", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 5, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ];
+    let mut input = Vec::new();
+    for message in &messages {
+        let content = message.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{content}", content.len()).bytes());
+    }
+
+    let uroman = Uroman::new();
+    let mut output = Vec::new();
+    uroman
+        .serve_language_server(input.as_slice(), &mut output, Some("ukr"))
+        .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let responses: Vec<Value> = output
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|message| serde_json::from_str(message.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect();
+    assert_eq!(responses.len(), 5);
+    let capabilities = &responses[0]["result"]["capabilities"];
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(
+        responses[1]["result"],
+        json!({
+            "contents": { "kind": "plaintext", "value": "Kyiv ie stolytseyu" },
+            "range": {
+                "start": { "line": 0, "character": 9 },
+                "end": { "line": 0, "character": 24 },
+            },
+        })
+    );
+    // Positions are in UTF-16 code units, so the hint on the second line is after the
+    // surrogate pair of the emoji.
+    let hints = responses[2]["result"].as_array().unwrap();
+    assert_eq!(hints.len(), 2);
+    assert_eq!(hints[0]["label"], "Kyiv ie stolytseyu");
+    assert_eq!(hints[1]["label"], "Ellenika");
+    assert_eq!(hints[1]["position"], json!({ "line": 1, "character": 11 }));
+    assert_eq!(responses[3]["error"]["code"], -32601);
+    assert_eq!(responses[4]["id"], 5);
+}
+
+#[test]
+fn test_align() {
+    let uroman = Uroman::new();
+    let alignment = uroman.align("Київ", Some("ukr"));
+    assert_eq!(alignment.romanized, "Kyiv");
+    assert_eq!(alignment.orig_to_rom, [[0, 1], [1, 3], [1, 3], [3, 4]]);
+    assert_eq!(alignment.rom_to_orig, [[0, 1], [1, 3], [1, 3], [3, 4]]);
+    assert_eq!(
+        serde_json::to_string(&alignment).unwrap(),
+        r#"{"romanized":"Kyiv","orig_to_rom":[[0,1],[1,3],[1,3],[3,4]],"rom_to_orig":[[0,1],[1,3],[1,3],[3,4]]}"#
+    );
+
+    for text in ["三千五百 yuan", "กษัตริย์ Ελληνικά", ""] {
+        let alignment = uroman.align(text, None);
+        let expected = uroman
+            .romanize_string::<rom_format::Str>(text, None)
+            .to_output_string();
+        assert_eq!(alignment.romanized, expected);
+        assert_eq!(alignment.orig_to_rom.len(), text.chars().count());
+        assert_eq!(alignment.rom_to_orig.len(), expected.chars().count());
+        let n_orig = alignment.orig_to_rom.len();
+        let n_rom = alignment.rom_to_orig.len();
+        assert_eq!(alignment.to_romanized(0..n_orig), 0..n_rom);
+        assert_eq!(alignment.to_original(0..n_rom), 0..n_orig);
+    }
+}
+
+#[test]
+fn test_to_icu_rules() {
+    let uroman = Uroman::new();
+    let rules = uroman.to_icu_rules(None);
+    assert!(rules.starts_with("# Romanization rules exported from uroman-rs"));
+    assert!(rules.contains("\nи > i ;\n"));
+    // Rules for other languages are left out, and rules restricted to `lcode` come first.
+    assert!(!rules.contains("\nи > y ;\n"));
+    let ukr_rules = uroman.to_icu_rules(Some("ukr"));
+    assert!(ukr_rules.contains("\nи > y ;\n"));
+    assert!(!ukr_rules.contains("\nи > i ;\n"));
+    // Syntax chars are escaped.
+    assert!(rules.contains("\n\\u3002 > \\u002E\\u0020 ;\n"));
+}
+
+#[test]
+fn test_lcode_fallback_chain() {
+    let uroman = Uroman::new();
+    let romanize = |lcode| {
+        uroman
+            .romanize_string::<rom_format::Str>("Щука у Києві", Some(lcode))
+            .to_output_string()
+    };
+    // Bulgarian and Ukrainian both have a rule for `щ`, and only Ukrainian has one for `и`.
+    assert_eq!(romanize("bul"), "Shtuka u Kiievi");
+    assert_eq!(romanize("bul,ukr"), "Shtuka u Kyievi");
+    assert_eq!(romanize("ukr, bul"), "Shchuka u Kyievi");
+    assert_eq!(romanize("xyz,ukr"), romanize("ukr"));
+
+    let rules = uroman.to_icu_rules(Some("bul,ukr"));
+    assert!(rules.contains("\nщ > sht ;\n"));
+    assert!(!rules.contains("\nщ > shch ;\n"));
+}
+
+#[test]
+fn test_normalize_lcode() {
+    use uroman::{RomanizationError, normalize_lcode};
+
+    assert_eq!(normalize_lcode("uk").unwrap(), "ukr");
+    assert_eq!(normalize_lcode("zh-Hant").unwrap(), "zho");
+    assert_eq!(normalize_lcode("sr_Latn, RU").unwrap(), "srp,rus");
+    assert_eq!(normalize_lcode("chi").unwrap(), "zho");
+    assert_eq!(normalize_lcode("ukr,srp2").unwrap(), "ukr,srp2");
+    for tag in ["xx", "sr-", "ukr-", "", "u k"] {
+        assert!(
+            matches!(
+                normalize_lcode(tag),
+                Err(RomanizationError::UnknownLanguageTag(_))
+            ),
+            "{tag:?}"
+        );
+    }
+
+    let uroman = Uroman::new();
+    let romanize = |lcode| {
+        uroman
+            .romanize_string::<rom_format::Str>("Щука у Києві", Some(lcode))
+            .to_output_string()
+    };
+    assert_eq!(romanize("uk"), romanize("ukr"));
+    assert_eq!(romanize("bg-BG,uk"), romanize("bul,ukr"));
+}
+
+#[test]
+fn test_validate_lcode() {
+    use uroman::RomanizationError;
+
+    let uroman = Uroman::new();
+    let effective_lcodes = uroman.effective_lcodes();
+    for lcode in ["ukr", "srp2", "san", "ben"] {
+        assert!(effective_lcodes.contains(lcode), "{lcode}");
+    }
+    assert!(!effective_lcodes.contains("fra"));
+
+    assert_eq!(uroman.validate_lcode("fra, uk").unwrap(), "fra,ukr");
+    for (lcode, expected) in [
+        ("bul,ukrr", vec!["ukr"]),
+        ("Ukrainian", vec!["ukr"]),
+        ("xx", vec![]),
+    ] {
+        let Err(RomanizationError::UnknownLanguageCode {
+            lcode: unknown,
+            suggestions,
+            effective_lcodes,
+        }) = uroman.validate_lcode(lcode)
+        else {
+            panic!("{lcode:?} was accepted");
+        };
+        assert_eq!(lcode.rsplit(',').next(), Some(unknown.as_str()));
+        assert_eq!(suggestions, expected);
+        assert!(effective_lcodes.iter().any(|lc| lc == "ukr"));
+    }
+}
+
+#[test]
+fn test_romanizer_backend() {
+    use uroman::RomanizerBackend;
+
+    struct Mock;
+
+    impl RomanizerBackend for Mock {
+        fn romanize_str(&self, s: &str, lcode: Option<&str>) -> String {
+            format!("{}:{s}", lcode.unwrap_or("-"))
+        }
+    }
+
+    let uroman = Uroman::new();
+    let backends: [&dyn RomanizerBackend; 2] = [&uroman, &Mock];
+    let outputs: Vec<_> = backends
+        .iter()
+        .map(|backend| backend.romanize_str("Київ", Some("ukr")))
+        .collect();
+    assert_eq!(outputs, ["Kyiv", "ukr:Київ"]);
+
+    assert_eq!(
+        RomanizerBackend::romanize_edges(&uroman, "Київ", Some("ukr")),
+        match uroman.romanize_with_format("Київ", Some("ukr"), Some(RomFormat::Edges)) {
+            RomanizationResult::Edges(edges) => edges,
+            RomanizationResult::Str(_) => unreachable!(),
+        }
+    );
+    let edges = Mock.romanize_edges("Київ", None);
+    assert_eq!(edges.len(), 1);
+    assert_eq!(
+        (edges[0].start(), edges[0].end(), edges[0].txt()),
+        (0, 4, "-:Київ")
+    );
+    assert_eq!(
+        Mock.romanize_format("", None, RomFormat::Lattice),
+        RomanizationResult::Edges(Vec::new())
+    );
+}
+
+#[test]
+fn test_incremental_romanization() {
+    let uroman = Uroman::new();
+    let mut romanization = uroman.incremental("Привет мир, こんにちは 世界", None);
+    let edits: [(std::ops::Range<usize>, &str); 5] = [
+        (7..10, "друг"),
+        (0..0, "Ой "),
+        (16..21, "さようなら"),
+        (3..9, ""),
+        (16..18, "三千五百"),
+    ];
+    for (range, replacement) in edits {
+        let replaced = romanization.apply_edit(range.clone(), replacement);
+        assert!(replaced.start <= range.start);
+
+        let text = romanization.text();
+        let expected = uroman
+            .romanize_string::<rom_format::Str>(&text, None)
+            .to_output_string();
+        assert_eq!(romanization.romanized(), expected, "after editing {text:?}");
+        assert!(
+            romanization
+                .edges()
+                .windows(2)
+                .all(|pair| pair[0].end() == pair[1].start())
+        );
+    }
+}
+
+#[test]
+fn test_display_positions() {
+    use uroman::RomanizeOptions;
+
+    let uroman = Uroman::new();
+    let options = RomanizeOptions::new()
+        .with_format(RomFormat::Edges)
+        .with_display_positions(true);
+    let RomanizationResult::Edges(edges) =
+        uroman.romanize_with_options("a שלום 12 עולם b", &options)
+    else {
+        panic!("expected edges");
+    };
+    let displayed: Vec<(&str, (usize, usize))> = edges
+        .iter()
+        .map(|edge| {
+            let data = edge.get_data();
+            (data.txt.as_ref(), data.display.unwrap())
+        })
+        .collect();
+    // The Hebrew words and the number between them are shown right to left, and the digits of
+    // the number left to right.
+    assert_eq!(
+        displayed,
+        [
+            ("a", (0, 1)),
+            (" ", (1, 2)),
+            ("sh", (13, 14)),
+            ("l", (12, 13)),
+            ("v", (11, 12)),
+            ("m", (10, 11)),
+            (" ", (9, 10)),
+            ("12", (7, 9)),
+            (" ", (6, 7)),
+            ("'", (5, 6)),
+            ("v", (4, 5)),
+            ("l", (3, 4)),
+            ("m", (2, 3)),
+            (" ", (14, 15)),
+            ("b", (15, 16)),
+        ]
+    );
+
+    // Without the option, edges have no display positions.
+    let RomanizationResult::Edges(edges) =
+        uroman.romanize_with_options("שלום", &options.with_display_positions(false))
+    else {
+        panic!("expected edges");
+    };
+    assert!(edges.iter().all(|edge| edge.get_data().display.is_none()));
+}
+
+#[test]
+fn test_combining_mark_policy() {
+    use uroman::CombiningMarkPolicy;
+
+    // No rule romanizes a candrabindu on a Bengali consonant, nor an acute accent after `e`.
+    let text = "ক\u{310} e\u{301}";
+    let romanize = |policy| {
+        Uroman::new()
+            .with_combining_marks(policy)
+            .romanize_string::<rom_format::Str>(text, None)
+            .to_output_string()
+    };
+    assert_eq!(romanize(CombiningMarkPolicy::Drop), "ka e");
+    assert_eq!(romanize(CombiningMarkPolicy::Attach), "ka\u{310} e\u{301}");
+    assert_eq!(
+        romanize(CombiningMarkPolicy::Name),
+        "ka[COMBINING CANDRABINDU] e[COMBINING ACUTE ACCENT]"
+    );
+    // Marks that rules romanize are not affected.
+    assert_eq!(
+        Uroman::new()
+            .with_combining_marks(CombiningMarkPolicy::Name)
+            .romanize_string::<rom_format::Str>("मैं", None)
+            .to_output_string(),
+        "maim"
+    );
+}
+
+#[test]
+fn test_unmappable_char_policy() {
+    use uroman::UnmappableCharPolicy;
+
+    let text = "Київ ☃, €5";
+    let romanize = |policy| {
+        Uroman::new()
+            .with_unmappable_chars(policy)
+            .romanize_string::<rom_format::Str>(text, None)
+            .to_output_string()
+    };
+    assert_eq!(romanize(UnmappableCharPolicy::Keep), "Kiyiv ☃, €5");
+    assert_eq!(
+        romanize(UnmappableCharPolicy::Replace("?".to_string())),
+        "Kiyiv ?, ?5"
+    );
+    assert_eq!(
+        romanize(UnmappableCharPolicy::Escape),
+        "Kiyiv \\u{2603}, \\u{20AC}5"
+    );
+}
+
+#[test]
+fn test_romanize_strict() {
+    use uroman::{RomanizationError, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    // Latin letters and punctuation pass through.
+    assert_eq!(
+        uroman.romanize_strict("«Київ», café", Some("ukr")).unwrap(),
+        "«Kyiv», cafe"
+    );
+    let result = uroman.romanize_strict("☃ Київ ☃ €", None);
+    let Err(RomanizationError::Unromanizable(chars)) = &result else {
+        panic!("expected unromanizable chars, got {result:?}");
+    };
+    assert_eq!(chars, &['☃', '€']);
+
+    // Files stop at the first line that can't be romanized.
+    let options = RomanizeOptions::new().with_strict(true);
+    let mut output = Vec::new();
+    let result = uroman.romanize_file_with_options("Київ\n5 €\n".as_bytes(), &mut output, &options);
+    let Err(RomanizationError::AtLine { line, source, .. }) = result else {
+        panic!("expected an error at a line, got {result:?}");
+    };
+    assert_eq!(line, 2);
+    assert!(matches!(*source, RomanizationError::Unromanizable(ref chars) if chars == &['€']));
+    assert_eq!(String::from_utf8(output).unwrap(), "Kiyiv\n");
+}
+
+#[test]
+fn test_rule_priority() {
+    use uroman::RulePriority;
+
+    // At the start of a word, the Pashto rule for `ای` ties with a rule for any language that
+    // only applies there, and the first loaded one is used.
+    let default = Uroman::new();
+    assert_romanizes_to_str(&default, "ایران", Some("pus"), "iran");
+    let by_language = Uroman::new()
+        .with_rule_priority(&[RulePriority::LanguageMatch, RulePriority::Restrictions]);
+    assert_romanizes_to_str(&by_language, "ایران", Some("pus"), "ayran");
+    assert_romanizes_to_str(&by_language, "ایران", None, "iran");
+    assert_romanizes_to_str(&by_language, "Бийск", Some("rus"), "Bysk");
+}
+
+#[test]
+fn test_rule_weights() {
+    let romanize = |uroman: &Uroman, s| {
+        uroman
+            .romanize_string::<rom_format::Str>(s, Some("pus"))
+            .to_output_string()
+    };
+    let mut uroman = Uroman::new();
+    assert_eq!(romanize(&uroman, "ایران"), "iran");
+    assert_eq!(romanize(&uroman, "αγκάθι"), "angathi");
+
+    let weights = "\
+# Prefer the Pashto rule at the start of words.
+::s ای ::t ay ::lcode pus ::weight 1
+
+::s γκ ::t ng ::weight -1
+";
+    uroman.load_rule_weights(weights.as_bytes()).unwrap();
+    assert_eq!(romanize(&uroman, "ایران"), "ayran");
+    // The span of the avoided rule is romanized char by char.
+    assert_eq!(romanize(&uroman, "αγκάθι"), "agkathi");
+
+    let result = uroman.load_rule_weights("::s γκ ::t x ::weight 1\n".as_bytes());
+    let Err(uroman::RomanizationError::AtLine { line, source, .. }) = result else {
+        panic!("expected an error at a line, got {result:?}");
+    };
+    assert_eq!(line, 1);
+    assert!(matches!(
+        *source,
+        uroman::RomanizationError::UnknownRule { .. }
+    ));
+    assert!(matches!(
+        uroman.load_rule_weights("::s γκ ::t ng ::weight low\n".as_bytes()),
+        Err(uroman::RomanizationError::AtLine { .. })
+    ));
+}
+
+#[test]
+fn test_python_compat_output() {
+    let uroman = Uroman::new();
+    let options = uroman::RomanizeOptions::new()
+        .with_format(RomFormat::Edges)
+        .with_compat(uroman::Compat::PythonV1_3);
+    let input = "::lcode ukr Київ\n☃ \"𝄞\"\n";
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_options(input.as_bytes(), &mut output, &options)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        concat!(
+            r#"[[0, 0, "", "lcode: ukr"], [0, 1, "K", "rom"], [1, 3, "yi", "rom"], [3, 4, "v", "rom"]]"#,
+            "\n",
+            // Non-ASCII chars are escaped, those beyond the BMP as surrogate pairs.
+            r#"[[0, 1, "\u2603", "orig"], [1, 2, " ", "Zs"], [2, 3, "\"", "orig"], [3, 4, "\ud834\udd1e", "orig"], [4, 5, "\"", "orig"]]"#,
+            "\n"
+        )
+    );
+
+    // The str format is the same either way.
+    let str_options = options.with_format(RomFormat::Str);
+    let result = uroman.romanize_with_options("Київ", &str_options);
+    assert_eq!(
+        result
+            .to_compat_output_string(str_options.output.compat)
+            .unwrap(),
+        "Kiyiv"
+    );
+}
+
+#[test]
+fn test_romanization_is_idempotent() {
+    let uroman = Uroman::new();
+    let input = std::fs::read_to_string("tests/test/multi-script.txt").unwrap();
+    for line in input.lines() {
+        let romanized = uroman
+            .romanize_string::<rom_format::Str>(line, None)
+            .to_output_string();
+        assert!(
+            !uroman.needs_romanization(&romanized),
+            "{romanized:?} of {line:?} needs romanization"
+        );
+        assert_eq!(
+            uroman
+                .romanize_string::<rom_format::Str>(&romanized, None)
+                .to_output_string(),
+            romanized
+        );
+        assert!(uroman.non_latin_share(&romanized) <= uroman.non_latin_share(line));
+    }
+    assert!(uroman.needs_romanization("ελληνικά"));
+    assert_eq!(uroman.non_latin_share("ελληνικά"), 1.0);
+}
+
+#[test]
+fn test_large_numbers_are_exact() {
+    let uroman = Uroman::new();
+    // Beyond the precision of f64 and the range of i64.
+    assert_romanizes_to_str(
+        &uroman,
+        "一千二百三十四京五千六百七十八兆",
+        None,
+        "12345678000000000000",
+    );
+    assert_romanizes_to_str(&uroman, "9007199254740993万", None, "90071992547409930000");
+    // Numbers that overflow are left in parts.
+    assert_romanizes_to_str(
+        &uroman,
+        "12345678901234567890123456789012345678901万",
+        None,
+        "12345678901234567890123456789012345678901·10000",
+    );
+}
+
+#[test]
+fn test_large_fractions_are_exact() {
+    let uroman = Uroman::new();
+    assert_romanizes_to_str(&uroman, "十分之五", None, "5/10");
+    assert_romanizes_to_str(
+        &uroman,
+        "九千九百九十九京分之一",
+        None,
+        "1/99990000000000000000",
+    );
+    assert_romanizes_to_str(
+        &uroman,
+        "一百分之12345678901234567890123",
+        None,
+        "12345678901234567890123%",
+    );
+}
+
+#[test]
+fn test_resource_caps() {
+    let uroman = Uroman::new().with_max_edges(Some(8));
+    let output = uroman.romanize_string::<rom_format::Str>("三千五百 Київ", None);
+    assert!(output.used_greedy_fallback());
+    assert_eq!(output.to_output_string(), "sanqianwubai Kyiv");
+
+    // Time runs out before the first edge.
+    let uroman = Uroman::new().with_time_limit(Some(std::time::Duration::ZERO));
+    let output = uroman.romanize_string::<rom_format::Str>("三千五百", None);
+    assert!(output.used_greedy_fallback());
+    assert_eq!(output.to_output_string(), "sanqianwubai");
+
+    let count_alternatives = |uroman: &Uroman| {
+        let RomanizationResult::Edges(edges) =
+            uroman.romanize_with_format("ایران ایران", None, Some(RomFormat::Alts))
+        else {
+            unreachable!();
+        };
+        edges
+            .iter()
+            .filter(|edge| edge.r#type().starts_with("rom-alt"))
+            .count()
+    };
+    assert!(count_alternatives(&Uroman::new()) > 1);
+    assert_eq!(
+        count_alternatives(&Uroman::new().with_max_alternatives(Some(1))),
+        1
+    );
+}
+
+#[test]
+fn test_romanize_with_deadline_and_cancellation() {
+    use std::time::{Duration, Instant};
+    use uroman::{CancellationToken, RomanizationError};
+
+    let uroman = Uroman::new();
+    let long_line = "Привіт, світе! ".repeat(1000);
+    let output = uroman
+        .romanize_string_with_deadline::<rom_format::Edges>(
+            &long_line,
+            Some("ukr"),
+            Instant::now() + Duration::from_secs(60),
+        )
+        .unwrap();
+    assert!(!output.used_greedy_fallback());
+
+    let result = uroman.romanize_string_with_deadline::<rom_format::Edges>(
+        &long_line,
+        Some("ukr"),
+        Instant::now(),
+    );
+    assert!(matches!(result, Err(RomanizationError::DeadlineExceeded)));
+
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    canceller.cancel();
+    assert!(token.is_cancelled());
+    let result =
+        uroman.romanize_string_with_cancellation::<rom_format::Str>(&long_line, None, &token);
+    assert!(matches!(result, Err(RomanizationError::Cancelled)));
+}
+
+#[test]
+fn test_validate_edge_path() {
+    let uroman = Uroman::new();
+    let input = std::fs::read_to_string("tests/test/multi-script.txt").unwrap();
+    for line in input.lines() {
+        let result = uroman.romanize_with_format(line, None, Some(RomFormat::Edges));
+        let RomanizationResult::Edges(edges) = result else {
+            unreachable!();
+        };
+        uroman::Edge::validate_path(&edges, line.chars().count()).unwrap();
+    }
+
+    let edges = [
+        uroman::Edge::new_regular(0, 2, "ab", "rom"),
+        uroman::Edge::new_regular(3, 4, "c", "rom"),
+    ];
+    assert!(matches!(
+        uroman::Edge::validate_path(&edges, 4),
+        Err(uroman::RomanizationError::InvalidEdgePath { index: 1, .. })
+    ));
+    assert!(matches!(
+        uroman::Edge::validate_path(&edges[..1], 4),
+        Err(uroman::RomanizationError::InvalidEdgePath { index: 1, .. })
+    ));
+}
+
+#[test]
+fn test_preserve_whitespace() {
+    let input = "a\u{a0}b\u{3000}c  Київ\tx\u{2003}١٢ ٣";
+    let uroman = Uroman::new();
+    let result = uroman.romanize_string::<rom_format::Str>(input, Some("ukr"));
+    assert_eq!(result.to_output_string(), "a b c  Kyiv\tx 12 3");
+
+    let uroman = Uroman::new().with_whitespace(uroman::WhitespacePolicy::Preserve);
+    let result = uroman.romanize_string::<rom_format::Str>(input, Some("ukr"));
+    assert_eq!(
+        result.to_output_string(),
+        "a\u{a0}b\u{3000}c  Kyiv\tx\u{2003}12 3"
+    );
+
+    let result = uroman.romanize_with_format(input, Some("ukr"), Some(RomFormat::Edges));
+    let RomanizationResult::Edges(edges) = result else {
+        unreachable!();
+    };
+    let chars: Vec<char> = input.chars().collect();
+    for (i, c) in chars.iter().enumerate().filter(|(_, c)| c.is_whitespace()) {
+        let edge = edges
+            .iter()
+            .find(|edge| edge.get_data().start == i)
+            .unwrap();
+        assert_eq!(edge.get_data().end, i + 1);
+        assert_eq!(edge.get_data().txt, c.to_string());
+    }
+}
+
+#[test]
+fn test_romanize_file_line_endings() {
+    use uroman::{LineEnding, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    let input = "Київ\r\nabc\n\nЛьвів";
+    let romanize = |options: &RomanizeOptions| {
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_options(input.as_bytes(), &mut output, options)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    let options = RomanizeOptions::new().with_silent(true);
+    assert_eq!(romanize(&options), "Kiyiv\nabc\n\nLviv\n");
+    let options = options.with_line_ending(LineEnding::CrLf);
+    assert_eq!(romanize(&options), "Kiyiv\r\nabc\r\n\r\nLviv\r\n");
+    let options = options.with_line_ending(LineEnding::Preserve);
+    assert_eq!(romanize(&options), "Kiyiv\r\nabc\n\nLviv");
+    let options = options.with_line_ending(LineEnding::Separator("\0".to_string()));
+    assert_eq!(romanize(&options), "Kiyiv\0abc\0\0Lviv\0");
+
+    let options = RomanizeOptions::new()
+        .with_silent(true)
+        .with_format(RomFormat::Edges)
+        .with_line_ending(LineEnding::Preserve);
+    assert!(!romanize(&options).ends_with('\n'));
+}
+
+#[test]
+fn test_romanize_file_in_chunks() {
+    use uroman::RomanizeOptions;
+
+    let uroman = Uroman::new();
+    let input = "u1 Київ і Львів, Одеса та Харків.\n::lcode ukr Київ Львів Одеса\nЛьвів";
+    let romanize = |options: &RomanizeOptions| {
+        let mut output = Vec::new();
+        let summary = uroman
+            .romanize_file_with_options(input.as_bytes(), &mut output, options)
+            .unwrap();
+        assert_eq!(summary.lines, 3);
+        String::from_utf8(output).unwrap()
+    };
+    // The alternatives of each chunk follow its edges, so they are compared as sets.
+    let sorted_edges = |output: &str| -> Vec<Vec<String>> {
+        serde_json::Deserializer::from_str(output)
+            .into_iter::<serde_json::Value>()
+            .map(|line| {
+                let line = line.unwrap();
+                let mut edges: Vec<String> = line
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|edge| edge.to_string())
+                    .collect();
+                edges.sort();
+                edges
+            })
+            .collect()
+    };
+
+    for rom_format in [RomFormat::Str, RomFormat::Edges, RomFormat::Alts] {
+        let options = RomanizeOptions::new()
+            .with_silent(true)
+            .with_utt_ids(true)
+            .with_format(rom_format);
+        let expected = romanize(&options);
+        // "u1" fills the first chunk of 10 bytes, and "Львів" the last one.
+        for max_chunk_bytes in [10, 16, 1000] {
+            let options = options.clone().with_max_chunk_bytes(max_chunk_bytes);
+            let output = romanize(&options);
+            if rom_format == RomFormat::Alts {
+                assert_eq!(
+                    sorted_edges(&output),
+                    sorted_edges(&expected),
+                    "{max_chunk_bytes}"
+                );
+            } else {
+                assert_eq!(output, expected, "{rom_format:?}, {max_chunk_bytes}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_romanize_file_in_chunks_with_escapes() {
+    use uroman::RomanizeOptions;
+
+    let uroman = Uroman::new();
+    let input = r"\u041a\u0438\u0457\u0432 \u041a\u0438\u0457\u0432";
+    let romanize = |options: &RomanizeOptions| {
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_options(input.as_bytes(), &mut output, options)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    // A chunk of 16 bytes would cut the third escape.
+    let options = RomanizeOptions::new()
+        .with_silent(true)
+        .with_lcode("ukr")
+        .with_decode_unicode(true);
+    assert_eq!(
+        romanize(&options.clone().with_max_chunk_bytes(16)),
+        "Kyiv Kyiv\n"
+    );
+
+    // The edges of the second word start at its char in the decoded line.
+    let options = options.with_format(RomFormat::Edges);
+    let expected = romanize(&options);
+    let output = romanize(&options.with_max_chunk_bytes(30));
+    assert_eq!(output, expected);
+    let edges: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        edges.as_array().unwrap().last().unwrap()["Regular"]["end"],
+        9
+    );
+}
+
+#[test]
+fn test_memory_budget() {
+    let long_line = "Київ ".repeat(1000);
+    let uroman = Uroman::new().with_memory_budget(Some(1 << 20));
+    let output = uroman.romanize_string::<rom_format::Str>(&long_line, Some("ukr"));
+    assert!(output.used_greedy_fallback());
+    let output = uroman.romanize_string::<rom_format::Str>("Київ", Some("ukr"));
+    assert!(!output.used_greedy_fallback());
+    assert_eq!(output.to_output_string(), "Kyiv");
+
+    let input: String = (0..200).map(|i| format!("Київ {i}\n")).collect();
+    let budget = 4096;
+    let mut cache = RomanizationCache::new(std::num::NonZeroUsize::new(1000).unwrap())
+        .with_memory_budget(budget);
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_context(
+            input.as_bytes(),
+            &mut output,
+            &uroman::RomanizeOptions::new(),
+            uroman::FileContext::new().with_cache(&mut cache),
+        )
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap().lines().count(), 200);
+    assert!(cache.len() < 200);
+    assert!(0 < cache.estimated_bytes() && cache.estimated_bytes() <= budget);
+}
+
+#[test]
+fn test_decode_invalid_unicode_escapes() {
+    use uroman::{InvalidEscapePolicy, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    let romanize = |s: &str, options: &RomanizeOptions| {
+        uroman
+            .romanize_with_options(s, options)
+            .to_output_string()
+            .unwrap()
+    };
+    let options = RomanizeOptions::new().with_decode_unicode(true);
+    assert_eq!(romanize(r"😀 \U0001F600 δ \x41", &options), "😀 😀 d \\x41");
+    assert_eq!(
+        romanize(r"\ud83dx \ude00\ud83d \U00110000 \UFFFFFFFF", &options),
+        "\u{FFFD}x \u{FFFD}\u{FFFD} \u{FFFD} \u{FFFD}"
+    );
+
+    let options = options.with_invalid_escapes(InvalidEscapePolicy::Keep);
+    assert_eq!(
+        romanize(r"😀 \ud83dx \U00110000", &options),
+        r"😀 \ud83dx \U00110000"
+    );
+    assert_eq!(romanize(r"\ud83d😀", &options), r"\ud83d😀");
+}
+
+#[test]
+fn test_decode_named_escapes_and_entities() {
+    use uroman::{InvalidEscapePolicy, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    let romanize = |s: &str, options: &RomanizeOptions| {
+        uroman
+            .romanize_with_options(s, options)
+            .to_output_string()
+            .unwrap()
+    };
+    let text = concat!(
+        r"\N{CYRILLIC CAPITAL LETTER KA}&#1080;&#x457;&#x432;",
+        r" &amp;lt; &foo; \N{NO SUCH CHAR} &#xD800;"
+    );
+    assert_eq!(
+        romanize("&eacute; \\N{DIGIT ONE}", &RomanizeOptions::new()),
+        "&eacute; \\N{DIGIT ONE}"
+    );
+
+    let options = RomanizeOptions::new().with_decode_named_escapes(true);
+    assert_eq!(romanize(r"\N{greek small letter delta}", &options), "d");
+    assert_eq!(
+        romanize(r"\N{NO SUCH CHAR} &eacute;", &options),
+        "\u{FFFD} &eacute;"
+    );
+
+    let options = options.with_decode_entities(true);
+    assert_eq!(
+        romanize(text, &options),
+        "Kiyiv &lt; &foo; \u{FFFD} \u{FFFD}"
+    );
+    let options = options.with_invalid_escapes(InvalidEscapePolicy::Keep);
+    assert_eq!(
+        romanize(text, &options),
+        r"Kiyiv &lt; &foo; \N{NO SUCH CHAR} &#xD800;"
+    );
+}
+
+#[test]
+fn test_generate_data_tables() {
+    let unicode_data = "\
+0041;LATIN CAPITAL LETTER A;Lu;0;L;;;;;N;;;;0061;
+0300;COMBINING GRAVE ACCENT;Mn;230;NSM;;;;;N;NON-SPACING GRAVE;;;;
+05D3;HEBREW LETTER DALET;Lo;0;R;;;;;N;;;;;
+0628;ARABIC LETTER BEH;Lo;0;AL;;;;;N;;;;;
+064E;ARABIC FATHA;Mn;30;NSM;;;;;N;ARABIC FATHAH;;;;
+0915;DEVANAGARI LETTER KA;Lo;0;L;;;;;N;;;;;
+093F;DEVANAGARI VOWEL SIGN I;Mc;0;L;;;;;N;;;;;
+094D;DEVANAGARI SIGN VIRAMA;Mn;9;NSM;;;;;N;;;;;
+0967;DEVANAGARI DIGIT ONE;Nd;0;L;;1;1;1;N;;;;;
+304B;HIRAGANA LETTER KA;Lo;0;L;;;;;N;;;;;
+304D;HIRAGANA LETTER KI;Lo;0;L;;;;;N;;;;;
+304F;HIRAGANA LETTER KU;Lo;0;L;;;;;N;;;;;
+3055;HIRAGANA LETTER SA;Lo;0;L;;;;;N;;;;;
+3057;HIRAGANA LETTER SI;Lo;0;L;;;;;N;;;;;
+3059;HIRAGANA LETTER SU;Lo;0;L;;;;;N;;;;;
+305F;HIRAGANA LETTER TA;Lo;0;L;;;;;N;;;;;
+3061;HIRAGANA LETTER TI;Lo;0;L;;;;;N;;;;;
+3064;HIRAGANA LETTER TU;Lo;0;L;;;;;N;;;;;
+4E00;<CJK Ideograph, First>;Lo;0;L;;;;;N;;;;;
+4E02;<CJK Ideograph, Last>;Lo;0;L;;;;;N;;;;;
+";
+    let scripts = "\
+# Scripts-16.0.0.txt
+0041          ; Latin # L&       LATIN CAPITAL LETTER A
+0300          ; Inherited # Mn       COMBINING GRAVE ACCENT
+05D3          ; Hebrew # Lo       HEBREW LETTER DALET
+0628          ; Arabic # Lo       ARABIC LETTER BEH
+064E          ; Inherited # Mn       ARABIC FATHA
+0900..097F    ; Devanagari # Mn [128] DEVANAGARI SIGN INVERTED CANDRABINDU..
+3041..3096    ; Hiragana # Lo  [86] HIRAGANA LETTER SMALL A..HIRAGANA LETTER SMALL KE
+4E00..4E02    ; Han # Lo   [3] CJK UNIFIED IDEOGRAPH-4E00..CJK UNIFIED IDEOGRAPH-4E02
+";
+    let uroman = Uroman::new();
+    let tables = uroman.generate_data_tables(unicode_data, scripts).unwrap();
+    let roms: Vec<(&str, &str)> = tables
+        .romanization_auto_table
+        .lines()
+        .map(|line| {
+            let s = &line[line.find("::s ").unwrap() + 4..line.find(" ::cat").unwrap()];
+            (s, line.rsplit_once("::t ").unwrap().1)
+        })
+        .collect();
+    assert_eq!(
+        roms,
+        [
+            ("A", "A"),
+            ("ד", "d"),
+            ("ب", "b"),
+            ("क", "k"),
+            ("ि", "i"),
+            ("か", "ka"),
+            ("き", "ki"),
+            ("く", "ku"),
+            ("さ", "sa"),
+            ("し", "si"),
+            ("す", "su"),
+            ("た", "ta"),
+            ("ち", "ti"),
+            ("つ", "tu"),
+        ]
+    );
+    assert!(
+        tables
+            .unicode_data_props
+            .contains("::script-name Arabic ::n-char 1 ::char ب\n")
+    );
+    assert!(tables.unicode_data_props.contains(
+        "::script-name Devanagari ::n-char 3 ::char कि् ::numeral १ ::sign-virama ् ::vowel-sign ि\n"
+    ));
+    assert_eq!(
+        tables.unicode_data_props_cjk,
+        "::script-name CJK ::n-char 3 ::char 一丁丂\n"
+    );
+    assert_eq!(tables.unicode_data_props_hangul, "");
+
+    let err = uroman
+        .generate_data_tables("0041;LATIN CAPITAL LETTER A\n", scripts)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        uroman::RomanizationError::InvalidUnicodeData(_)
+    ));
+}
+
+#[test]
+fn test_overrides_dir() {
+    let dir = std::env::temp_dir().join(format!("uroman-overrides-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("romanization-table.txt"),
+        "# Site-wide corrections\n::s ї ::t ji\n::s Київ ::t Kyiv\n",
+    )
+    .unwrap();
+    let uroman = Uroman::new().with_overrides_dir(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let romanize = |s, lcode| {
+        uroman
+            .romanize_string::<rom_format::Str>(s, lcode)
+            .to_output_string()
+    };
+    assert_eq!(romanize("ї", None), "ji");
+    assert_eq!(romanize("Київ", None), "Kyiv");
+    // Rules for other conditions are kept.
+    assert_eq!(romanize("її", Some("ukr")), "yiyi");
+
+    let err = Uroman::new().with_overrides_dir(&dir).unwrap_err();
+    assert!(matches!(err, uroman::RomanizationError::Io(_)));
+}
+
+#[test]
+fn test_script_spans() {
+    let uroman = Uroman::new();
+    assert_eq!(uroman.script_spans(""), []);
+    assert_eq!(uroman.script_spans("1, 2"), [(0..4, "Common")]);
+    // Combining marks are part of the span of the char before them.
+    assert_eq!(
+        uroman.script_spans("- cafe\u{301} नमस्ते\u{200D}!"),
+        [
+            (0..2, "Common"),
+            (2..7, "Latin"),
+            (7..8, "Common"),
+            (8..15, "Devanagari"),
+            (15..16, "Common"),
+        ]
+    );
+    assert_eq!(
+        uroman.script_spans("Αθήνα Київ"),
+        [(0..5, "Greek"), (5..6, "Common"), (6..10, "Cyrillic")]
+    );
+}
+
+#[test]
+fn test_guess_lcode() {
+    let uroman = Uroman::new();
+    assert!(uroman.guess_lcode("").is_empty());
+    assert_eq!(uroman.guess_lcode("한국어"), [("kor", 1.0)]);
+
+    let guesses = uroman.guess_lcode("Київ, Україна");
+    assert_eq!(guesses[0].0, "ukr");
+    assert!(guesses[0].1 > 0.5);
+    assert!(guesses.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    // `srp2` counts for `srp`.
+    assert_eq!(uroman.guess_lcode("Ђорђе")[0].0, "srp");
+
+    // Latin letters without language-specific rules are attributed to no language.
+    let guesses = uroman.guess_lcode("Київ hello world");
+    let total: f64 = guesses.iter().map(|(_, confidence)| confidence).sum();
+    assert!(total < 0.5);
+}
+
+#[test]
+fn test_mixed_script_tokens() {
+    let uroman = Uroman::new();
+    assert_eq!(uroman.mixed_script_tokens(""), []);
+    assert_eq!(uroman.mixed_script_tokens("Київ, Kyiv 2024 東京タワー 한국語"), []);
+    // The `С` of `Сoca` and the `о` of `Cоla` are Cyrillic, the `ο` of `Cοla` Greek.
+    assert_eq!(
+        uroman.mixed_script_tokens("Сoca-Cоla, Cοla\u{301}!"),
+        [
+            (0..4, vec!["Cyrillic", "Latin"]),
+            (5..9, vec!["Latin", "Cyrillic"]),
+            (11..16, vec!["Latin", "Greek"]),
+        ]
+    );
+}
+
+#[test]
+fn test_normalized_homoglyphs() {
+    let text = "Сoca-Cоla ｐａｙ ΡΑΥpal рау";
+    let romanize = |uroman: &Uroman| {
+        uroman
+            .romanize_string::<rom_format::Str>(text, None)
+            .to_output_string()
+    };
+    let uroman = Uroman::new();
+    assert_eq!(romanize(&uroman), "Soca-Cola pay RAYpal rau");
+    let uroman = Uroman::new().with_normalized_homoglyphs(true);
+    assert_eq!(romanize(&uroman), "Coca-Cola pay PAYpal rau");
+
+    // Offsets are those of the original text.
+    let result = uroman.romanize_string::<rom_format::Edges>("x рaypal", None);
+    let edges: Vec<serde_json::Value> =
+        serde_json::from_str(&result.to_output_string().unwrap()).unwrap();
+    assert_eq!(edges.len(), 8);
+    assert_eq!(edges[2]["Regular"]["start"], 2);
+    assert_eq!(edges[2]["Regular"]["txt"], "p");
+    assert_eq!(edges[7]["Regular"]["end"], 8);
+}
+
+#[test]
+fn test_romanize_filename() {
+    let uroman = Uroman::new();
+    assert_eq!(uroman.romanize_filename("Київ.txt", Some("ukr")), "Kyiv.txt");
+    assert_eq!(uroman.romanize_filename("a/b\\c\t?.", None), "a_b_c__");
+    assert_eq!(uroman.romanize_filename(" .hidden ", None), ".hidden");
+    for name in ["", "..", "☃"] {
+        assert_eq!(uroman.romanize_filename(name, None), "_");
+    }
+
+    // Names that Windows reserves for devices, with any extension, and trailing dots and
+    // spaces, which Windows drops.
+    for (name, expected) in [
+        ("CON", "CON_"),
+        ("nul.txt", "nul_.txt"),
+        ("Aux.tar.gz", "Aux_.tar.gz"),
+        ("com1", "com1_"),
+        ("LPT9.log", "LPT9_.log"),
+        ("ПРН. .", "PRN_"),
+        ("COM0", "COM0"),
+        ("console.txt", "console.txt"),
+        ("report. . ", "report"),
+    ] {
+        assert_eq!(uroman.romanize_filename(name, None), expected);
+    }
+}
+
+#[test]
+fn test_html_report() {
+    use uroman::{HtmlReport, InputFormat, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    let mut report = HtmlReport::new(Vec::new(), "A <review>").unwrap();
+    let options = RomanizeOptions::default();
+    report
+        .add_line(&uroman, "Київ", &options.clone().with_lcode("ukr"))
+        .unwrap();
+    report.add_line(&uroman, "a ☃", &options).unwrap();
+    let stats = report.stats().clone();
+    assert_eq!(stats.lines, 2);
+    assert_eq!(stats.chars, 7);
+    assert_eq!(stats.unromanized_chars, 1);
+    assert_eq!(stats.greedy_lines, 0);
+    assert_eq!(stats.spans_by_source.values().sum::<usize>(), stats.spans);
+
+    let html = String::from_utf8(report.finish().unwrap()).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>A &lt;review&gt;</title>"));
+    assert!(html.contains("<td dir=\"auto\">Київ</td>"));
+    assert!(html.contains("class=\"s unrom\""));
+    assert!(html.contains("<tr><th>Lines</th><td>2</td></tr>"));
+    assert!(html.ends_with("</html>\n"));
+
+    // Lines are romanized as in files, and only the romanized text shows.
+    let mut report = HtmlReport::new(Vec::new(), "Fields").unwrap();
+    let utt_ids = options.clone().with_utt_ids(true);
+    report
+        .add_line(&uroman, "id1\t::lcode ukr Київ", &utt_ids)
+        .unwrap();
+    let jsonl = options
+        .with_lcode("ukr")
+        .with_input_format(InputFormat::Jsonl);
+    report
+        .add_line(&uroman, r#"{"ід": "Київ", "n": 1}"#, &jsonl)
+        .unwrap();
+    assert_eq!(report.stats().chars, 8);
+    let html = String::from_utf8(report.finish().unwrap()).unwrap();
+    // Київ is Kyiv in Ukrainian, and Kiyiv without a language code.
+    assert!(html.contains(">yi</span>"));
+    assert!(!html.contains(">i</span>"));
+    assert!(!html.contains(">id1</span>"));
+    assert!(!html.contains(">ід</span>"));
+}
+
+#[test]
+fn test_compare_outputs() {
+    use uroman::compare_outputs;
+
+    let comparison = compare_outputs("Kyiv\nLviv\n", "Kyiv\r\nLviv", 10);
+    assert!(comparison.is_identical());
+    assert_eq!(comparison.char_error_rate(), 0.0);
+
+    let comparison = compare_outputs("abc\nKyiv\nx\n", "abd\nKiev\nx\nextra\n", 2);
+    assert_eq!(comparison.lines, 4);
+    assert_eq!(comparison.differing_lines, 3);
+    assert_eq!(comparison.reference_chars, 8);
+    assert_eq!(comparison.distance, 1 + 2 + 5);
+    assert_eq!(comparison.char_error_rate(), 1.0);
+    let lines: Vec<(usize, usize)> = comparison
+        .divergences
+        .iter()
+        .map(|divergence| (divergence.line, divergence.distance))
+        .collect();
+    assert_eq!(lines, [(4, 5), (2, 2)]);
+    assert_eq!(comparison.divergences[0].reference, "");
+}
+
+#[test]
+fn test_unigrams() {
+    use uroman::RomanizationError;
+
+    let romanize = |uroman: &Uroman, lcode| {
+        uroman
+            .romanize_string::<rom_format::Str>("محمد يوم، قول", Some(lcode))
+            .to_output_string()
+    };
+    let mut uroman = Uroman::new();
+    assert_eq!(romanize(&uroman, "ara"), "mhmd ywm, qwl");
+
+    let unigrams = "# romanized Arabic words\nYom 10\nywm 4\nqul 2\nqol 1\nqul 1\nmhmd 5\n";
+    uroman.load_unigrams("ar", unigrams.as_bytes()).unwrap();
+    assert_eq!(romanize(&uroman, "ara"), "mhmd yom, qul");
+    // The list is only used for its language.
+    assert_eq!(romanize(&uroman, "fas"), romanize(&Uroman::new(), "fas"));
+
+    let err = uroman.load_unigrams("ara", "yom\n".as_bytes()).unwrap_err();
+    assert!(matches!(
+        err,
+        RomanizationError::AtLine { line: 1, ref source, .. }
+            if matches!(**source, RomanizationError::InvalidUnigram(_))
+    ));
+}
+
+#[test]
+fn test_load_rules() {
+    use uroman::{RULE_CONDITIONS, RomanizationError};
+
+    let romanize = |uroman: &Uroman, s| {
+        uroman
+            .romanize_string::<rom_format::Str>(s, Some("ell"))
+            .to_output_string()
+    };
+    let mut uroman = Uroman::new();
+    let rules = "# Greek\n\
+                 ::s ντ ::t d ::lcode ell ::use-only-at-start-of-word ::comment custom\n\
+                 ::s ξ ::t ks ::use-only-at-end-of-word\n";
+    uroman.load_rules(rules.as_bytes()).unwrap();
+    assert_eq!(romanize(&uroman, "ντομάτα κέντρο"), "domata kendro");
+    assert_eq!(romanize(&uroman, "ξέρω Φοίνιξ"), "xero Foiniks");
+    assert_eq!(
+        uroman
+            .romanize_string::<rom_format::Str>("ντομάτα", None)
+            .to_output_string(),
+        "ndomata"
+    );
+
+    for (rules, line) in [
+        ("::s ν ::t n ::use-only-at-star-of-word\n", 1),
+        ("# no source\n::t n\n", 2),
+    ] {
+        let err = uroman.load_rules(rules.as_bytes()).unwrap_err();
+        assert!(matches!(
+            err,
+            RomanizationError::AtLine { line: l, ref source, .. }
+                if l == line && matches!(**source, RomanizationError::InvalidRule { .. })
+        ));
+    }
+    for condition in RULE_CONDITIONS {
+        let rule = format!("::s ν ::t n ::{condition}\n");
+        Uroman::new().load_rules(rule.as_bytes()).unwrap();
+    }
+}
+
+#[test]
+fn test_line_directives() {
+    use uroman::RomanizeOptions;
+
+    let uroman = Uroman::new();
+    let romanize = |input: &str, options: &RomanizeOptions| {
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_options(input.as_bytes(), &mut output, options)
+            .map(|_| String::from_utf8(output).unwrap())
+    };
+    let options = RomanizeOptions::new().with_silent(true);
+    let input = "::no-numbers ::LCODE\tukr  Київ ١٢\n\
+                 ::no_numbers ١٢\n\
+                 Київ ١٢\n\
+                 ::scheme iso Київ\n\
+                 ::format edges ::lcode ukr Ї\n";
+    let output = romanize(input, &options).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[..4],
+        [
+            "::no-numbers ::LCODE\tukr  Kyiv ١٢",
+            "::no_numbers ١٢",
+            "Kiyiv 12",
+            "::scheme iso Kiyiv",
+        ]
+    );
+    let edges: serde_json::Value = serde_json::from_str(&lines[4..].join("\n")).unwrap();
+    assert_eq!(edges[0], serde_json::json!([0, 0, "", "lcode: ukr"]));
+    assert_eq!(edges[1], serde_json::json!([0, 0, "", "format: edges"]));
+    assert_eq!(edges[2]["Regular"]["txt"], "Yi");
+
+    // A line of a file in an edge format can be a string, also when read in chunks.
+    let options = options.with_format(RomFormat::Edges);
+    for options in [options.clone(), options.with_max_chunk_bytes(16)] {
+        let output = romanize("::format str Київ Київ Київ\n", &options).unwrap();
+        assert_eq!(output, "::format str Kiyiv Kiyiv Kiyiv\n");
+    }
+
+    // An invalid or repeated directive starts the text, and doesn't stop the file.
+    let input = "::format json Київ\n\
+                 ::lcode ukr ::lcode rus Київ\n\
+                 ::no-numbers ::no-numbers ١٢\n";
+    assert_eq!(
+        romanize(input, &RomanizeOptions::new()).unwrap(),
+        "::format json Kiyiv\n\
+         ::lcode ukr ::lcode rus Kyiv\n\
+         ::no-numbers ::no-numbers ١٢\n"
+    );
+}
+
+#[test]
+fn test_rule_conditions() {
+    use uroman::{RomFormat, RomanizationResult, Uroman};
+
+    let mut uroman = Uroman::new().with_rule_conditions(true);
+    let rules = "::s ντ ::t d ::t-alt nt ::lcode ell ::use-only-at-start-of-word\n";
+    uroman.load_rules(rules.as_bytes()).unwrap();
+    let edges = |s: &str, lcode, rom_format| {
+        let result = uroman.romanize_with_format(s, lcode, Some(rom_format));
+        let RomanizationResult::Edges(edges) = result else {
+            unreachable!();
+        };
+        edges
+            .iter()
+            .map(|edge| {
+                let data = edge.get_data();
+                (
+                    data.txt.to_string(),
+                    data.r#type.to_string(),
+                    data.conditions.clone(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let conditions = |conditions: &[&str]| Some(conditions.iter().map(|c| c.to_string()).collect());
+
+    let edges_ell = edges("ντομάτα 12", Some("ell"), RomFormat::Alts);
+    let start_of_word = conditions(&["lcode: ell", "use-only-at-start-of-word"]);
+    assert_eq!(
+        edges_ell[0],
+        ("d".into(), "rom".into(), start_of_word.clone())
+    );
+    assert_eq!(edges_ell[1], ("o".into(), "rom".into(), conditions(&[])));
+    // Numbers are not romanized by a single rule.
+    assert!(
+        edges_ell
+            .iter()
+            .any(|(txt, _, conditions)| txt == "12" && conditions.is_none())
+    );
+    assert_eq!(
+        edges_ell.last().unwrap(),
+        &("nt".into(), "rom-alt".into(), start_of_word)
+    );
+
+    // The rule doesn't apply to other languages, or to the middle of words.
+    for (s, lcode) in [("ντομάτα", None), ("κέντρο", Some("ell"))] {
+        let edges = edges(s, lcode, RomFormat::Edges);
+        assert!(
+            edges
+                .iter()
+                .all(|(_, _, conditions)| conditions == &Some(vec![]))
+        );
+    }
+
+    let edges = Uroman::new().romanize_with_format("ντομάτα", Some("ell"), Some(RomFormat::Edges));
+    let RomanizationResult::Edges(edges) = edges else {
+        unreachable!();
+    };
+    assert!(
+        edges
+            .iter()
+            .all(|edge| edge.get_data().conditions.is_none())
+    );
+}
+
+#[test]
+fn test_case_carrying() {
+    use uroman::{RomFormat, RomanizationResult, Uroman, rom_format};
+
+    let s = "Σ σ ЯБЛОКО Яблоко яблоко БОРЩ Щи ΝΤΟΜΑΤΑ ᏣᎳᎩ";
+    let romanize = |uroman: &Uroman, lcode| {
+        uroman
+            .romanize_string::<rom_format::Str>(s, lcode)
+            .to_output_string()
+    };
+    assert_eq!(
+        romanize(&Uroman::new(), None),
+        "S s YaBLOKO Yabloko yabloko BORShch Shchi NTOMATA tsalagi"
+    );
+    let uroman = Uroman::new().with_case_carrying(true);
+    assert_eq!(
+        romanize(&uroman, None),
+        "S s YABLOKO Yabloko yabloko BORSHCH Shchi NDOMATA tsalagi"
+    );
+
+    // The alternatives of rules carry the case too.
+    let result = uroman.romanize_with_format("ΝΤΟΜΑΤΑ", Some("ell"), Some(RomFormat::Alts));
+    let RomanizationResult::Edges(edges) = result else {
+        unreachable!();
+    };
+    let txts: Vec<&str> = edges.iter().map(|edge| edge.txt()).collect();
+    assert_eq!(txts, ["ND", "O", "M", "A", "T", "A", "D", " NT"]);
+}
+
+#[test]
+fn test_load_protected() {
+    use uroman::{RomFormat, RomanizationError, RomanizationResult, Uroman, rom_format};
+
+    let mut uroman = Uroman::new().with_normalized_homoglyphs(true);
+    let s = "Ωmega кодировка ISO-8859 и ПК-12 Ωμέγα";
+    let romanize = |uroman: &Uroman| {
+        uroman
+            .romanize_string::<rom_format::Str>(s, None)
+            .to_output_string()
+    };
+    assert_eq!(romanize(&uroman), "Omega kodirovka ISO-8859 i PK-12 Omega");
+
+    let protected = "# Names\nΩmega\n  ISO-8859  \n/[А-Я]{2}-\\d+/\n";
+    uroman.load_protected(protected.as_bytes()).unwrap();
+    assert_eq!(romanize(&uroman), "Ωmega kodirovka ISO-8859 i ПК-12 Omega");
+
+    let result = uroman.romanize_with_format(s, None, Some(RomFormat::Edges));
+    let RomanizationResult::Edges(edges) = result else {
+        unreachable!();
+    };
+    let protected: Vec<(usize, usize, &str)> = edges
+        .iter()
+        .filter(|edge| edge.r#type() == "protected")
+        .map(|edge| (edge.start(), edge.end(), edge.txt()))
+        .collect();
+    assert_eq!(
+        protected,
+        [(0, 5, "Ωmega"), (16, 24, "ISO-8859"), (27, 32, "ПК-12")]
+    );
+    assert!(
+        edges
+            .windows(2)
+            .all(|pair| pair[0].end() == pair[1].start())
+    );
+
+    // An invalid regex loads nothing.
+    let err = uroman
+        .load_protected("кодировка\n/[/\n".as_bytes())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        RomanizationError::AtLine { ref source, .. }
+            if matches!(**source, RomanizationError::InvalidProtectedPattern { .. })
+    ));
+    assert_eq!(romanize(&uroman), "Ωmega kodirovka ISO-8859 i ПК-12 Omega");
+}
+
+#[test]
+fn test_disagreements() {
+    use uroman::Uroman;
+
+    let uroman = Uroman::new();
+    assert_eq!(uroman.disagreements("Київ", Some("ukr"), "Kyiv"), []);
+
+    let disagreements = uroman.disagreements("Львів 12, Αθήνα", Some("ukr"), "L'viv 12, Athina");
+    let spans: Vec<(&str, &str, &str, &str)> = disagreements
+        .iter()
+        .map(|d| (&*d.orig, &*d.ours, &*d.theirs, &*d.script))
+        .collect();
+    assert_eq!(
+        spans,
+        [("ь", "", "'", "Cyrillic"), ("ή", "e", "i", "Greek")]
+    );
+    assert!(disagreements.iter().all(|d| !d.source.is_empty()));
+
+    // Chars that the other romanizer adds at the end belong to the last span.
+    let disagreements = uroman.disagreements("Київ", Some("ukr"), "Kyivs");
+    assert_eq!(disagreements.len(), 1);
+    assert_eq!(
+        (&*disagreements[0].orig, &*disagreements[0].theirs),
+        ("в", "vs")
+    );
+}
+
+#[test]
+fn test_input_formats() {
+    use uroman::{InputFormat, RomanizationError, RomanizeOptions, Uroman};
+
+    let uroman = Uroman::new();
+    let romanize = |input: &str, input_format: InputFormat| {
+        let options = RomanizeOptions::new()
+            .with_format(RomFormat::Edges)
+            .with_input_format(input_format);
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_options(input.as_bytes(), &mut output, &options)
+            .map(|_| String::from_utf8(output).unwrap())
+    };
+
+    let jsonl = "{\"Ключ\": \"Київ\", \"n\": [1, \"Ж\"],  \"q\": \"\\\"Αθήνα\\\"\"}\n\n";
+    assert_eq!(
+        romanize(jsonl, InputFormat::Jsonl).unwrap(),
+        "{\"Ключ\": \"Kiyiv\", \"n\": [1, \"Zh\"],  \"q\": \"\\\"Athena\\\"\"}\n\n"
+    );
+    let err = romanize("{\"a\": \"Київ\"\n", InputFormat::Jsonl).unwrap_err();
+    assert!(matches!(
+        err,
+        RomanizationError::AtLine { line: 1, ref source, .. }
+            if matches!(**source, RomanizationError::InvalidJsonLine(_))
+    ));
+
+    assert_eq!(
+        romanize("Київ\tЛьвів\n\tЖ\n", InputFormat::Tsv).unwrap(),
+        "Kiyiv\tLviv\n\tZh\n"
+    );
+
+    let srt = "1\n00:00:01,000 --> 00:00:02,500\nКиїв\n\n2\n00:00:03,000 --> 00:00:04,000\n";
+    assert_eq!(
+        romanize(srt, InputFormat::Srt).unwrap(),
+        "1\n00:00:01,000 --> 00:00:02,500\nKiyiv\n\n2\n00:00:03,000 --> 00:00:04,000\n"
+    );
+    // A subtitle line of digits isn't a cue number, as no timing follows it.
+    assert_eq!(
+        romanize(
+            "1\n00:00:01,000 --> 00:00:02,500\n2024\n१९\n",
+            InputFormat::Srt
+        )
+        .unwrap(),
+        "1\n00:00:01,000 --> 00:00:02,500\n2024\n19\n"
+    );
+
+    assert_eq!(InputFormat::detect(srt), InputFormat::Srt);
+    assert_eq!(InputFormat::detect(jsonl), InputFormat::Jsonl);
+    assert_eq!(InputFormat::detect("Київ\tЛьвів\n\tЖ\n"), InputFormat::Tsv);
+    assert_eq!(InputFormat::detect("Київ\tЛьвів\nЖ\n"), InputFormat::Text);
+    assert_eq!(InputFormat::detect("{\"a\": 1}\nКиїв\n"), InputFormat::Text);
+    assert_eq!(
+        InputFormat::from_extension("subs/ep1.SRT".as_ref()),
+        Some(InputFormat::Srt)
+    );
+    assert_eq!(InputFormat::from_extension("notes.txt".as_ref()), None);
+}
+
+#[test]
+fn test_alignment_offsets() {
+    use uroman::{Alignment, OffsetUnit, RomanizeOptions, Uroman};
+
+    let uroman = Uroman::new();
+    let edges = |text: &str, rom_format: RomFormat| {
+        let options = RomanizeOptions::new()
+            .with_lcode("ukr")
+            .with_format(rom_format);
+        let RomanizationResult::Edges(edges) = uroman.romanize_with_options(text, &options) else {
+            unreachable!();
+        };
+        edges
+    };
+
+    // "иї" is romanized as a whole, as "yi".
+    let alignment = Alignment::from_edges(
+        "Київ 2",
+        &edges("Київ 2", RomFormat::Edges),
+        OffsetUnit::Chars,
+    );
+    assert_eq!(alignment, uroman.align("Київ 2", Some("ukr")));
+    assert_eq!(
+        (alignment.orig_to_rom.len(), alignment.rom_to_orig.len()),
+        (6, 6)
+    );
+    let orig_to_rom: Vec<usize> = (0..=6).map(|i| alignment.to_romanized_offset(i)).collect();
+    assert_eq!(orig_to_rom, [0, 1, 1, 3, 4, 5, 6]);
+    let rom_to_orig: Vec<usize> = (0..=6).map(|i| alignment.to_original_offset(i)).collect();
+    assert_eq!(rom_to_orig, [0, 1, 1, 3, 4, 5, 6]);
+    assert_eq!(alignment.to_romanized(2..3), 1..3);
+    assert_eq!(alignment.to_original(2..2), 1..1);
+    assert_eq!(alignment.to_original(0..2), 0..3);
+
+    // The alternatives of the alts format overlap the edges of the romanization.
+    let text = "Львів";
+    let bytes = Alignment::from_edges(text, &edges(text, RomFormat::Alts), OffsetUnit::Bytes);
+    assert_eq!(bytes.romanized, "Lviv");
+    assert_eq!(bytes.orig_to_rom.len(), text.len());
+    assert_eq!(bytes.rom_to_orig.len(), "Lviv".len());
+    assert_eq!(&text[bytes.to_original(1..2)], "в");
+    assert_eq!(bytes.to_romanized(2..4), 1..1);
+    assert!(
+        serde_json::to_string(&bytes)
+            .unwrap()
+            .contains(r#""unit":"bytes""#)
+    );
+}
+
+#[test]
+fn test_romanize_tokens() {
+    let uroman = Uroman::new();
+    let tokens = ["Привіт", ",", "Ки", "їв", "", " ", "12", "北京"];
+    let romanized = uroman.romanize_tokens(&tokens, Some("ukr"));
+    assert_eq!(
+        romanized,
+        ["Pryvit", ",", "Ky", "yiv", "", " ", "12", "beijing"]
+    );
+    assert!(uroman.romanize_tokens(&[], None).is_empty());
+}
+
+#[test]
+fn test_sentence_chunks() {
+    let whole = Uroman::new();
+    let chunked = Uroman::new().with_sentence_chunks(Some(8));
+    let texts = [
+        "Привіт!  Як справи? «Добре.» Дякую…\tБувай",
+        "Բարև։ Ինչպե՞ս ես։",
+        "你好。我很好！谢谢",
+        "مرحبا؟ شكرا",
+        "नमस्ते। धन्यवाद॥ ठीक",
+        "v1.2 Київ",
+    ];
+    for text in texts {
+        let romanize = |uroman: &Uroman| {
+            let edges = uroman
+                .romanize_string::<rom_format::Edges>(text, None)
+                .to_output_string()
+                .unwrap();
+            (
+                uroman
+                    .romanize_string::<rom_format::Str>(text, None)
+                    .to_output_string(),
+                edges,
+            )
+        };
+        assert_eq!(romanize(&chunked), romanize(&whole), "{text}");
+    }
+
+    // Every string is split with a limit of 0, and a terminator at its end ends the last
+    // sentence.
+    let chunked = Uroman::new().with_sentence_chunks(Some(0));
+    assert_eq!(
+        chunked
+            .romanize_string::<rom_format::Str>("Київ.", Some("ukr"))
+            .to_output_string(),
+        "Kyiv."
+    );
+}
+
+#[test]
+fn test_romanizing_writer() {
+    use std::io::Write;
+    use uroman::{LineEnding, RomanizeOptions};
+
+    let uroman = Uroman::new();
+    let options = RomanizeOptions::new()
+        .with_lcode("ukr")
+        .with_line_ending(LineEnding::Preserve);
+
+    // A char split between two writes stays whole, and line endings are kept.
+    let mut writer = uroman.romanizing_writer(Vec::new(), options.clone());
+    let text = "Київ\r\nЛьвів\n\nОдеса";
+    let (first, second) = text.as_bytes().split_at(3);
+    writer.write_all(first).unwrap();
+    writer.write_all(second).unwrap();
+    writer.flush().unwrap();
+    assert_eq!(writer.get_ref().as_slice(), b"Kyiv\r\nLviv\n\n");
+    let output = writer.finish().unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "Kyiv\r\nLviv\n\nOdesa");
+
+    // The rest of the last line is written when the writer is dropped.
+    let mut output = Vec::new();
+    {
+        let mut writer =
+            uroman.romanizing_writer(&mut output, options.clone().with_format(RomFormat::Edges));
+        write!(writer, "::format str Ж").unwrap();
+    }
+    assert_eq!(String::from_utf8(output).unwrap(), "::format str Zh");
+
+    let mut writer = uroman.romanizing_writer(Vec::new(), options.clone().with_strict(true));
+    let err = writeln!(writer, "Київ ☃").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    assert!(writer.finish().unwrap().is_empty());
+
+    // A write that fails after some lines takes only the bytes of those.
+    let mut writer = uroman.romanizing_writer(Vec::new(), options.with_strict(true));
+    let text = "Київ\nЛьвів ☃\nОдеса\n";
+    assert_eq!(writer.write(text.as_bytes()).unwrap(), "Київ\n".len());
+    assert!(writer.write(&text.as_bytes()["Київ\n".len()..]).is_err());
+    assert_eq!(
+        String::from_utf8(writer.finish().unwrap()).unwrap(),
+        "Kyiv\n"
+    );
+
+    // Lines end with `\n` by default, as in files.
+    let mut writer = uroman.romanizing_writer(Vec::new(), RomanizeOptions::new());
+    write!(writer, "Київ\r\nЛьвів").unwrap();
+    assert_eq!(
+        String::from_utf8(writer.finish().unwrap()).unwrap(),
+        "Kiyiv\nLviv\n"
+    );
+}
+
+#[test]
+fn test_structured_input_in_chunks() {
+    use uroman::{InputFormat, RomanizeOptions, Uroman};
+
+    let uroman = Uroman::new();
+    let romanize = |input: &str, input_format: InputFormat, max_chunk_bytes: Option<usize>| {
+        let mut options = RomanizeOptions::new()
+            .with_format(RomFormat::Edges)
+            .with_input_format(input_format);
+        options.max_chunk_bytes = max_chunk_bytes;
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_options(input.as_bytes(), &mut output, &options)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    // Lines of the structured formats are read whole, whatever `max_chunk_bytes` is.
+    for (input, input_format) in [
+        ("Київ]\tЛьвів Одеса\n", InputFormat::Tsv),
+        (
+            "1\n00:00:01,000 --> 00:00:02,500\nКиїв і Львів]\n",
+            InputFormat::Srt,
+        ),
+        ("{\"a\": \"Київ і Львів\"}\n", InputFormat::Jsonl),
+    ] {
+        assert_eq!(
+            romanize(input, input_format, Some(8)),
+            romanize(input, input_format, None)
+        );
+    }
+    assert_eq!(
+        romanize("Київ]\tЛьвів Одеса\n", InputFormat::Tsv, Some(8)),
+        "Kiyiv]\tLviv Odesa\n"
+    );
+}
+
+#[test]
+fn test_cache_key_options() {
+    use uroman::{InputFormat, RomanizeOptions, Subsystems, Uroman};
+
+    let uroman = Uroman::new();
+    let mut cache = RomanizationCache::new(8.try_into().unwrap());
+    let mut romanize = |input: &str, options: &RomanizeOptions| {
+        let mut output = Vec::new();
+        uroman
+            .romanize_file_with_context(
+                input.as_bytes(),
+                &mut output,
+                options,
+                uroman::FileContext::new().with_cache(&mut cache),
+            )
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    // Lines romanized with other options aren't served from the cache.
+    let input = "१२३ नमस्ते\n";
+    assert_eq!(romanize(input, &RomanizeOptions::new()), "123 namaste\n");
+    let subsystems = Subsystems::default()
+        .with_numbers(false)
+        .with_abugida_vowels(false);
+    assert_eq!(
+        romanize(input, &RomanizeOptions::new().with_subsystems(subsystems)),
+        "१२३ nmste\n"
+    );
+
+    let options = RomanizeOptions::new().with_format(RomFormat::Edges);
+    let without_positions = romanize(input, &options);
+    let with_positions = romanize(input, &options.with_display_positions(true));
+    assert!(!without_positions.contains("display"));
+    assert!(with_positions.contains("display"));
+
+    let options = RomanizeOptions::new();
+    let jsonl = "{\"Ключ\": \"Київ\"}\n";
+    assert_eq!(romanize(jsonl, &options), "{\"Klyuch\": \"Kiyiv\"}\n");
+    assert_eq!(
+        romanize(jsonl, &options.with_input_format(InputFormat::Jsonl)),
+        "{\"Ключ\": \"Kiyiv\"}\n"
+    );
+}
+
+#[test]
+fn test_strict_with_options() {
+    use uroman::{RomanizationError, RomanizeOptions, Subsystems};
+
+    // The check romanizes with the subsystems of the options, as the output does.
+    let uroman = Uroman::new();
+    let options = RomanizeOptions::new()
+        .with_strict(true)
+        .with_subsystems(Subsystems::default().with_numbers(false));
+    let result = uroman.check_romanizable("१२ नमस्ते", &options);
+    assert!(
+        matches!(result, Err(RomanizationError::Unromanizable(ref chars)) if chars == &['१', '२'])
+    );
+    assert!(
+        uroman
+            .check_romanizable("१२ नमस्ते", &RomanizeOptions::new())
+            .is_ok()
+    );
+
+    let mut output = Vec::new();
+    let result = uroman.romanize_file_with_options("नमस्ते\n१२\n".as_bytes(), &mut output, &options);
+    let Err(RomanizationError::AtLine { line, source, .. }) = result else {
+        panic!("expected an error at a line, got {result:?}");
+    };
+    assert_eq!(line, 2);
+    assert!(matches!(*source, RomanizationError::Unromanizable(_)));
+    assert_eq!(String::from_utf8(output).unwrap(), "namaste\n");
+}
+
+#[test]
+fn test_romanize_options_policies() {
+    use uroman::{FormatCharPolicy, Progress, RomanizationCache, RomanizeOptions};
+
+    let uroman = Uroman::new().with_format_chars(FormatCharPolicy::Drop);
+    let text = "می\u{200c}خواهم";
+    let options = RomanizeOptions::new().with_format_chars(FormatCharPolicy::Boundary);
+    let result = uroman.romanize_with_options(text, &options);
+    assert_eq!(result.to_output_string().unwrap(), "mi khwahm");
+    // `None` keeps the policy of the `Uroman`.
+    let result = uroman.romanize_with_options(text, &RomanizeOptions::new());
+    assert_eq!(result.to_output_string().unwrap(), "mikhwahm");
+
+    let mut cache = RomanizationCache::new(16.try_into().unwrap());
+    let mut lines = 0;
+    let mut progress = |p: Progress| lines = p.lines;
+    let context = uroman::FileContext::new()
+        .with_cache(&mut cache)
+        .with_progress(&mut progress);
+    let input = format!("{text}\n{text}\n");
+    let mut output = Vec::new();
+    uroman
+        .romanize_file_with_context(input.as_bytes(), &mut output, &options, context)
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "mi khwahm\nmi khwahm\n");
+    assert_eq!(lines, 2);
+}
@@ -0,0 +1,82 @@
+#ifndef UROMAN_H
+#define UROMAN_H
+
+/* Generated by cbindgen from src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A loaded romanizer. It can be shared between threads.
+typedef struct Uroman Uroman;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Loads the romanization data and returns a new romanizer, or NULL if loading failed.
+//
+// The romanizer must be released with [`uroman_free`].
+struct Uroman *uroman_new(void);
+
+// Romanizes `text`, optionally with an ISO 639-3 language code `lcode` (e.g. "ukr"), which
+// may be NULL.
+//
+// Returns NULL if any argument is not valid UTF-8, or if `uroman` or `text` is NULL.
+//
+// # Safety
+//
+// `uroman` must be NULL or returned by [`uroman_new`] and not yet freed. `text` and `lcode`
+// must be NULL or point to NUL-terminated strings.
+char *uroman_romanize(const struct Uroman *uroman, const char *text, const char *lcode);
+
+// Romanizes the UTF-16 `text` of `text_len` code units like [`uroman_romanize`]. `text` need
+// not be NUL-terminated, so a Java string can be passed as it is.
+//
+// The romanization is returned as UTF-16 without a terminating NUL, and its length in code
+// units is stored in `*out_len`. It must be released with [`uroman_utf16_free`].
+//
+// Returns NULL if `text` has unpaired surrogates, if `lcode` is not valid UTF-8, or if
+// `uroman`, `text` or `out_len` is NULL.
+//
+// # Safety
+//
+// `uroman` must be NULL or returned by [`uroman_new`] and not yet freed. `text` must be NULL
+// or point to `text_len` code units. `lcode` must be NULL or point to a NUL-terminated
+// string. `out_len` must be NULL or valid for writes.
+uint16_t *uroman_romanize_utf16(const struct Uroman *uroman,
+                                const uint16_t *text,
+                                size_t text_len,
+                                const char *lcode,
+                                size_t *out_len);
+
+// Releases a string returned by [`uroman_romanize`]. Does nothing if `s` is NULL.
+//
+// # Safety
+//
+// `s` must be NULL or returned by [`uroman_romanize`] and not yet freed.
+void uroman_string_free(char *s);
+
+// Releases a string of `len` code units returned by [`uroman_romanize_utf16`]. Does nothing
+// if `s` is NULL.
+//
+// # Safety
+//
+// `s` must be NULL or returned by [`uroman_romanize_utf16`] and not yet freed, and `len` must
+// be the length stored in its `out_len`.
+void uroman_utf16_free(uint16_t *s, size_t len);
+
+// Releases a romanizer returned by [`uroman_new`]. Does nothing if `uroman` is NULL.
+//
+// # Safety
+//
+// `uroman` must be NULL or returned by [`uroman_new`] and not yet freed.
+void uroman_free(struct Uroman *uroman);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* UROMAN_H */
@@ -0,0 +1,183 @@
+//! C API for uroman-rs.
+//!
+//! The header `include/uroman.h` is generated from this file by cbindgen:
+//!
+//! ```sh
+//! cbindgen --config cbindgen.toml --output include/uroman.h
+//! ```
+//!
+//! All strings are NUL-terminated UTF-8. Strings returned by the library are owned by the
+//! caller and must be released with [`uroman_string_free`].
+//!
+//! For callers whose strings are UTF-16, such as Java through JNI or bindings generated by
+//! jextract, [`uroman_romanize_utf16`] takes and returns UTF-16 with explicit lengths, which
+//! are released with [`uroman_utf16_free`].
+
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::{ptr, slice};
+
+use uroman::rom_format;
+
+/// A loaded romanizer. It can be shared between threads.
+pub struct Uroman(uroman::Uroman);
+
+/// Loads the romanization data and returns a new romanizer, or NULL if loading failed.
+///
+/// The romanizer must be released with [`uroman_free`].
+#[unsafe(no_mangle)]
+pub extern "C" fn uroman_new() -> *mut Uroman {
+    catch_unwind(|| Box::into_raw(Box::new(Uroman(uroman::Uroman::new()))))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Romanizes `text`, optionally with an ISO 639-3 language code `lcode` (e.g. "ukr"), which
+/// may be NULL.
+///
+/// Returns NULL if any argument is not valid UTF-8, or if `uroman` or `text` is NULL.
+///
+/// # Safety
+///
+/// `uroman` must be NULL or returned by [`uroman_new`] and not yet freed. `text` and `lcode`
+/// must be NULL or point to NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uroman_romanize(
+    uroman: *const Uroman,
+    text: *const c_char,
+    lcode: *const c_char,
+) -> *mut c_char {
+    // SAFETY: the caller guarantees that non-NULL pointers are valid.
+    let (Some(uroman), Some(text), Some(lcode)) = (
+        unsafe { uroman.as_ref() },
+        unsafe { str_arg(text) },
+        unsafe { lcode_arg(lcode) },
+    ) else {
+        return ptr::null_mut();
+    };
+
+    romanize(uroman, text, lcode)
+        .and_then(|romanized| CString::new(romanized).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Romanizes the UTF-16 `text` of `text_len` code units like [`uroman_romanize`]. `text` need
+/// not be NUL-terminated, so a Java string can be passed as it is.
+///
+/// The romanization is returned as UTF-16 without a terminating NUL, and its length in code
+/// units is stored in `*out_len`. It must be released with [`uroman_utf16_free`].
+///
+/// Returns NULL if `text` has unpaired surrogates, if `lcode` is not valid UTF-8, or if
+/// `uroman`, `text` or `out_len` is NULL.
+///
+/// # Safety
+///
+/// `uroman` must be NULL or returned by [`uroman_new`] and not yet freed. `text` must be NULL
+/// or point to `text_len` code units. `lcode` must be NULL or point to a NUL-terminated
+/// string. `out_len` must be NULL or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uroman_romanize_utf16(
+    uroman: *const Uroman,
+    text: *const u16,
+    text_len: usize,
+    lcode: *const c_char,
+    out_len: *mut usize,
+) -> *mut u16 {
+    if text.is_null() || out_len.is_null() {
+        return ptr::null_mut();
+    }
+    // SAFETY: the caller guarantees that non-NULL pointers are valid.
+    let (Some(uroman), Some(lcode)) = (unsafe { uroman.as_ref() }, unsafe { lcode_arg(lcode) })
+    else {
+        return ptr::null_mut();
+    };
+    // SAFETY: `text` is not NULL and points to `text_len` code units.
+    let Ok(text) = String::from_utf16(unsafe { slice::from_raw_parts(text, text_len) }) else {
+        return ptr::null_mut();
+    };
+
+    let Some(romanized) = romanize(uroman, &text, lcode) else {
+        return ptr::null_mut();
+    };
+    let romanized: Box<[u16]> = romanized.encode_utf16().collect();
+    // SAFETY: `out_len` is not NULL and valid for writes.
+    unsafe { out_len.write(romanized.len()) };
+    Box::into_raw(romanized).cast()
+}
+
+/// Releases a string returned by [`uroman_romanize`]. Does nothing if `s` is NULL.
+///
+/// # Safety
+///
+/// `s` must be NULL or returned by [`uroman_romanize`] and not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uroman_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: `s` was created by `CString::into_raw`.
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Releases a string of `len` code units returned by [`uroman_romanize_utf16`]. Does nothing
+/// if `s` is NULL.
+///
+/// # Safety
+///
+/// `s` must be NULL or returned by [`uroman_romanize_utf16`] and not yet freed, and `len` must
+/// be the length stored in its `out_len`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uroman_utf16_free(s: *mut u16, len: usize) {
+    if !s.is_null() {
+        // SAFETY: `s` was created by `Box::into_raw` from a slice of `len` code units.
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(s, len)) });
+    }
+}
+
+/// Releases a romanizer returned by [`uroman_new`]. Does nothing if `uroman` is NULL.
+///
+/// # Safety
+///
+/// `uroman` must be NULL or returned by [`uroman_new`] and not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn uroman_free(uroman: *mut Uroman) {
+    if !uroman.is_null() {
+        // SAFETY: `uroman` was created by `Box::into_raw`.
+        drop(unsafe { Box::from_raw(uroman) });
+    }
+}
+
+/// Romanizes `text` to a string, or returns `None` if romanizing panicked.
+fn romanize(uroman: &Uroman, text: &str, lcode: Option<&str>) -> Option<String> {
+    catch_unwind(AssertUnwindSafe(|| {
+        uroman
+            .0
+            .romanize_string::<rom_format::Str>(text, lcode)
+            .to_output_string()
+    }))
+    .ok()
+}
+
+/// Returns the optional language code behind `lcode`, or `None` if it is not UTF-8.
+///
+/// # Safety
+///
+/// `lcode` must be NULL or point to a NUL-terminated string.
+unsafe fn lcode_arg<'a>(lcode: *const c_char) -> Option<Option<&'a str>> {
+    if lcode.is_null() {
+        return Some(None);
+    }
+    // SAFETY: the caller's guarantee is passed on.
+    unsafe { str_arg(lcode) }.map(Some)
+}
+
+/// Returns the UTF-8 string behind `s`, or `None` if it is NULL or not UTF-8.
+///
+/// # Safety
+///
+/// `s` must be NULL or point to a NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    // SAFETY: `s` is not NULL, and NUL-terminated by the caller's guarantee.
+    unsafe { CStr::from_ptr(s) }.to_str().ok()
+}
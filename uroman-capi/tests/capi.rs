@@ -0,0 +1,63 @@
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+use uroman_capi::{
+    uroman_free, uroman_new, uroman_romanize, uroman_romanize_utf16, uroman_string_free,
+    uroman_utf16_free,
+};
+
+#[test]
+fn test_romanize_through_c_api() {
+    let uroman = uroman_new();
+    assert!(!uroman.is_null());
+
+    let text = CString::new("Київ").unwrap();
+    let lcode = CString::new("ukr").unwrap();
+    unsafe {
+        let romanized = uroman_romanize(uroman, text.as_ptr(), lcode.as_ptr());
+        assert_eq!(CStr::from_ptr(romanized).to_str().unwrap(), "Kyiv");
+        uroman_string_free(romanized);
+
+        let romanized = uroman_romanize(uroman, text.as_ptr(), ptr::null());
+        assert!(!romanized.is_null());
+        uroman_string_free(romanized);
+
+        assert!(uroman_romanize(uroman, ptr::null(), ptr::null()).is_null());
+        let invalid = CString::new(vec![0xff, 0xfe]).unwrap();
+        assert!(uroman_romanize(uroman, invalid.as_ptr(), ptr::null()).is_null());
+
+        uroman_free(uroman);
+    }
+}
+
+#[test]
+fn test_romanize_utf16_through_c_api() {
+    let uroman = uroman_new();
+    let lcode = CString::new("ukr").unwrap();
+    let text: Vec<u16> = "Київ".encode_utf16().collect();
+    let mut len = 0;
+    unsafe {
+        let romanized =
+            uroman_romanize_utf16(uroman, text.as_ptr(), text.len(), lcode.as_ptr(), &mut len);
+        let romanized_text = String::from_utf16(std::slice::from_raw_parts(romanized, len));
+        assert_eq!(romanized_text.unwrap(), "Kyiv");
+        uroman_utf16_free(romanized, len);
+
+        let romanized = uroman_romanize_utf16(uroman, text.as_ptr(), 0, ptr::null(), &mut len);
+        assert!(!romanized.is_null());
+        assert_eq!(len, 0);
+        uroman_utf16_free(romanized, len);
+
+        let unpaired_surrogate = [0xd800];
+        let romanized = uroman_romanize_utf16(
+            uroman,
+            unpaired_surrogate.as_ptr(),
+            1,
+            ptr::null(),
+            &mut len,
+        );
+        assert!(romanized.is_null());
+
+        uroman_free(uroman);
+    }
+}
@@ -0,0 +1,59 @@
+//! A loadable SQLite extension for uroman-rs, for romanizing columns in SQL.
+//!
+//! Build it with `cargo build --release -p uroman-sqlite`, and load it with
+//! `.load target/release/liburoman_sqlite` in the sqlite3 shell or with `load_extension` from
+//! any SQLite client. It adds the function `uroman(text [, lcode])`:
+//!
+//! ```sql
+//! SELECT uroman(name, 'ukr') FROM cities;
+//! ```
+
+use std::ffi::{c_char, c_int};
+use std::sync::LazyLock;
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, Result, ffi};
+use uroman::{Uroman, rom_format};
+
+/// Loaded on the first call of `uroman`, and shared by all connections.
+static UROMAN: LazyLock<Uroman> = LazyLock::new(Uroman::new);
+
+/// The entry point that SQLite calls when loading the extension.
+///
+/// # Safety
+///
+/// Only SQLite may call this, with the pointers it passes to extension entry points.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sqlite3_extension_init(
+    db: *mut ffi::sqlite3,
+    err_msg: *mut *mut c_char,
+    api: *mut ffi::sqlite3_api_routines,
+) -> c_int {
+    // SAFETY: the pointers come from SQLite.
+    unsafe { Connection::extension_init2(db, err_msg, api, register_functions) }
+}
+
+/// Registers `uroman(text)` and `uroman(text, lcode)` on `db`.
+///
+/// Both return NULL for a NULL `text`, and romanize without a language code for a NULL
+/// `lcode`. They are deterministic, so they can be used in indexes and generated columns.
+fn register_functions(db: Connection) -> Result<bool> {
+    for n_args in [1, 2] {
+        db.create_scalar_function(
+            "uroman",
+            n_args,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let text: Option<String> = ctx.get(0)?;
+                let lcode: Option<String> = if ctx.len() > 1 { ctx.get(1)? } else { None };
+                Ok(text.map(|text| {
+                    UROMAN
+                        .romanize_string::<rom_format::Str>(&text, lcode.as_deref())
+                        .to_output_string()
+                }))
+            },
+        )?;
+    }
+    // SQLite may unload the extension when the connection is closed.
+    Ok(false)
+}
@@ -0,0 +1,36 @@
+use std::env::consts::DLL_PREFIX;
+use std::process::Command;
+
+#[test]
+fn test_load_extension_in_sqlite_shell() {
+    // Test builds don't link the cdylib, so build it next to the `deps` directory of this test.
+    let mut build = Command::new(env!("CARGO"));
+    build.args(["build", "--quiet", "--package", "uroman-sqlite"]);
+    if !cfg!(debug_assertions) {
+        build.arg("--release");
+    }
+    assert!(build.status().unwrap().success());
+
+    let exe = std::env::current_exe().unwrap();
+    let extension = exe
+        .parent()
+        .and_then(|deps| deps.parent())
+        .unwrap()
+        .join(format!("{DLL_PREFIX}uroman_sqlite"));
+
+    let Ok(output) = Command::new("sqlite3")
+        .arg(":memory:")
+        .arg(format!(".load {}", extension.display()))
+        .arg("SELECT uroman('Київ', 'ukr'), uroman('Київ'), uroman(NULL) IS NULL;")
+        .output()
+    else {
+        eprintln!("sqlite3 is not installed, skipping");
+        return;
+    };
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Kyiv|Kiyiv|1\n");
+}
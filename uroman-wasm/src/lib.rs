@@ -0,0 +1,45 @@
+//! WebAssembly bindings for uroman-rs, for romanizing in the browser.
+//!
+//! Build with `wasm-pack build uroman-wasm --profile wasm-release`, and leave out the features
+//! of scripts you don't need (e.g. `--no-default-features --features hangul`) to shrink the
+//! .wasm further.
+
+use std::sync::LazyLock;
+
+use uroman::{RomFormat, Uroman};
+use wasm_bindgen::prelude::*;
+
+/// Loaded on the first call of [`romanize`].
+static UROMAN: LazyLock<Uroman> = LazyLock::new(Uroman::new);
+
+/// Romanizes `text`, optionally with an ISO 639-3 language code `lcode` (e.g. "ukr").
+///
+/// `format` is one of "str" (the default), "edges", "alts" and "lattice". The edge formats
+/// are returned as JSON.
+#[wasm_bindgen]
+pub fn romanize(
+    text: &str,
+    lcode: Option<String>,
+    format: Option<String>,
+) -> Result<String, JsError> {
+    romanize_with_format(text, lcode.as_deref(), format.as_deref()).map_err(|e| JsError::new(&e))
+}
+
+/// The implementation of [`romanize`], with a plain error message instead of a `JsError`.
+pub fn romanize_with_format(
+    text: &str,
+    lcode: Option<&str>,
+    format: Option<&str>,
+) -> Result<String, String> {
+    let rom_format = match format.unwrap_or("str") {
+        "str" => RomFormat::Str,
+        "edges" => RomFormat::Edges,
+        "alts" => RomFormat::Alts,
+        "lattice" => RomFormat::Lattice,
+        other => return Err(format!("unknown format {other:?}")),
+    };
+    UROMAN
+        .romanize_with_format(text, lcode, Some(rom_format))
+        .to_output_string()
+        .map_err(|e| e.to_string())
+}
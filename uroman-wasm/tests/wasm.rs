@@ -0,0 +1,12 @@
+use uroman_wasm::romanize_with_format;
+
+#[test]
+fn test_romanize_with_format() {
+    assert_eq!(
+        romanize_with_format("Київ", Some("ukr"), None).unwrap(),
+        "Kyiv"
+    );
+    let edges = romanize_with_format("Київ", None, Some("edges")).unwrap();
+    assert!(edges.starts_with('['));
+    assert!(romanize_with_format("Київ", None, Some("json")).is_err());
+}